@@ -9,7 +9,7 @@
 //! ```
 
 use monocle::lens::parse::ParseFilters;
-use monocle::lens::search::{SearchDumpType, SearchFilters};
+use monocle::lens::search::{SearchDumpType, SearchFilters, SearchLens};
 
 fn main() -> anyhow::Result<()> {
     // Search for BGP updates from first hour of 2025
@@ -28,7 +28,7 @@ fn main() -> anyhow::Result<()> {
     println!("Searching for BGP messages...");
     println!("Filter: community *:100");
 
-    let broker = filters.build_broker()?;
+    let broker = SearchLens::new().build_broker(&filters)?;
     let items = broker.query()?;
 
     println!("Found {} MRT files", items.len());
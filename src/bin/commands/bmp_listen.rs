@@ -0,0 +1,303 @@
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use bgpkit_parser::encoder::MrtUpdatesEncoder;
+use bgpkit_parser::BgpElem;
+use clap::Args;
+use monocle::database::MsgStore;
+use monocle::lens::as2rel::PathRelThresholds;
+use monocle::lens::parse::bmp::{BmpReader, BmpRecord, BmpRecordFilter};
+use monocle::lens::parse::time_window::TimeWindows;
+use monocle::lens::parse::ParseFilters;
+use monocle::utils::{OutputFormat, TimestampFormat};
+use monocle::MonocleConfig;
+
+use super::elem_format::{
+    add_windows_field, available_fields_help, enrich_msg_store, format_elem, get_header,
+    load_elem_as_info, load_elem_as_path_rel, load_elem_asinfo_filters, parse_fields,
+    print_bmp_event,
+};
+use super::rpki::load_elem_rpki;
+
+/// Elements written to SQLite/MRT output per batch
+const BMP_WRITE_BATCH_SIZE: usize = 1_000;
+
+/// Longest time elements wait before being written to SQLite/MRT output
+const BMP_WRITE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Arguments for the BmpListen command
+#[derive(Args)]
+pub(crate) struct BmpListenArgs {
+    /// Address to accept BMP sessions on
+    #[clap(long, default_value = "0.0.0.0:11019")]
+    pub listen: SocketAddr,
+
+    /// Also write elements to this SQLite database
+    #[clap(long, value_name = "PATH")]
+    pub sqlite_path: Option<PathBuf>,
+
+    /// Also write elements to this MRT file as BGP4MP updates
+    #[clap(long, short = 'M', value_name = "PATH")]
+    pub mrt_path: Option<PathBuf>,
+
+    /// Do not print elements to stdout (requires --sqlite-path or --mrt-path)
+    #[clap(long)]
+    pub quiet: bool,
+
+    /// Comma-separated list of fields to output; `collector` is the router address
+    #[clap(long, short = 'f', value_name = "FIELDS", help = available_fields_help())]
+    pub fields: Option<String>,
+
+    /// Timestamp output format (unix or rfc3339), applied to all output formats including JSON
+    #[clap(long, value_enum, default_value = "unix")]
+    pub time_format: TimestampFormat,
+
+    #[clap(flatten)]
+    pub rel_thresholds: PathRelThresholds,
+
+    #[clap(flatten)]
+    pub filters: ParseFilters,
+}
+
+/// Messages from connection threads to the writer
+#[allow(clippy::large_enum_variant)]
+enum ListenEvent {
+    Connected(Arc<str>),
+    Record(Arc<str>, BmpRecord),
+    Disconnected(Arc<str>, u64, Option<String>),
+}
+
+pub fn run(config: &MonocleConfig, args: BmpListenArgs, output_format: OutputFormat) {
+    if let Err(e) = run_inner(config, args, output_format) {
+        eprintln!("ERROR: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run_inner(
+    config: &MonocleConfig,
+    args: BmpListenArgs,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let BmpListenArgs {
+        listen,
+        sqlite_path,
+        mrt_path,
+        quiet,
+        fields,
+        time_format,
+        rel_thresholds,
+        mut filters,
+    } = args;
+
+    if output_format == OutputFormat::Table {
+        return Err(anyhow!(
+            "table output needs all rows up front; use psv, markdown or a JSON format"
+        ));
+    }
+    if quiet && sqlite_path.is_none() && mrt_path.is_none() {
+        return Err(anyhow!("--quiet requires --sqlite-path or --mrt-path"));
+    }
+    filters.validate()?;
+    let windows = filters.time_windows()?;
+    let fields_arg = fields;
+    let mut fields = parse_fields(&fields_arg, true).map_err(|e| anyhow!(e))?;
+    add_windows_field(&mut fields, &fields_arg, &windows);
+    load_elem_rpki(config, &mut filters, fields.contains(&"rpki")).map_err(|e| anyhow!("{e}"))?;
+    load_elem_asinfo_filters(config, &mut filters).map_err(|e| anyhow!(e))?;
+    load_elem_as_info(config, &fields).map_err(|e| anyhow!(e))?;
+    load_elem_as_path_rel(config, &fields, rel_thresholds).map_err(|e| anyhow!(e))?;
+    let record_filter = BmpRecordFilter::new(&filters)?;
+
+    let msg_store = sqlite_path
+        .as_ref()
+        .map(|path| MsgStore::new(Some(&path.to_string_lossy()), false).and_then(enrich_msg_store))
+        .transpose()?;
+    let mrt_writer = match mrt_path.as_ref() {
+        Some(path) => {
+            let path = path.to_string_lossy();
+            Some((MrtUpdatesEncoder::new(), oneio::get_writer(&path)?))
+        }
+        None => None,
+    };
+
+    let listener = TcpListener::bind(listen)?;
+    eprintln!("listening for BMP sessions on {}", listener.local_addr()?);
+
+    // Sessions are accepted on a background thread; output stays on this one
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let sender = sender.clone();
+                    thread::spawn(move || read_session(stream, sender));
+                }
+                Err(e) => eprintln!("WARNING: failed to accept BMP session: {}", e),
+            }
+        }
+    });
+
+    ListenWriter {
+        record_filter,
+        fields,
+        windows,
+        output_format: (!quiet).then_some(output_format),
+        time_format,
+        msg_store,
+        mrt_writer,
+        pending: Vec::new(),
+    }
+    .run(receiver)
+}
+
+/// Read one router's BMP session until it closes
+fn read_session(stream: TcpStream, sender: Sender<ListenEvent>) {
+    let router: Arc<str> = match stream.peer_addr() {
+        Ok(addr) => addr.ip().to_string().into(),
+        Err(_) => "unknown".into(),
+    };
+    if sender.send(ListenEvent::Connected(router.clone())).is_err() {
+        return;
+    }
+
+    let mut reader = BmpReader::new(stream).live();
+    let mut error = None;
+    for record in reader.by_ref() {
+        match record {
+            Ok(record) => {
+                if sender
+                    .send(ListenEvent::Record(router.clone(), record))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            Err(e) => error = Some(e.to_string()),
+        }
+    }
+    let _ = sender.send(ListenEvent::Disconnected(router, reader.messages(), error));
+}
+
+/// Prints records and writes elements to the SQLite/MRT outputs
+struct ListenWriter {
+    record_filter: BmpRecordFilter,
+    fields: Vec<&'static str>,
+    /// Time windows shown in the "windows" field
+    windows: TimeWindows,
+    /// Output format for stdout, `None` with `--quiet`
+    output_format: Option<OutputFormat>,
+    time_format: TimestampFormat,
+    msg_store: Option<MsgStore>,
+    mrt_writer: Option<(MrtUpdatesEncoder, Box<dyn Write>)>,
+    /// Elements not yet written to SQLite/MRT output
+    pending: Vec<(BgpElem, String)>,
+}
+
+impl ListenWriter {
+    fn run(mut self, receiver: Receiver<ListenEvent>) -> Result<()> {
+        if let Some(header) = self
+            .output_format
+            .and_then(|format| get_header(format, &self.fields))
+        {
+            write_stdout(&header);
+        }
+
+        let mut last_write = Instant::now();
+        loop {
+            let event = match receiver.recv_timeout(BMP_WRITE_INTERVAL) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            match event {
+                Some(ListenEvent::Connected(router)) => {
+                    eprintln!("router {router} connected");
+                }
+                Some(ListenEvent::Disconnected(router, messages, error)) => {
+                    match error {
+                        Some(error) => eprintln!(
+                            "router {router} disconnected after {messages} messages: {error}"
+                        ),
+                        None => eprintln!("router {router} disconnected after {messages} messages"),
+                    }
+                    self.write_pending()?;
+                }
+                Some(ListenEvent::Record(router, record)) => self.handle_record(&router, record),
+                None => {}
+            }
+            if self.pending.len() >= BMP_WRITE_BATCH_SIZE
+                || (!self.pending.is_empty() && last_write.elapsed() >= BMP_WRITE_INTERVAL)
+            {
+                self.write_pending()?;
+                last_write = Instant::now();
+            }
+        }
+        self.write_pending()
+    }
+
+    fn handle_record(&mut self, router: &str, record: BmpRecord) {
+        if !self.record_filter.matches(&record) {
+            return;
+        }
+        match record {
+            BmpRecord::PeerEvent(event) => {
+                if let Some(format) = self.output_format {
+                    print_bmp_event(&event, format, self.time_format);
+                }
+            }
+            BmpRecord::Elem(elem) => {
+                if let Some(line) = self.output_format.and_then(|format| {
+                    format_elem(
+                        &elem,
+                        format,
+                        &self.fields,
+                        Some(router),
+                        Some(&self.windows),
+                        self.time_format,
+                    )
+                }) {
+                    write_stdout(&line);
+                }
+                if self.msg_store.is_some() || self.mrt_writer.is_some() {
+                    self.pending.push((elem, router.to_string()));
+                }
+            }
+        }
+    }
+
+    /// Write pending elements to SQLite and MRT output
+    fn write_pending(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        if let Some(store) = &self.msg_store {
+            store.insert_elems(&self.pending)?;
+        }
+        if let Some((encoder, writer)) = &mut self.mrt_writer {
+            for (elem, _) in &self.pending {
+                encoder.process_elem(elem);
+            }
+            writer.write_all(&encoder.export_bytes())?;
+            writer.flush()?;
+        }
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+/// Print a line to stdout, exiting quietly once the reader has gone away
+fn write_stdout(line: &str) {
+    if let Err(e) = writeln!(std::io::stdout(), "{}", line) {
+        if e.kind() != std::io::ErrorKind::BrokenPipe {
+            eprintln!("ERROR: {e}");
+        }
+        std::process::exit(1);
+    }
+}
@@ -74,6 +74,33 @@ pub enum ConfigCommands {
 
     /// List available data sources and their status
     Sources,
+
+    /// List, show and validate saved queries (`[queries.<NAME>]` tables)
+    Queries {
+        #[clap(subcommand)]
+        command: QueryCommands,
+    },
+}
+
+/// Saved query subcommands
+#[derive(Subcommand)]
+pub enum QueryCommands {
+    /// List saved queries and the filters they set
+    List,
+
+    /// Show a saved query as a JSON filter file
+    Show {
+        /// Name of the saved query
+        #[clap(value_name = "NAME")]
+        name: String,
+    },
+
+    /// Check saved queries for invalid fields and filter values
+    Validate {
+        /// Name of the saved query to check (default: all)
+        #[clap(value_name = "NAME")]
+        name: Option<String>,
+    },
 }
 
 #[derive(Debug, Serialize)]
@@ -156,6 +183,13 @@ pub fn run(config: &MonocleConfig, args: ConfigArgs, output_format: OutputFormat
             run_backup(config, &destination, output_format)
         }
         Some(ConfigCommands::Sources) => run_sources(config, output_format),
+        Some(ConfigCommands::Queries { command }) => match command {
+            QueryCommands::List => run_queries_list(config, output_format),
+            QueryCommands::Show { name } => run_queries_show(config, &name, output_format),
+            QueryCommands::Validate { name } => {
+                run_queries_validate(config, name.as_deref(), output_format)
+            }
+        },
     }
 }
 
@@ -835,6 +869,137 @@ fn run_sources(config: &MonocleConfig, output_format: OutputFormat) {
     }
 }
 
+/// A saved query in `config queries list` JSON output
+#[derive(Debug, Serialize)]
+struct SavedQueryInfo<'a> {
+    name: &'a str,
+    query: &'a serde_json::Value,
+}
+
+/// Result of `config queries validate` for one saved query
+#[derive(Debug, Serialize)]
+struct SavedQueryCheck<'a> {
+    name: &'a str,
+    valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn print_json<T: Serialize>(value: &T, output_format: OutputFormat) {
+    let json = match output_format {
+        OutputFormat::JsonPretty => serde_json::to_string_pretty(value),
+        _ => serde_json::to_string(value),
+    };
+    match json {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Error serializing output: {}", e),
+    }
+}
+
+/// One-line summary of the fields a saved query sets, e.g. `elem_type=w prefixes=2`
+fn summarize_query(query: &serde_json::Value) -> String {
+    let Some(fields) = query.as_object() else {
+        return "(not a table)".to_string();
+    };
+    let parts: Vec<String> = fields
+        .iter()
+        .filter_map(|(key, value)| match value {
+            serde_json::Value::Array(items) if items.is_empty() => None,
+            serde_json::Value::Array(items) => Some(format!("{}={}", key, items.len())),
+            serde_json::Value::Bool(false) | serde_json::Value::Null => None,
+            serde_json::Value::Bool(true) => Some(key.clone()),
+            serde_json::Value::String(v) => Some(format!("{}={}", key, v)),
+            other => Some(format!("{}={}", key, other)),
+        })
+        .collect();
+    if parts.is_empty() {
+        "(no filters)".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+fn run_queries_list(config: &MonocleConfig, output_format: OutputFormat) {
+    if output_format.is_json() {
+        let queries: Vec<SavedQueryInfo> = config
+            .queries
+            .iter()
+            .map(|(name, query)| SavedQueryInfo { name, query })
+            .collect();
+        print_json(&queries, output_format);
+        return;
+    }
+
+    if config.queries.is_empty() {
+        println!(
+            "No saved queries. Add [queries.<name>] tables to {}",
+            MonocleConfig::config_file_path()
+        );
+        return;
+    }
+    let width = config.queries.keys().map(String::len).max().unwrap_or(0);
+    for (name, query) in &config.queries {
+        println!(
+            "  {:<width$}  {}",
+            name,
+            summarize_query(query),
+            width = width
+        );
+    }
+}
+
+fn run_queries_show(config: &MonocleConfig, name: &str, output_format: OutputFormat) {
+    if let Err(e) = config.saved_query(name) {
+        eprintln!("ERROR: {}", e);
+        std::process::exit(1);
+    }
+    let query = &config.queries[name];
+    match output_format {
+        OutputFormat::Json | OutputFormat::JsonLine => print_json(query, output_format),
+        _ => print_json(query, OutputFormat::JsonPretty),
+    }
+}
+
+fn run_queries_validate(config: &MonocleConfig, name: Option<&str>, output_format: OutputFormat) {
+    let names: Vec<&str> = match name {
+        Some(name) => vec![name],
+        None => config.saved_query_names(),
+    };
+
+    let checks: Vec<SavedQueryCheck> = names
+        .into_iter()
+        .map(|name| {
+            let error = config
+                .saved_query(name)
+                .and_then(|query| query.validate())
+                .err()
+                .map(|e| e.to_string());
+            SavedQueryCheck {
+                name,
+                valid: error.is_none(),
+                error,
+            }
+        })
+        .collect();
+
+    if output_format.is_json() {
+        print_json(&checks, output_format);
+    } else if checks.is_empty() {
+        println!("No saved queries to validate");
+    } else {
+        for check in &checks {
+            match &check.error {
+                None => println!("✓ {}", check.name),
+                Some(error) => println!("✗ {}: {}", check.name, error),
+            }
+        }
+    }
+
+    if checks.iter().any(|check| !check.valid) {
+        std::process::exit(1);
+    }
+}
+
 /// Format duration in seconds to human-readable string
 fn format_duration(secs: u64) -> String {
     if secs >= 86400 {
@@ -4,8 +4,28 @@
 //! across parse and search commands, including field selection and
 //! multiple output format support.
 
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
 use bgpkit_parser::BgpElem;
-use monocle::utils::{OrderByField, OrderDirection, OutputFormat, TimestampFormat};
+use monocle::database::{ElemParquetStore, MsgStore};
+use monocle::lens::as2rel::{AsPathAnnotator, PathRelThresholds};
+use monocle::lens::parse::as_path_filter::AsPathStats;
+use monocle::lens::parse::bmp::BmpPeerEvent;
+use monocle::lens::parse::enrich::{
+    open_asinfo_database, AsInfoEnricher, ElemAsInfo, AS_INFO_FIELDS,
+};
+use monocle::lens::parse::sort::{ElemOrder, ElemSorter};
+use monocle::lens::parse::time_window::TimeWindows;
+use monocle::lens::parse::ParseFilters;
+use monocle::lens::rpki::{RpkiValidationState, RpkiValidator};
+use monocle::lens::search::{
+    parse_bucket_width, parse_dedup_window, sparkline, AggregateRow, DedupEvent, ElemAggregator,
+    ElemDeduplicator, HistogramSeries, TimeHistogram,
+};
+use monocle::utils::{GroupByField, OrderByField, OrderDirection, OutputFormat, TimestampFormat};
+use monocle::MonocleConfig;
 use serde_json::json;
 use tabled::builder::Builder;
 use tabled::settings::Style;
@@ -16,10 +36,19 @@ pub const AVAILABLE_FIELDS: &[&str] = &[
     "timestamp",
     "peer_ip",
     "peer_asn",
+    "peer_name",
     "prefix",
     "path_id",
     "as_path",
+    "as_path_rel",
+    "path_len",
+    "unique_path_len",
+    "prepend_count",
     "origin_asns",
+    "origin_name",
+    "origin_country",
+    "origin_org",
+    "rpki",
     "origin",
     "next_hop",
     "local_pref",
@@ -29,8 +58,120 @@ pub const AVAILABLE_FIELDS: &[&str] = &[
     "aggr_asn",
     "aggr_ip",
     "collector",
+    "windows",
 ];
 
+/// ROAs the "rpki" field validates elements against, set once per run
+static RPKI_VALIDATOR: OnceLock<RpkiValidator> = OnceLock::new();
+
+/// Set the ROAs for the "rpki" field; without them the field is empty
+pub fn set_rpki_validator(validator: RpkiValidator) {
+    let _ = RPKI_VALIDATOR.set(validator);
+}
+
+/// RPKI validation state of `elem`, `None` for withdrawals or without ROAs
+fn rpki_state(elem: &BgpElem) -> Option<RpkiValidationState> {
+    RPKI_VALIDATOR.get()?.validate_elem(elem)
+}
+
+/// ASInfo data of the AS name, country and organization fields, set once per run
+static AS_INFO: OnceLock<AsInfoEnricher> = OnceLock::new();
+
+/// Set the ASInfo data for the AS information fields; without it they are empty
+pub fn set_as_info(enricher: AsInfoEnricher) {
+    let _ = AS_INFO.set(enricher);
+}
+
+/// Load the ASInfo data for the AS information fields, if any of them is selected
+///
+/// The ASInfo data is refreshed first when it is empty or outdated.
+pub fn load_elem_as_info(config: &MonocleConfig, fields: &[&str]) -> Result<(), String> {
+    if !fields.iter().any(|field| AS_INFO_FIELDS.contains(field)) {
+        return Ok(());
+    }
+    set_as_info(AsInfoEnricher::from_config(config).map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+/// Resolve the country and organization filters of `filters` to ASNs, if any is set
+///
+/// The ASInfo data is refreshed first when it is empty or outdated.
+pub fn load_elem_asinfo_filters(
+    config: &MonocleConfig,
+    filters: &mut ParseFilters,
+) -> Result<(), String> {
+    if filters.asinfo_filters.is_empty() {
+        return Ok(());
+    }
+    let db = open_asinfo_database(config).map_err(|e| e.to_string())?;
+    filters.load_asinfo(&db).map_err(|e| e.to_string())
+}
+
+/// as2rel data of the "as_path_rel" field, set once per run
+static AS_PATH_REL: OnceLock<AsPathAnnotator> = OnceLock::new();
+
+/// Load the as2rel data for the "as_path_rel" field, if it is selected
+///
+/// The as2rel data is refreshed first when it is empty or outdated.
+pub fn load_elem_as_path_rel(
+    config: &MonocleConfig,
+    fields: &[&str],
+    thresholds: PathRelThresholds,
+) -> Result<(), String> {
+    if !fields.contains(&"as_path_rel") {
+        return Ok(());
+    }
+    let annotator = AsPathAnnotator::from_config(config, thresholds).map_err(|e| e.to_string())?;
+    let _ = AS_PATH_REL.set(annotator);
+    Ok(())
+}
+
+/// AS path of `elem` annotated with AS relationships, `None` without as2rel data
+fn as_path_rel(elem: &BgpElem) -> Option<String> {
+    let annotated = AS_PATH_REL.get()?.annotate(elem.as_path.as_ref()?)?;
+    Some(annotated.to_string())
+}
+
+/// Add the AS information columns to SQLite output when the ASInfo data is loaded
+pub fn enrich_msg_store(store: MsgStore) -> anyhow::Result<MsgStore> {
+    match AS_INFO.get() {
+        Some(enricher) => store.with_as_info(enricher.clone()),
+        None => Ok(store),
+    }
+}
+
+/// Fill the "rpki", AS information and "as_path_rel" columns of Parquet
+/// output when their data is loaded
+pub fn enrich_parquet_store(mut store: ElemParquetStore) -> ElemParquetStore {
+    if let Some(validator) = RPKI_VALIDATOR.get() {
+        store = store.with_rpki(validator.clone());
+    }
+    if let Some(enricher) = AS_INFO.get() {
+        store = store.with_as_info(enricher.clone());
+    }
+    if let Some(annotator) = AS_PATH_REL.get() {
+        store = store.with_as_path_rel(annotator.clone());
+    }
+    store
+}
+
+/// Look up the AS information of a batch of elements at once, so that
+/// formatting them does not query the ASInfo data per element
+pub fn prefetch_as_info<'a>(elems: impl IntoIterator<Item = &'a BgpElem>) {
+    if let Some(enricher) = AS_INFO.get() {
+        enricher.prefetch(elems);
+    }
+}
+
+/// AS information of `elem`, if any of `fields` is one of the `AS_INFO_FIELDS`
+/// and the ASInfo data is loaded
+fn elem_as_info(elem: &BgpElem, fields: &[&str]) -> Option<ElemAsInfo> {
+    if !fields.iter().any(|field| AS_INFO_FIELDS.contains(field)) {
+        return None;
+    }
+    Some(AS_INFO.get()?.lookup(elem))
+}
+
 /// Default fields to output for parse command (no collector)
 pub const DEFAULT_FIELDS_PARSE: &[&str] = &[
     "type",
@@ -72,6 +213,7 @@ pub const DEFAULT_FIELDS_SEARCH: &[&str] = &[
 pub fn format_elems_table(
     elems: &[(BgpElem, Option<String>)],
     fields: &[&str],
+    windows: Option<&TimeWindows>,
     time_format: TimestampFormat,
 ) -> String {
     let mut builder = Builder::default();
@@ -80,10 +222,21 @@ pub fn format_elems_table(
     builder.push_record(fields.iter().copied());
 
     // Add data rows
+    prefetch_as_info(elems.iter().map(|(elem, _)| elem));
     for (elem, collector) in elems {
+        let as_info = elem_as_info(elem, fields);
         let row: Vec<String> = fields
             .iter()
-            .map(|f| get_field_value_with_time_format(elem, f, collector.as_deref(), time_format))
+            .map(|f| {
+                field_value(
+                    elem,
+                    f,
+                    collector.as_deref(),
+                    windows,
+                    time_format,
+                    as_info.as_ref(),
+                )
+            })
             .collect();
         builder.push_record(row);
     }
@@ -148,21 +301,60 @@ pub fn parse_fields(
     }
 }
 
+/// Append the "windows" field to the default fields when time windows are in
+/// use, so each element shows the windows it matched. Fields chosen explicitly
+/// with `--fields` are left as they are.
+pub fn add_windows_field(
+    fields: &mut Vec<&'static str>,
+    fields_arg: &Option<String>,
+    windows: &TimeWindows,
+) {
+    if fields_arg.is_none() && !windows.is_empty() && !fields.contains(&"windows") {
+        fields.push("windows");
+    }
+}
+
 /// Get the value of a specific field from a BgpElem
 /// For the "collector" field, pass the collector value via the `collector` parameter.
 /// Uses default Unix timestamp format for backward compatibility.
 #[allow(dead_code)]
 pub fn get_field_value(elem: &BgpElem, field: &str, collector: Option<&str>) -> String {
-    get_field_value_with_time_format(elem, field, collector, TimestampFormat::Unix)
+    get_field_value_with_time_format(elem, field, collector, None, TimestampFormat::Unix)
 }
 
 /// Get the value of a specific field from a BgpElem with configurable timestamp format
 /// For the "collector" field, pass the collector value via the `collector` parameter.
+/// For the "windows" field, pass the search time windows via the `windows` parameter.
+/// The "rpki" field uses the ROAs set with [`set_rpki_validator`], the AS name, country and
+/// organization fields the ASInfo data set with [`set_as_info`], and "as_path_rel" the as2rel
+/// data loaded by [`load_elem_as_path_rel`].
 pub fn get_field_value_with_time_format(
     elem: &BgpElem,
     field: &str,
     collector: Option<&str>,
+    windows: Option<&TimeWindows>,
     time_format: TimestampFormat,
+) -> String {
+    let as_info = elem_as_info(elem, &[field]);
+    field_value(
+        elem,
+        field,
+        collector,
+        windows,
+        time_format,
+        as_info.as_ref(),
+    )
+}
+
+/// Like [`get_field_value_with_time_format`], with the AS information of
+/// `elem` looked up by the caller
+fn field_value(
+    elem: &BgpElem,
+    field: &str,
+    collector: Option<&str>,
+    windows: Option<&TimeWindows>,
+    time_format: TimestampFormat,
+    as_info: Option<&ElemAsInfo>,
 ) -> String {
     match field {
         "type" => {
@@ -186,6 +378,10 @@ pub fn get_field_value_with_time_format(
             .as_ref()
             .map(|p| p.to_string())
             .unwrap_or_default(),
+        "as_path_rel" => as_path_rel(elem).unwrap_or_default(),
+        "path_len" | "unique_path_len" | "prepend_count" => path_stat(elem, field)
+            .map(|value| value.to_string())
+            .unwrap_or_default(),
         "origin_asns" => elem
             .origin_asns
             .as_ref()
@@ -196,6 +392,13 @@ pub fn get_field_value_with_time_format(
                     .join(" ")
             })
             .unwrap_or_default(),
+        "rpki" => rpki_state(elem)
+            .map(|state| state.to_string())
+            .unwrap_or_default(),
+        "peer_name" | "origin_name" | "origin_country" | "origin_org" => as_info
+            .and_then(|info| info.field(field))
+            .unwrap_or_default()
+            .to_string(),
         "origin" => elem
             .origin
             .as_ref()
@@ -234,10 +437,24 @@ pub fn get_field_value_with_time_format(
             .map(|i| i.to_string())
             .unwrap_or_default(),
         "collector" => collector.unwrap_or("").to_string(),
+        "windows" => windows
+            .map(|w| w.labels_at(elem.timestamp).join(" "))
+            .unwrap_or_default(),
         _ => String::new(),
     }
 }
 
+/// AS path length field of `elem`, `None` without an AS path
+fn path_stat(elem: &BgpElem, field: &str) -> Option<u32> {
+    let stats = AsPathStats::new(elem.as_path.as_ref()?);
+    match field {
+        "path_len" => Some(stats.path_len),
+        "unique_path_len" => Some(stats.unique_path_len),
+        "prepend_count" => Some(stats.prepend_count),
+        _ => None,
+    }
+}
+
 /// Format a BgpElem according to the output format and selected fields.
 /// The `collector` parameter provides the collector value for the "collector" field.
 /// The `time_format` parameter controls how timestamps are displayed, including JSON
@@ -249,22 +466,24 @@ pub fn format_elem(
     output_format: OutputFormat,
     fields: &[&str],
     collector: Option<&str>,
+    windows: Option<&TimeWindows>,
     time_format: TimestampFormat,
 ) -> Option<String> {
     match output_format {
         OutputFormat::Json | OutputFormat::JsonLine => {
-            let obj = build_json_object(elem, fields, collector, time_format);
+            let obj = build_json_object(elem, fields, collector, windows, time_format);
             Some(serde_json::to_string(&obj).unwrap_or_else(|_| elem.to_string()))
         }
         OutputFormat::JsonPretty => {
-            let obj = build_json_object(elem, fields, collector, time_format);
+            let obj = build_json_object(elem, fields, collector, windows, time_format);
             Some(serde_json::to_string_pretty(&obj).unwrap_or_else(|_| elem.to_string()))
         }
         OutputFormat::Psv => {
             // Pipe-separated values (no header for backward compatibility)
+            let as_info = elem_as_info(elem, fields);
             let values: Vec<String> = fields
                 .iter()
-                .map(|f| get_field_value_with_time_format(elem, f, collector, time_format))
+                .map(|f| field_value(elem, f, collector, windows, time_format, as_info.as_ref()))
                 .collect();
             Some(values.join("|"))
         }
@@ -275,9 +494,10 @@ pub fn format_elem(
         }
         OutputFormat::Markdown => {
             // Markdown table row
+            let as_info = elem_as_info(elem, fields);
             let values: Vec<String> = fields
                 .iter()
-                .map(|f| get_field_value_with_time_format(elem, f, collector, time_format))
+                .map(|f| field_value(elem, f, collector, windows, time_format, as_info.as_ref()))
                 .collect();
             Some(format!("| {} |", values.join(" | ")))
         }
@@ -285,13 +505,15 @@ pub fn format_elem(
 }
 
 /// Build a JSON object with only the selected fields
-/// The `collector` parameter provides the collector value for the "collector" field.
+/// The `collector` parameter provides the collector value for the "collector" field,
+/// and `windows` the search time windows for the "windows" field.
 /// The `time_format` parameter controls the `timestamp` field representation:
 /// `Unix` (default) emits a numeric f64; `Rfc3339` emits a formatted string.
 pub fn build_json_object(
     elem: &BgpElem,
     fields: &[&str],
     collector: Option<&str>,
+    windows: Option<&TimeWindows>,
     time_format: TimestampFormat,
 ) -> serde_json::Value {
     // If all default parse fields are selected and no collector field, use the
@@ -299,19 +521,30 @@ pub fn build_json_object(
     // includes fields like `origin_asns`, `only_to_customer`, etc. that are not
     // listed in DEFAULT_FIELDS_PARSE). When RFC3339 timestamps are requested,
     // override just the `timestamp` key on top of the native serialization.
-    if fields == DEFAULT_FIELDS_PARSE && !fields.contains(&"collector") {
+    // A trailing `windows` field (added by `--window`) is appended the same way.
+    let native_fields = fields.strip_suffix(&["windows"]).unwrap_or(fields);
+    if native_fields == DEFAULT_FIELDS_PARSE {
         let mut obj = json!(elem);
-        if time_format != TimestampFormat::Unix {
-            if let Some(map) = obj.as_object_mut() {
+        if let Some(map) = obj.as_object_mut() {
+            if time_format != TimestampFormat::Unix {
                 map.insert(
                     "timestamp".to_string(),
                     json!(time_format.format_timestamp(elem.timestamp)),
                 );
             }
+            if native_fields.len() < fields.len() {
+                map.insert(
+                    "windows".to_string(),
+                    json!(windows
+                        .map(|w| w.labels_at(elem.timestamp))
+                        .unwrap_or_default()),
+                );
+            }
         }
         return obj;
     }
 
+    let as_info = elem_as_info(elem, fields);
     let mut obj = serde_json::Map::new();
     for field in fields {
         let value = match *field {
@@ -334,10 +567,28 @@ pub fn build_json_object(
                 Some(p) => json!(p.to_string()),
                 None => serde_json::Value::Null,
             },
+            "as_path_rel" => match as_path_rel(elem) {
+                Some(value) => json!(value),
+                None => serde_json::Value::Null,
+            },
+            "path_len" | "unique_path_len" | "prepend_count" => match path_stat(elem, field) {
+                Some(value) => json!(value),
+                None => serde_json::Value::Null,
+            },
             "origin_asns" => match &elem.origin_asns {
                 Some(asns) => json!(asns.iter().map(|asn| asn.to_string()).collect::<Vec<_>>()),
                 None => serde_json::Value::Null,
             },
+            "rpki" => match rpki_state(elem) {
+                Some(state) => json!(state.to_string()),
+                None => serde_json::Value::Null,
+            },
+            "peer_name" | "origin_name" | "origin_country" | "origin_org" => {
+                match as_info.as_ref().and_then(|info| info.field(field)) {
+                    Some(value) => json!(value),
+                    None => serde_json::Value::Null,
+                }
+            }
             "origin" => match &elem.origin {
                 Some(o) => json!(o.to_string()),
                 None => serde_json::Value::Null,
@@ -371,6 +622,10 @@ pub fn build_json_object(
                 Some(c) => json!(c),
                 None => serde_json::Value::Null,
             },
+            "windows" => match windows {
+                Some(w) => json!(w.labels_at(elem.timestamp)),
+                None => serde_json::Value::Null,
+            },
             _ => serde_json::Value::Null,
         };
         obj.insert((*field).to_string(), value);
@@ -401,56 +656,590 @@ pub fn get_header(output_format: OutputFormat, fields: &[&str]) -> Option<String
     }
 }
 
-/// Sort a collection of BgpElems by the specified field and direction.
-///
-/// This function sorts the elements in place, which is more efficient than
-/// creating a new vector.
-pub fn sort_elems(
-    elems: &mut [(BgpElem, Option<String>)],
-    order_by: OrderByField,
+/// Create the sorter for `--order-by`, keeping up to `memory_mb` MiB (at least
+/// 1) of elements in memory and spilling sorted runs below `spill_dir` beyond that.
+pub fn elem_sorter(
+    order_by: Vec<OrderByField>,
     direction: OrderDirection,
+    memory_mb: usize,
+    spill_dir: Option<PathBuf>,
+) -> ElemSorter {
+    let sorter = ElemSorter::new(
+        ElemOrder::new(order_by, direction),
+        memory_mb.max(1).saturating_mul(1024 * 1024),
+    );
+    match spill_dir {
+        Some(dir) => sorter.with_spill_dir(dir),
+        None => sorter,
+    }
+}
+
+/// Print the output of an [`ElemSorter`] in the given format.
+///
+/// Elements are streamed to stdout, except for Table format, which has to
+/// collect all rows to size its columns. Exits on read or write errors.
+pub fn print_sorted_elems(
+    sorter: ElemSorter,
+    output_format: OutputFormat,
+    fields: &[&str],
+    windows: &TimeWindows,
+    time_format: TimestampFormat,
 ) {
-    elems.sort_by(|(a, _), (b, _)| {
-        let cmp = match order_by {
-            OrderByField::Timestamp => a
-                .timestamp
-                .partial_cmp(&b.timestamp)
-                .unwrap_or(std::cmp::Ordering::Equal),
-            OrderByField::Prefix => a.prefix.to_string().cmp(&b.prefix.to_string()),
-            OrderByField::PeerIp => a.peer_ip.to_string().cmp(&b.peer_ip.to_string()),
-            OrderByField::PeerAsn => a.peer_asn.cmp(&b.peer_asn),
-            OrderByField::AsPath => {
-                let a_path = a
-                    .as_path
-                    .as_ref()
-                    .map(|p| p.to_string())
-                    .unwrap_or_default();
-                let b_path = b
-                    .as_path
-                    .as_ref()
-                    .map(|p| p.to_string())
-                    .unwrap_or_default();
-                a_path.cmp(&b_path)
+    if sorter.is_empty() {
+        return;
+    }
+    let sorted = match sorter.finish() {
+        Ok(sorted) => sorted,
+        Err(e) => {
+            eprintln!("ERROR: failed to sort output: {e}");
+            std::process::exit(1);
+        }
+    };
+    let sorted = sorted.map(|item| {
+        item.unwrap_or_else(|e| {
+            eprintln!("ERROR: failed to sort output: {e}");
+            std::process::exit(1);
+        })
+    });
+
+    if output_format == OutputFormat::Table {
+        let elems: Vec<(BgpElem, Option<String>)> = sorted.collect();
+        println!(
+            "{}",
+            format_elems_table(&elems, fields, Some(windows), time_format)
+        );
+        return;
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    let mut write_line = |line: String| {
+        if let Err(e) = writeln!(stdout, "{line}") {
+            if e.kind() != std::io::ErrorKind::BrokenPipe {
+                eprintln!("ERROR: {e}");
+            }
+            std::process::exit(1);
+        }
+    };
+    if let Some(header) = get_header(output_format, fields) {
+        write_line(header);
+    }
+    for (elem, collector) in sorted {
+        if let Some(line) = format_elem(
+            &elem,
+            output_format,
+            fields,
+            collector.as_deref(),
+            Some(windows),
+            time_format,
+        ) {
+            write_line(line);
+        }
+    }
+}
+
+/// Format a BMP peer up/down event.
+///
+/// JSON formats render the event as its own object, with `type` set to
+/// `peer_up` or `peer_down` so it stands apart from `A`/`W` elements. Other
+/// formats render a one-line summary, since the event has none of the
+/// element columns.
+pub fn format_bmp_event(
+    event: &BmpPeerEvent,
+    output_format: OutputFormat,
+    time_format: TimestampFormat,
+) -> String {
+    let timestamp = if time_format == TimestampFormat::Unix {
+        json!(event.timestamp)
+    } else {
+        json!(time_format.format_timestamp(event.timestamp))
+    };
+    match output_format {
+        OutputFormat::Json | OutputFormat::JsonLine | OutputFormat::JsonPretty => {
+            let mut obj = json!(event);
+            obj["timestamp"] = timestamp;
+            if output_format == OutputFormat::JsonPretty {
+                serde_json::to_string_pretty(&obj).unwrap_or_default()
+            } else {
+                obj.to_string()
+            }
+        }
+        _ => {
+            let mut line = format!(
+                "{} {} AS{} at {}",
+                event.event_type,
+                event.peer_ip,
+                event.peer_asn,
+                time_format.format_timestamp(event.timestamp)
+            );
+            if let Some(ref reason) = event.reason {
+                line.push_str(&format!(" ({reason})"));
+            }
+            line
+        }
+    }
+}
+
+/// Print a BMP peer event: to stdout as a record in JSON formats, and to
+/// stderr as a notice otherwise, so tabular element output stays intact
+pub fn print_bmp_event(
+    event: &BmpPeerEvent,
+    output_format: OutputFormat,
+    time_format: TimestampFormat,
+) {
+    let line = format_bmp_event(event, output_format, time_format);
+    match output_format {
+        OutputFormat::Json | OutputFormat::JsonLine | OutputFormat::JsonPretty => {
+            println!("{line}")
+        }
+        _ => eprintln!("{line}"),
+    }
+}
+
+/// Column names of aggregated output: the group-by fields followed by the counters
+pub fn aggregate_columns(group_by: &[GroupByField]) -> Vec<String> {
+    group_by
+        .iter()
+        .map(|f| f.to_string())
+        .chain(["count", "first_seen", "last_seen"].map(String::from))
+        .collect()
+}
+
+/// Format aggregated rows (from `--group-by`) in the given output format.
+/// Returns the lines to print; for Table format this is a single rendered table.
+pub fn format_aggregate_rows(
+    rows: &[AggregateRow],
+    group_by: &[GroupByField],
+    output_format: OutputFormat,
+    time_format: TimestampFormat,
+) -> Vec<String> {
+    let columns = aggregate_columns(group_by);
+    let text_values = |row: &AggregateRow| -> Vec<String> {
+        row.key
+            .iter()
+            .cloned()
+            .chain([
+                row.stats.count.to_string(),
+                time_format.format_timestamp(row.stats.first_seen),
+                time_format.format_timestamp(row.stats.last_seen),
+            ])
+            .collect()
+    };
+
+    match output_format {
+        OutputFormat::Json | OutputFormat::JsonLine | OutputFormat::JsonPretty => rows
+            .iter()
+            .map(|row| {
+                let obj = build_aggregate_json(row, group_by, time_format);
+                if output_format == OutputFormat::JsonPretty {
+                    serde_json::to_string_pretty(&obj).unwrap_or_default()
+                } else {
+                    serde_json::to_string(&obj).unwrap_or_default()
+                }
+            })
+            .collect(),
+        OutputFormat::Psv => rows.iter().map(|row| text_values(row).join("|")).collect(),
+        OutputFormat::Markdown => {
+            let column_refs: Vec<&str> = columns.iter().map(|c| c.as_str()).collect();
+            get_header(output_format, &column_refs)
+                .into_iter()
+                .chain(
+                    rows.iter()
+                        .map(|row| format!("| {} |", text_values(row).join(" | "))),
+                )
+                .collect()
+        }
+        OutputFormat::Table => {
+            let mut builder = Builder::default();
+            builder.push_record(columns);
+            for row in rows {
+                builder.push_record(text_values(row));
             }
-            OrderByField::NextHop => {
-                let a_hop = a
-                    .next_hop
-                    .as_ref()
-                    .map(|h| h.to_string())
-                    .unwrap_or_default();
-                let b_hop = b
-                    .next_hop
-                    .as_ref()
-                    .map(|h| h.to_string())
-                    .unwrap_or_default();
-                a_hop.cmp(&b_hop)
+            let mut table = builder.build();
+            table.with(Style::rounded());
+            vec![table.to_string()]
+        }
+    }
+}
+
+/// Build a JSON object for one aggregated row. Value types follow the element
+/// JSON output: numeric peer ASN, origin ASNs as a list, empty values as null.
+fn build_aggregate_json(
+    row: &AggregateRow,
+    group_by: &[GroupByField],
+    time_format: TimestampFormat,
+) -> serde_json::Value {
+    let mut obj = serde_json::Map::new();
+    for (field, value) in group_by.iter().zip(&row.key) {
+        let json_value = if value.is_empty() {
+            serde_json::Value::Null
+        } else {
+            match field {
+                GroupByField::PeerAsn => value
+                    .parse::<u32>()
+                    .map(|asn| json!(asn))
+                    .unwrap_or_else(|_| json!(value)),
+                GroupByField::OriginAsns => json!(value.split_whitespace().collect::<Vec<_>>()),
+                _ => json!(value),
             }
         };
-        match direction {
-            OrderDirection::Asc => cmp,
-            OrderDirection::Desc => cmp.reverse(),
+        obj.insert(field.to_string(), json_value);
+    }
+    obj.insert("count".to_string(), json!(row.stats.count));
+    for (name, ts) in [
+        ("first_seen", row.stats.first_seen),
+        ("last_seen", row.stats.last_seen),
+    ] {
+        let value = if time_format == TimestampFormat::Unix {
+            json!(ts)
+        } else {
+            json!(time_format.format_timestamp(ts))
+        };
+        obj.insert(name.to_string(), value);
+    }
+    serde_json::Value::Object(obj)
+}
+
+/// Maximum width of the sparkline column in histogram table output
+const SPARKLINE_WIDTH: usize = 60;
+
+/// Format a histogram time series in the given output format.
+///
+/// Table and Markdown show one row per series with its total, peak and a
+/// sparkline; PSV and JSON Lines emit one record per bucket and series; JSON
+/// emits the whole series object.
+pub fn format_histogram(
+    series: &HistogramSeries,
+    output_format: OutputFormat,
+    time_format: TimestampFormat,
+) -> Vec<String> {
+    let format_ts = |ts: i64| time_format.format_timestamp(ts as f64);
+    let json_ts = |ts: i64| {
+        if time_format == TimestampFormat::Unix {
+            json!(ts)
+        } else {
+            json!(format_ts(ts))
         }
-    });
+    };
+
+    match output_format {
+        OutputFormat::Json | OutputFormat::JsonPretty => {
+            let mut obj = json!(series);
+            obj["buckets"] = json!(series
+                .buckets
+                .iter()
+                .map(|ts| json_ts(*ts))
+                .collect::<Vec<_>>());
+            let out = if output_format == OutputFormat::JsonPretty {
+                serde_json::to_string_pretty(&obj)
+            } else {
+                serde_json::to_string(&obj)
+            };
+            vec![out.unwrap_or_default()]
+        }
+        OutputFormat::JsonLine => series
+            .buckets
+            .iter()
+            .enumerate()
+            .flat_map(|(i, ts)| {
+                series.series.iter().map(move |entry| {
+                    let mut obj = serde_json::Map::new();
+                    obj.insert("bucket_start".to_string(), json_ts(*ts));
+                    for (field, value) in series.split_by.iter().zip(&entry.key) {
+                        obj.insert(field.to_string(), json!(value));
+                    }
+                    obj.insert("count".to_string(), json!(entry.counts[i]));
+                    serde_json::Value::Object(obj).to_string()
+                })
+            })
+            .collect(),
+        OutputFormat::Psv => series
+            .buckets
+            .iter()
+            .enumerate()
+            .flat_map(|(i, ts)| {
+                series.series.iter().map(move |entry| {
+                    std::iter::once(format_ts(*ts))
+                        .chain(entry.key.iter().cloned())
+                        .chain(std::iter::once(entry.counts[i].to_string()))
+                        .collect::<Vec<_>>()
+                        .join("|")
+                })
+            })
+            .collect(),
+        OutputFormat::Table | OutputFormat::Markdown => {
+            let columns: Vec<String> = series
+                .split_by
+                .iter()
+                .map(|f| f.to_string())
+                .chain(["total", "peak", "sparkline"].map(String::from))
+                .collect();
+            let rows: Vec<Vec<String>> = series
+                .series
+                .iter()
+                .map(|entry| {
+                    entry
+                        .key
+                        .iter()
+                        .cloned()
+                        .chain([
+                            entry.total.to_string(),
+                            entry.counts.iter().max().copied().unwrap_or(0).to_string(),
+                            sparkline(&entry.counts, SPARKLINE_WIDTH),
+                        ])
+                        .collect()
+                })
+                .collect();
+            let mut lines = vec![format!(
+                "{} buckets of {}s from {} to {}",
+                series.buckets.len(),
+                series.bucket_secs,
+                series
+                    .buckets
+                    .first()
+                    .map(|ts| format_ts(*ts))
+                    .unwrap_or_default(),
+                series
+                    .buckets
+                    .last()
+                    .map(|ts| format_ts(ts + series.bucket_secs as i64))
+                    .unwrap_or_default(),
+            )];
+            if output_format == OutputFormat::Markdown {
+                let column_refs: Vec<&str> = columns.iter().map(|c| c.as_str()).collect();
+                lines.extend(get_header(output_format, &column_refs));
+                lines.extend(rows.iter().map(|row| format!("| {} |", row.join(" | "))));
+            } else {
+                let mut builder = Builder::default();
+                builder.push_record(columns);
+                for row in rows {
+                    builder.push_record(row);
+                }
+                let mut table = builder.build();
+                table.with(Style::rounded());
+                lines.push(table.to_string());
+            }
+            lines
+        }
+    }
+}
+
+/// Columns appended to the selected fields in `--dedup` output
+pub const DEDUP_COLUMNS: &[&str] = &["last_seen", "count", "peer_count", "collector_count"];
+
+/// Column names of `--dedup` output: the selected fields followed by the event counters
+pub fn dedup_columns<'a>(fields: &[&'a str]) -> Vec<&'a str> {
+    fields.iter().chain(DEDUP_COLUMNS).copied().collect()
+}
+
+/// Format deduplicated events in the given output format.
+///
+/// Each event is shown as its earliest observation with the selected fields,
+/// followed by [`DEDUP_COLUMNS`]. Returns one line per event, except for Table
+/// format which is a single rendered table; no Markdown header is included.
+/// `windows` provides the search time windows for the "windows" field.
+pub fn format_dedup_events(
+    events: &[DedupEvent],
+    output_format: OutputFormat,
+    fields: &[&str],
+    windows: Option<&TimeWindows>,
+    time_format: TimestampFormat,
+) -> Vec<String> {
+    let counters = |event: &DedupEvent| {
+        [
+            time_format.format_timestamp(event.last_seen),
+            event.count.to_string(),
+            event.peer_count().to_string(),
+            event.collector_count().to_string(),
+        ]
+    };
+    prefetch_as_info(events.iter().map(|event| &event.elem));
+    let row = |event: &DedupEvent| -> Vec<String> {
+        let as_info = elem_as_info(&event.elem, fields);
+        fields
+            .iter()
+            .map(|f| {
+                field_value(
+                    &event.elem,
+                    f,
+                    Some(&event.collector),
+                    windows,
+                    time_format,
+                    as_info.as_ref(),
+                )
+            })
+            .chain(counters(event))
+            .collect()
+    };
+
+    match output_format {
+        OutputFormat::Json | OutputFormat::JsonPretty | OutputFormat::JsonLine => events
+            .iter()
+            .map(|event| {
+                let mut obj = build_json_object(
+                    &event.elem,
+                    fields,
+                    Some(&event.collector),
+                    windows,
+                    time_format,
+                );
+                if let Some(map) = obj.as_object_mut() {
+                    map.insert(
+                        "last_seen".to_string(),
+                        if time_format == TimestampFormat::Unix {
+                            json!(event.last_seen)
+                        } else {
+                            json!(time_format.format_timestamp(event.last_seen))
+                        },
+                    );
+                    map.insert("count".to_string(), json!(event.count));
+                    map.insert("peer_count".to_string(), json!(event.peer_count()));
+                    map.insert(
+                        "collector_count".to_string(),
+                        json!(event.collector_count()),
+                    );
+                    map.insert("collectors".to_string(), json!(event.collectors));
+                }
+                let out = if output_format == OutputFormat::JsonPretty {
+                    serde_json::to_string_pretty(&obj)
+                } else {
+                    serde_json::to_string(&obj)
+                };
+                out.unwrap_or_default()
+            })
+            .collect(),
+        OutputFormat::Psv => events.iter().map(|event| row(event).join("|")).collect(),
+        OutputFormat::Markdown => events
+            .iter()
+            .map(|event| format!("| {} |", row(event).join(" | ")))
+            .collect(),
+        OutputFormat::Table => {
+            let mut builder = Builder::default();
+            builder.push_record(dedup_columns(fields));
+            for event in events {
+                builder.push_record(row(event));
+            }
+            let mut table = builder.build();
+            table.with(Style::rounded());
+            vec![table.to_string()]
+        }
+    }
+}
+
+/// Summary output that replaces individual elements: `--group-by` counts, a
+/// `--histogram` time series (split by the `--group-by` fields, if any) or
+/// `--dedup` events.
+#[derive(Debug)]
+pub enum ElemSummary {
+    Aggregate(ElemAggregator),
+    Histogram(TimeHistogram),
+    Dedup(ElemDeduplicator),
+}
+
+impl ElemSummary {
+    /// Build the summary requested on the command line, if any
+    pub fn from_args(
+        group_by: &[GroupByField],
+        histogram: Option<&str>,
+        dedup: Option<&str>,
+    ) -> Result<Option<Self>, String> {
+        if let Some(window) = dedup {
+            let window_secs = parse_dedup_window(window).map_err(|e| e.to_string())?;
+            return Ok(Some(Self::Dedup(ElemDeduplicator::new(window_secs))));
+        }
+        match histogram {
+            Some(width) => {
+                let bucket_secs = parse_bucket_width(width).map_err(|e| e.to_string())?;
+                Ok(Some(Self::Histogram(TimeHistogram::new(
+                    bucket_secs,
+                    group_by.to_vec(),
+                ))))
+            }
+            None if !group_by.is_empty() => Ok(Some(Self::Aggregate(ElemAggregator::new(
+                group_by.to_vec(),
+            )))),
+            None => Ok(None),
+        }
+    }
+
+    /// Restrict histogram buckets to `[start, end)`; no-op for group-by counts
+    pub fn with_range(self, start: i64, end: i64) -> Result<Self, String> {
+        match self {
+            Self::Histogram(histogram) => {
+                let histogram = histogram.with_range(start, end);
+                histogram.check_range().map_err(|e| e.to_string())?;
+                Ok(Self::Histogram(histogram))
+            }
+            summary => Ok(summary),
+        }
+    }
+
+    /// An empty summary with the same configuration, used for per-file partials
+    pub fn empty_like(&self) -> Self {
+        match self {
+            Self::Aggregate(aggregator) => {
+                Self::Aggregate(ElemAggregator::new(aggregator.fields().to_vec()))
+            }
+            Self::Histogram(histogram) => Self::Histogram(histogram.empty_like()),
+            Self::Dedup(dedup) => Self::Dedup(ElemDeduplicator::new(dedup.window_secs())),
+        }
+    }
+
+    pub fn add(&mut self, elem: &BgpElem, collector: Option<&str>) {
+        match self {
+            Self::Aggregate(aggregator) => aggregator.add(elem, collector),
+            Self::Histogram(histogram) => histogram.add(elem, collector),
+            Self::Dedup(dedup) => dedup.add(elem, collector),
+        }
+    }
+
+    /// Merge a partial summary created with [`ElemSummary::empty_like`]
+    pub fn merge(&mut self, other: ElemSummary) {
+        match (self, other) {
+            (Self::Aggregate(a), Self::Aggregate(b)) => a.merge(b),
+            (Self::Histogram(a), Self::Histogram(b)) => a.merge(b),
+            (Self::Dedup(a), Self::Dedup(b)) => a.merge(b),
+            _ => {}
+        }
+    }
+
+    /// Format the summary; nothing is printed for an empty group-by or dedup
+    /// result. `fields` selects the element fields shown for dedup events,
+    /// with `windows` providing the search time windows for the "windows" field.
+    pub fn format(
+        &self,
+        output_format: OutputFormat,
+        fields: &[&str],
+        windows: Option<&TimeWindows>,
+        time_format: TimestampFormat,
+    ) -> Result<Vec<String>, String> {
+        match self {
+            Self::Aggregate(aggregator) if aggregator.is_empty() => Ok(vec![]),
+            Self::Aggregate(aggregator) => Ok(format_aggregate_rows(
+                &aggregator.rows(),
+                aggregator.fields(),
+                output_format,
+                time_format,
+            )),
+            Self::Histogram(histogram) => {
+                let series = histogram.series().map_err(|e| e.to_string())?;
+                Ok(format_histogram(&series, output_format, time_format))
+            }
+            Self::Dedup(dedup) if dedup.is_empty() => Ok(vec![]),
+            Self::Dedup(dedup) => {
+                let mut lines: Vec<String> = get_header(output_format, &dedup_columns(fields))
+                    .into_iter()
+                    .collect();
+                lines.extend(format_dedup_events(
+                    &dedup.events(),
+                    output_format,
+                    fields,
+                    windows,
+                    time_format,
+                ));
+                Ok(lines)
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -495,7 +1284,7 @@ mod tests {
     fn test_json_timestamp_unix_is_numeric() {
         let elem = test_elem();
         let fields = vec!["timestamp", "prefix"];
-        let obj = build_json_object(&elem, &fields, None, TimestampFormat::Unix);
+        let obj = build_json_object(&elem, &fields, None, None, TimestampFormat::Unix);
         let ts = obj.get("timestamp").unwrap();
         assert_eq!(ts.as_f64().unwrap(), 1234567890.0);
     }
@@ -504,7 +1293,7 @@ mod tests {
     fn test_json_timestamp_rfc3339_is_string() {
         let elem = test_elem();
         let fields = vec!["timestamp", "prefix"];
-        let obj = build_json_object(&elem, &fields, None, TimestampFormat::Rfc3339);
+        let obj = build_json_object(&elem, &fields, None, None, TimestampFormat::Rfc3339);
         let ts = obj.get("timestamp").unwrap();
         assert!(ts.is_string(), "expected string for rfc3339 timestamp");
         let s = ts.as_str().unwrap();
@@ -522,6 +1311,7 @@ mod tests {
             OutputFormat::JsonLine,
             &fields,
             None,
+            None,
             TimestampFormat::Unix,
         )
         .unwrap();
@@ -530,6 +1320,7 @@ mod tests {
             OutputFormat::JsonLine,
             &fields,
             None,
+            None,
             TimestampFormat::Rfc3339,
         )
         .unwrap();
@@ -544,15 +1335,26 @@ mod tests {
     #[test]
     fn test_table_timestamp_rfc3339() {
         let elem = test_elem();
-        let value =
-            get_field_value_with_time_format(&elem, "timestamp", None, TimestampFormat::Rfc3339);
+        let value = get_field_value_with_time_format(
+            &elem,
+            "timestamp",
+            None,
+            None,
+            TimestampFormat::Rfc3339,
+        );
         assert!(value.contains('T'));
     }
 
     #[test]
     fn test_default_fields_unix_falls_back_to_native_serialization() {
         let elem = test_elem();
-        let obj = build_json_object(&elem, DEFAULT_FIELDS_PARSE, None, TimestampFormat::Unix);
+        let obj = build_json_object(
+            &elem,
+            DEFAULT_FIELDS_PARSE,
+            None,
+            None,
+            TimestampFormat::Unix,
+        );
         // With default fields, build_json_object should use native elem serialization
         // which contains fields NOT in DEFAULT_FIELDS_PARSE (e.g. `origin_asns`).
         // If the fallback broke, origin_asns would be absent from the output.
@@ -565,10 +1367,134 @@ mod tests {
         assert!(obj.get("timestamp").unwrap().is_number());
     }
 
+    #[test]
+    fn test_default_fields_with_windows_keep_native_serialization() {
+        let elem = test_elem();
+        let spec = format!("w={}/1h", elem.timestamp as i64).parse().unwrap();
+        let windows = TimeWindows::resolve(&[spec]).unwrap();
+        let mut fields = DEFAULT_FIELDS_PARSE.to_vec();
+        add_windows_field(&mut fields, &None, &windows);
+
+        let obj = build_json_object(&elem, &fields, None, Some(&windows), TimestampFormat::Unix);
+        assert!(obj.get("origin_asns").is_some());
+        assert_eq!(obj["windows"], json!(["w"]));
+    }
+
+    #[test]
+    fn test_format_aggregate_rows() {
+        let group_by = vec![GroupByField::PeerAsn, GroupByField::OriginAsns];
+        let mut aggregator = ElemAggregator::new(group_by.clone());
+        aggregator.add(&test_elem(), None);
+        aggregator.add(&test_elem(), None);
+        let rows = aggregator.rows();
+
+        let psv = format_aggregate_rows(&rows, &group_by, OutputFormat::Psv, TimestampFormat::Unix);
+        assert_eq!(psv, vec!["65000|65001|2|1234567890|1234567890"]);
+
+        let json =
+            format_aggregate_rows(&rows, &group_by, OutputFormat::Json, TimestampFormat::Unix);
+        let value: serde_json::Value = serde_json::from_str(&json[0]).unwrap();
+        assert_eq!(value["peer_asn"], 65000);
+        assert_eq!(value["origin_asns"], json!(["65001"]));
+        assert_eq!(value["count"], 2);
+
+        let markdown = format_aggregate_rows(
+            &rows,
+            &group_by,
+            OutputFormat::Markdown,
+            TimestampFormat::Unix,
+        );
+        assert!(markdown[0].starts_with("| peer_asn | origin_asns | count |"));
+    }
+
+    #[test]
+    fn test_format_dedup_events() {
+        let mut summary = ElemSummary::from_args(&[], None, Some("10s"))
+            .unwrap()
+            .unwrap();
+        let mut later = test_elem();
+        later.timestamp += 5.0;
+        summary.add(&test_elem(), Some("rrc00"));
+        summary.add(&later, Some("route-views2"));
+        let spec = format!("w={}/1h", test_elem().timestamp as i64)
+            .parse()
+            .unwrap();
+        let windows = TimeWindows::resolve(&[spec]).unwrap();
+
+        let psv = summary
+            .format(
+                OutputFormat::Psv,
+                &["prefix", "collector", "windows"],
+                Some(&windows),
+                TimestampFormat::Unix,
+            )
+            .unwrap();
+        assert_eq!(psv, vec!["10.0.0.0/8|rrc00|w|1234567895|2|1|2"]);
+
+        let markdown = summary
+            .format(
+                OutputFormat::Markdown,
+                &["prefix"],
+                None,
+                TimestampFormat::Unix,
+            )
+            .unwrap();
+        assert_eq!(
+            markdown[0],
+            "| prefix | last_seen | count | peer_count | collector_count |\n| --- | --- | --- | --- | --- |"
+        );
+
+        let json = summary
+            .format(
+                OutputFormat::JsonLine,
+                &["prefix", "windows"],
+                Some(&windows),
+                TimestampFormat::Unix,
+            )
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json[0]).unwrap();
+        assert_eq!(value["windows"], json!(["w"]));
+        assert_eq!(value["count"], 2);
+        assert_eq!(value["collectors"], json!(["route-views2", "rrc00"]));
+    }
+
+    #[test]
+    fn test_format_histogram() {
+        let mut summary = ElemSummary::from_args(&[GroupByField::Type], Some("1m"), None)
+            .unwrap()
+            .unwrap();
+        summary.add(&test_elem(), None);
+
+        let psv = summary
+            .format(OutputFormat::Psv, &[], None, TimestampFormat::Unix)
+            .unwrap();
+        assert_eq!(psv, vec!["1234567860|A|1"]);
+
+        let table = summary
+            .format(OutputFormat::Table, &[], None, TimestampFormat::Unix)
+            .unwrap();
+        assert_eq!(table[0], "1 buckets of 60s from 1234567860 to 1234567920");
+        assert!(table[1].contains('█'));
+
+        let json = summary
+            .format(OutputFormat::Json, &[], None, TimestampFormat::Rfc3339)
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json[0]).unwrap();
+        assert_eq!(value["bucket_secs"], 60);
+        assert!(value["buckets"][0].as_str().unwrap().contains('T'));
+        assert_eq!(value["series"][0]["counts"], json!([1]));
+    }
+
     #[test]
     fn test_default_fields_rfc3339_preserves_shape_overrides_timestamp() {
         let elem = test_elem();
-        let obj = build_json_object(&elem, DEFAULT_FIELDS_PARSE, None, TimestampFormat::Rfc3339);
+        let obj = build_json_object(
+            &elem,
+            DEFAULT_FIELDS_PARSE,
+            None,
+            None,
+            TimestampFormat::Rfc3339,
+        );
         // The full native shape should be preserved (origin_asns present)
         assert!(
             obj.get("origin_asns").is_some(),
@@ -1,10 +1,13 @@
 pub mod as2rel;
+pub mod bmp_listen;
 pub mod config;
 pub mod country;
 pub mod elem_format;
 pub mod inspect;
 pub mod ip;
 pub mod parse;
+pub mod parse_files;
+pub mod parse_stats;
 pub mod pfx2as;
 pub mod rib;
 pub mod rpki;
@@ -1,27 +1,49 @@
-use std::io::Write;
-use std::path::PathBuf;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
 
 use bgpkit_parser::encoder::{MrtRibEncoder, MrtUpdatesEncoder};
-use bgpkit_parser::parser::filter::Filterable;
 use bgpkit_parser::BgpElem;
 use clap::Args;
 
+use monocle::database::{ElemParquetStore, MsgStore, PARQUET_ROW_GROUP_SIZE};
+use monocle::lens::as2rel::PathRelThresholds;
+use monocle::lens::parse::bmp::{BmpReader, BmpRecord, BmpRecordFilter};
 use monocle::lens::parse::filter_file::{load_prefix_file, merge_prefix_file, FilterFile};
+use monocle::lens::parse::input::{InputFormat, ParseInput};
+use monocle::lens::parse::ris_live::{self, RisLiveClient, RisLiveElem, RisLiveReader};
+use monocle::lens::parse::sort::ElemSorter;
 use monocle::lens::parse::text_dump;
+use monocle::lens::parse::time_window::TimeWindows;
 use monocle::lens::parse::{MrtType, ParseFilters, ParseLens};
-use monocle::utils::{OrderByField, OrderDirection, OutputFormat, TimestampFormat};
+use monocle::lens::search::{is_remote, local_file_item, local_file_items};
+use monocle::utils::{GroupByField, OrderByField, OrderDirection, OutputFormat, TimestampFormat};
+use monocle::MonocleConfig;
 
 use super::elem_format::{
-    available_fields_help, format_elem, format_elems_table, get_header, parse_fields, sort_elems,
+    add_windows_field, available_fields_help, elem_sorter, enrich_msg_store, enrich_parquet_store,
+    format_elem, format_elems_table, get_header, load_elem_as_info, load_elem_as_path_rel,
+    load_elem_asinfo_filters, parse_fields, prefetch_as_info, print_bmp_event, print_sorted_elems,
+    ElemSummary,
 };
+use super::parse_files::{self, FilesOutput, ParseFilesOptions};
+use super::parse_stats::format_stats;
+use super::rpki::load_elem_rpki;
 
 /// Arguments for the Parse command
 #[derive(Args)]
 pub(crate) struct ParseArgs {
-    /// File path to an MRT file or Cisco `sh ip bgp` text dump, local or remote.
-    /// Format is auto-detected: binary MRT vs plaintext BGP table dump.
-    #[clap(name = "FILE")]
-    pub file_path: PathBuf,
+    /// File path to an MRT file, raw BMP capture, RIS Live JSON file or router table dump, local
+    /// or remote. Format is auto-detected: binary MRT, BMP stream, RIS Live JSON or a Cisco
+    /// `sh ip bgp`, BIRD `show route all`, FRR `show bgp ... json` or Junos `show route detail`
+    /// (text or JSON) table dump. `-` reads any of these from stdin, with gzip, bzip2, xz and
+    /// zstd compression detected from the content, and a `ws://` or `wss://` URL subscribes to a
+    /// RIS Live websocket.
+    ///
+    /// Several files in any of these formats, directories (read recursively) and glob patterns
+    /// (`*`, `?`, `[...]`, `**`) are parsed in parallel into one output, with the collector taken
+    /// from each path.
+    #[clap(name = "FILE", required = true)]
+    pub file_paths: Vec<PathBuf>,
 
     /// Pretty-print JSON output
     #[clap(long)]
@@ -31,6 +53,15 @@ pub(crate) struct ParseArgs {
     #[clap(long, short = 'M')]
     pub mrt_path: Option<PathBuf>,
 
+    /// Apache Parquet output file path; columns follow --fields with typed
+    /// timestamps, ASNs and prefix lengths
+    #[clap(long, value_name = "PATH", conflicts_with = "mrt_path")]
+    pub parquet_path: Option<PathBuf>,
+
+    /// SQLite output file path
+    #[clap(long, value_name = "PATH", conflicts_with_all = ["mrt_path", "parquet_path"])]
+    pub sqlite_path: Option<PathBuf>,
+
     /// MRT output subtype: `rib` for TABLE_DUMP_V2 RIB entries, `updates` for BGP4MP messages.
     /// Default: auto-inferred (`rib` for text dumps, `updates` for MRT files).
     #[clap(long, value_enum, value_name = "TYPE")]
@@ -40,45 +71,120 @@ pub(crate) struct ParseArgs {
     #[clap(long, short = 'f', value_name = "FIELDS", help = available_fields_help())]
     pub fields: Option<String>,
 
-    /// Order output by field (enables buffering)
-    #[clap(long, value_enum)]
-    pub order_by: Option<OrderByField>,
+    /// Order output by one or more fields, e.g. `--order-by prefix,timestamp`.
+    /// Spills sorted runs to temporary files beyond --sort-memory-mb
+    #[clap(long, value_enum, value_delimiter = ',')]
+    pub order_by: Vec<OrderByField>,
 
     /// Order direction (asc or desc, default: asc)
     #[clap(long, value_enum, default_value = "asc")]
     pub order: OrderDirection,
 
+    /// Memory budget in MiB for --order-by before spilling to disk
+    /// (default: `sort_memory_mb` from the config, 512)
+    #[clap(long, value_name = "MB", requires = "order_by")]
+    pub sort_memory_mb: Option<usize>,
+
+    /// Directory for --order-by spill files (default: system temporary directory)
+    #[clap(long, value_name = "DIR", requires = "order_by")]
+    pub sort_dir: Option<PathBuf>,
+
     /// Timestamp output format (unix or rfc3339), applied to all output formats including JSON
     #[clap(long, value_enum, default_value = "unix")]
     pub time_format: TimestampFormat,
 
+    #[clap(flatten)]
+    pub rel_thresholds: PathRelThresholds,
+
+    /// Output counts per group instead of elements, e.g. `--group-by origin_asns,type`.
+    /// Adds count, first_seen and last_seen columns
+    #[clap(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        conflicts_with_all = ["fields", "order_by", "mrt_path", "parquet_path", "sqlite_path"]
+    )]
+    pub group_by: Vec<GroupByField>,
+
+    /// Output element counts per time bucket of the given width (e.g. `5m`, `1h`, `300`)
+    /// instead of elements. Combine with --group-by to split the series
+    #[clap(
+        long,
+        value_name = "WIDTH",
+        conflicts_with_all = ["fields", "order_by", "mrt_path", "parquet_path", "sqlite_path"]
+    )]
+    pub histogram: Option<String>,
+
     /// Load filters from a JSON file (prefixes, origin_asns, peer_asns, etc.)
     /// Merged with CLI filter flags (AND across dimensions, union within each)
     #[clap(long, value_name = "PATH")]
     pub filter_file: Option<PathBuf>,
 
+    /// Apply a saved query from the `[queries.<NAME>]` config section
+    /// Merged like --filter-file; CLI flags and --filter-file win for single values
+    #[clap(long, value_name = "NAME")]
+    pub query: Option<String>,
+
     /// Load a newline-delimited list of prefixes from a file
     /// Lines starting with # and blank lines are ignored
     #[clap(long, value_name = "PATH")]
     pub prefix_file: Option<PathBuf>,
 
+    /// Stop after this many elements (0 = unlimited)
+    #[clap(long, value_name = "N")]
+    pub max_results: Option<u64>,
+
+    /// Number of files parsed in parallel (0 = auto/rayon default; overrides
+    /// the `search_concurrency` config)
+    #[clap(long)]
+    pub concurrency: Option<usize>,
+
+    /// With several files, output elements in global timestamp order by
+    /// merging the per-file element streams
+    #[clap(long, conflicts_with_all = ["order_by", "group_by", "histogram"])]
+    pub ordered: bool,
+
+    /// Report MRT record types, the peer index table, per-peer element counts, time span,
+    /// IPv4/IPv6 split, ADD-PATH usage and truncated or corrupt records with their byte
+    /// offsets instead of printing elements. Filters are not applied
+    #[clap(
+        long,
+        conflicts_with_all = [
+            "fields", "order_by", "group_by", "histogram", "mrt_path", "parquet_path",
+            "sqlite_path", "ordered", "max_results"
+        ]
+    )]
+    pub stats: bool,
+
     /// Filter by AS path regex string
     #[clap(flatten)]
     pub filters: ParseFilters,
 }
 
-pub fn run(args: ParseArgs, output_format: OutputFormat) {
+pub fn run(config: &MonocleConfig, args: ParseArgs, output_format: OutputFormat) {
     let ParseArgs {
-        file_path,
+        file_paths,
         pretty,
         mrt_path,
+        parquet_path,
+        sqlite_path,
         mrt_type,
         fields: fields_arg,
         order_by,
         order,
+        sort_memory_mb,
+        sort_dir,
         time_format,
+        rel_thresholds,
+        group_by,
+        histogram,
         filter_file,
+        query,
         prefix_file,
+        max_results,
+        concurrency,
+        ordered,
+        stats,
         mut filters,
     } = args;
 
@@ -89,6 +195,15 @@ pub fn run(args: ParseArgs, output_format: OutputFormat) {
             std::process::exit(1);
         }
     }
+    if let Some(ref name) = query {
+        if let Err(e) = config
+            .saved_query(name)
+            .and_then(|ff| ff.merge_into(&mut filters))
+        {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+    }
     if let Some(ref pf) = prefix_file {
         match load_prefix_file(pf) {
             Ok(prefixes) => merge_prefix_file(prefixes, &mut filters),
@@ -99,8 +214,19 @@ pub fn run(args: ParseArgs, output_format: OutputFormat) {
         }
     }
 
-    // Parse and validate fields (false = parse command, no collector in defaults)
-    let fields = match parse_fields(&fields_arg, false) {
+    let file_paths: Vec<String> = file_paths
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    // Several files, directories and glob patterns are parsed in parallel
+    let multi_file = file_paths.len() > 1
+        || file_paths.iter().any(|path| {
+            !is_remote(path) && (path.contains(['*', '?', '[']) || Path::new(path).is_dir())
+        });
+
+    // Parse and validate fields; the collector is only shown by default
+    // when parsing several files
+    let mut fields = match parse_fields(&fields_arg, multi_file) {
         Ok(f) => f,
         Err(e) => {
             eprintln!("ERROR: {}", e);
@@ -108,57 +234,142 @@ pub fn run(args: ParseArgs, output_format: OutputFormat) {
         }
     };
 
+    let mut summary = match ElemSummary::from_args(&group_by, histogram.as_deref(), None) {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("ERROR: {e}");
+            std::process::exit(1);
+        }
+    };
+
     let lens = ParseLens::new();
 
     if let Err(e) = lens.validate_filters(&filters) {
         eprintln!("ERROR: {e}");
         return;
     }
+    let windows = filters.time_windows().unwrap_or_default();
+    add_windows_field(&mut fields, &fields_arg, &windows);
 
-    let file_path_str = match file_path.to_str() {
-        Some(path) => path,
-        None => {
-            eprintln!("Invalid file path");
-            std::process::exit(1);
-        }
+    let output_format = if pretty && output_format == OutputFormat::Json {
+        OutputFormat::JsonPretty
+    } else {
+        output_format
     };
 
-    // ── Format detection ──────────────────────────────────────────
-    let reader = match oneio::get_reader(file_path_str) {
-        Ok(r) => r,
-        Err(e) => {
-            eprintln!("Failed to open {}: {}", file_path_str, e);
+    if stats {
+        let paths = if multi_file {
+            match local_file_items(&file_paths) {
+                Ok(items) => items.into_iter().map(|item| item.url).collect(),
+                Err(e) => {
+                    eprintln!("ERROR: {e}");
+                    std::process::exit(1);
+                }
+            }
+        } else {
+            file_paths
+        };
+        print_stats(&lens, &paths, output_format, time_format);
+        return;
+    }
+
+    if let Err(e) = load_elem_rpki(config, &mut filters, fields.contains(&"rpki")) {
+        eprintln!("ERROR: {e}");
+        std::process::exit(1);
+    }
+    if let Err(e) = load_elem_asinfo_filters(config, &mut filters) {
+        eprintln!("ERROR: {e}");
+        std::process::exit(1);
+    }
+    if let Err(e) = load_elem_as_info(config, &fields) {
+        eprintln!("ERROR: {e}");
+        std::process::exit(1);
+    }
+    if let Err(e) = load_elem_as_path_rel(config, &fields, rel_thresholds) {
+        eprintln!("ERROR: {e}");
+        std::process::exit(1);
+    }
+
+    if multi_file {
+        let result = files_output(
+            summary,
+            mrt_path.map(|path| (path, mrt_type.unwrap_or(MrtType::Updates))),
+            parquet_path.map(|path| (path, &fields, windows.clone())),
+            sqlite_path,
+            (!order_by.is_empty()).then(|| {
+                elem_sorter(
+                    order_by,
+                    order,
+                    sort_memory_mb.unwrap_or(config.sort_memory_mb),
+                    sort_dir,
+                )
+            }),
+        )
+        .and_then(|output| {
+            let options = ParseFilesOptions {
+                fields,
+                windows,
+                output_format,
+                time_format,
+                max_results,
+                concurrency: concurrency.unwrap_or(config.search_concurrency),
+                ordered,
+            };
+            parse_files::run(&file_paths, filters, output, options)
+        });
+        if let Err(e) = result {
+            eprintln!("ERROR: {e}");
             std::process::exit(1);
         }
-    };
+        return;
+    }
 
-    let (is_text, _peeked) = match text_dump::detect_text_dump(reader) {
-        Ok(result) => result,
-        Err(e) => {
-            eprintln!("Failed to read {}: {}", file_path_str, e);
-            std::process::exit(1);
+    let file_path_str = file_paths[0].as_str();
+    let limit = max_results
+        .filter(|max| *max > 0)
+        .map_or(usize::MAX, |max| max as usize);
+
+    // ── Format detection ──────────────────────────────────────────
+    // Inputs are opened once: stdin cannot be re-read after sniffing
+    let source = if ris_live::is_websocket_url(file_path_str) {
+        ElemSource::RisLiveWebsocket
+    } else {
+        match ParseInput::open(file_path_str) {
+            Ok(input) => ElemSource::Input(input),
+            Err(e) => {
+                eprintln!("Failed to open {}: {}", file_path_str, e);
+                std::process::exit(1);
+            }
         }
     };
+    let is_text = source.is_text();
 
     // Determine MRT type: explicit flag takes precedence, otherwise infer.
     let mrt_type = mrt_type.unwrap_or_else(|| MrtType::infer(is_text));
 
     // Preserve streaming output for ordinary MRT parsing. Text dumps and MRT
     // exports require materialization for their respective encoders/parsers.
-    let output_format = if pretty && output_format == OutputFormat::Json {
-        OutputFormat::JsonPretty
-    } else {
-        output_format
-    };
-    let needs_buffering = output_format == OutputFormat::Table || order_by.is_some();
-    if !is_text && mrt_path.is_none() && !needs_buffering {
-        let parser = match lens.create_parser(&filters, file_path_str) {
-            Ok(parser) => parser,
-            Err(error) => {
-                eprintln!("Failed to create parser for {}: {}", file_path_str, error);
-                std::process::exit(1);
-            }
-        };
+    if let (false, Some(summary)) = (is_text, summary.as_mut()) {
+        let parser =
+            open_elems(&filters, file_path_str, source, output_format, time_format).take(limit);
+        for elem in parser {
+            summary.add(&elem, None);
+        }
+        print_summary(summary, output_format, time_format);
+        return;
+    }
+
+    if let (false, Some(parquet_out_path)) = (is_text, parquet_path.as_ref()) {
+        let parser =
+            open_elems(&filters, file_path_str, source, output_format, time_format).take(limit);
+        write_parquet(parquet_out_path, &fields, windows, parser);
+        return;
+    }
+
+    let needs_buffering = output_format == OutputFormat::Table || !order_by.is_empty();
+    if !is_text && mrt_path.is_none() && sqlite_path.is_none() && !needs_buffering {
+        let parser =
+            open_elems(&filters, file_path_str, source, output_format, time_format).take(limit);
         let mut stdout = std::io::stdout();
         if let Some(header) = get_header(output_format, &fields) {
             if let Err(error) = writeln!(stdout, "{}", header) {
@@ -169,7 +380,14 @@ pub fn run(args: ParseArgs, output_format: OutputFormat) {
             }
         }
         for elem in parser {
-            if let Some(output) = format_elem(&elem, output_format, &fields, None, time_format) {
+            if let Some(output) = format_elem(
+                &elem,
+                output_format,
+                &fields,
+                None,
+                Some(&windows),
+                time_format,
+            ) {
                 if let Err(error) = writeln!(stdout, "{}", output) {
                     if error.kind() != std::io::ErrorKind::BrokenPipe {
                         eprintln!("ERROR: {error}");
@@ -182,51 +400,77 @@ pub fn run(args: ParseArgs, output_format: OutputFormat) {
     }
 
     // ── Parse ─────────────────────────────────────────────────────
-    let elems: Vec<(BgpElem, Option<String>)> = if is_text {
-        // Re-open the file for full parsing (detect_text_dump consumed the reader)
-        let reader = match oneio::get_reader(file_path_str) {
-            Ok(r) => r,
-            Err(e) => {
-                eprintln!("Failed to re-open {}: {}", file_path_str, e);
-                std::process::exit(1);
-            }
-        };
-        let buf_reader = std::io::BufReader::new(reader);
-        let text_filters = match filters.to_filters() {
-            Ok(filters) => filters,
-            Err(e) => {
-                eprintln!("Failed to build text dump filters: {e}");
-                std::process::exit(1);
-            }
-        };
-        let timestamp = text_dump::infer_timestamp_from_path(file_path_str).unwrap_or(0.0);
-        match text_dump::parse_text_dump_with_timestamp(buf_reader, timestamp) {
-            Ok(elems) => elems
-                .into_iter()
-                .filter(|elem| elem.match_filters(&text_filters))
-                .map(|elem| (elem, None))
-                .collect(),
-            Err(e) => {
-                eprintln!("Failed to parse text dump {}: {}", file_path_str, e);
+    let elems = open_elems(&filters, file_path_str, source, output_format, time_format).take(limit);
+
+    // ── Sorted text output ─────────────────────────────────────────
+    // Elements go through the external sorter instead of being collected,
+    // so sorting large files is bounded by --sort-memory-mb
+    if !order_by.is_empty()
+        && mrt_path.is_none()
+        && parquet_path.is_none()
+        && sqlite_path.is_none()
+        && summary.is_none()
+    {
+        let mut sorter = elem_sorter(
+            order_by,
+            order,
+            sort_memory_mb.unwrap_or(config.sort_memory_mb),
+            sort_dir,
+        );
+        for elem in elems {
+            if let Err(e) = sorter.push(elem, None) {
+                eprintln!("ERROR: failed to sort output: {e}");
                 std::process::exit(1);
             }
         }
-    } else {
-        // Standard MRT parse: re-open since detect_text_dump consumed the reader
-        let parser = match lens.create_parser(&filters, file_path_str) {
-            Ok(p) => p,
-            Err(e) => {
-                eprintln!("Failed to create parser for {}: {}", file_path_str, e);
-                std::process::exit(1);
-            }
-        };
-        parser.into_iter().map(|elem| (elem, None)).collect()
-    };
+        print_sorted_elems(sorter, output_format, &fields, &windows, time_format);
+        return;
+    }
+
+    let elems: Vec<(BgpElem, Option<String>)> = elems.map(|elem| (elem, None)).collect();
+
+    // ── Parquet output ─────────────────────────────────────────────
+    if let Some(ref parquet_out_path) = parquet_path {
+        write_parquet(
+            parquet_out_path,
+            &fields,
+            windows,
+            elems.into_iter().map(|(elem, _)| elem),
+        );
+        return;
+    }
+
+    // ── SQLite output ──────────────────────────────────────────────
+    if let Some(ref sqlite_out_path) = sqlite_path {
+        let collector = local_file_item(file_path_str).collector_id;
+        let elems: Vec<(BgpElem, String)> = elems
+            .into_iter()
+            .map(|(elem, _)| (elem, collector.clone()))
+            .collect();
+        eprintln!("writing SQLite to {}...", sqlite_out_path.display());
+        if let Err(e) = MsgStore::new(Some(&sqlite_out_path.to_string_lossy()), false)
+            .and_then(enrich_msg_store)
+            .and_then(|store| store.insert_elems(&elems))
+        {
+            eprintln!("Failed to write SQLite data: {}", e);
+            std::process::exit(1);
+        }
+        eprintln!("done. total of {} messages written", elems.len());
+        return;
+    }
 
     if elems.is_empty() {
         return;
     }
 
+    if let Some(summary) = summary.as_mut() {
+        for (elem, _) in &elems {
+            summary.add(elem, None);
+        }
+        print_summary(summary, output_format, time_format);
+        return;
+    }
+
     let mut stdout = std::io::stdout();
 
     // ── MRT output ─────────────────────────────────────────────────
@@ -286,15 +530,12 @@ pub fn run(args: ParseArgs, output_format: OutputFormat) {
 
     // ── Text output ────────────────────────────────────────────────
 
-    // Sort if ordering is requested
-    let mut elems = elems;
-    if let Some(order_field) = order_by {
-        sort_elems(&mut elems, order_field, order);
-    }
-
     // Output based on format
     if output_format == OutputFormat::Table {
-        println!("{}", format_elems_table(&elems, &fields, time_format));
+        println!(
+            "{}",
+            format_elems_table(&elems, &fields, Some(&windows), time_format)
+        );
     } else {
         // Print header for markdown format
         if let Some(header) = get_header(output_format, &fields) {
@@ -307,12 +548,14 @@ pub fn run(args: ParseArgs, output_format: OutputFormat) {
         }
 
         // Output elements
+        prefetch_as_info(elems.iter().map(|(elem, _)| elem));
         for (elem, collector) in &elems {
             if let Some(output_str) = format_elem(
                 elem,
                 output_format,
                 &fields,
                 collector.as_deref(),
+                Some(&windows),
                 time_format,
             ) {
                 if let Err(e) = writeln!(stdout, "{}", output_str) {
@@ -325,3 +568,264 @@ pub fn run(args: ParseArgs, output_format: OutputFormat) {
         }
     }
 }
+
+/// Build the output for parsing several files from the output arguments, in
+/// the order a single file's output is chosen
+fn files_output(
+    summary: Option<ElemSummary>,
+    mrt: Option<(PathBuf, MrtType)>,
+    parquet: Option<(PathBuf, &Vec<&'static str>, TimeWindows)>,
+    sqlite_path: Option<PathBuf>,
+    sorter: Option<ElemSorter>,
+) -> anyhow::Result<FilesOutput> {
+    if let Some(summary) = summary {
+        return Ok(FilesOutput::Summary(summary));
+    }
+    if let Some((path, mrt_type)) = mrt {
+        return Ok(FilesOutput::mrt(path, mrt_type));
+    }
+    if let Some((path, fields, windows)) = parquet {
+        let store =
+            enrich_parquet_store(ElemParquetStore::new(&path, fields)?.with_windows(windows));
+        eprintln!("writing Parquet to {}...", path.display());
+        return Ok(FilesOutput::Parquet(store));
+    }
+    if let Some(path) = sqlite_path {
+        eprintln!("writing SQLite to {}...", path.display());
+        return FilesOutput::sqlite(&path);
+    }
+    Ok(match sorter {
+        Some(sorter) => FilesOutput::Sorted(sorter),
+        None => FilesOutput::stdout(),
+    })
+}
+
+/// Where the elements of an input come from
+enum ElemSource {
+    /// File, URL or stdin, with its detected format
+    Input(ParseInput),
+    /// RIS Live websocket (`ws://` or `wss://` URL)
+    RisLiveWebsocket,
+}
+
+impl ElemSource {
+    fn is_text(&self) -> bool {
+        matches!(
+            self,
+            Self::Input(ParseInput {
+                format: InputFormat::TextDump(_),
+                ..
+            })
+        )
+    }
+}
+
+/// Open the elements of an input with `filters` applied, exiting if the input
+/// cannot be parsed. BMP peer up/down events are printed as they are read, and
+/// unparsable BMP or RIS Live messages are reported and skipped. Table dumps
+/// are parsed completely before the first element is returned.
+fn open_elems(
+    filters: &ParseFilters,
+    path: &str,
+    source: ElemSource,
+    output_format: OutputFormat,
+    time_format: TimestampFormat,
+) -> Box<dyn Iterator<Item = BgpElem> + Send> {
+    let input = match source {
+        ElemSource::Input(input) => input,
+        ElemSource::RisLiveWebsocket => {
+            return match RisLiveClient::connect(path, &ris_live::ris_subscribe(filters)) {
+                Ok(client) => filter_ris_live_elems(filters, client),
+                Err(e) => {
+                    eprintln!("ERROR: {}", e);
+                    std::process::exit(1);
+                }
+            };
+        }
+    };
+    let format = input.format;
+    let reader = input.into_reader();
+    match format {
+        InputFormat::Mrt => match filters.to_reader_elem_iter(reader) {
+            Ok(parser) => Box::new(parser),
+            Err(e) => {
+                eprintln!("Failed to create parser for {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        InputFormat::Bmp => open_bmp_elems(filters, path, reader, output_format, time_format),
+        InputFormat::RisLive => filter_ris_live_elems(filters, RisLiveReader::new(reader)),
+        InputFormat::TextDump(_) => open_text_dump_elems(filters, path, reader),
+    }
+}
+
+/// Parse a router table dump, see [`open_elems`]
+fn open_text_dump_elems(
+    filters: &ParseFilters,
+    path: &str,
+    reader: Box<dyn BufRead + Send>,
+) -> Box<dyn Iterator<Item = BgpElem> + Send> {
+    let elem_filter = match filters.to_elem_filter() {
+        Ok(elem_filter) => elem_filter,
+        Err(e) => {
+            eprintln!("Failed to build text dump filters: {e}");
+            std::process::exit(1);
+        }
+    };
+    let timestamp = text_dump::infer_timestamp_from_path(path).unwrap_or(0.0);
+    match text_dump::parse_text_dump_with_timestamp(reader, timestamp) {
+        Ok(elems) => Box::new(
+            elems
+                .into_iter()
+                .filter(move |elem| elem_filter.matches(elem)),
+        ),
+        Err(e) => {
+            eprintln!("Failed to parse text dump {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Apply `filters` to RIS Live elements, reporting and skipping bad messages
+fn filter_ris_live_elems(
+    filters: &ParseFilters,
+    elems: impl Iterator<Item = anyhow::Result<RisLiveElem>> + Send + 'static,
+) -> Box<dyn Iterator<Item = BgpElem> + Send> {
+    let elem_filter = match filters.to_elem_filter() {
+        Ok(elem_filter) => elem_filter,
+        Err(e) => {
+            eprintln!("Failed to build RIS Live filters: {e}");
+            std::process::exit(1);
+        }
+    };
+    Box::new(elems.filter_map(move |elem| match elem {
+        Ok((elem, _host)) => elem_filter.matches(&elem).then_some(elem),
+        Err(e) => {
+            eprintln!("WARNING: {}", e);
+            None
+        }
+    }))
+}
+
+/// Open a BMP file as elements, see [`open_elems`]
+fn open_bmp_elems(
+    filters: &ParseFilters,
+    path: &str,
+    reader: Box<dyn BufRead + Send>,
+    output_format: OutputFormat,
+    time_format: TimestampFormat,
+) -> Box<dyn Iterator<Item = BgpElem> + Send> {
+    let record_filter = match BmpRecordFilter::new(filters) {
+        Ok(record_filter) => record_filter,
+        Err(e) => {
+            eprintln!("Failed to create BMP reader for {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+    Box::new(
+        BmpReader::new(reader).filter_map(move |record| match record {
+            Ok(record) if !record_filter.matches(&record) => None,
+            Ok(BmpRecord::Elem(elem)) => Some(elem),
+            Ok(BmpRecord::PeerEvent(event)) => {
+                print_bmp_event(&event, output_format, time_format);
+                None
+            }
+            Err(e) => {
+                eprintln!("WARNING: {}", e);
+                None
+            }
+        }),
+    )
+}
+
+/// Write elements to a Parquet file, one row group per [`PARQUET_ROW_GROUP_SIZE`] elements
+fn write_parquet(
+    path: &Path,
+    fields: &[&str],
+    windows: TimeWindows,
+    elems: impl IntoIterator<Item = BgpElem>,
+) {
+    let mut store = match ElemParquetStore::new(path, fields) {
+        Ok(store) => enrich_parquet_store(store.with_windows(windows)),
+        Err(e) => {
+            eprintln!("Failed to create Parquet output: {}", e);
+            std::process::exit(1);
+        }
+    };
+    eprintln!("writing Parquet to {}...", path.display());
+
+    let mut batch: Vec<BgpElem> = Vec::with_capacity(PARQUET_ROW_GROUP_SIZE);
+    let mut elems = elems.into_iter().peekable();
+    while elems.peek().is_some() {
+        batch.extend(elems.by_ref().take(PARQUET_ROW_GROUP_SIZE));
+        if let Err(e) = store.write_elems(batch.iter().map(|elem| (elem, None))) {
+            eprintln!("Failed to write Parquet data: {}", e);
+            std::process::exit(1);
+        }
+        batch.clear();
+    }
+
+    match store.finish() {
+        Ok(rows) => eprintln!("done. total of {} messages written", rows),
+        Err(e) => {
+            eprintln!("Failed to write Parquet data: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print `--group-by` or `--histogram` results to stdout
+fn print_summary(summary: &ElemSummary, output_format: OutputFormat, time_format: TimestampFormat) {
+    let lines = match summary.format(output_format, &[], None, time_format) {
+        Ok(lines) => lines,
+        Err(e) => {
+            eprintln!("ERROR: {e}");
+            std::process::exit(1);
+        }
+    };
+    let mut stdout = std::io::stdout();
+    for line in lines {
+        if let Err(e) = writeln!(stdout, "{}", line) {
+            if e.kind() != std::io::ErrorKind::BrokenPipe {
+                eprintln!("ERROR: {e}");
+            }
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `--stats`: print the report of each file, continuing past unreadable ones
+fn print_stats(
+    lens: &ParseLens,
+    paths: &[String],
+    output_format: OutputFormat,
+    time_format: TimestampFormat,
+) {
+    let mut failed = false;
+    let mut stdout = std::io::stdout();
+    for (index, path) in paths.iter().enumerate() {
+        let stats = match lens.stats(path) {
+            Ok(stats) => stats,
+            Err(e) => {
+                eprintln!("ERROR: {path}: {e}");
+                failed = true;
+                continue;
+            }
+        };
+        let mut lines = format_stats(path, &stats, output_format, time_format);
+        if index > 0 && !output_format.is_json() {
+            lines.insert(0, String::new());
+        }
+        for line in lines {
+            if let Err(e) = writeln!(stdout, "{}", line) {
+                if e.kind() != std::io::ErrorKind::BrokenPipe {
+                    eprintln!("ERROR: {e}");
+                }
+                std::process::exit(1);
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
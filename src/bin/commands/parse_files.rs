@@ -0,0 +1,400 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use bgpkit_parser::encoder::{MrtRibEncoder, MrtUpdatesEncoder};
+use bgpkit_parser::BgpElem;
+use monocle::database::{ElemParquetStore, MsgStore};
+use monocle::lens::parse::input::is_stdin;
+use monocle::lens::parse::ris_live::is_websocket_url;
+use monocle::lens::parse::sort::ElemSorter;
+use monocle::lens::parse::time_window::TimeWindows;
+use monocle::lens::parse::{MrtType, ParseFilters};
+use monocle::lens::search::{
+    local_file_items, SearchControl, SearchDumpType, SearchElementBatch, SearchExecutionOptions,
+    SearchExitReason, SearchFilters, SearchLens, SearchProgress, SearchSink,
+};
+use monocle::utils::{OutputFormat, TimestampFormat};
+
+use super::elem_format::{
+    enrich_msg_store, format_elem, format_elems_table, get_header, prefetch_as_info,
+    print_sorted_elems, ElemSummary,
+};
+
+/// Elements written to SQLite output per transaction
+const SQLITE_WRITE_BATCH_SIZE: usize = 1_000;
+
+/// Where `monocle parse` writes the elements of several files
+pub(super) enum FilesOutput {
+    /// Print elements as they are parsed; table output is buffered
+    Stdout {
+        header_printed: bool,
+        table_rows: Vec<(BgpElem, Option<String>)>,
+    },
+    /// `--order-by`
+    Sorted(ElemSorter),
+    /// `--group-by` or `--histogram`
+    Summary(ElemSummary),
+    /// `--mrt-path`, encoded once all files are parsed
+    Mrt {
+        path: PathBuf,
+        mrt_type: MrtType,
+        rib: MrtRibEncoder,
+        updates: MrtUpdatesEncoder,
+        count: u64,
+    },
+    /// `--parquet-path`
+    Parquet(ElemParquetStore),
+    /// `--sqlite-path`
+    Sqlite {
+        store: MsgStore,
+        pending: Vec<(BgpElem, String)>,
+    },
+}
+
+impl FilesOutput {
+    pub(super) fn stdout() -> Self {
+        Self::Stdout {
+            header_printed: false,
+            table_rows: Vec::new(),
+        }
+    }
+
+    pub(super) fn mrt(path: PathBuf, mrt_type: MrtType) -> Self {
+        Self::Mrt {
+            path,
+            mrt_type,
+            rib: MrtRibEncoder::new(),
+            updates: MrtUpdatesEncoder::new(),
+            count: 0,
+        }
+    }
+
+    pub(super) fn sqlite(path: &std::path::Path) -> Result<Self> {
+        Ok(Self::Sqlite {
+            store: enrich_msg_store(MsgStore::new(Some(&path.to_string_lossy()), false)?)?,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Whether elements go to a file, with progress shown on stderr
+    fn is_file(&self) -> bool {
+        matches!(
+            self,
+            Self::Mrt { .. } | Self::Parquet(_) | Self::Sqlite { .. }
+        )
+    }
+}
+
+/// Options for parsing several files
+pub(super) struct ParseFilesOptions {
+    pub fields: Vec<&'static str>,
+    pub windows: TimeWindows,
+    pub output_format: OutputFormat,
+    pub time_format: TimestampFormat,
+    pub max_results: Option<u64>,
+    /// Files parsed in parallel; 0 uses rayon's default
+    pub concurrency: usize,
+    pub ordered: bool,
+}
+
+/// Receives the elements of all files from the search executor
+struct ParseFilesSink {
+    files: Vec<String>,
+    options: ParseFilesOptions,
+    output: Mutex<FilesOutput>,
+    /// First output error, which stops parsing
+    error: Mutex<Option<anyhow::Error>>,
+    /// Set once output stopped the executor; later file errors are not reported
+    stopped: AtomicBool,
+    progress: Option<indicatif::ProgressBar>,
+}
+
+impl ParseFilesSink {
+    fn stop(&self, error: Option<anyhow::Error>) -> SearchControl {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let (Some(error), Ok(mut slot)) = (error, self.error.lock()) {
+            slot.get_or_insert(error);
+        }
+        SearchControl::Stop
+    }
+
+    fn write(&self, output: &mut FilesOutput, batch: SearchElementBatch) -> Result<bool> {
+        let options = &self.options;
+        match output {
+            FilesOutput::Stdout {
+                header_printed,
+                table_rows,
+            } => {
+                if options.output_format == OutputFormat::Table {
+                    let collector = Some(batch.collector);
+                    table_rows.extend(
+                        batch
+                            .elements
+                            .into_iter()
+                            .map(|elem| (elem, collector.clone())),
+                    );
+                    return Ok(true);
+                }
+                let mut lines = Vec::with_capacity(batch.elements.len() + 1);
+                if !*header_printed {
+                    lines.extend(get_header(options.output_format, &options.fields));
+                    *header_printed = true;
+                }
+                prefetch_as_info(&batch.elements);
+                lines.extend(batch.elements.iter().filter_map(|elem| {
+                    format_elem(
+                        elem,
+                        options.output_format,
+                        &options.fields,
+                        Some(&batch.collector),
+                        Some(&options.windows),
+                        options.time_format,
+                    )
+                }));
+                let stdout = std::io::stdout();
+                let mut stdout = stdout.lock();
+                for line in lines {
+                    if let Err(e) = writeln!(stdout, "{line}") {
+                        if e.kind() == std::io::ErrorKind::BrokenPipe {
+                            return Ok(false);
+                        }
+                        return Err(e.into());
+                    }
+                }
+            }
+            FilesOutput::Sorted(sorter) => {
+                for elem in batch.elements {
+                    sorter.push(elem, Some(batch.collector.clone()))?;
+                }
+            }
+            FilesOutput::Summary(summary) => {
+                for elem in &batch.elements {
+                    summary.add(elem, Some(&batch.collector));
+                }
+            }
+            FilesOutput::Mrt {
+                mrt_type,
+                rib,
+                updates,
+                count,
+                ..
+            } => {
+                for elem in &batch.elements {
+                    match mrt_type {
+                        MrtType::Rib => rib.process_elem(elem),
+                        MrtType::Updates => updates.process_elem(elem),
+                    }
+                }
+                *count += batch.elements.len() as u64;
+            }
+            FilesOutput::Parquet(store) => store.write_elems(
+                batch
+                    .elements
+                    .iter()
+                    .map(|elem| (elem, Some(batch.collector.as_str()))),
+            )?,
+            FilesOutput::Sqlite { store, pending } => {
+                let collector = batch.collector;
+                pending.extend(
+                    batch
+                        .elements
+                        .into_iter()
+                        .map(|elem| (elem, collector.clone())),
+                );
+                if pending.len() >= SQLITE_WRITE_BATCH_SIZE {
+                    store.insert_elems(pending)?;
+                    pending.clear();
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl SearchSink for ParseFilesSink {
+    fn on_progress(&self, progress: SearchProgress) {
+        match progress {
+            SearchProgress::FileCompleted {
+                file_index,
+                success: false,
+                error: Some(error),
+                ..
+            } if !self.stopped.load(Ordering::Relaxed) => {
+                let file = self.files.get(file_index).map(String::as_str);
+                let message = format!("WARNING: {}: {}", file.unwrap_or_default(), error);
+                match &self.progress {
+                    Some(pb) => pb.println(message),
+                    None => eprintln!("{message}"),
+                }
+            }
+            SearchProgress::ProgressUpdate {
+                files_completed,
+                total_files,
+                total_messages,
+                ..
+            } => {
+                if let Some(pb) = &self.progress {
+                    pb.set_message(format!(
+                        "Processed {files_completed}/{total_files} files, found {total_messages} messages"
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_elements(&self, batch: SearchElementBatch) -> SearchControl {
+        let Ok(mut output) = self.output.lock() else {
+            return self.stop(Some(anyhow!("output lock poisoned")));
+        };
+        match self.write(&mut output, batch) {
+            Ok(true) => SearchControl::Continue,
+            Ok(false) => self.stop(None),
+            Err(e) => self.stop(Some(e)),
+        }
+    }
+}
+
+/// Parse all files matched by `paths` in parallel into one output
+pub(super) fn run(
+    paths: &[String],
+    filters: ParseFilters,
+    output: FilesOutput,
+    options: ParseFilesOptions,
+) -> Result<()> {
+    if let Some(path) = paths
+        .iter()
+        .find(|path| is_stdin(path) || is_websocket_url(path))
+    {
+        return Err(anyhow!("{} can only be parsed on its own", path));
+    }
+    let items = local_file_items(paths)?;
+    // Time filters apply to RIB dumps too, like for a single file
+    let filters = SearchFilters {
+        parse_filters: filters,
+        dump_type: SearchDumpType::RibUpdates,
+        ..Default::default()
+    };
+
+    let progress = output.is_file().then(|| {
+        let pb = indicatif::ProgressBar::new_spinner();
+        pb.set_message(format!(
+            "Processed 0/{} files, found 0 messages",
+            items.len()
+        ));
+        pb.enable_steady_tick(Duration::from_millis(100));
+        pb
+    });
+    let execution = SearchExecutionOptions {
+        concurrency: Some(options.concurrency),
+        max_results: options.max_results,
+        ordered: options.ordered,
+        detect_format: true,
+        ..Default::default()
+    };
+    let sink = Arc::new(ParseFilesSink {
+        files: items.iter().map(|item| item.url.clone()).collect(),
+        options,
+        output: Mutex::new(output),
+        error: Mutex::new(None),
+        stopped: AtomicBool::new(false),
+        progress,
+    });
+
+    let outcome = SearchLens::new().search_items(&filters, items, execution, sink.clone())?;
+    let sink = Arc::try_unwrap(sink).map_err(|_| anyhow!("parse output is still in use"))?;
+    if let Some(pb) = &sink.progress {
+        pb.finish_and_clear();
+    }
+    if let Some(error) = sink
+        .error
+        .into_inner()
+        .map_err(|_| anyhow!("output lock poisoned"))?
+    {
+        return Err(error);
+    }
+    if outcome.exit_reason == SearchExitReason::Cancelled {
+        // Stdout was closed
+        return Ok(());
+    }
+
+    let summary = &outcome.summary;
+    if summary.failed_files > 0 {
+        eprintln!(
+            "WARNING: {} of {} files could not be parsed",
+            summary.failed_files, summary.total_files
+        );
+    }
+
+    let options = sink.options;
+    match sink
+        .output
+        .into_inner()
+        .map_err(|_| anyhow!("output lock poisoned"))?
+    {
+        FilesOutput::Stdout { table_rows, .. } => {
+            if !table_rows.is_empty() {
+                println!(
+                    "{}",
+                    format_elems_table(
+                        &table_rows,
+                        &options.fields,
+                        Some(&options.windows),
+                        options.time_format
+                    )
+                );
+            }
+        }
+        FilesOutput::Sorted(sorter) => print_sorted_elems(
+            sorter,
+            options.output_format,
+            &options.fields,
+            &options.windows,
+            options.time_format,
+        ),
+        FilesOutput::Summary(summary) => {
+            let lines = summary
+                .format(options.output_format, &[], None, options.time_format)
+                .map_err(|e| anyhow!(e))?;
+            let mut stdout = std::io::stdout();
+            for line in lines {
+                if let Err(e) = writeln!(stdout, "{}", line) {
+                    if e.kind() == std::io::ErrorKind::BrokenPipe {
+                        break;
+                    }
+                    return Err(e.into());
+                }
+            }
+        }
+        FilesOutput::Mrt {
+            path,
+            mrt_type,
+            mut rib,
+            mut updates,
+            count,
+        } => {
+            eprintln!("writing MRT ({mrt_type:?}) to {}...", path.display());
+            let bytes = match mrt_type {
+                MrtType::Rib => rib.export_bytes(),
+                MrtType::Updates => updates.export_bytes(),
+            };
+            let mut writer = oneio::get_writer(&path.to_string_lossy())?;
+            writer.write_all(&bytes)?;
+            writer.flush()?;
+            eprintln!("done. total of {} messages written", count);
+        }
+        FilesOutput::Parquet(store) => {
+            let rows = store.finish()?;
+            eprintln!("done. total of {} messages written", rows);
+        }
+        FilesOutput::Sqlite { store, pending } => {
+            store.insert_elems(&pending)?;
+            eprintln!("done. total of {} messages written", summary.total_messages);
+        }
+    }
+    Ok(())
+}
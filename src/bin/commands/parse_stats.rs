@@ -0,0 +1,238 @@
+use monocle::lens::parse::stats::MrtStats;
+use monocle::utils::{OutputFormat, TimestampFormat};
+use serde_json::{json, Value};
+use tabled::builder::Builder;
+use tabled::settings::Style;
+
+/// One table of the report: a title, column names and rows
+struct Section {
+    name: &'static str,
+    title: String,
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<Value>>,
+}
+
+/// Format the `parse --stats` report of `file`
+pub(super) fn format_stats(
+    file: &str,
+    stats: &MrtStats,
+    output_format: OutputFormat,
+    time_format: TimestampFormat,
+) -> Vec<String> {
+    let timestamp = |ts: Option<f64>| match (ts, time_format) {
+        (None, _) => Value::Null,
+        (Some(ts), TimestampFormat::Unix) => json!(ts),
+        (Some(ts), _) => json!(time_format.format_timestamp(ts)),
+    };
+
+    match output_format {
+        OutputFormat::Json | OutputFormat::JsonPretty => {
+            let mut obj = json!(stats);
+            obj["file"] = json!(file);
+            obj["first_timestamp"] = timestamp(stats.first_timestamp);
+            obj["last_timestamp"] = timestamp(stats.last_timestamp);
+            let out = if output_format == OutputFormat::JsonPretty {
+                serde_json::to_string_pretty(&obj)
+            } else {
+                serde_json::to_string(&obj)
+            };
+            vec![out.unwrap_or_default()]
+        }
+        OutputFormat::JsonLine => sections(file, stats, &timestamp)
+            .into_iter()
+            .flat_map(|section| {
+                section.rows.into_iter().map(move |row| {
+                    let mut obj = serde_json::Map::new();
+                    obj.insert("section".to_string(), json!(section.name));
+                    for (column, value) in section.columns.iter().zip(row) {
+                        obj.insert(column.to_string(), value);
+                    }
+                    Value::Object(obj).to_string()
+                })
+            })
+            .collect(),
+        OutputFormat::Psv => {
+            let mut lines = Vec::new();
+            for section in sections(file, stats, &timestamp) {
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                lines.push(section.columns.join("|"));
+                lines.extend(
+                    section
+                        .rows
+                        .iter()
+                        .map(|row| row.iter().map(cell).collect::<Vec<_>>().join("|")),
+                );
+            }
+            lines
+        }
+        OutputFormat::Table | OutputFormat::Markdown => {
+            let mut lines = Vec::new();
+            for section in sections(file, stats, &timestamp) {
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                lines.push(if output_format == OutputFormat::Markdown {
+                    format!("### {}", section.title)
+                } else {
+                    section.title
+                });
+                let mut builder = Builder::default();
+                builder.push_record(section.columns.iter().copied());
+                for row in &section.rows {
+                    builder.push_record(row.iter().map(cell));
+                }
+                let mut table = builder.build();
+                if output_format == OutputFormat::Markdown {
+                    table.with(Style::markdown());
+                } else {
+                    table.with(Style::rounded());
+                }
+                lines.push(table.to_string());
+            }
+            lines
+        }
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn sections(
+    file: &str,
+    stats: &MrtStats,
+    timestamp: &dyn Fn(Option<f64>) -> Value,
+) -> Vec<Section> {
+    let mut summary = vec![
+        ("file", json!(file)),
+        ("compression", json!(stats.compression)),
+        ("bytes", json!(stats.bytes)),
+        ("records", json!(stats.records)),
+        ("elements", json!(stats.elements)),
+        ("announcements", json!(stats.announcements)),
+        ("withdrawals", json!(stats.withdrawals)),
+        ("ipv4_elements", json!(stats.ipv4_elements)),
+        ("ipv6_elements", json!(stats.ipv6_elements)),
+        ("add_path_records", json!(stats.add_path_records)),
+        ("add_path_elements", json!(stats.add_path_elements)),
+        ("first_timestamp", timestamp(stats.first_timestamp)),
+        ("last_timestamp", timestamp(stats.last_timestamp)),
+        ("duration_secs", json!(stats.duration())),
+        ("peers", json!(stats.peers.len())),
+        ("truncated_records", json!(stats.truncated_records())),
+        ("corrupt_records", json!(stats.corrupt_records())),
+    ];
+    if let Some(peer_index) = &stats.peer_index {
+        summary.push(("collector_bgp_id", json!(peer_index.collector_bgp_id)));
+        summary.push(("view_name", json!(peer_index.view_name)));
+    }
+
+    let mut sections = vec![
+        Section {
+            name: "summary",
+            title: "Summary".to_string(),
+            columns: vec!["field", "value"],
+            rows: summary
+                .into_iter()
+                .map(|(field, value)| vec![json!(field), value])
+                .collect(),
+        },
+        Section {
+            name: "record_type",
+            title: "Record types".to_string(),
+            columns: vec!["entry_type", "subtype", "records", "bytes"],
+            rows: stats
+                .record_types
+                .iter()
+                .map(|t| {
+                    vec![
+                        json!(t.entry_type),
+                        json!(t.subtype),
+                        json!(t.records),
+                        json!(t.bytes),
+                    ]
+                })
+                .collect(),
+        },
+    ];
+    if let Some(peer_index) = &stats.peer_index {
+        sections.push(Section {
+            name: "peer_index",
+            title: "Peer index table".to_string(),
+            columns: vec!["index", "peer_ip", "peer_asn", "peer_bgp_id"],
+            rows: peer_index
+                .peers
+                .iter()
+                .map(|p| {
+                    vec![
+                        json!(p.index),
+                        json!(p.peer_ip),
+                        json!(p.peer_asn),
+                        json!(p.peer_bgp_id),
+                    ]
+                })
+                .collect(),
+        });
+    }
+    if !stats.peers.is_empty() {
+        sections.push(Section {
+            name: "peer",
+            title: "Peers".to_string(),
+            columns: vec![
+                "peer_ip",
+                "peer_asn",
+                "elements",
+                "announcements",
+                "withdrawals",
+            ],
+            rows: stats
+                .peers
+                .iter()
+                .map(|p| {
+                    vec![
+                        json!(p.peer_ip),
+                        json!(p.peer_asn),
+                        json!(p.elements),
+                        json!(p.announcements),
+                        json!(p.withdrawals),
+                    ]
+                })
+                .collect(),
+        });
+    }
+    if !stats.issues.is_empty() {
+        sections.push(Section {
+            name: "issue",
+            title: "Truncated and corrupt records".to_string(),
+            columns: vec![
+                "offset",
+                "kind",
+                "entry_type",
+                "subtype",
+                "length",
+                "message",
+            ],
+            rows: stats
+                .issues
+                .iter()
+                .map(|i| {
+                    vec![
+                        json!(i.offset),
+                        json!(i.kind),
+                        json!(i.entry_type),
+                        json!(i.subtype),
+                        json!(i.length),
+                        json!(i.message),
+                    ]
+                })
+                .collect(),
+        });
+    }
+    sections
+}
@@ -3,7 +3,8 @@ use std::io::{BufWriter, Write};
 use std::path::Path;
 
 use anyhow::{anyhow, Result};
-use monocle::database::{MonocleDatabase, RibSqliteStore, StoredRibEntry};
+use clap::Args;
+use monocle::database::{MonocleDatabase, RibParquetStore, RibSqliteStore, StoredRibEntry};
 use monocle::lens::rib::RibLens;
 use monocle::utils::{OutputFormat, TimestampFormat};
 use monocle::MonocleConfig;
@@ -15,6 +16,18 @@ use super::elem_format::get_header;
 
 pub use monocle::lens::rib::RibArgs;
 
+/// Arguments for the Rib command
+#[derive(Args)]
+pub struct RibCommandArgs {
+    #[clap(flatten)]
+    pub args: RibArgs,
+
+    /// Apply a saved query from the `[queries.<NAME>]` config section
+    /// Merged with CLI filter flags; CLI flags win for single values
+    #[clap(long, value_name = "NAME")]
+    pub query: Option<String>,
+}
+
 const DEFAULT_FIELDS_RIB: &[&str] = &[
     "collector",
     "timestamp",
@@ -25,7 +38,12 @@ const DEFAULT_FIELDS_RIB: &[&str] = &[
     "origin_asns",
 ];
 
-pub fn run(config: &MonocleConfig, args: RibArgs, output_format: OutputFormat, no_update: bool) {
+pub fn run(
+    config: &MonocleConfig,
+    args: RibCommandArgs,
+    output_format: OutputFormat,
+    no_update: bool,
+) {
     if let Err(error) = run_inner(config, args, output_format, no_update) {
         eprintln!("ERROR: {}", error);
         std::process::exit(1);
@@ -34,10 +52,17 @@ pub fn run(config: &MonocleConfig, args: RibArgs, output_format: OutputFormat, n
 
 fn run_inner(
     config: &MonocleConfig,
-    args: RibArgs,
+    args: RibCommandArgs,
     output_format: OutputFormat,
     no_update: bool,
 ) -> Result<()> {
+    let RibCommandArgs { mut args, query } = args;
+    if let Some(name) = query {
+        config
+            .saved_query(&name)?
+            .merge_into_rib(&mut args.filters)?;
+    }
+
     let sqlite_path = config.sqlite_path();
     let db = MonocleDatabase::open(&sqlite_path)
         .map_err(|e| anyhow!("Failed to open database '{}': {}", sqlite_path, e))?;
@@ -45,6 +70,8 @@ fn run_inner(
 
     if args.sqlite_path.is_some() {
         run_sqlite_output(&lens, &args, no_update)
+    } else if args.parquet_path.is_some() {
+        run_parquet_output(&lens, &args, no_update)
     } else {
         run_stdout(&lens, &args, output_format, no_update)
     }
@@ -130,6 +157,34 @@ fn run_sqlite_output(lens: &RibLens<'_>, args: &RibArgs, no_update: bool) -> Res
     Ok(())
 }
 
+fn run_parquet_output(lens: &RibLens<'_>, args: &RibArgs, no_update: bool) -> Result<()> {
+    args.validate()?;
+    let output_path = args
+        .parquet_path
+        .as_deref()
+        .ok_or_else(|| anyhow!("Missing --parquet-path for Parquet output"))?;
+
+    remove_existing_file(output_path)?;
+    remove_existing_file(&RibParquetStore::updates_path(output_path))?;
+
+    let mut parquet_store = RibParquetStore::new(output_path)?;
+    let summary =
+        lens.reconstruct_snapshots(args, no_update, |rib_ts, state_store, filtered_updates| {
+            parquet_store.insert_snapshot(rib_ts, state_store, filtered_updates)
+        })?;
+    let updates_path = parquet_store.finish()?;
+
+    eprintln!(
+        "wrote {} reconstructed RIB snapshot(s) to {}",
+        summary.rib_ts.len(),
+        output_path.display()
+    );
+    if let Some(updates_path) = updates_path {
+        eprintln!("wrote contributing updates to {}", updates_path.display());
+    }
+    Ok(())
+}
+
 fn parse_fields(fields_arg: &Option<String>) -> Result<Vec<&'static str>> {
     match fields_arg {
         None => Ok(DEFAULT_FIELDS_RIB.to_vec()),
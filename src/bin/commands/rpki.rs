@@ -1,6 +1,7 @@
 use chrono::NaiveDate;
 use clap::Subcommand;
 use monocle::database::{MonocleDatabase, RpkiRoaRecord};
+use monocle::lens::parse::ParseFilters;
 use monocle::lens::rpki::commons::parse_historical_source;
 use monocle::lens::rpki::{
     HistoricalRpkiCollectorOption, RpkiAspaLookupArgs, RpkiAspaTableEntry, RpkiDataSource,
@@ -14,6 +15,8 @@ use tabled::settings::width::Width;
 use tabled::settings::Style;
 use tabled::Table;
 
+use super::elem_format::set_rpki_validator;
+
 #[derive(Subcommand)]
 pub enum RpkiCommands {
     /// validate a prefix-asn pair using cached RPKI data
@@ -213,6 +216,35 @@ fn ensure_rpki_cache(
     Ok(())
 }
 
+/// Load the ROAs for `--rpki` and the `rpki` output field, if either is used
+///
+/// Without `--rpki-date` the ROAs come from the RPKI cache, which is refreshed
+/// first when it is empty or outdated.
+pub(super) fn load_elem_rpki(
+    config: &MonocleConfig,
+    filters: &mut ParseFilters,
+    rpki_field: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if filters.rpki_filters.is_empty() && !rpki_field {
+        return Ok(());
+    }
+    let validator = if filters.rpki_filters.rpki_date.is_some() {
+        filters.load_rpki(None)?
+    } else {
+        let db = MonocleDatabase::open_in_dir(&config.data_dir)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+        ensure_rpki_cache(&RpkiLens::new(&db), false, config.rpki_cache_ttl())?;
+        filters.load_rpki(Some(&db))?
+    };
+    eprintln!(
+        "[monocle] Validating against {} ROAs from {}",
+        validator.roa_count(),
+        validator.source()
+    );
+    set_rpki_validator(validator.clone());
+    Ok(())
+}
+
 /// Ensure ASInfo data is available for enriching ASPA output
 fn ensure_asinfo_for_aspa(
     db: &MonocleDatabase,
@@ -432,43 +464,6 @@ fn parse_data_source(source: &str) -> Result<RpkiDataSource, String> {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_parse_rpkispools_data_source() {
-        assert!(matches!(
-            parse_data_source("rpkispools"),
-            Ok(RpkiDataSource::RpkiSpools)
-        ));
-    }
-
-    #[test]
-    fn test_parse_data_source_explains_cloudflare_is_current_only() {
-        let error = match parse_data_source("cloudflare") {
-            Err(error) => error,
-            Ok(_) => panic!("cloudflare is not historical"),
-        };
-        assert!(error.contains("current (undated) RPKI data"));
-    }
-
-    #[test]
-    fn test_parse_collector_lists_rpkiviews_only_massars() {
-        let error = match parse_collector("unknown") {
-            Err(error) => error,
-            Ok(_) => panic!("collector should be rejected"),
-        };
-        assert!(error.contains("massars"));
-    }
-
-    #[test]
-    fn test_reject_unknown_historical_source_and_collector() {
-        assert!(parse_data_source("unknown").is_err());
-        assert!(parse_collector("unknown").is_err());
-    }
-}
-
 fn parse_collector(collector: &str) -> Result<HistoricalRpkiCollectorOption, String> {
     match collector.to_lowercase().as_str() {
         "sobornost" => Ok(HistoricalRpkiCollectorOption::Sobornost),
@@ -1125,3 +1120,40 @@ fn output_aspas_entries(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rpkispools_data_source() {
+        assert!(matches!(
+            parse_data_source("rpkispools"),
+            Ok(RpkiDataSource::RpkiSpools)
+        ));
+    }
+
+    #[test]
+    fn test_parse_data_source_explains_cloudflare_is_current_only() {
+        let error = match parse_data_source("cloudflare") {
+            Err(error) => error,
+            Ok(_) => panic!("cloudflare is not historical"),
+        };
+        assert!(error.contains("current (undated) RPKI data"));
+    }
+
+    #[test]
+    fn test_parse_collector_lists_rpkiviews_only_massars() {
+        let error = match parse_collector("unknown") {
+            Err(error) => error,
+            Ok(_) => panic!("collector should be rejected"),
+        };
+        assert!(error.contains("massars"));
+    }
+
+    #[test]
+    fn test_reject_unknown_historical_source_and_collector() {
+        assert!(parse_data_source("unknown").is_err());
+        assert!(parse_collector("unknown").is_err());
+    }
+}
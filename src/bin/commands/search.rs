@@ -1,7 +1,9 @@
 use super::search_remote;
-pub use search_remote::{RemoteSearchFilters, RemoteSearchRequest};
+pub use search_remote::{
+    RemoteDedupRequest, RemoteHistogramRequest, RemoteSearchFilters, RemoteSearchRequest,
+};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver, Sender};
@@ -12,17 +14,26 @@ use std::time::Duration;
 use bgpkit_parser::encoder::MrtUpdatesEncoder;
 use bgpkit_parser::BgpElem;
 use clap::Args;
-use monocle::database::MsgStore;
+use monocle::database::{ElemParquetStore, MsgStore};
+use monocle::lens::as2rel::PathRelThresholds;
 use monocle::lens::parse::filter_file::{load_prefix_file, merge_prefix_file, FilterFile};
-use monocle::lens::search::SearchFilters;
-use monocle::utils::{OrderByField, OrderDirection, OutputFormat, TimestampFormat};
+use monocle::lens::parse::time_window::TimeWindows;
+use monocle::lens::search::{
+    broker_backend_from_config, is_remote, merge_broker_items, query_all, BrokerBackend,
+    BrokerQuery, SearchControl, SearchElementBatch, SearchExecutionOptions, SearchFilters,
+    SearchLens, SearchProgress, SearchSink, BROKER_PAGE_SIZE,
+};
+use monocle::utils::{GroupByField, OrderByField, OrderDirection, OutputFormat, TimestampFormat};
 use monocle::MonocleConfig;
 use rayon::prelude::*;
 use tracing::{info, warn};
 
 use super::elem_format::{
-    available_fields_help, format_elem, format_elems_table, get_header, parse_fields, sort_elems,
+    add_windows_field, available_fields_help, elem_sorter, enrich_msg_store, enrich_parquet_store,
+    format_elem, format_elems_table, get_header, load_elem_as_info, load_elem_as_path_rel,
+    load_elem_asinfo_filters, parse_fields, prefetch_as_info, print_sorted_elems, ElemSummary,
 };
+use super::rpki::load_elem_rpki;
 
 /// Arguments for the Search command
 #[derive(Args)]
@@ -39,10 +50,20 @@ pub struct SearchArgs {
     #[clap(long, short = 'M')]
     pub mrt_path: Option<PathBuf>,
 
+    /// Apache Parquet output file path; columns follow --fields with typed
+    /// timestamps, ASNs and prefix lengths
+    #[clap(long, value_name = "PATH")]
+    pub parquet_path: Option<PathBuf>,
+
     /// SQLite reset database content if exists
     #[clap(long)]
     pub sqlite_reset: bool,
 
+    /// Resume an interrupted search into --sqlite-path, skipping files already
+    /// recorded as completed in the database
+    #[clap(long, requires = "sqlite_path", conflicts_with = "sqlite_reset")]
+    pub resume: bool,
+
     /// Output matching broker files (URLs) and exit without searching
     #[clap(long)]
     pub broker_files: bool,
@@ -51,23 +72,95 @@ pub struct SearchArgs {
     #[clap(long, short = 'f', value_name = "FIELDS", help = available_fields_help())]
     pub fields: Option<String>,
 
-    /// Order output by field (enables buffering)
-    #[clap(long, value_enum)]
-    pub order_by: Option<OrderByField>,
+    /// Order output by one or more fields, e.g. `--order-by prefix,timestamp`.
+    /// Spills sorted runs to temporary files beyond --sort-memory-mb
+    #[clap(long, value_enum, value_delimiter = ',')]
+    pub order_by: Vec<OrderByField>,
 
     /// Order direction (asc or desc, default: asc)
     #[clap(long, value_enum, default_value = "asc")]
     pub order: OrderDirection,
 
+    /// Memory budget in MiB for --order-by before spilling to disk
+    /// (default: `sort_memory_mb` from the config, 512)
+    #[clap(long, value_name = "MB", requires = "order_by")]
+    pub sort_memory_mb: Option<usize>,
+
+    /// Directory for --order-by spill files (default: system temporary directory)
+    #[clap(long, value_name = "DIR", requires = "order_by")]
+    pub sort_dir: Option<PathBuf>,
+
     /// Timestamp output format (unix or rfc3339), applied to all output formats including JSON
     #[clap(long, value_enum, default_value = "unix")]
     pub time_format: TimestampFormat,
 
+    #[clap(flatten)]
+    pub rel_thresholds: PathRelThresholds,
+
+    /// Output counts per group instead of elements, e.g. `--group-by origin_asns,collector`.
+    /// Adds count, first_seen and last_seen columns
+    #[clap(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        conflicts_with_all = ["fields", "order_by", "sqlite_path", "mrt_path", "parquet_path"]
+    )]
+    pub group_by: Vec<GroupByField>,
+
+    /// Output element counts per time bucket of the given width (e.g. `5m`, `1h`, `300`)
+    /// instead of elements. Combine with --group-by to split the series
+    #[clap(
+        long,
+        value_name = "WIDTH",
+        conflicts_with_all = ["fields", "order_by", "sqlite_path", "mrt_path", "parquet_path"]
+    )]
+    pub histogram: Option<String>,
+
+    /// Collapse elements with the same prefix, AS path, communities and type
+    /// that are observed at most WINDOW apart (e.g. `0`, `30s`, `5m`) into one
+    /// event, with counts of observing peers and collectors
+    #[clap(
+        long,
+        value_name = "WINDOW",
+        conflicts_with_all = [
+            "group_by",
+            "histogram",
+            "order_by",
+            "sqlite_path",
+            "mrt_path",
+            "parquet_path"
+        ]
+    )]
+    pub dedup: Option<String>,
+
+    /// Stream output in global timestamp order by merging the per-file element
+    /// streams, with a bounded buffer per open file instead of buffering all results
+    #[clap(
+        long,
+        conflicts_with_all = [
+            "order_by",
+            "group_by",
+            "histogram",
+            "dedup",
+            "sqlite_path",
+            "mrt_path",
+            "parquet_path",
+            "use_cache",
+            "cache_dir"
+        ]
+    )]
+    pub ordered: bool,
+
     /// Load filters from a JSON file (prefixes, origin_asns, peer_asns, etc.)
     /// Merged with CLI filter flags (AND across dimensions, union within each)
     #[clap(long, value_name = "PATH")]
     pub filter_file: Option<PathBuf>,
 
+    /// Apply a saved query from the `[queries.<NAME>]` config section
+    /// Merged like --filter-file; CLI flags and --filter-file win for single values
+    #[clap(long, value_name = "NAME")]
+    pub query: Option<String>,
+
     /// Load a newline-delimited list of prefixes from a file
     /// Lines starting with # and blank lines are ignored
     #[clap(long, value_name = "PATH")]
@@ -111,13 +204,187 @@ const INITIAL_DELAY: u64 = 1;
 /// Maximum retry delay in seconds
 const MAX_DELAY: u64 = 30;
 
+/// Elements of files that are still being parsed, keyed by file URL
+///
+/// A file's elements reach the SQLite store only once the file is complete,
+/// in the same transaction as its checkpoint, so `--resume` never stores the
+/// elements of a file twice.
+#[derive(Default)]
+struct PendingFiles(HashMap<Arc<str>, Vec<(BgpElem, String)>>);
+
+impl PendingFiles {
+    fn push(&mut self, file_url: Arc<str>, elem: BgpElem, collector: String) {
+        self.0.entry(file_url).or_default().push((elem, collector));
+    }
+
+    /// Remove the elements of a completed file and store them in `sqlite_db`
+    /// along with the file's checkpoint
+    fn complete(
+        &mut self,
+        file_url: &Arc<str>,
+        sqlite_db: Option<&MsgStore>,
+    ) -> Vec<(BgpElem, String)> {
+        let file_elems = self.0.remove(file_url).unwrap_or_default();
+        if let Some(db) = sqlite_db {
+            if let Err(e) = db.insert_file_elems(file_url, &file_elems) {
+                eprintln!("Failed to insert elements to database: {}", e);
+            }
+        }
+        file_elems
+    }
+
+    /// Elements of files that never completed, which are not in the SQLite store
+    fn into_incomplete(self) -> Vec<(Arc<str>, Vec<(BgpElem, String)>)> {
+        self.0.into_iter().collect()
+    }
+}
+
+/// Append one completed file's elements to the Parquet output
+fn write_parquet_elems(
+    store: &mut ElemParquetStore,
+    elems: &[(BgpElem, String)],
+) -> Result<(), anyhow::Error> {
+    let rows = elems
+        .iter()
+        .map(|(elem, collector)| (elem, Some(collector.as_str())));
+    store.write_elems(rows)
+}
+
+/// Prints elements in the order the ordered search executor merges them
+///
+/// Table output still needs every row before printing, so it is buffered.
+struct OrderedOutputSink {
+    fields: Vec<&'static str>,
+    windows: TimeWindows,
+    output_format: OutputFormat,
+    time_format: TimestampFormat,
+    state: Mutex<OrderedOutputState>,
+}
+
+#[derive(Default)]
+struct OrderedOutputState {
+    header_printed: bool,
+    table_rows: Vec<(BgpElem, Option<String>)>,
+}
+
+impl OrderedOutputSink {
+    /// Print the buffered table, if any
+    fn finish(&self) {
+        let Ok(state) = self.state.lock() else {
+            return;
+        };
+        if self.output_format == OutputFormat::Table && !state.table_rows.is_empty() {
+            println!(
+                "{}",
+                format_elems_table(
+                    &state.table_rows,
+                    &self.fields,
+                    Some(&self.windows),
+                    self.time_format
+                )
+            );
+        }
+    }
+}
+
+impl SearchSink for OrderedOutputSink {
+    fn on_progress(&self, progress: SearchProgress) {
+        if let SearchProgress::FileCompleted {
+            error: Some(error),
+            success: false,
+            ..
+        } = progress
+        {
+            eprintln!("ERROR: {error}");
+        }
+    }
+
+    fn on_elements(&self, batch: SearchElementBatch) -> SearchControl {
+        let Ok(mut state) = self.state.lock() else {
+            return SearchControl::Stop;
+        };
+        if self.output_format == OutputFormat::Table {
+            let collector = Some(batch.collector);
+            state.table_rows.extend(
+                batch
+                    .elements
+                    .into_iter()
+                    .map(|elem| (elem, collector.clone())),
+            );
+            return SearchControl::Continue;
+        }
+
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        let mut lines = Vec::with_capacity(batch.elements.len() + 1);
+        if !state.header_printed {
+            lines.extend(get_header(self.output_format, &self.fields));
+            state.header_printed = true;
+        }
+        prefetch_as_info(&batch.elements);
+        lines.extend(batch.elements.iter().filter_map(|elem| {
+            format_elem(
+                elem,
+                self.output_format,
+                &self.fields,
+                Some(&batch.collector),
+                Some(&self.windows),
+                self.time_format,
+            )
+        }));
+        for line in lines {
+            if let Err(e) = writeln!(stdout, "{line}") {
+                if e.kind() != std::io::ErrorKind::BrokenPipe {
+                    eprintln!("ERROR: {e}");
+                }
+                return SearchControl::Stop;
+            }
+        }
+        SearchControl::Continue
+    }
+}
+
+/// Run a local search with `--ordered`, printing elements in global timestamp order
+fn run_ordered_search(
+    backend: Arc<dyn BrokerBackend>,
+    filters: &SearchFilters,
+    fields: Vec<&'static str>,
+    windows: TimeWindows,
+    output_format: OutputFormat,
+    time_format: TimestampFormat,
+) {
+    let sink = Arc::new(OrderedOutputSink {
+        fields,
+        windows,
+        output_format,
+        time_format,
+        state: Mutex::new(OrderedOutputState::default()),
+    });
+    let options = SearchExecutionOptions {
+        ordered: true,
+        ..Default::default()
+    };
+    if let Err(e) =
+        SearchLens::with_backend(backend).search_with_options(filters, options, sink.clone())
+    {
+        eprintln!("ERROR: {e}");
+        std::process::exit(1);
+    }
+    sink.finish();
+}
+
 /// Message types sent through the writer channel
+///
+/// Files are parsed in parallel, so elements carry the URL of the file they
+/// came from and are buffered per file until that file completes.
 #[derive(Debug)]
 enum WriterMessage {
-    /// BGP element with its collector ID
-    Element(Box<BgpElem>, String),
+    /// BGP element with its collector ID and source file URL
+    Element(Box<BgpElem>, String, Arc<str>),
+    /// Partial `--group-by` or `--histogram` summary of one file
+    Summary(Box<ElemSummary>),
     /// Signal that a file has been completely processed
-    FileComplete,
+    FileComplete(Arc<str>),
 }
 
 /// Progress update messages for real-time display
@@ -162,6 +429,54 @@ impl FailedItem {
     }
 }
 
+/// Sends the elements of one parsed file to the writer thread, followed by the
+/// file completion signal. With `--group-by` or `--histogram`, the file is
+/// summarized on the calling worker thread into a copy of `summary` and only
+/// that partial summary is sent.
+///
+/// Returns the number of elements read and whether the writer accepted all of them.
+fn send_file_elems(
+    sender: &Sender<WriterMessage>,
+    parser: impl IntoIterator<Item = BgpElem>,
+    collector: &str,
+    file_url: Arc<str>,
+    summary: Option<&ElemSummary>,
+) -> (u32, bool) {
+    let mut elems_count = 0;
+    if let Some(summary) = summary {
+        let mut partial = summary.empty_like();
+        for elem in parser {
+            partial.add(&elem, Some(collector));
+            elems_count += 1;
+        }
+        if sender
+            .send(WriterMessage::Summary(Box::new(partial)))
+            .is_err()
+        {
+            return (elems_count, false);
+        }
+    } else {
+        for elem in parser {
+            if sender
+                .send(WriterMessage::Element(
+                    Box::new(elem),
+                    collector.to_string(),
+                    file_url.clone(),
+                ))
+                .is_err()
+            {
+                // Channel closed, break out
+                return (elems_count, false);
+            }
+            elems_count += 1;
+        }
+    }
+
+    // Send file completion signal to trigger per-file commit
+    let sent = sender.send(WriterMessage::FileComplete(file_url)).is_ok();
+    (elems_count, sent)
+}
+
 /// Constructs the local cache path for a given URL and collector.
 ///
 /// The path structure is: {cache_dir}/{collector}/{path_after_domain}
@@ -467,23 +782,16 @@ mod broker_cache {
 /// Fetches broker items, using cache if available and appropriate.
 /// Returns (items, used_cache) tuple.
 fn fetch_broker_items_cached(
-    filters: &SearchFilters,
+    backend: &dyn BrokerBackend,
+    query: &BrokerQuery,
     cache_dir: Option<&Path>,
 ) -> Result<(Vec<bgpkit_broker::BrokerItem>, bool), String> {
-    // Get time range from filters
-    let (ts_start, ts_end) = filters
-        .parse_filters
-        .parse_start_end_strings()
-        .map_err(|e| format!("Failed to parse time range: {}", e))?;
+    let (ts_start, ts_end) = (query.ts_start, query.ts_end);
 
     // Extract filter parameters
-    let collector = filters.collector.as_deref();
-    let project = filters.project.as_deref();
-    let data_type = match filters.dump_type {
-        monocle::lens::search::SearchDumpType::Updates => Some("updates"),
-        monocle::lens::search::SearchDumpType::Rib => Some("rib"),
-        monocle::lens::search::SearchDumpType::RibUpdates => None,
-    };
+    let collector = query.collector.as_deref();
+    let project = query.project.as_deref();
+    let data_type = query.data_type.as_deref();
 
     // Check if we should use cache
     if let Some(cache_dir) = cache_dir {
@@ -515,12 +823,8 @@ fn fetch_broker_items_cached(
     }
 
     // Query broker API
-    let broker = filters
-        .build_broker()
-        .map_err(|e| format!("Failed to build broker: {}", e))?;
-
-    let items = broker
-        .query()
+    let items = backend
+        .query(query)
         .map_err(|e| format!("Failed to query broker: {}", e))?;
 
     // Store in cache if appropriate
@@ -557,6 +861,25 @@ fn fetch_broker_items_cached(
     Ok((items, false))
 }
 
+/// Fetches the broker items of several queries (one per group of search time
+/// windows), using the cache per query. Files listed by more than one query
+/// are only returned once. Returns (items, used_cache) with `used_cache` set
+/// when every query was answered from the cache.
+fn fetch_broker_plan_cached(
+    backend: &dyn BrokerBackend,
+    queries: &[BrokerQuery],
+    cache_dir: Option<&Path>,
+) -> Result<(Vec<bgpkit_broker::BrokerItem>, bool), String> {
+    let mut listings = Vec::with_capacity(queries.len());
+    let mut all_cached = true;
+    for query in queries {
+        let (items, used_cache) = fetch_broker_items_cached(backend, query, cache_dir)?;
+        listings.push(items);
+        all_cached &= used_cache;
+    }
+    Ok((merge_broker_items(listings), all_cached))
+}
+
 pub fn run(config: &MonocleConfig, args: SearchArgs, output_format: OutputFormat) {
     let concurrency = args.concurrency.unwrap_or(config.search_concurrency);
     if concurrency > 0 {
@@ -580,13 +903,23 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
         dry_run,
         sqlite_path,
         mrt_path,
+        parquet_path,
         sqlite_reset,
+        resume,
         broker_files,
         fields: fields_arg,
         order_by,
         order,
+        sort_memory_mb,
+        sort_dir,
         time_format,
+        rel_thresholds,
+        group_by,
+        histogram,
+        dedup,
+        ordered,
         filter_file,
+        query,
         prefix_file,
         use_cache,
         cache_dir,
@@ -598,8 +931,15 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
 
     // Load and merge file-based filters into CLI filters
     if let Some(ref pf) = filter_file {
-        if let Err(e) =
-            FilterFile::load(pf).and_then(|ff| ff.merge_into(&mut filters.parse_filters))
+        if let Err(e) = FilterFile::load(pf).and_then(|ff| ff.merge_into_search(&mut filters)) {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(ref name) = query {
+        if let Err(e) = config
+            .saved_query(name)
+            .and_then(|ff| ff.merge_into_search(&mut filters))
         {
             eprintln!("ERROR: {}", e);
             std::process::exit(1);
@@ -631,10 +971,50 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
             eprintln!("ERROR: {e}");
             return;
         }
+        if !group_by.is_empty() && histogram.is_none() {
+            eprintln!(
+                "ERROR: --group-by is only supported with --remote-url together with --histogram"
+            );
+            std::process::exit(1);
+        }
+        let histogram = histogram.map(|bucket_width| RemoteHistogramRequest {
+            bucket_width,
+            split_by: group_by,
+        });
+        // The server applies --rpki and the country and organization filters;
+        // ROAs are only loaded here for the rpki field
+        let remote_fields: Vec<&str> = fields_arg
+            .as_deref()
+            .map(|fields| fields.split(',').map(str::trim).collect())
+            .unwrap_or_default();
+        if let Err(e) = load_elem_as_info(config, &remote_fields) {
+            eprintln!("ERROR: {e}");
+            std::process::exit(1);
+        }
+        if let Err(e) = load_elem_as_path_rel(config, &remote_fields, rel_thresholds) {
+            eprintln!("ERROR: {e}");
+            std::process::exit(1);
+        }
+        if remote_fields.contains(&"rpki") {
+            let mut parse_filters = filters.parse_filters.clone();
+            parse_filters.rpki_filters.rpki.clear();
+            if let Err(e) = load_elem_rpki(config, &mut parse_filters, true) {
+                eprintln!("ERROR: {e}");
+                std::process::exit(1);
+            }
+        }
+        let request = RemoteSearchRequest {
+            filters: remote_search_filters(&filters),
+            batch_size: None,
+            max_results: None,
+            histogram,
+            dedup: dedup.map(|window| RemoteDedupRequest { window }),
+            ordered,
+        };
         run_remote_search_wrapper(
             &url,
             remote_token.as_deref(),
-            &filters,
+            request,
             fields_arg.as_deref(),
             output_format,
             time_format,
@@ -642,13 +1022,21 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
         return;
     }
 
+    let backend = match broker_backend_from_config(config) {
+        Ok(backend) => backend,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     let cache_dir = match cache_dir {
         Some(cache_dir) => Some(cache_dir),
         None => use_cache.then(|| PathBuf::from(config.cache_dir())),
     };
 
     // Parse and validate fields (true = search command, include collector in defaults)
-    let fields = match parse_fields(&fields_arg, true) {
+    let mut fields = match parse_fields(&fields_arg, true) {
         Ok(f) => f,
         Err(e) => {
             eprintln!("ERROR: {}", e);
@@ -656,10 +1044,42 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
         }
     };
 
+    let summary = match ElemSummary::from_args(&group_by, histogram.as_deref(), dedup.as_deref()) {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     if let Err(e) = filters.validate() {
         eprintln!("ERROR: {e}");
         return;
     }
+    let windows = match filters.time_windows() {
+        Ok(windows) => windows,
+        Err(e) => {
+            eprintln!("ERROR: {e}");
+            std::process::exit(1);
+        }
+    };
+    add_windows_field(&mut fields, &fields_arg, &windows);
+    if let Err(e) = load_elem_rpki(config, &mut filters.parse_filters, fields.contains(&"rpki")) {
+        eprintln!("ERROR: {e}");
+        std::process::exit(1);
+    }
+    if let Err(e) = load_elem_asinfo_filters(config, &mut filters.parse_filters) {
+        eprintln!("ERROR: {e}");
+        std::process::exit(1);
+    }
+    if let Err(e) = load_elem_as_info(config, &fields) {
+        eprintln!("ERROR: {e}");
+        std::process::exit(1);
+    }
+    if let Err(e) = load_elem_as_path_rel(config, &fields, rel_thresholds) {
+        eprintln!("ERROR: {e}");
+        std::process::exit(1);
+    }
 
     // Validate cache directory access upfront if caching is enabled
     if let Some(ref cache_dir) = cache_dir {
@@ -673,7 +1093,9 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
     let sqlite_db = sqlite_path.and_then(|p| {
         p.to_str().map(|s| {
             sqlite_path_str = s.to_string();
-            match MsgStore::new_from_option(&Some(sqlite_path_str.clone()), sqlite_reset) {
+            match MsgStore::new_from_option(&Some(sqlite_path_str.clone()), sqlite_reset)
+                .and_then(enrich_msg_store)
+            {
                 Ok(store) => store,
                 Err(e) => {
                     eprintln!("Failed to create SQLite store: {}", e);
@@ -682,22 +1104,67 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
             }
         })
     });
+    // Files already stored by an earlier run of the same search
+    let completed_files: HashSet<String> = match (&sqlite_db, resume) {
+        (Some(db), true) => match db.verified_completed_files() {
+            Ok(files) => {
+                eprintln!(
+                    "resuming search: {} files already completed in {}",
+                    files.len(),
+                    sqlite_path_str
+                );
+                files.into_keys().collect()
+            }
+            Err(e) => {
+                eprintln!("Failed to read search checkpoints: {}", e);
+                std::process::exit(1);
+            }
+        },
+        _ => HashSet::new(),
+    };
     let mrt_path = mrt_path.and_then(|p| p.to_str().map(|s| s.to_string()));
-    let show_progress = sqlite_db.is_some() || mrt_path.is_some();
-
-    // Create base broker for pagination
-    let base_broker = match filters.build_broker() {
-        Ok(broker) => broker,
+    let parquet_store = parquet_path
+        .as_ref()
+        .map(|p| match ElemParquetStore::new(p, &fields) {
+            Ok(store) => enrich_parquet_store(store.with_windows(windows.clone())),
+            Err(e) => {
+                eprintln!("Failed to create Parquet output: {}", e);
+                std::process::exit(1);
+            }
+        });
+    let output_paths: Vec<String> = [
+        Some(sqlite_path_str.clone()).filter(|p| !p.is_empty()),
+        mrt_path.clone(),
+        parquet_path.map(|p| p.display().to_string()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let show_progress = sqlite_db.is_some() || mrt_path.is_some() || parquet_store.is_some();
+
+    // Build the file listing queries used for pagination: one per group of
+    // overlapping time windows, or a single query for the whole search window
+    let broker_query = match filters.to_broker_query() {
+        Ok(query) => query,
         Err(e) => {
-            eprintln!("Failed to create broker: {}", e);
+            eprintln!("Failed to create broker query: {}", e);
             std::process::exit(1);
         }
     };
+    let broker_queries = match filters.to_broker_queries() {
+        Ok(queries) => queries,
+        Err(e) => {
+            eprintln!("Failed to create broker query: {}", e);
+            std::process::exit(1);
+        }
+    };
+    // The broker cache is only used for backends that opt in
+    let broker_cache_dir = cache_dir.as_deref().filter(|_| backend.cache_results());
 
     if broker_files {
         // Output all matching broker files and exit without searching
-        let (items, used_cache) = if cache_dir.is_some() {
-            match fetch_broker_items_cached(&filters, cache_dir.as_deref()) {
+        let (items, used_cache) = if broker_cache_dir.is_some() {
+            match fetch_broker_plan_cached(backend.as_ref(), &broker_queries, broker_cache_dir) {
                 Ok(result) => result,
                 Err(e) => {
                     eprintln!("Failed to query broker: {}", e);
@@ -706,7 +1173,7 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
             }
         } else {
             // Cache disabled, query broker directly
-            match base_broker.query() {
+            match query_all(backend.as_ref(), &broker_queries) {
                 Ok(items) => (items, false),
                 Err(e) => {
                     eprintln!("Failed to query broker: {}", e);
@@ -747,7 +1214,7 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
 
     if dry_run {
         // For dry run, get first page to show what would be processed
-        let items = match base_broker.clone().page(1).query_single_page() {
+        let items = match backend.query_page(&broker_queries[0], 1, BROKER_PAGE_SIZE) {
             Ok(items) => items,
             Err(e) => {
                 eprintln!("Failed to query broker for dry run: {}", e);
@@ -783,6 +1250,18 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
         return;
     }
 
+    if ordered {
+        run_ordered_search(
+            backend,
+            &filters,
+            fields,
+            windows,
+            output_format,
+            time_format,
+        );
+        return;
+    }
+
     let (sender, receiver): (Sender<WriterMessage>, Receiver<WriterMessage>) = channel();
     // Single progress channel for all updates
     let (progress_sender, progress_receiver): (Sender<ProgressUpdate>, Receiver<ProgressUpdate>) =
@@ -791,18 +1270,36 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
     // Clone fields for the writer thread
     let fields_for_writer: Vec<&'static str> = fields.clone();
     let is_table_format = output_format == OutputFormat::Table;
-    // Determine if we need buffering for sorting (in addition to table format)
-    let needs_sorting = order_by.is_some();
-    let needs_buffering = is_table_format || needs_sorting;
-    // Clone ordering parameters for writer thread
-    let order_by_for_writer = order_by;
-    let order_for_writer = order;
-    // Clone time format for writer thread
+    // Sorted output goes through the external sorter, which spills to disk
+    // beyond the memory budget; unsorted Table output is buffered in memory
+    let mut sorter = (!order_by.is_empty()).then(|| {
+        elem_sorter(
+            order_by,
+            order,
+            sort_memory_mb.unwrap_or(config.sort_memory_mb),
+            sort_dir,
+        )
+    });
+    // Clone time format and time windows for writer thread
     let time_format_for_writer = time_format;
+    let windows_for_writer = windows;
+    // Histogram buckets cover the search window; workers fill copies of the template
+    let mut summary = match summary
+        .map(|summary| summary.with_range(broker_query.ts_start, broker_query.ts_end))
+        .transpose()
+    {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let summary_template = summary.as_ref().map(ElemSummary::empty_like);
 
     // dedicated thread for handling output of results
     let writer_thread = thread::spawn(move || {
-        let display_stdout = sqlite_db.is_none() && mrt_path.is_none();
+        let display_stdout = sqlite_db.is_none() && mrt_path.is_none() && parquet_store.is_none();
+        let mut parquet_store = parquet_store;
         let mut mrt_writer = match mrt_path {
             Some(p) => match oneio::get_writer(p.as_str()) {
                 Ok(writer) => Some((MrtUpdatesEncoder::new(), writer)),
@@ -814,20 +1311,27 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
             None => None,
         };
 
-        let mut current_file_cache = vec![];
+        let mut pending_files = PendingFiles::default();
         let mut total_msg_count = 0;
         let mut header_printed = false;
-        // Buffer for Table format or sorted output - collects all elements before display
+        // Buffer for Table format - collects all elements before display
         let mut output_buffer: Vec<(BgpElem, Option<String>)> = Vec::new();
 
         for msg in receiver {
             match msg {
-                WriterMessage::Element(elem, collector) => {
+                WriterMessage::Element(elem, collector, file_url) => {
                     total_msg_count += 1;
 
                     if display_stdout {
-                        // For Table format or when sorting is needed, buffer all elements
-                        if needs_buffering {
+                        if let Some(sorter) = sorter.as_mut() {
+                            if let Err(e) = sorter.push(*elem, Some(collector)) {
+                                eprintln!("ERROR: failed to sort output: {e}");
+                                std::process::exit(1);
+                            }
+                            continue;
+                        }
+                        // For Table format, buffer all elements
+                        if is_table_format {
                             output_buffer.push((*elem, Some(collector)));
                             continue;
                         }
@@ -844,6 +1348,7 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
                             output_format,
                             &fields_for_writer,
                             Some(&collector),
+                            Some(&windows_for_writer),
                             time_format_for_writer,
                         ) {
                             println!("{output_str}");
@@ -851,39 +1356,59 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
                         continue;
                     }
 
-                    current_file_cache.push((*elem, collector));
+                    pending_files.push(file_url, *elem, collector);
                 }
-                WriterMessage::FileComplete => {
-                    // Commit current file's data to SQLite
-                    if !current_file_cache.is_empty() {
-                        if let Some(db) = &sqlite_db {
-                            if let Err(e) = db.insert_elems(&current_file_cache) {
-                                eprintln!("Failed to insert elements to database: {}", e);
-                            }
+                WriterMessage::Summary(partial) => {
+                    if let Some(summary) = summary.as_mut() {
+                        summary.merge(*partial);
+                    }
+                }
+                WriterMessage::FileComplete(file_url) => {
+                    let file_elems = pending_files.complete(&file_url, sqlite_db.as_ref());
+                    if let Some((encoder, _writer)) = &mut mrt_writer {
+                        for (elem, _) in &file_elems {
+                            encoder.process_elem(elem);
                         }
-                        if let Some((encoder, _writer)) = &mut mrt_writer {
-                            for (elem, _) in &current_file_cache {
-                                encoder.process_elem(elem);
-                            }
+                    }
+                    if let Some(store) = &mut parquet_store {
+                        if let Err(e) = write_parquet_elems(store, &file_elems) {
+                            eprintln!("ERROR: failed to write Parquet output: {e}");
+                            std::process::exit(1);
                         }
-                        current_file_cache.clear();
                     }
                 }
             }
         }
 
-        // Handle any remaining data in cache (in case last file didn't send FileComplete)
-        if !current_file_cache.is_empty() {
-            if let Some(db) = &sqlite_db {
-                if let Err(e) = db.insert_elems(&current_file_cache) {
-                    eprintln!("Failed to insert elements to database: {}", e);
-                }
+        // Files that didn't send FileComplete are left out of SQLite, where
+        // they have no checkpoint, so a resumed search processes them again
+        for (file_url, file_elems) in pending_files.into_incomplete() {
+            if sqlite_db.is_some() {
+                eprintln!(
+                    "WARNING: {} was not fully processed, its {} elements are not stored in {}",
+                    file_url,
+                    file_elems.len(),
+                    sqlite_path_str
+                );
             }
             if let Some((encoder, _writer)) = &mut mrt_writer {
-                for (elem, _) in &current_file_cache {
+                for (elem, _) in &file_elems {
                     encoder.process_elem(elem);
                 }
             }
+            if let Some(store) = &mut parquet_store {
+                if let Err(e) = write_parquet_elems(store, &file_elems) {
+                    eprintln!("ERROR: failed to write Parquet output: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        if let Some(store) = parquet_store {
+            if let Err(e) = store.finish() {
+                eprintln!("ERROR: failed to write Parquet output: {e}");
+                std::process::exit(1);
+            }
         }
 
         if let Some((encoder, writer)) = &mut mrt_writer {
@@ -894,42 +1419,44 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
         }
         drop(mrt_writer);
 
-        // For buffered output (Table format or sorted), process at the end
-        if display_stdout && needs_buffering && !output_buffer.is_empty() {
-            // Sort if ordering is requested
-            if let Some(order_field) = order_by_for_writer {
-                sort_elems(&mut output_buffer, order_field, order_for_writer);
-            }
-
-            // Output based on format
-            if is_table_format {
-                println!(
-                    "{}",
-                    format_elems_table(&output_buffer, &fields_for_writer, time_format_for_writer)
-                );
-            } else {
-                // Print header for markdown format
-                if let Some(header) = get_header(output_format, &fields_for_writer) {
-                    println!("{header}");
-                }
+        // For sorted or Table output, print at the end
+        if let (true, Some(sorter)) = (display_stdout, sorter) {
+            print_sorted_elems(
+                sorter,
+                output_format,
+                &fields_for_writer,
+                &windows_for_writer,
+                time_format_for_writer,
+            );
+        } else if display_stdout && is_table_format && !output_buffer.is_empty() {
+            println!(
+                "{}",
+                format_elems_table(
+                    &output_buffer,
+                    &fields_for_writer,
+                    Some(&windows_for_writer),
+                    time_format_for_writer
+                )
+            );
+        }
 
-                // Output sorted elements
-                for (elem, collector) in &output_buffer {
-                    if let Some(output_str) = format_elem(
-                        elem,
-                        output_format,
-                        &fields_for_writer,
-                        collector.as_deref(),
-                        time_format_for_writer,
-                    ) {
-                        println!("{output_str}");
-                    }
-                }
+        if let Some(summary) = summary {
+            match summary.format(
+                output_format,
+                &fields_for_writer,
+                Some(&windows_for_writer),
+                time_format_for_writer,
+            ) {
+                Ok(lines) => lines.iter().for_each(|line| println!("{line}")),
+                Err(e) => eprintln!("ERROR: {e}"),
             }
         }
 
         if !display_stdout {
-            eprintln!("found {total_msg_count} messages, written into file {sqlite_path_str}");
+            eprintln!(
+                "found {total_msg_count} messages, written into {}",
+                output_paths.join(", ")
+            );
         }
     });
 
@@ -1003,8 +1530,8 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
     let failed_items_clone = Arc::clone(&failed_items);
 
     // Only use broker cache when caching is enabled; otherwise use pagination
-    let (all_items, used_broker_cache) = if cache_dir.is_some() {
-        match fetch_broker_items_cached(&filters, cache_dir.as_deref()) {
+    let (all_items, used_broker_cache) = if broker_cache_dir.is_some() {
+        match fetch_broker_plan_cached(backend.as_ref(), &broker_queries, broker_cache_dir) {
             Ok((items, used_cache)) => (Some(items), used_cache),
             Err(e) => {
                 // If cache fetch failed, log and continue with pagination
@@ -1040,27 +1567,45 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
             );
             Box::new(batches.into_iter())
         } else {
-            // Use pagination iterator
-            Box::new(std::iter::from_fn({
-                let broker = base_broker.clone();
-                let mut current_page = 1i64;
-                move || {
-                    let items = broker.clone().page(current_page).query_single_page().ok()?;
-                    if items.is_empty() {
-                        return None;
-                    }
-                    current_page += 1;
-                    Some(items)
+            // Use pagination iterator, paging through each query in turn
+            Box::new(broker_queries.clone().into_iter().flat_map({
+                let backend = backend.clone();
+                move |broker_query| {
+                    let backend = backend.clone();
+                    let mut current_page = 1i64;
+                    std::iter::from_fn(move || {
+                        let items = backend
+                            .query_page(&broker_query, current_page, BROKER_PAGE_SIZE)
+                            .ok()?;
+                        if items.is_empty() {
+                            return None;
+                        }
+                        current_page += 1;
+                        Some(items)
+                    })
                 }
             }))
         };
+    // A file can overlap windows listed by different queries; parse it only once
+    let mut listed_files: HashSet<String> = HashSet::new();
 
-    for items in items_iter {
+    let mut skipped_files = 0usize;
+    for mut items in items_iter {
         if items.is_empty() {
             info!("Reached empty batch, finishing");
             break;
         }
 
+        if broker_queries.len() > 1 {
+            items.retain(|item| listed_files.insert(item.url.clone()));
+        }
+
+        if !completed_files.is_empty() {
+            let before = items.len();
+            items.retain(|item| !completed_files.contains(&item.url));
+            skipped_files += before - items.len();
+        }
+
         let page_size = items.len();
 
         // Send page started update to progress thread
@@ -1102,9 +1647,12 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
             |(s, progress_sender, failed_items), item| {
                 let url = item.url.clone();
                 let collector = item.collector_id.clone();
+                let file_url: Arc<str> = Arc::from(url.as_str());
 
                 // Determine the file path to parse (local cache or remote URL)
-                let file_path = if let Some(ref cache_dir) = cache_dir_clone {
+                // Local files (e.g. from a local archive) are read in place
+                let item_cache_dir = cache_dir_clone.as_ref().filter(|_| is_remote(&url));
+                let file_path = if let Some(cache_dir) = item_cache_dir {
                     let cache_path = match url_to_cache_path(cache_dir, &collector, &url) {
                         Some(p) => p,
                         None => {
@@ -1143,7 +1691,7 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
                     info!("start parsing {}", file_path.as_str());
                 }
 
-                let parser = match filters.to_parser(file_path.as_str()) {
+                let parser = match filters.to_elem_iter(file_path.as_str()) {
                     Ok(p) => p,
                     Err(e) => {
                         let error_msg = format!("Failed to parse {}: {}", file_path.as_str(), e);
@@ -1152,7 +1700,7 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
                         }
 
                         // If using cache and parse failed, delete the cached file (might be corrupted)
-                        if cache_dir_clone.is_some() {
+                        if item_cache_dir.is_some() {
                             let _ = std::fs::remove_file(&file_path);
                         }
 
@@ -1174,21 +1722,8 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
                     }
                 };
 
-                let mut elems_count = 0;
-                for elem in parser {
-                    if s.send(WriterMessage::Element(Box::new(elem), collector.clone()))
-                        .is_err()
-                    {
-                        // Channel closed, break out
-                        break;
-                    }
-                    elems_count += 1;
-                }
-
-                // Send file completion signal to trigger per-file commit
-                if s.send(WriterMessage::FileComplete).is_err() {
-                    // Channel closed, ignore
-                }
+                let (elems_count, _) =
+                    send_file_elems(s, parser, &collector, file_url, summary_template.as_ref());
 
                 // Send success progress update
                 if progress_sender
@@ -1223,6 +1758,13 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
         info!("Completed processing across {} {}", page - 1, unit);
     }
 
+    if skipped_files > 0 {
+        info!(
+            "Skipped {} files already completed by a previous run",
+            skipped_files
+        );
+    }
+
     // Retry phase for failed items
     let failed_count = {
         match failed_items.lock() {
@@ -1279,7 +1821,10 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
                 total_retries += 1;
 
                 // Determine file path (cache or remote URL)
-                let file_path = if let Some(ref cache_dir) = cache_dir {
+                let item_cache_dir = cache_dir
+                    .as_ref()
+                    .filter(|_| is_remote(&failed_item.item.url));
+                let file_path = if let Some(cache_dir) = item_cache_dir {
                     let cache_path = match url_to_cache_path(
                         cache_dir,
                         &failed_item.item.collector_id,
@@ -1316,7 +1861,7 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
                     failed_item.item.url.clone()
                 };
 
-                let parser = match filters.to_parser(file_path.as_str()) {
+                let parser = match filters.to_elem_iter(file_path.as_str()) {
                     Ok(p) => p,
                     Err(e) => {
                         let error_msg = format!("Retry failed to parse {}: {}", file_path, e);
@@ -1324,7 +1869,7 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
                             warn!("{}", error_msg);
                         }
                         // Delete cached file on parse failure
-                        if cache_dir.is_some() {
+                        if item_cache_dir.is_some() {
                             let _ = std::fs::remove_file(&file_path);
                         }
                         failed_item.increment_attempt(error_msg);
@@ -1333,28 +1878,14 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
                     }
                 };
 
-                let mut elems_count = 0;
-                let mut parse_successful = true;
-
-                for elem in parser {
-                    if sender
-                        .send(WriterMessage::Element(
-                            Box::new(elem),
-                            failed_item.item.collector_id.clone(),
-                        ))
-                        .is_err()
-                    {
-                        // Channel closed, mark as failed
-                        parse_successful = false;
-                        break;
-                    }
-                    elems_count += 1;
-                }
-
-                // Send file completion signal for retry as well
-                if parse_successful && sender.send(WriterMessage::FileComplete).is_err() {
-                    parse_successful = false;
-                }
+                let file_url: Arc<str> = Arc::from(failed_item.item.url.as_str());
+                let (elems_count, parse_successful) = send_file_elems(
+                    &sender,
+                    parser,
+                    &failed_item.item.collector_id,
+                    file_url,
+                    summary_template.as_ref(),
+                );
 
                 if parse_successful {
                     successful_retries += 1;
@@ -1407,6 +1938,109 @@ fn run_inner(config: &MonocleConfig, args: SearchArgs, output_format: OutputForm
     }
 }
 
+/// Convert local SearchFilters to wire RemoteSearchFilters
+fn remote_search_filters(filters: &SearchFilters) -> RemoteSearchFilters {
+    let path_filters = &filters.parse_filters.as_path_filters;
+    let asinfo_filters = &filters.parse_filters.asinfo_filters;
+    RemoteSearchFilters {
+        prefix: filters.parse_filters.prefix.clone(),
+        include_super: filters.parse_filters.include_super,
+        include_sub: filters.parse_filters.include_sub,
+        origin_asn: filters.parse_filters.origin_asn.clone(),
+        peer_asn: filters.parse_filters.peer_asn.clone(),
+        peer_ip: filters
+            .parse_filters
+            .peer_ip
+            .iter()
+            .map(|ip| ip.to_string())
+            .collect(),
+        communities: filters.parse_filters.communities.clone(),
+        elem_type: filters.parse_filters.elem_type.as_ref().map(|et| match et {
+            monocle::lens::parse::ParseElemType::A => "A".to_string(),
+            monocle::lens::parse::ParseElemType::W => "W".to_string(),
+        }),
+        as_path: filters.parse_filters.as_path.clone(),
+        as_path_style: filters.parse_filters.as_path_style,
+        ext_communities: filters.parse_filters.ext_communities.clone(),
+        med: filters.parse_filters.med.clone(),
+        local_pref: filters.parse_filters.local_pref.clone(),
+        next_hop: filters.parse_filters.next_hop.clone(),
+        origin: filters.parse_filters.origin.clone(),
+        aggr_asn: filters.parse_filters.aggr_asn.clone(),
+        atomic: filters.parse_filters.atomic,
+        rpki: filters.parse_filters.rpki_filters.rpki.clone(),
+        rpki_date: filters.parse_filters.rpki_filters.rpki_date,
+        rpki_source: filters.parse_filters.rpki_filters.rpki_source.clone(),
+        origin_country: asinfo_filters.origin_country.clone(),
+        peer_country: asinfo_filters.peer_country.clone(),
+        origin_org: asinfo_filters.origin_org.clone(),
+        path_len: path_filters.path_len.clone(),
+        unique_path_len: path_filters.unique_path_len.clone(),
+        prepend_count: path_filters.prepend_count.clone(),
+        as_set: path_filters.as_set,
+        private_asn: path_filters.private_asn,
+        reserved_asn: path_filters.reserved_asn,
+        path_loop: path_filters.path_loop,
+        where_expr: filters.parse_filters.where_expr.clone(),
+        start_ts: filters.parse_filters.start_ts.clone(),
+        end_ts: filters.parse_filters.end_ts.clone(),
+        windows: filters.parse_filters.windows.clone(),
+        collector: filters.collector.clone(),
+        project: filters.project.clone(),
+        dump_type: Some(
+            match filters.dump_type {
+                monocle::lens::search::SearchDumpType::Updates => "updates",
+                monocle::lens::search::SearchDumpType::Rib => "rib",
+                monocle::lens::search::SearchDumpType::RibUpdates => "rib_updates",
+            }
+            .to_string(),
+        ),
+    }
+}
+
+/// Run the async remote search client on a tokio runtime.
+fn run_remote_search_wrapper(
+    url: &str,
+    auth_token: Option<&str>,
+    request: RemoteSearchRequest,
+    fields_arg: Option<&str>,
+    output_format: OutputFormat,
+    time_format: TimestampFormat,
+) {
+    // Parse fields
+    let fields_arg = fields_arg.map(|s| s.to_string());
+    let mut fields = match parse_fields(&fields_arg, true) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Ok(windows) = TimeWindows::resolve(&request.filters.windows) {
+        add_windows_field(&mut fields, &fields_arg, &windows);
+    }
+
+    let rt = match tokio::runtime::Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => {
+            eprintln!("Failed to create tokio runtime: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = rt.block_on(search_remote::run_remote_search(
+        url,
+        auth_token,
+        request,
+        output_format,
+        &fields,
+        time_format,
+    )) {
+        eprintln!("Remote search failed: {e}");
+        std::process::exit(1);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1478,83 +2112,41 @@ mod tests {
         let result = url_to_cache_path(&cache_dir, collector, url);
         assert_eq!(result, None);
     }
-}
 
-/// Wrapper to convert local SearchFilters to wire RemoteSearchFilters and run
-/// the async remote search client on a tokio runtime.
-fn run_remote_search_wrapper(
-    url: &str,
-    auth_token: Option<&str>,
-    filters: &SearchFilters,
-    fields_arg: Option<&str>,
-    output_format: OutputFormat,
-    time_format: TimestampFormat,
-) {
-    // Convert internal SearchFilters to wire RemoteSearchFilters
-    let wire = RemoteSearchFilters {
-        prefix: filters.parse_filters.prefix.clone(),
-        include_super: filters.parse_filters.include_super,
-        include_sub: filters.parse_filters.include_sub,
-        origin_asn: filters.parse_filters.origin_asn.clone(),
-        peer_asn: filters.parse_filters.peer_asn.clone(),
-        peer_ip: filters
-            .parse_filters
-            .peer_ip
-            .iter()
-            .map(|ip| ip.to_string())
-            .collect(),
-        communities: filters.parse_filters.communities.clone(),
-        elem_type: filters.parse_filters.elem_type.as_ref().map(|et| match et {
-            monocle::lens::parse::ParseElemType::A => "A".to_string(),
-            monocle::lens::parse::ParseElemType::W => "W".to_string(),
-        }),
-        as_path: filters.parse_filters.as_path.clone(),
-        start_ts: filters.parse_filters.start_ts.clone().unwrap_or_default(),
-        end_ts: filters.parse_filters.end_ts.clone().unwrap_or_default(),
-        collector: filters.collector.clone(),
-        project: filters.project.clone(),
-        dump_type: Some(
-            match filters.dump_type {
-                monocle::lens::search::SearchDumpType::Updates => "updates",
-                monocle::lens::search::SearchDumpType::Rib => "rib",
-                monocle::lens::search::SearchDumpType::RibUpdates => "rib_updates",
+    #[test]
+    fn test_resume_after_interrupted_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("search.sqlite3");
+        let path = path.to_str().unwrap();
+        let file_a: Arc<str> = Arc::from("file-a");
+        let file_b: Arc<str> = Arc::from("file-b");
+        let push = |pending: &mut PendingFiles, file_url: &Arc<str>, count: usize| {
+            for _ in 0..count {
+                pending.push(file_url.clone(), BgpElem::default(), "rrc00".to_string());
             }
-            .to_string(),
-        ),
-    };
-
-    let request = RemoteSearchRequest {
-        filters: wire,
-        batch_size: None,
-        max_results: None,
-    };
-
-    // Parse fields
-    let fields = match parse_fields(&fields_arg.map(|s| s.to_string()), true) {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("ERROR: {}", e);
-            std::process::exit(1);
-        }
-    };
-
-    let rt = match tokio::runtime::Runtime::new() {
-        Ok(rt) => rt,
-        Err(e) => {
-            eprintln!("Failed to create tokio runtime: {e}");
-            std::process::exit(1);
-        }
-    };
+        };
 
-    if let Err(e) = rt.block_on(search_remote::run_remote_search(
-        url,
-        auth_token,
-        request,
-        output_format,
-        &fields,
-        time_format,
-    )) {
-        eprintln!("Remote search failed: {e}");
-        std::process::exit(1);
+        // First run: file-a completes, file-b is interrupted after one element
+        let db = MsgStore::new(Some(path), false).unwrap();
+        let mut pending = PendingFiles::default();
+        push(&mut pending, &file_a, 2);
+        push(&mut pending, &file_b, 1);
+        assert_eq!(pending.complete(&file_a, Some(&db)).len(), 2);
+        let incomplete = pending.into_incomplete();
+        assert_eq!(incomplete.len(), 1);
+        assert_eq!(incomplete[0].0, file_b);
+        assert_eq!(db.count().unwrap(), 2);
+        drop(db);
+
+        // Resumed run: only file-b is processed again, fully this time
+        let db = MsgStore::new(Some(path), false).unwrap();
+        let completed = db.verified_completed_files().unwrap();
+        assert_eq!(completed.keys().collect::<Vec<_>>(), vec!["file-a"]);
+        let mut pending = PendingFiles::default();
+        push(&mut pending, &file_b, 3);
+        pending.complete(&file_b, Some(&db));
+        assert!(pending.into_incomplete().is_empty());
+        assert_eq!(db.count().unwrap(), 5);
+        assert_eq!(db.verified_completed_files().unwrap().len(), 2);
     }
 }
@@ -8,12 +8,19 @@
 use std::collections::HashMap;
 
 use bgpkit_parser::BgpElem;
+use chrono::NaiveDate;
 use futures::StreamExt;
-use monocle::utils::{OutputFormat, TimestampFormat};
+use monocle::lens::parse::as_path_regex::AsPathStyle;
+use monocle::lens::parse::time_window::{TimeWindowSpec, TimeWindows};
+use monocle::lens::rpki::RpkiDataSource;
+use monocle::lens::search::{DedupEvent, HistogramSeries};
+use monocle::utils::{GroupByField, OutputFormat, TimestampFormat};
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncBufReadExt;
 
-use super::elem_format::{format_elem, format_elems_table};
+use super::elem_format::{
+    dedup_columns, format_dedup_events, format_elem, format_elems_table, format_histogram,
+};
 
 /// Wire DTO matching the server's `SearchStreamRequest`.
 #[derive(Debug, Clone, Serialize)]
@@ -23,6 +30,26 @@ pub struct RemoteSearchRequest {
     pub batch_size: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_results: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub histogram: Option<RemoteHistogramRequest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<RemoteDedupRequest>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub ordered: bool,
+}
+
+/// Histogram mode options — field names match the server's `HistogramRequest`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteHistogramRequest {
+    pub bucket_width: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub split_by: Vec<GroupByField>,
+}
+
+/// Dedup mode options — field names match the server's `DedupRequest`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteDedupRequest {
+    pub window: String,
 }
 
 /// Wire filters — field names match the server's `SearchStreamFilters`.
@@ -46,8 +73,56 @@ pub struct RemoteSearchFilters {
     pub elem_type: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub as_path: Option<String>,
-    pub start_ts: String,
-    pub end_ts: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub as_path_style: Option<AsPathStyle>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ext_communities: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub med: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub local_pref: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub next_hop: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub origin: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aggr_asn: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub atomic: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rpki: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpki_date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpki_source: Option<RpkiDataSource>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub origin_country: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub peer_country: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub origin_org: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub path_len: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unique_path_len: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prepend_count: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub as_set: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private_asn: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reserved_asn: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_loop: Option<bool>,
+    #[serde(rename = "where", skip_serializing_if = "Option::is_none")]
+    pub where_expr: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_ts: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_ts: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<TimeWindowSpec>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub collector: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -65,6 +140,12 @@ pub struct ElementsBatch {
     pub elements: Vec<BgpElem>,
 }
 
+/// SSE event data for `dedup` events.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DedupBatch {
+    pub events: Vec<DedupEvent>,
+}
+
 /// Progress event (loosely typed — we just print it).
 #[derive(Debug, Clone, Deserialize, serde::Serialize)]
 #[serde(untagged)]
@@ -78,6 +159,9 @@ pub enum ProgressData {
 pub struct SearchStreamResult {
     pub exit_reason: String,
     pub stats: SearchStreamStats,
+    /// Complete time series, present in histogram mode
+    #[serde(default)]
+    pub histogram: Option<HistogramSeries>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -125,15 +209,23 @@ pub async fn run_remote_search(
     );
     let mut lines = tokio::io::BufReader::new(&mut reader).lines();
 
+    // Window labels are derived from element timestamps, same as local search
+    let windows = TimeWindows::resolve(&request.filters.windows)?;
     let mut buffered_elems: Vec<(BgpElem, Option<String>)> = Vec::new();
+    let mut buffered_events: Vec<DedupEvent> = Vec::new();
     let is_table = output_format == OutputFormat::Table;
 
     // For non-table formats that need a header (markdown)
-    if output_format == OutputFormat::Markdown {
-        println!("| {} |", fields.join(" | "));
+    if output_format == OutputFormat::Markdown && request.histogram.is_none() {
+        let columns = if request.dedup.is_some() {
+            dedup_columns(fields)
+        } else {
+            fields.to_vec()
+        };
+        println!("| {} |", columns.join(" | "));
         println!(
             "|{}|",
-            fields.iter().map(|_| "---").collect::<Vec<_>>().join("|")
+            columns.iter().map(|_| "---").collect::<Vec<_>>().join("|")
         );
     }
 
@@ -174,6 +266,7 @@ pub async fn run_remote_search(
                                 output_format,
                                 fields,
                                 batch.collector.as_deref(),
+                                Some(&windows),
                                 time_format,
                             ) {
                                 println!("{}", output_str);
@@ -181,6 +274,23 @@ pub async fn run_remote_search(
                         }
                     }
                 }
+                "dedup" => {
+                    if let Ok(batch) = serde_json::from_str::<DedupBatch>(&data_line) {
+                        if is_table {
+                            buffered_events.extend(batch.events);
+                        } else {
+                            for line in format_dedup_events(
+                                &batch.events,
+                                output_format,
+                                fields,
+                                Some(&windows),
+                                time_format,
+                            ) {
+                                println!("{}", line);
+                            }
+                        }
+                    }
+                }
                 "completed" => {
                     if let Ok(result) = serde_json::from_str::<SearchStreamResult>(&data_line) {
                         let stats = result.stats;
@@ -193,11 +303,21 @@ pub async fn run_remote_search(
                             stats.matched_elements,
                             stats.duration_secs
                         );
+                        if let Some(series) = result.histogram {
+                            for line in format_histogram(&series, output_format, time_format) {
+                                println!("{}", line);
+                            }
+                        }
                     }
                     // Flush table output before returning success
-                    if is_table && !buffered_elems.is_empty() {
-                        let table = format_elems_table(&buffered_elems, fields, time_format);
-                        println!("{}", table);
+                    if is_table {
+                        print_buffered_table(
+                            &buffered_elems,
+                            &buffered_events,
+                            fields,
+                            &windows,
+                            time_format,
+                        );
                     }
                     return Ok(());
                 }
@@ -211,9 +331,14 @@ pub async fn run_remote_search(
                         eprintln!("[cancelled]");
                     }
                     // Flush any partial results before returning error
-                    if is_table && !buffered_elems.is_empty() {
-                        let table = format_elems_table(&buffered_elems, fields, time_format);
-                        println!("{}", table);
+                    if is_table {
+                        print_buffered_table(
+                            &buffered_elems,
+                            &buffered_events,
+                            fields,
+                            &windows,
+                            time_format,
+                        );
                     }
                     return Err(anyhow::anyhow!("remote search was cancelled"));
                 }
@@ -223,9 +348,14 @@ pub async fn run_remote_search(
                     } else {
                         eprintln!("[error] {}", data_line);
                     }
-                    if is_table && !buffered_elems.is_empty() {
-                        let table = format_elems_table(&buffered_elems, fields, time_format);
-                        println!("{}", table);
+                    if is_table {
+                        print_buffered_table(
+                            &buffered_elems,
+                            &buffered_events,
+                            fields,
+                            &windows,
+                            time_format,
+                        );
                     }
                     return Err(anyhow::anyhow!("remote search failed: {}", data_line));
                 }
@@ -240,3 +370,30 @@ pub async fn run_remote_search(
         "remote search ended without completion event"
     ))
 }
+
+/// Print elements or dedup events buffered for Table output
+fn print_buffered_table(
+    elems: &[(BgpElem, Option<String>)],
+    events: &[DedupEvent],
+    fields: &[&str],
+    windows: &TimeWindows,
+    time_format: TimestampFormat,
+) {
+    if !elems.is_empty() {
+        println!(
+            "{}",
+            format_elems_table(elems, fields, Some(windows), time_format)
+        );
+    }
+    if !events.is_empty() {
+        for line in format_dedup_events(
+            events,
+            OutputFormat::Table,
+            fields,
+            Some(windows),
+            time_format,
+        ) {
+            println!("{}", line);
+        }
+    }
+}
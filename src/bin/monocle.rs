@@ -11,13 +11,14 @@ mod commands;
 
 // Re-export argument types from command modules for use in the Commands enum
 use commands::as2rel::As2relArgs;
+use commands::bmp_listen::BmpListenArgs;
 use commands::config::ConfigArgs;
 use commands::country::CountryArgs;
 use commands::inspect::InspectArgs;
 use commands::ip::IpArgs;
 use commands::parse::ParseArgs;
 use commands::pfx2as::Pfx2asArgs;
-use commands::rib::RibArgs;
+use commands::rib::RibCommandArgs;
 use commands::rpki::RpkiCommands;
 use commands::search::SearchArgs;
 use commands::time::TimeArgs;
@@ -59,7 +60,10 @@ enum Commands {
     Search(SearchArgs),
 
     /// Reconstruct final RIB state at one or more arbitrary timestamps.
-    Rib(RibArgs),
+    Rib(RibCommandArgs),
+
+    /// Listen for BMP sessions from routers and print or store their routes.
+    BmpListen(BmpListenArgs),
 
     /// Start the Monocle HTTP service (REST: /api/v1, search stream: /api/v1/search/stream)
     ///
@@ -186,11 +190,14 @@ fn main() {
     // You can check for the existence of subcommands, and if found, use their
     // matches just as you would the top level cmd
     match cli.command {
-        Commands::Parse(args) => commands::parse::run(args, streaming_output_format),
+        Commands::Parse(args) => commands::parse::run(&config, args, streaming_output_format),
         Commands::Search(args) => commands::search::run(&config, args, streaming_output_format),
         Commands::Rib(args) => {
             commands::rib::run(&config, args, streaming_output_format, cli.no_update)
         }
+        Commands::BmpListen(args) => {
+            commands::bmp_listen::run(&config, args, streaming_output_format)
+        }
 
         Commands::Server(args) => {
             // The server requires the `server` feature (axum + tokio). Keep the CLI
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Result};
@@ -27,6 +27,9 @@ pub const DEFAULT_SERVER_SEARCH_TIMEOUT_SECS: u64 = 0;
 /// Default search concurrency (0 = rayon default / CPU count)
 pub const DEFAULT_SEARCH_CONCURRENCY: usize = 0;
 
+/// Default memory budget in MiB for `--order-by` before spilling to disk
+pub const DEFAULT_SORT_MEMORY_MB: usize = 512;
+
 /// Default maximum concurrent SSE search requests
 pub const DEFAULT_SERVER_MAX_CONCURRENT_SEARCHES: usize = 3;
 
@@ -78,6 +81,20 @@ pub struct MonocleConfig {
     /// Search concurrency, 0 = rayon default / CPU count (default: 0)
     pub search_concurrency: usize,
 
+    /// Memory budget in MiB for `--order-by` before sorted runs are spilled
+    /// to temporary files (default: 512)
+    pub sort_memory_mb: usize,
+
+    /// Local RouteViews/RIS-style MRT archive directory (optional)
+    /// If set, search and rib list files from this directory instead of the BGPKIT broker
+    pub local_archive_dir: Option<String>,
+
+    /// Self-hosted BGPKIT broker URL (optional, default: public broker)
+    pub broker_url: Option<String>,
+
+    /// Static JSON manifest of MRT files to use instead of a broker (optional)
+    pub broker_manifest: Option<String>,
+
     /// Search timeout in seconds, 0 = no timeout (default: 0)
     pub server_search_timeout_secs: u64,
 
@@ -89,6 +106,10 @@ pub struct MonocleConfig {
 
     /// Bearer token for auth (required when server_auth_enabled = true)
     pub server_auth_token: String,
+
+    /// Named saved queries from `[queries.<name>]` tables, in the JSON filter
+    /// file format (see [`MonocleConfig::saved_query`])
+    pub queries: BTreeMap<String, serde_json::Value>,
 }
 
 const EMPTY_CONFIG: &str = r#"### monocle configuration file
@@ -116,6 +137,16 @@ const EMPTY_CONFIG: &str = r#"### monocle configuration file
 ### Search execution configuration
 ### Search concurrency; 0 = auto/rayon default. Can also be set with MONOCLE_SEARCH_CONCURRENCY.
 # search_concurrency = 0
+### Memory (MiB) used by --order-by before spilling sorted runs to temporary files.
+### Can also be set with MONOCLE_SORT_MEMORY_MB.
+# sort_memory_mb = 512
+### Where search, rib and the SSE server find MRT files; set at most one.
+### Self-hosted BGPKIT broker instead of the public one:
+# broker_url = "https://broker.example.com/v3/broker"
+### Static JSON manifest of broker items (e.g. saved `monocle search --broker-files --json`):
+# broker_manifest = "/srv/mrt-manifest.json"
+### Local MRT archive laid out like the RouteViews/RIS archives:
+# local_archive_dir = "/srv/mrt-archive"
 
 ### HTTP service configuration
 ### These settings control the monocle HTTP/SSE server.
@@ -135,6 +166,15 @@ const EMPTY_CONFIG: &str = r#"### monocle configuration file
 ### /health stays open for container health checks
 # server_auth_enabled = false
 # server_auth_token = ""
+
+### Saved queries
+### Each [queries.<name>] table takes the fields of a JSON filter file (prefixes,
+### origin_asns, elem_type, collector, ...). Use them with `--query <name>` in
+### parse, search and rib, or as "query" in SSE search requests. parse rejects
+### queries that set collector, project or dump_type.
+# [queries.our-withdrawals]
+# prefixes = ["192.0.2.0/24", "2001:db8::/32"]
+# elem_type = "w"
 "#;
 
 #[derive(Debug, Clone)]
@@ -247,10 +287,15 @@ impl Default for MonocleConfig {
             server_max_search_batch_size: DEFAULT_SERVER_MAX_SEARCH_BATCH_SIZE,
             server_max_search_results: DEFAULT_SERVER_MAX_SEARCH_RESULTS,
             search_concurrency: DEFAULT_SEARCH_CONCURRENCY,
+            sort_memory_mb: DEFAULT_SORT_MEMORY_MB,
+            local_archive_dir: None,
+            broker_url: None,
+            broker_manifest: None,
             server_search_timeout_secs: DEFAULT_SERVER_SEARCH_TIMEOUT_SECS,
             server_max_concurrent_searches: DEFAULT_SERVER_MAX_CONCURRENT_SEARCHES,
             server_auth_enabled: DEFAULT_SERVER_AUTH_ENABLED,
             server_auth_token: String::new(),
+            queries: BTreeMap::new(),
         }
     }
 }
@@ -303,9 +348,26 @@ impl MonocleConfig {
             .build()
             .map_err(|e| anyhow!("Failed to build configuration: {}", e))?;
 
-        let config = settings
-            .try_deserialize::<HashMap<String, String>>()
-            .map_err(|e| anyhow!("Failed to deserialize configuration: {}", e))?;
+        let queries = match settings.get::<BTreeMap<String, serde_json::Value>>("queries") {
+            Ok(queries) => queries,
+            Err(config::ConfigError::NotFound(_)) => BTreeMap::new(),
+            Err(e) => return Err(anyhow!("Failed to read [queries] configuration: {}", e)),
+        };
+
+        // Scalar settings; the [queries] table is read separately above
+        let mut config: HashMap<String, String> = HashMap::new();
+        for (key, value) in settings
+            .try_deserialize::<HashMap<String, config::Value>>()
+            .map_err(|e| anyhow!("Failed to deserialize configuration: {}", e))?
+        {
+            if key == "queries" {
+                continue;
+            }
+            let value = value
+                .into_string()
+                .map_err(|e| anyhow!("Invalid value for configuration key '{}': {}", key, e))?;
+            config.insert(key, value);
+        }
 
         // Parse data directory
         let data_dir = match config.get("data_dir") {
@@ -383,6 +445,22 @@ impl MonocleConfig {
             .get("search_concurrency")
             .and_then(|s| s.parse().ok())
             .unwrap_or(DEFAULT_SEARCH_CONCURRENCY);
+        let sort_memory_mb = config
+            .get("sort_memory_mb")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_SORT_MEMORY_MB);
+        let local_archive_dir = config
+            .get("local_archive_dir")
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+        let broker_url = config
+            .get("broker_url")
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+        let broker_manifest = config
+            .get("broker_manifest")
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
         let server_search_timeout_secs = config
             .get("server_search_timeout_secs")
             .and_then(|s| s.parse().ok())
@@ -414,10 +492,15 @@ impl MonocleConfig {
             server_max_search_batch_size,
             server_max_search_results,
             search_concurrency,
+            sort_memory_mb,
+            local_archive_dir,
+            broker_url,
+            broker_manifest,
             server_search_timeout_secs,
             server_max_concurrent_searches,
             server_auth_enabled,
             server_auth_token,
+            queries,
         })
     }
 
@@ -464,6 +547,30 @@ impl MonocleConfig {
         std::time::Duration::from_secs(self.rpki_rtr_timeout_secs)
     }
 
+    /// Names of the saved queries, sorted
+    pub fn saved_query_names(&self) -> Vec<&str> {
+        self.queries.keys().map(String::as_str).collect()
+    }
+
+    /// Look up a saved query by name and parse it as a filter file
+    #[cfg(feature = "lib")]
+    pub fn saved_query(&self, name: &str) -> Result<crate::lens::parse::filter_file::FilterFile> {
+        let value = self.queries.get(name).ok_or_else(|| {
+            let names = self.saved_query_names();
+            if names.is_empty() {
+                anyhow!("Unknown saved query '{}': no queries configured", name)
+            } else {
+                anyhow!(
+                    "Unknown saved query '{}': available queries are {}",
+                    name,
+                    names.join(", ")
+                )
+            }
+        })?;
+        serde_json::from_value(value.clone())
+            .map_err(|e| anyhow!("Invalid saved query '{}': {}", name, e))
+    }
+
     /// Display configuration summary
     pub fn summary(&self) -> String {
         let mut lines = vec![
@@ -499,6 +606,16 @@ impl MonocleConfig {
                 self.search_concurrency.to_string()
             }
         ));
+        lines.push(format!("Sort Memory:        {} MiB", self.sort_memory_mb));
+        if let Some(dir) = &self.local_archive_dir {
+            lines.push(format!("Local Archive:      {}", dir));
+        }
+        if let Some(path) = &self.broker_manifest {
+            lines.push(format!("Broker Manifest:    {}", path));
+        }
+        if let Some(url) = &self.broker_url {
+            lines.push(format!("Broker URL:         {}", url));
+        }
         lines.push(format!(
             "Search Timeout:     {} seconds",
             self.server_search_timeout_secs
@@ -512,6 +629,12 @@ impl MonocleConfig {
             }
         ));
         lines.push(format!("Auth Enabled:       {}", self.server_auth_enabled));
+        if !self.queries.is_empty() {
+            lines.push(format!(
+                "Saved Queries:      {}",
+                self.saved_query_names().join(", ")
+            ));
+        }
 
         // Check if cache directories exist and show status
         let cache_dir = self.cache_dir();
@@ -988,6 +1111,10 @@ mod tests {
             DEFAULT_SERVER_MAX_SEARCH_RESULTS
         );
         assert_eq!(config.search_concurrency, DEFAULT_SEARCH_CONCURRENCY);
+        assert_eq!(config.sort_memory_mb, DEFAULT_SORT_MEMORY_MB);
+        assert_eq!(config.local_archive_dir, None);
+        assert_eq!(config.broker_url, None);
+        assert_eq!(config.broker_manifest, None);
         assert_eq!(
             config.server_search_timeout_secs,
             DEFAULT_SERVER_SEARCH_TIMEOUT_SECS
@@ -998,6 +1125,7 @@ mod tests {
         );
         assert!(!config.server_auth_enabled);
         assert_eq!(config.server_auth_token, "");
+        assert!(config.queries.is_empty());
     }
 
     #[test]
@@ -1070,6 +1198,89 @@ mod tests {
         assert_eq!(config.rtr_timeout(), std::time::Duration::from_secs(30));
     }
 
+    #[test]
+    #[cfg(feature = "lib")]
+    fn test_load_saved_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("monocle.toml");
+        std::fs::write(
+            &path,
+            format!(
+                r#"data_dir = "{}"
+server_port = 9000
+
+[queries.ours]
+prefixes = ["192.0.2.0/24"]
+elem_type = "w"
+collector = "rrc00,route-views2"
+
+[queries.leak]
+origin_asns = ["64496"]
+"#,
+                dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let config = MonocleConfig::new(&Some(path.to_string_lossy().to_string())).unwrap();
+
+        assert_eq!(config.server_port, 9000);
+        assert_eq!(config.saved_query_names(), vec!["leak", "ours"]);
+
+        let query = config.saved_query("ours").unwrap();
+        assert_eq!(query.prefixes, vec!["192.0.2.0/24"]);
+        assert_eq!(query.collector.as_deref(), Some("rrc00,route-views2"));
+        assert!(config
+            .saved_query("missing")
+            .unwrap_err()
+            .to_string()
+            .contains("available queries are leak, ours"));
+    }
+
+    #[test]
+    #[cfg(feature = "lib")]
+    fn test_sample_saved_query_works_with_parse() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("monocle.toml");
+        // Uncomment the sample query of the default config file
+        let sample: String = EMPTY_CONFIG
+            .lines()
+            .skip_while(|line| !line.starts_with("# [queries."))
+            .map(|line| format!("{}\n", line.trim_start_matches("# ")))
+            .collect();
+        std::fs::write(
+            &path,
+            format!("data_dir = \"{}\"\n{}", dir.path().display(), sample),
+        )
+        .unwrap();
+
+        let config = MonocleConfig::new(&Some(path.to_string_lossy().to_string())).unwrap();
+        let query = config.saved_query("our-withdrawals").unwrap();
+        let mut filters = crate::lens::parse::ParseFilters::default();
+        query.merge_into(&mut filters).unwrap();
+        assert_eq!(filters.prefix, vec!["192.0.2.0/24", "2001:db8::/32"]);
+    }
+
+    #[test]
+    fn test_reject_non_scalar_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("monocle.toml");
+        std::fs::write(
+            &path,
+            format!(
+                "data_dir = \"{}\"\nbroker_url = [\"http://localhost:8080\"]\n",
+                dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let err = match MonocleConfig::new(&Some(path.to_string_lossy().to_string())) {
+            Ok(_) => panic!("a list value for broker_url must be rejected"),
+            Err(e) => e.to_string(),
+        };
+        assert!(err.contains("'broker_url'"), "{}", err);
+    }
+
     #[test]
     fn test_data_source_from_str() {
         assert_eq!(DataSource::from_str("asinfo"), Some(DataSource::Asinfo));
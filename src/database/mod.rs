@@ -128,6 +128,10 @@ pub use session::MsgStore;
 #[cfg(feature = "lib")]
 pub use session::{RibRouteKey, RibSqliteStore, RibStateStore, StoredRibEntry, StoredRibUpdate};
 
+// Parquet exports of search results and RIB snapshots
+#[cfg(feature = "parquet")]
+pub use session::{ElemParquetStore, RibParquetStore, PARQUET_ELEM_FIELDS, PARQUET_ROW_GROUP_SIZE};
+
 // =============================================================================
 // Helper function
 // =============================================================================
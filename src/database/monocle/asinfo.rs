@@ -824,6 +824,36 @@ impl<'a> AsinfoRepository<'a> {
         result
     }
 
+    /// Batch lookup of registration countries (from core table)
+    pub fn lookup_countries_batch(&self, asns: &[u32]) -> HashMap<u32, String> {
+        let mut result = HashMap::new();
+
+        if asns.is_empty() {
+            return result;
+        }
+
+        let placeholders: Vec<String> = asns.iter().map(|_| "?".to_string()).collect();
+        let query = format!(
+            "SELECT asn, country FROM asinfo_core WHERE asn IN ({})",
+            placeholders.join(",")
+        );
+
+        if let Ok(mut stmt) = self.conn.prepare(&query) {
+            let params: Vec<&dyn rusqlite::ToSql> =
+                asns.iter().map(|a| a as &dyn rusqlite::ToSql).collect();
+
+            if let Ok(rows) = stmt.query_map(params.as_slice(), |row| {
+                Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?))
+            }) {
+                for row in rows.flatten() {
+                    result.insert(row.0, row.1);
+                }
+            }
+        }
+
+        result
+    }
+
     // =========================================================================
     // Individual Table Queries
     // =========================================================================
@@ -1122,7 +1152,41 @@ mod tests {
         assert_eq!(names.len(), 2);
         assert_eq!(names.get(&13335), Some(&"CLOUDFLARENET".to_string()));
         assert_eq!(names.get(&15169), Some(&"GOOGLE".to_string()));
-        assert!(names.get(&99999).is_none());
+        assert!(!names.contains_key(&99999));
+    }
+
+    #[test]
+    fn test_lookup_countries_batch() {
+        let db = setup_test_db();
+        let repo = AsinfoRepository::new(&db.conn);
+
+        let records = vec![
+            JsonlRecord {
+                asn: 13335,
+                name: "CLOUDFLARENET".to_string(),
+                country: "US".to_string(),
+                as2org: None,
+                peeringdb: None,
+                hegemony: None,
+                population: None,
+            },
+            JsonlRecord {
+                asn: 3320,
+                name: "DTAG".to_string(),
+                country: "DE".to_string(),
+                as2org: None,
+                peeringdb: None,
+                hegemony: None,
+                population: None,
+            },
+        ];
+
+        repo.store_from_jsonl(&records, "test://source").unwrap();
+
+        let countries = repo.lookup_countries_batch(&[13335, 3320, 99999]);
+        assert_eq!(countries.len(), 2);
+        assert_eq!(countries.get(&13335), Some(&"US".to_string()));
+        assert_eq!(countries.get(&3320), Some(&"DE".to_string()));
     }
 
     #[test]
@@ -1220,7 +1284,7 @@ mod tests {
         assert_eq!(names.get(&3), Some(&"AS2ORG_ORG3".to_string()));
         assert_eq!(names.get(&4), Some(&"AS2ORG_NAME4".to_string()));
         assert_eq!(names.get(&5), Some(&"CORE5".to_string()));
-        assert!(names.get(&99999).is_none());
+        assert!(!names.contains_key(&99999));
     }
 
     #[test]
@@ -11,14 +11,21 @@
 //! # Feature Requirements
 //!
 //! The `MsgStore` type requires the `lib` feature because it depends
-//! on `bgpkit_parser::BgpElem` for storing BGP elements.
+//! on `bgpkit_parser::BgpElem` for storing BGP elements. The Parquet stores
+//! additionally require the `parquet` feature.
 
 #[cfg(feature = "lib")]
 mod msg_store;
+#[cfg(feature = "parquet")]
+mod parquet_store;
 #[cfg(feature = "lib")]
 mod rib_store;
 
 #[cfg(feature = "lib")]
 pub use msg_store::MsgStore;
+#[cfg(feature = "parquet")]
+pub use parquet_store::{
+    ElemParquetStore, RibParquetStore, PARQUET_ELEM_FIELDS, PARQUET_ROW_GROUP_SIZE,
+};
 #[cfg(feature = "lib")]
 pub use rib_store::{RibRouteKey, RibSqliteStore, RibStateStore, StoredRibEntry, StoredRibUpdate};
@@ -2,13 +2,24 @@
 //!
 //! This module provides storage for BGP messages during search operations.
 //! Unlike the shared database, this is intended for one-time use per search session.
+//!
+//! Each fully processed input file can be recorded in a `checkpoints` table in
+//! the same transaction as its elements, so an interrupted search can be resumed
+//! without re-inserting files that were already stored.
+//!
+//! With [`MsgStore::with_as_info`], the AS names, countries and organizations
+//! of elements are stored as well.
+
+use std::collections::{HashMap, HashSet};
 
 use anyhow::{anyhow, Result};
 use bgpkit_parser::models::ElemType;
 use bgpkit_parser::BgpElem;
 use itertools::Itertools;
+use rusqlite::Transaction;
 
 use crate::database::core::DatabaseConn;
+use crate::lens::parse::enrich::{AsInfoEnricher, AS_INFO_FIELDS};
 
 /// Message store for BGP search results
 ///
@@ -17,6 +28,7 @@ use crate::database::core::DatabaseConn;
 /// creates its own database file.
 pub struct MsgStore {
     db: DatabaseConn,
+    as_info: Option<AsInfoEnricher>,
 }
 
 impl MsgStore {
@@ -27,7 +39,7 @@ impl MsgStore {
     /// * `reset` - If `true`, drops existing data before initializing.
     pub fn new(db_path: Option<&str>, reset: bool) -> Result<Self> {
         let db = DatabaseConn::open(db_path)?;
-        let store = MsgStore { db };
+        let store = MsgStore { db, as_info: None };
         store.initialize(reset)?;
         Ok(store)
     }
@@ -47,6 +59,10 @@ impl MsgStore {
                 .conn
                 .execute("DROP TABLE IF EXISTS elems", [])
                 .map_err(|e| anyhow!("Failed to drop elems table: {}", e))?;
+            self.db
+                .conn
+                .execute("DROP TABLE IF EXISTS checkpoints", [])
+                .map_err(|e| anyhow!("Failed to drop checkpoints table: {}", e))?;
         }
 
         self.db
@@ -76,6 +92,20 @@ impl MsgStore {
             )
             .map_err(|e| anyhow!("Failed to create elems table: {}", e))?;
 
+        self.db
+            .conn
+            .execute(
+                r#"
+                CREATE TABLE IF NOT EXISTS checkpoints (
+                    url TEXT PRIMARY KEY,
+                    elem_count INTEGER NOT NULL,
+                    completed_at INTEGER NOT NULL
+                );
+                "#,
+                [],
+            )
+            .map_err(|e| anyhow!("Failed to create checkpoints table: {}", e))?;
+
         // Add indexes for common query patterns
         self.db.conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_timestamp ON elems(timestamp)",
@@ -100,6 +130,31 @@ impl MsgStore {
         Ok(())
     }
 
+    /// Also store the AS information of elements, in `peer_name`,
+    /// `origin_name`, `origin_country` and `origin_org` columns
+    ///
+    /// The columns are added to an existing `elems` table that lacks them.
+    pub fn with_as_info(mut self, enricher: AsInfoEnricher) -> Result<Self> {
+        let columns: HashSet<String> = {
+            let mut stmt = self
+                .db
+                .conn
+                .prepare("SELECT name FROM pragma_table_info('elems')")?;
+            let names = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            names.collect::<Result<_, _>>()?
+        };
+        for field in AS_INFO_FIELDS {
+            if !columns.contains(*field) {
+                self.db
+                    .conn
+                    .execute(&format!("ALTER TABLE elems ADD COLUMN {field} TEXT"), [])
+                    .map_err(|e| anyhow!("Failed to add {} column: {}", field, e))?;
+            }
+        }
+        self.as_info = Some(enricher);
+        Ok(self)
+    }
+
     /// Insert BGP elements into the store
     ///
     /// # Arguments
@@ -116,56 +171,159 @@ impl MsgStore {
             .unchecked_transaction()
             .map_err(|e| anyhow!("Failed to begin transaction: {}", e))?;
 
-        {
-            // Use prepared statement for better performance
-            let mut stmt = tx
-                .prepare_cached(
-                    "INSERT INTO elems (timestamp, elem_type, collector, peer_ip, peer_asn,
-                     prefix, next_hop, as_path, origin_asns, origin, local_pref, med,
-                     communities, atomic, aggr_asn, aggr_ip)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
-                )
-                .map_err(|e| anyhow!("Failed to prepare statement: {}", e))?;
-
-            for (elem, collector) in elems {
-                let elem_type = match elem.elem_type {
-                    ElemType::ANNOUNCE => "A",
-                    ElemType::WITHDRAW => "W",
-                };
-                let origin_string = elem
-                    .origin_asns
-                    .as_ref()
-                    .and_then(|asns| asns.first())
-                    .map(|asn| asn.to_string());
-                let communities_str = elem.communities.as_ref().map(|v| v.iter().join(" "));
-
-                stmt.execute(rusqlite::params![
-                    elem.timestamp as u32,
-                    elem_type,
-                    collector,
-                    elem.peer_ip.to_string(),
-                    elem.peer_asn.to_u32(),
-                    elem.prefix.to_string(),
-                    elem.next_hop.as_ref().map(|v| v.to_string()),
-                    elem.as_path.as_ref().map(|v| v.to_string()),
-                    origin_string,
-                    elem.origin.as_ref().map(|v| v.to_string()),
-                    elem.local_pref,
-                    elem.med,
-                    communities_str,
-                    if elem.atomic { "AG" } else { "NAG" },
-                    elem.aggr_asn.map(|asn| asn.to_u32()),
-                    elem.aggr_ip.as_ref().map(|v| v.to_string()),
-                ])
-                .map_err(|e| anyhow!("Failed to insert element: {}", e))?;
-            }
-        }
+        Self::insert_elems_tx(&tx, elems, self.as_info.as_ref())?;
 
         tx.commit()
             .map_err(|e| anyhow!("Failed to commit transaction: {}", e))?;
         Ok(())
     }
 
+    /// Insert all BGP elements of one input file and record the file as completed
+    ///
+    /// Elements and the checkpoint are written in a single transaction, so a file
+    /// is either fully stored and checkpointed or not stored at all.
+    ///
+    /// # Arguments
+    /// * `url` - URL or path of the processed file
+    /// * `elems` - Slice of (BgpElem, collector_name) tuples found in the file
+    pub fn insert_file_elems(&self, url: &str, elems: &[(BgpElem, String)]) -> Result<()> {
+        let tx = self
+            .db
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| anyhow!("Failed to begin transaction: {}", e))?;
+
+        Self::insert_elems_tx(&tx, elems, self.as_info.as_ref())?;
+        tx.execute(
+            "INSERT OR REPLACE INTO checkpoints (url, elem_count, completed_at)
+             VALUES (?1, ?2, ?3)",
+            rusqlite::params![url, elems.len() as i64, chrono::Utc::now().timestamp()],
+        )
+        .map_err(|e| anyhow!("Failed to record checkpoint: {}", e))?;
+
+        tx.commit()
+            .map_err(|e| anyhow!("Failed to commit transaction: {}", e))?;
+        Ok(())
+    }
+
+    /// Get the files recorded as completed, mapped to their element counts
+    pub fn completed_files(&self) -> Result<HashMap<String, u64>> {
+        let mut stmt = self
+            .db
+            .conn
+            .prepare("SELECT url, elem_count FROM checkpoints")
+            .map_err(|e| anyhow!("Failed to prepare checkpoint query: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })
+            .map_err(|e| anyhow!("Failed to query checkpoints: {}", e))?;
+
+        let mut files = HashMap::new();
+        for row in rows {
+            let (url, count) = row.map_err(|e| anyhow!("Failed to read checkpoint: {}", e))?;
+            files.insert(url, count);
+        }
+        Ok(files)
+    }
+
+    /// Get the files recorded as completed, checking that their element counts
+    /// add up to the number of stored elements
+    ///
+    /// Fails if the store holds elements outside of completed files, e.g. from
+    /// [`MsgStore::insert_elems`], since resuming would then mix in partial data.
+    pub fn verified_completed_files(&self) -> Result<HashMap<String, u64>> {
+        let files = self.completed_files()?;
+        let checkpointed: u64 = files.values().sum();
+        let stored = self.count()?;
+        if checkpointed != stored {
+            return Err(anyhow!(
+                "store has {} elements but its checkpoints account for {}, reset it to start over",
+                stored,
+                checkpointed
+            ));
+        }
+        Ok(files)
+    }
+
+    fn insert_elems_tx(
+        tx: &Transaction,
+        elems: &[(BgpElem, String)],
+        as_info: Option<&AsInfoEnricher>,
+    ) -> Result<()> {
+        let mut columns = "timestamp, elem_type, collector, peer_ip, peer_asn, prefix, next_hop,
+             as_path, origin_asns, origin, local_pref, med, communities, atomic, aggr_asn, aggr_ip"
+            .to_string();
+        let mut column_count = 16;
+        if let Some(enricher) = as_info {
+            enricher.prefetch(elems.iter().map(|(elem, _)| elem));
+            for field in AS_INFO_FIELDS {
+                columns.push_str(", ");
+                columns.push_str(field);
+            }
+            column_count += AS_INFO_FIELDS.len();
+        }
+        let placeholders = (1..=column_count).map(|i| format!("?{i}")).join(", ");
+
+        // Use prepared statement for better performance
+        let mut stmt = tx
+            .prepare_cached(&format!(
+                "INSERT INTO elems ({columns}) VALUES ({placeholders})"
+            ))
+            .map_err(|e| anyhow!("Failed to prepare statement: {}", e))?;
+
+        for (elem, collector) in elems {
+            let elem_type = match elem.elem_type {
+                ElemType::ANNOUNCE => "A",
+                ElemType::WITHDRAW => "W",
+            };
+            let origin_string = elem
+                .origin_asns
+                .as_ref()
+                .and_then(|asns| asns.first())
+                .map(|asn| asn.to_string());
+            let communities_str = elem.communities.as_ref().map(|v| v.iter().join(" "));
+            let peer_ip = elem.peer_ip.to_string();
+            let prefix = elem.prefix.to_string();
+            let next_hop = elem.next_hop.as_ref().map(|v| v.to_string());
+            let as_path = elem.as_path.as_ref().map(|v| v.to_string());
+            let origin = elem.origin.as_ref().map(|v| v.to_string());
+            let aggr_ip = elem.aggr_ip.as_ref().map(|v| v.to_string());
+            let values = rusqlite::params![
+                elem.timestamp as u32,
+                elem_type,
+                collector,
+                peer_ip,
+                elem.peer_asn.to_u32(),
+                prefix,
+                next_hop,
+                as_path,
+                origin_string,
+                origin,
+                elem.local_pref,
+                elem.med,
+                communities_str,
+                if elem.atomic { "AG" } else { "NAG" },
+                elem.aggr_asn.map(|asn| asn.to_u32()),
+                aggr_ip,
+            ];
+
+            match as_info.map(|enricher| enricher.lookup(elem)) {
+                Some(info) => stmt.execute(rusqlite::params_from_iter(values.iter().chain(
+                    rusqlite::params![
+                        info.peer_name,
+                        info.origin_name,
+                        info.origin_country,
+                        info.origin_org,
+                    ],
+                ))),
+                None => stmt.execute(values),
+            }
+            .map_err(|e| anyhow!("Failed to insert element: {}", e))?;
+        }
+        Ok(())
+    }
+
     /// Get the count of stored elements
     pub fn count(&self) -> Result<u64> {
         self.db.table_count("elems")
@@ -245,4 +403,76 @@ mod tests {
         let store2 = MsgStore::new(None, true).unwrap();
         assert_eq!(store2.count().unwrap(), 0);
     }
+
+    #[test]
+    fn test_file_checkpoints() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("search.sqlite3");
+        let path = path.to_str().unwrap();
+
+        let store = MsgStore::new(Some(path), false).unwrap();
+        let elems = vec![
+            (create_test_elem(), "rrc00".to_string()),
+            (create_test_elem(), "rrc00".to_string()),
+        ];
+        store.insert_file_elems("file-a", &elems).unwrap();
+        store.insert_file_elems("file-b", &[]).unwrap();
+        drop(store);
+
+        // Checkpoints survive reopening without reset
+        let store = MsgStore::new(Some(path), false).unwrap();
+        let completed = store.completed_files().unwrap();
+        assert_eq!(completed.len(), 2);
+        assert_eq!(completed.get("file-a"), Some(&2));
+        assert_eq!(completed.get("file-b"), Some(&0));
+        assert_eq!(store.count().unwrap(), 2);
+        assert_eq!(store.verified_completed_files().unwrap(), completed);
+
+        // Elements stored without a checkpoint fail verification
+        store
+            .insert_elems(&[(create_test_elem(), "rrc00".to_string())])
+            .unwrap();
+        assert!(store.verified_completed_files().is_err());
+        drop(store);
+
+        // Reset clears checkpoints along with elements
+        let store = MsgStore::new(Some(path), true).unwrap();
+        assert!(store.completed_files().unwrap().is_empty());
+        assert_eq!(store.count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_with_as_info() {
+        let db = crate::database::MonocleDatabase::open_in_memory().unwrap();
+        let records: Vec<crate::database::JsonlRecord> = serde_json::from_value(
+            serde_json::json!([{"asn": 65001, "name": "EXAMPLE-NET", "country": "NL"}]),
+        )
+        .unwrap();
+        db.asinfo()
+            .store_from_jsonl(&records, "test://source")
+            .unwrap();
+        let enricher = AsInfoEnricher::new(db).unwrap();
+
+        // Columns are added to a table created without them
+        let store = MsgStore::new(None, false)
+            .unwrap()
+            .with_as_info(enricher)
+            .unwrap();
+        store
+            .insert_elems(&[(create_test_elem(), "rrc00".to_string())])
+            .unwrap();
+
+        let row: (Option<String>, String, String, Option<String>) = store
+            .connection()
+            .query_row(
+                "SELECT peer_name, origin_name, origin_country, origin_org FROM elems",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            row,
+            (None, "EXAMPLE-NET".to_string(), "NL".to_string(), None)
+        );
+    }
 }
@@ -0,0 +1,902 @@
+//! Apache Parquet export for BGP elements and reconstructed RIB snapshots
+//!
+//! [`ElemParquetStore`] writes BGP elements with the same field set as the
+//! CLI's `--fields` option. Unlike the text and JSON outputs, columns keep
+//! their native types:
+//!
+//! | Field | Arrow type |
+//! |-------|------------|
+//! | `timestamp` | `Timestamp(Microsecond, "UTC")` |
+//! | `peer_asn`, `path_id`, `local_pref`, `med`, `aggr_asn` | `UInt32` |
+//! | `path_len`, `unique_path_len`, `prepend_count` | `UInt32` |
+//! | `prefix` | `Utf8`, plus a `prefix_len` `UInt8` column |
+//! | `as_path`, `origin_asns` | `List<UInt32>` |
+//! | `communities`, `windows` | `List<Utf8>` |
+//! | `atomic` | `Boolean` |
+//! | `rpki` | `Dictionary(UInt8, Utf8)` |
+//! | everything else | `Utf8` |
+//!
+//! AS paths are flattened into their ASNs in path order; AS sets contribute
+//! their members in place. Rows are buffered per [`ElemParquetStore::write_elems`]
+//! call and flushed to disk as row groups of up to [`PARQUET_ROW_GROUP_SIZE`]
+//! rows, so a search can stream batches into the file as they arrive.
+//!
+//! [`RibParquetStore`] is the Parquet counterpart of
+//! [`RibSqliteStore`](super::RibSqliteStore): snapshot entries go to the
+//! given file, and the updates that led to the 2nd and later snapshots go to
+//! a sibling `<name>.updates.parquet` file.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::types::{UInt32Type, UInt8Type};
+use arrow_array::{
+    ArrayRef, BooleanArray, DictionaryArray, ListArray, RecordBatch, StringArray,
+    TimestampMicrosecondArray, UInt32Array, UInt8Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use bgpkit_parser::models::{AsPathSegment, ElemType};
+use bgpkit_parser::BgpElem;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use super::{RibStateStore, StoredRibEntry, StoredRibUpdate};
+use crate::lens::as2rel::AsPathAnnotator;
+use crate::lens::parse::as_path_filter::AsPathStats;
+use crate::lens::parse::enrich::{AsInfoEnricher, ElemAsInfo, AS_INFO_FIELDS};
+use crate::lens::parse::time_window::TimeWindows;
+use crate::lens::rpki::RpkiValidator;
+
+/// Maximum number of rows per Parquet row group
+pub const PARQUET_ROW_GROUP_SIZE: usize = 65_536;
+
+/// Fields [`ElemParquetStore`] can write, matching the CLI's `--fields` names
+pub const PARQUET_ELEM_FIELDS: &[&str] = &[
+    "type",
+    "timestamp",
+    "peer_ip",
+    "peer_asn",
+    "peer_name",
+    "prefix",
+    "path_id",
+    "as_path",
+    "as_path_rel",
+    "path_len",
+    "unique_path_len",
+    "prepend_count",
+    "origin_asns",
+    "origin_name",
+    "origin_country",
+    "origin_org",
+    "origin",
+    "next_hop",
+    "local_pref",
+    "med",
+    "communities",
+    "atomic",
+    "aggr_asn",
+    "aggr_ip",
+    "rpki",
+    "collector",
+    "windows",
+];
+
+/// Parquet writer for BGP elements
+pub struct ElemParquetStore {
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+    fields: Vec<&'static str>,
+    windows: TimeWindows,
+    rpki: Option<RpkiValidator>,
+    as_info: Option<AsInfoEnricher>,
+    as_path_rel: Option<AsPathAnnotator>,
+    rows: u64,
+}
+
+impl ElemParquetStore {
+    /// Create a Parquet file at `path` with one column per field in `fields`
+    ///
+    /// The file is truncated if it exists. Unknown field names are rejected.
+    pub fn new(path: &Path, fields: &[&str]) -> Result<Self> {
+        let fields = fields
+            .iter()
+            .map(|field| {
+                PARQUET_ELEM_FIELDS
+                    .iter()
+                    .find(|known| *known == field)
+                    .copied()
+                    .ok_or_else(|| anyhow!("unknown Parquet field '{}'", field))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let schema = Arc::new(Schema::new(
+            fields
+                .iter()
+                .flat_map(|field| elem_schema_fields(field))
+                .collect::<Vec<_>>(),
+        ));
+        let writer = create_writer(path, schema.clone())?;
+        Ok(Self {
+            writer,
+            schema,
+            fields,
+            windows: TimeWindows::default(),
+            rpki: None,
+            as_info: None,
+            as_path_rel: None,
+            rows: 0,
+        })
+    }
+
+    /// Time windows used to fill the `windows` column
+    pub fn with_windows(mut self, windows: TimeWindows) -> Self {
+        self.windows = windows;
+        self
+    }
+
+    /// ROAs used to fill the `rpki` column; without them it is empty
+    pub fn with_rpki(mut self, validator: RpkiValidator) -> Self {
+        self.rpki = Some(validator);
+        self
+    }
+
+    /// ASInfo data used to fill the `peer_name`, `origin_name`,
+    /// `origin_country` and `origin_org` columns; without it they are empty
+    pub fn with_as_info(mut self, enricher: AsInfoEnricher) -> Self {
+        self.as_info = Some(enricher);
+        self
+    }
+
+    /// as2rel data used to fill the `as_path_rel` column; without it the
+    /// column is empty
+    pub fn with_as_path_rel(mut self, annotator: AsPathAnnotator) -> Self {
+        self.as_path_rel = Some(annotator);
+        self
+    }
+
+    /// Arrow schema of the written file
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Number of rows written so far
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Append elements, each with the collector used for the `collector` column
+    pub fn write_elems<'a, I>(&mut self, elems: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (&'a BgpElem, Option<&'a str>)>,
+    {
+        let elems: Vec<(&BgpElem, Option<&str>)> = elems.into_iter().collect();
+        if elems.is_empty() {
+            return Ok(());
+        }
+        // AS information is looked up for the whole batch at once
+        let as_info: Vec<ElemAsInfo> = match &self.as_info {
+            Some(enricher) if self.fields.iter().any(|f| AS_INFO_FIELDS.contains(f)) => {
+                enricher.prefetch(elems.iter().map(|(elem, _)| *elem));
+                elems
+                    .iter()
+                    .map(|(elem, _)| enricher.lookup(elem))
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        let columns: Vec<ArrayRef> = self
+            .fields
+            .iter()
+            .flat_map(|field| self.elem_columns(field, &elems, &as_info))
+            .collect();
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)
+            .map_err(|e| anyhow!("Failed to build Parquet record batch: {}", e))?;
+        self.writer
+            .write(&batch)
+            .map_err(|e| anyhow!("Failed to write Parquet row group: {}", e))?;
+        self.rows += elems.len() as u64;
+        Ok(())
+    }
+
+    /// Flush buffered rows and write the file footer, returning the number of rows written
+    pub fn finish(self) -> Result<u64> {
+        self.writer
+            .close()
+            .map_err(|e| anyhow!("Failed to finish Parquet file: {}", e))?;
+        Ok(self.rows)
+    }
+
+    fn elem_columns(
+        &self,
+        field: &str,
+        elems: &[(&BgpElem, Option<&str>)],
+        as_info: &[ElemAsInfo],
+    ) -> Vec<ArrayRef> {
+        let column: ArrayRef = match field {
+            "type" => Arc::new(StringArray::from_iter_values(elems.iter().map(
+                |(elem, _)| match elem.elem_type {
+                    ElemType::ANNOUNCE => "A",
+                    ElemType::WITHDRAW => "W",
+                },
+            ))),
+            "timestamp" => Arc::new(
+                TimestampMicrosecondArray::from_iter_values(
+                    elems
+                        .iter()
+                        .map(|(elem, _)| timestamp_micros(elem.timestamp)),
+                )
+                .with_timezone("UTC"),
+            ),
+            "peer_ip" => Arc::new(StringArray::from_iter_values(
+                elems.iter().map(|(elem, _)| elem.peer_ip.to_string()),
+            )),
+            "peer_asn" => Arc::new(UInt32Array::from_iter_values(
+                elems.iter().map(|(elem, _)| elem.peer_asn.to_u32()),
+            )),
+            "prefix" => {
+                return vec![
+                    Arc::new(StringArray::from_iter_values(
+                        elems.iter().map(|(elem, _)| elem.prefix.to_string()),
+                    )),
+                    Arc::new(UInt8Array::from_iter_values(
+                        elems
+                            .iter()
+                            .map(|(elem, _)| elem.prefix.prefix.prefix_len()),
+                    )),
+                ]
+            }
+            "path_id" => Arc::new(UInt32Array::from_iter(
+                elems.iter().map(|(elem, _)| elem.prefix.path_id),
+            )),
+            "as_path" => Arc::new(ListArray::from_iter_primitive::<UInt32Type, _, _>(
+                elems.iter().map(|(elem, _)| as_path_asns(elem)),
+            )),
+            "path_len" | "unique_path_len" | "prepend_count" => {
+                Arc::new(UInt32Array::from_iter(elems.iter().map(|(elem, _)| {
+                    let stats = AsPathStats::new(elem.as_path.as_ref()?);
+                    match field {
+                        "path_len" => Some(stats.path_len),
+                        "unique_path_len" => Some(stats.unique_path_len),
+                        _ => Some(stats.prepend_count),
+                    }
+                })))
+            }
+            "origin_asns" => Arc::new(ListArray::from_iter_primitive::<UInt32Type, _, _>(
+                elems.iter().map(|(elem, _)| {
+                    elem.origin_asns.as_ref().map(|asns| {
+                        asns.iter()
+                            .map(|asn| Some(asn.to_u32()))
+                            .collect::<Vec<_>>()
+                    })
+                }),
+            )),
+            "origin" => Arc::new(StringArray::from_iter(
+                elems
+                    .iter()
+                    .map(|(elem, _)| elem.origin.as_ref().map(|o| o.to_string())),
+            )),
+            "next_hop" => Arc::new(StringArray::from_iter(
+                elems
+                    .iter()
+                    .map(|(elem, _)| elem.next_hop.as_ref().map(|h| h.to_string())),
+            )),
+            "local_pref" => Arc::new(UInt32Array::from_iter(
+                elems.iter().map(|(elem, _)| elem.local_pref),
+            )),
+            "med" => Arc::new(UInt32Array::from_iter(
+                elems.iter().map(|(elem, _)| elem.med),
+            )),
+            "communities" => string_list_array(elems.iter().map(|(elem, _)| {
+                elem.communities
+                    .as_ref()
+                    .map(|c| c.iter().map(|comm| comm.to_string()).collect())
+            })),
+            "atomic" => Arc::new(BooleanArray::from(
+                elems
+                    .iter()
+                    .map(|(elem, _)| elem.atomic)
+                    .collect::<Vec<_>>(),
+            )),
+            "aggr_asn" => Arc::new(UInt32Array::from_iter(
+                elems
+                    .iter()
+                    .map(|(elem, _)| elem.aggr_asn.map(|asn| asn.to_u32())),
+            )),
+            "aggr_ip" => Arc::new(StringArray::from_iter(
+                elems
+                    .iter()
+                    .map(|(elem, _)| elem.aggr_ip.map(|ip| ip.to_string())),
+            )),
+            "collector" => Arc::new(StringArray::from_iter(
+                elems.iter().map(|(_, collector)| *collector),
+            )),
+            "windows" => string_list_array(elems.iter().map(|(elem, _)| {
+                Some(
+                    self.windows
+                        .labels_at(elem.timestamp)
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect(),
+                )
+            })),
+            "rpki" => {
+                let states: Vec<Option<String>> = elems
+                    .iter()
+                    .map(|(elem, _)| {
+                        let state = self.rpki.as_ref()?.validate_elem(elem)?;
+                        Some(state.to_string())
+                    })
+                    .collect();
+                Arc::new(
+                    states
+                        .iter()
+                        .map(Option::as_deref)
+                        .collect::<DictionaryArray<UInt8Type>>(),
+                )
+            }
+            "as_path_rel" => Arc::new(StringArray::from_iter(elems.iter().map(|(elem, _)| {
+                let annotated = self
+                    .as_path_rel
+                    .as_ref()?
+                    .annotate(elem.as_path.as_ref()?)?;
+                Some(annotated.to_string())
+            }))),
+            "peer_name" | "origin_name" | "origin_country" | "origin_org" => Arc::new(
+                StringArray::from_iter((0..elems.len()).map(|i| as_info.get(i)?.field(field))),
+            ),
+            _ => Arc::new(StringArray::from_iter(elems.iter().map(|_| None::<&str>))),
+        };
+        vec![column]
+    }
+}
+
+/// Parquet export for reconstructed RIB snapshots
+///
+/// Writes the same columns as the `ribs` and `updates` tables of
+/// [`RibSqliteStore`](super::RibSqliteStore), with `as_path` and
+/// `origin_asns` as `List<UInt32>` and an extra `prefix_len` column.
+pub struct RibParquetStore {
+    ribs: ArrowWriter<File>,
+    updates: Option<ArrowWriter<File>>,
+    updates_path: PathBuf,
+    snapshot_index: usize,
+}
+
+impl RibParquetStore {
+    /// Create the snapshot file at `path`; the updates file is created next
+    /// to it once the first updates are written
+    pub fn new(path: &Path) -> Result<Self> {
+        let ribs = create_writer(path, rib_schema(false))?;
+        Ok(Self {
+            ribs,
+            updates: None,
+            updates_path: Self::updates_path(path),
+            snapshot_index: 0,
+        })
+    }
+
+    /// Path of the updates file written next to the snapshot file at `path`
+    pub fn updates_path(path: &Path) -> PathBuf {
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        path.with_file_name(format!("{stem}.updates.parquet"))
+    }
+
+    /// Write a RIB snapshot and, for the 2nd and later snapshots, the updates
+    /// that contributed to it
+    pub fn insert_snapshot(
+        &mut self,
+        rib_ts: i64,
+        state_store: &RibStateStore,
+        filtered_updates: &[StoredRibUpdate],
+    ) -> Result<()> {
+        let mut entries: Vec<StoredRibEntry> = Vec::with_capacity(PARQUET_ROW_GROUP_SIZE);
+        state_store.visit_entries(|entry| {
+            entries.push(entry.clone());
+            if entries.len() == PARQUET_ROW_GROUP_SIZE {
+                let rows: Vec<RibRow> = entries.iter().map(RibRow::entry).collect();
+                write_rib_rows(&mut self.ribs, rib_ts, &rows, false)?;
+                entries.clear();
+            }
+            Ok(())
+        })?;
+        if !entries.is_empty() {
+            let rows: Vec<RibRow> = entries.iter().map(RibRow::entry).collect();
+            write_rib_rows(&mut self.ribs, rib_ts, &rows, false)?;
+        }
+
+        if self.snapshot_index > 0 && !filtered_updates.is_empty() {
+            if self.updates.is_none() {
+                self.updates = Some(create_writer(&self.updates_path, rib_schema(true))?);
+            }
+            if let Some(writer) = self.updates.as_mut() {
+                for chunk in filtered_updates.chunks(PARQUET_ROW_GROUP_SIZE) {
+                    let rows: Vec<RibRow> = chunk.iter().map(RibRow::update).collect();
+                    write_rib_rows(writer, rib_ts, &rows, true)?;
+                }
+            }
+        }
+
+        self.snapshot_index += 1;
+        Ok(())
+    }
+
+    /// Write the file footers; returns the updates file path if one was written
+    pub fn finish(self) -> Result<Option<PathBuf>> {
+        self.ribs
+            .close()
+            .map_err(|e| anyhow!("Failed to finish Parquet file: {}", e))?;
+        match self.updates {
+            Some(writer) => {
+                writer
+                    .close()
+                    .map_err(|e| anyhow!("Failed to finish Parquet updates file: {}", e))?;
+                Ok(Some(self.updates_path))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+fn create_writer(path: &Path, schema: SchemaRef) -> Result<ArrowWriter<File>> {
+    let file = File::create(path)
+        .map_err(|e| anyhow!("Failed to create Parquet file {}: {}", path.display(), e))?;
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_max_row_group_size(PARQUET_ROW_GROUP_SIZE)
+        .build();
+    ArrowWriter::try_new(file, schema, Some(props))
+        .map_err(|e| anyhow!("Failed to create Parquet writer: {}", e))
+}
+
+fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+}
+
+fn list_type(item: DataType) -> DataType {
+    DataType::List(Arc::new(Field::new_list_field(item, true)))
+}
+
+fn timestamp_micros(timestamp: f64) -> i64 {
+    (timestamp * 1_000_000.0).round() as i64
+}
+
+/// Length of a `prefix/len` string, if it has one
+fn prefix_len(prefix: &str) -> Option<u8> {
+    prefix
+        .rsplit_once('/')
+        .and_then(|(_, len)| len.parse().ok())
+}
+
+fn elem_schema_fields(field: &str) -> Vec<Field> {
+    match field {
+        "type" => vec![Field::new("type", DataType::Utf8, false)],
+        "timestamp" => vec![Field::new("timestamp", timestamp_type(), false)],
+        "peer_ip" => vec![Field::new("peer_ip", DataType::Utf8, false)],
+        "peer_asn" => vec![Field::new("peer_asn", DataType::UInt32, false)],
+        "prefix" => vec![
+            Field::new("prefix", DataType::Utf8, false),
+            Field::new("prefix_len", DataType::UInt8, false),
+        ],
+        "as_path" | "origin_asns" => vec![Field::new(field, list_type(DataType::UInt32), true)],
+        "communities" | "windows" => vec![Field::new(field, list_type(DataType::Utf8), true)],
+        "atomic" => vec![Field::new("atomic", DataType::Boolean, false)],
+        "rpki" => vec![Field::new(
+            "rpki",
+            DataType::Dictionary(Box::new(DataType::UInt8), Box::new(DataType::Utf8)),
+            true,
+        )],
+        "path_id" | "local_pref" | "med" | "aggr_asn" | "path_len" | "unique_path_len"
+        | "prepend_count" => {
+            vec![Field::new(field, DataType::UInt32, true)]
+        }
+        _ => vec![Field::new(field, DataType::Utf8, true)],
+    }
+}
+
+fn as_path_asns(elem: &BgpElem) -> Option<Vec<Option<u32>>> {
+    elem.as_path.as_ref().map(|path| {
+        path.iter_segments()
+            .flat_map(|segment| match segment {
+                AsPathSegment::AsSequence(asns)
+                | AsPathSegment::AsSet(asns)
+                | AsPathSegment::ConfedSequence(asns)
+                | AsPathSegment::ConfedSet(asns) => asns.iter().map(|asn| Some(asn.to_u32())),
+            })
+            .collect()
+    })
+}
+
+fn string_list_array<I>(values: I) -> ArrayRef
+where
+    I: IntoIterator<Item = Option<Vec<String>>>,
+{
+    let mut builder = ListBuilder::new(StringBuilder::new());
+    for value in values {
+        match value {
+            Some(items) => {
+                for item in items {
+                    builder.values().append_value(item);
+                }
+                builder.append(true);
+            }
+            None => builder.append(false),
+        }
+    }
+    Arc::new(builder.finish())
+}
+
+fn rib_schema(updates: bool) -> SchemaRef {
+    let mut fields = vec![
+        Field::new("rib_ts", timestamp_type(), false),
+        Field::new("timestamp", timestamp_type(), false),
+        Field::new("collector", DataType::Utf8, false),
+        Field::new("peer_ip", DataType::Utf8, false),
+        Field::new("peer_asn", DataType::UInt32, false),
+        Field::new("prefix", DataType::Utf8, false),
+        Field::new("prefix_len", DataType::UInt8, true),
+        Field::new("path_id", DataType::UInt32, true),
+        Field::new("as_path", list_type(DataType::UInt32), true),
+        Field::new("origin_asns", list_type(DataType::UInt32), true),
+    ];
+    if updates {
+        fields.push(Field::new("elem_type", DataType::Utf8, false));
+    }
+    Arc::new(Schema::new(fields))
+}
+
+/// Columns shared by [`StoredRibEntry`] and [`StoredRibUpdate`]
+struct RibRow<'a> {
+    timestamp: f64,
+    collector: &'a str,
+    peer_ip: String,
+    peer_asn: u32,
+    prefix: &'a str,
+    path_id: Option<u32>,
+    as_path: Option<&'a str>,
+    origin_asns: Option<&'a [u32]>,
+    elem_type: Option<ElemType>,
+}
+
+impl<'a> RibRow<'a> {
+    fn entry(entry: &'a StoredRibEntry) -> Self {
+        Self {
+            timestamp: entry.timestamp,
+            collector: &entry.collector,
+            peer_ip: entry.peer_ip.to_string(),
+            peer_asn: entry.peer_asn,
+            prefix: &entry.prefix,
+            path_id: entry.path_id,
+            as_path: entry.as_path.as_deref(),
+            origin_asns: entry.origin_asns.as_deref(),
+            elem_type: None,
+        }
+    }
+
+    fn update(update: &'a StoredRibUpdate) -> Self {
+        Self {
+            timestamp: update.timestamp,
+            collector: &update.collector,
+            peer_ip: update.peer_ip.to_string(),
+            peer_asn: update.peer_asn,
+            prefix: &update.prefix,
+            path_id: update.path_id,
+            as_path: update.as_path.as_deref(),
+            origin_asns: update.origin_asns.as_deref(),
+            elem_type: Some(update.elem_type),
+        }
+    }
+}
+
+/// ASNs of an AS path in its text form, e.g. `64496 64497 {64498,64499}`
+fn parse_as_path(path: &str) -> Vec<Option<u32>> {
+    path.split(|c: char| c.is_whitespace() || matches!(c, ',' | '{' | '}' | '(' | ')'))
+        .filter_map(|token| token.parse::<u32>().ok())
+        .map(Some)
+        .collect()
+}
+
+fn write_rib_rows(
+    writer: &mut ArrowWriter<File>,
+    rib_ts: i64,
+    rows: &[RibRow],
+    updates: bool,
+) -> Result<()> {
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(
+            TimestampMicrosecondArray::from_iter_values(
+                rows.iter().map(|_| rib_ts.saturating_mul(1_000_000)),
+            )
+            .with_timezone("UTC"),
+        ),
+        Arc::new(
+            TimestampMicrosecondArray::from_iter_values(
+                rows.iter().map(|row| timestamp_micros(row.timestamp)),
+            )
+            .with_timezone("UTC"),
+        ),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|row| row.collector),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|row| row.peer_ip.as_str()),
+        )),
+        Arc::new(UInt32Array::from_iter_values(
+            rows.iter().map(|row| row.peer_asn),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|row| row.prefix),
+        )),
+        Arc::new(UInt8Array::from_iter(
+            rows.iter().map(|row| prefix_len(row.prefix)),
+        )),
+        Arc::new(UInt32Array::from_iter(rows.iter().map(|row| row.path_id))),
+        Arc::new(ListArray::from_iter_primitive::<UInt32Type, _, _>(
+            rows.iter().map(|row| row.as_path.map(parse_as_path)),
+        )),
+        Arc::new(ListArray::from_iter_primitive::<UInt32Type, _, _>(
+            rows.iter().map(|row| {
+                row.origin_asns
+                    .map(|asns| asns.iter().copied().map(Some).collect::<Vec<_>>())
+            }),
+        )),
+    ];
+    if updates {
+        columns.push(Arc::new(StringArray::from_iter_values(rows.iter().map(
+            |row| match row.elem_type {
+                Some(ElemType::WITHDRAW) => "WITHDRAW",
+                _ => "ANNOUNCE",
+            },
+        ))));
+    }
+    let batch = RecordBatch::try_new(rib_schema(updates), columns)
+        .map_err(|e| anyhow!("Failed to build Parquet record batch: {}", e))?;
+    writer
+        .write(&batch)
+        .map_err(|e| anyhow!("Failed to write Parquet row group: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::Array;
+    use bgpkit_parser::models::{AsPath, Community, MetaCommunity, NetworkPrefix};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::str::FromStr;
+    use tempfile::TempDir;
+
+    fn test_elem() -> BgpElem {
+        BgpElem {
+            timestamp: 1704067200.5,
+            peer_asn: 64500.into(),
+            prefix: NetworkPrefix::from_str("192.0.2.0/24").unwrap(),
+            as_path: Some(AsPath::from_sequence([64500, 64501, 64502])),
+            origin_asns: Some(vec![64502.into()]),
+            communities: Some(vec![MetaCommunity::Plain(Community::Custom(
+                64500.into(),
+                100,
+            ))]),
+            ..Default::default()
+        }
+    }
+
+    fn read_batches(path: &Path) -> Vec<RecordBatch> {
+        let file = File::open(path).unwrap();
+        ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_elem_parquet_round_trip() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("elems.parquet");
+        let elem = test_elem();
+
+        let mut store = ElemParquetStore::new(
+            &path,
+            &[
+                "timestamp",
+                "peer_asn",
+                "prefix",
+                "as_path",
+                "prepend_count",
+                "communities",
+                "collector",
+            ],
+        )?;
+        store.write_elems([(&elem, Some("rrc00")), (&elem, None)])?;
+        assert_eq!(store.finish()?, 2);
+
+        let batches = read_batches(&path);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(
+            batch.schema().field_with_name("timestamp")?.data_type(),
+            &timestamp_type()
+        );
+
+        let timestamps = batch
+            .column_by_name("timestamp")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        assert_eq!(timestamps.value(0), 1_704_067_200_500_000);
+        let prefix_lens = batch
+            .column_by_name("prefix_len")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<UInt8Array>()
+            .unwrap();
+        assert_eq!(prefix_lens.value(0), 24);
+        let as_path = batch
+            .column_by_name("as_path")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap();
+        let hops = as_path.value(0);
+        let hops = hops.as_any().downcast_ref::<UInt32Array>().unwrap();
+        assert_eq!(hops.values().to_vec(), vec![64500, 64501, 64502]);
+        let prepends = batch
+            .column_by_name("prepend_count")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<UInt32Array>()
+            .unwrap();
+        assert_eq!(prepends.value(0), 0);
+        let collectors = batch
+            .column_by_name("collector")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(collectors.value(0), "rrc00");
+        assert!(collectors.is_null(1));
+
+        assert!(ElemParquetStore::new(&path, &["bogus"]).is_err());
+        Ok(())
+    }
+
+    fn dictionary_column(batch: &RecordBatch, name: &str) -> Vec<Option<String>> {
+        let column = batch
+            .column_by_name(name)
+            .unwrap()
+            .as_any()
+            .downcast_ref::<DictionaryArray<UInt8Type>>()
+            .unwrap();
+        let values = column.downcast_dict::<StringArray>().unwrap();
+        values
+            .into_iter()
+            .map(|value| value.map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn test_elem_parquet_enriched_fields() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("elems.parquet");
+        let mut trie = bgpkit_commons::rpki::RpkiTrie::new(None);
+        trie.insert_roa(bgpkit_commons::rpki::Roa {
+            prefix: "192.0.2.0/24".parse()?,
+            asn: 64502,
+            max_length: 24,
+            rir: None,
+            not_before: None,
+            not_after: None,
+        });
+        let db = crate::database::MonocleDatabase::open_in_memory()?;
+        let records: Vec<crate::database::JsonlRecord> =
+            serde_json::from_value(serde_json::json!([
+                {"asn": 64500, "name": "PEER-AS", "country": "DE"},
+                {"asn": 64502, "name": "ORIGIN-AS", "country": "US"}
+            ]))?;
+        db.asinfo().store_from_jsonl(&records, "test://source")?;
+        let as2rel_db = crate::database::MonocleDatabase::open_in_memory()?;
+        for rel in [0, 1] {
+            as2rel_db.connection().execute(
+                "INSERT INTO as2rel (asn1, asn2, paths_count, peers_count, rel)
+                 VALUES (64500, 64501, 0, 10, ?1)",
+                [rel],
+            )?;
+        }
+        let withdrawal = BgpElem {
+            elem_type: ElemType::WITHDRAW,
+            as_path: None,
+            origin_asns: None,
+            ..test_elem()
+        };
+
+        let mut store = ElemParquetStore::new(
+            &path,
+            &["rpki", "peer_name", "origin_country", "as_path_rel"],
+        )?
+        .with_rpki(RpkiValidator::from_trie(trie, "test"))
+        .with_as_info(AsInfoEnricher::new(db)?)
+        .with_as_path_rel(AsPathAnnotator::new(
+            as2rel_db,
+            crate::lens::as2rel::PathRelThresholds::default(),
+        )?);
+        store.write_elems([(&test_elem(), None), (&withdrawal, None)])?;
+        store.finish()?;
+
+        let batch = &read_batches(&path)[0];
+        assert_eq!(
+            dictionary_column(batch, "rpki"),
+            vec![Some("valid".to_string()), None]
+        );
+        let column = |name: &str| -> Vec<Option<String>> {
+            let values = batch
+                .column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap();
+            values.iter().map(|v| v.map(str::to_string)).collect()
+        };
+        assert_eq!(
+            column("peer_name"),
+            vec![Some("PEER-AS".to_string()), Some("PEER-AS".to_string())]
+        );
+        assert_eq!(column("origin_country"), vec![Some("US".to_string()), None]);
+        assert_eq!(
+            column("as_path_rel"),
+            vec![Some("64500 >c 64501 ? 64502".to_string()), None]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rib_parquet_snapshots_and_updates() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("rib.parquet");
+        let mut store = RibParquetStore::new(&path)?;
+
+        let mut state = RibStateStore::new_temp()?;
+        state.upsert_entry(StoredRibEntry::from_elem(Arc::from("rrc00"), test_elem()))?;
+        store.insert_snapshot(1704067200, &state, &[])?;
+
+        let update = StoredRibUpdate::from_elem(
+            1704069000,
+            Arc::from("rrc00"),
+            test_elem(),
+            ElemType::WITHDRAW,
+        );
+        store.insert_snapshot(1704069000, &state, &[update])?;
+        let updates_path = store.finish()?.expect("updates file");
+        assert_eq!(updates_path, dir.path().join("rib.updates.parquet"));
+
+        let ribs: usize = read_batches(&path).iter().map(|b| b.num_rows()).sum();
+        assert_eq!(ribs, 2);
+
+        let updates = read_batches(&updates_path);
+        assert_eq!(updates[0].num_rows(), 1);
+        let as_path = updates[0]
+            .column_by_name("as_path")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap()
+            .value(0);
+        let hops = as_path.as_any().downcast_ref::<UInt32Array>().unwrap();
+        assert_eq!(hops.values().to_vec(), vec![64500, 64501, 64502]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_as_path_with_set() {
+        assert_eq!(
+            parse_as_path("64496 64497 {64498,64499}"),
+            vec![Some(64496), Some(64497), Some(64498), Some(64499)]
+        );
+    }
+}
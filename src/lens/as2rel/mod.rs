@@ -4,9 +4,11 @@
 //! It uses SQLite as the backend database.
 
 pub mod args;
+pub mod path;
 pub mod types;
 
 pub use args::{As2relOutputArgs, As2relSearchArgs, As2relUpdateArgs, RelationshipFilter};
+pub use path::{AnnotatedAsPath, AsPathAnnotator, HopRel, PathRelThresholds, RelHop};
 pub use types::{
     As2relDataMeta, As2relOutputFormat, As2relSearchResult, As2relSearchResultWithName,
     As2relSortOrder, As2relUpdateProgress, As2relUpdateStage,
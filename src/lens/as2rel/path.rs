@@ -0,0 +1,498 @@
+//! AS relationship annotation of AS paths.
+//!
+//! [`AsPathAnnotator`] labels each pair of adjacent ASes in a path with their
+//! relationship from the local as2rel data, read left to right:
+//!
+//! | Label | Relationship |
+//! |-------|--------------|
+//! | `>c` | the right AS is a customer of the left AS (provider-to-customer) |
+//! | `>p` | the right AS is a provider of the left AS (customer-to-provider) |
+//! | `p2p` | the ASes peer |
+//! | `?` | unknown, or below the [`PathRelThresholds`] |
+//!
+//! For example `174 p2p 3356 >c 13335`. Prepends are collapsed first. A path
+//! is valley-free if, read from the origin, it goes up to providers, crosses
+//! at most one peering link and then only goes down to customers; read left
+//! to right that is `>p`* `p2p`? `>c`*. Hops that break this are marked as
+//! violations, such as a customer announcing routes of one provider to
+//! another (`>c` followed by `>p`). Unknown hops are never violations.
+//!
+//! Relationships are looked up once per AS pair and cached in memory. Failed
+//! lookups are logged, treated as unknown and not cached.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+
+use anyhow::{anyhow, Result};
+use bgpkit_parser::models::AsPath;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::MonocleConfig;
+use crate::database::{As2relRecord, MonocleDatabase};
+
+/// Relationship of two adjacent ASes in a path, from the left AS to the right AS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HopRel {
+    /// The right AS is a customer of the left AS
+    ProviderToCustomer,
+    /// The right AS is a provider of the left AS
+    CustomerToProvider,
+    /// The ASes peer
+    PeerToPeer,
+    /// No relationship known, or too little confidence in it
+    Unknown,
+}
+
+impl HopRel {
+    /// Label of the relationship: `>c`, `>p`, `p2p` or `?`
+    pub fn label(&self) -> &'static str {
+        match self {
+            HopRel::ProviderToCustomer => ">c",
+            HopRel::CustomerToProvider => ">p",
+            HopRel::PeerToPeer => "p2p",
+            HopRel::Unknown => "?",
+        }
+    }
+}
+
+impl fmt::Display for HopRel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Confidence required to label a hop, otherwise it is [`HopRel::Unknown`]
+///
+/// The as2rel data counts how many collector peers observe each AS pair and
+/// each direction of a provider relationship; the remaining observations of a
+/// pair are taken as peering. The relationship with the most observations is
+/// used if it meets both thresholds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct PathRelThresholds {
+    /// Minimum number of peers observing an AS relationship for the as_path_rel field
+    #[cfg_attr(
+        feature = "cli",
+        clap(long = "rel-min-peers", value_name = "N", default_value_t = 1)
+    )]
+    pub min_peers_count: u32,
+
+    /// Minimum share of a pair's observations agreeing on its AS relationship for the
+    /// as_path_rel field, in percent (0-100)
+    #[cfg_attr(
+        feature = "cli",
+        clap(
+            long = "rel-min-agreement",
+            value_name = "PERCENT",
+            default_value_t = 0.0
+        )
+    )]
+    pub min_agreement_pct: f32,
+}
+
+impl Default for PathRelThresholds {
+    fn default() -> Self {
+        Self {
+            min_peers_count: 1,
+            min_agreement_pct: 0.0,
+        }
+    }
+}
+
+impl PathRelThresholds {
+    /// Check that the agreement is a percentage
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=100.0).contains(&self.min_agreement_pct) {
+            return Err(anyhow!("--rel-min-agreement must be between 0 and 100"));
+        }
+        Ok(())
+    }
+}
+
+/// One annotated pair of adjacent ASes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelHop {
+    pub asn1: u32,
+    pub asn2: u32,
+    pub rel: HopRel,
+    /// Number of peers observing the most observed relationship of the pair,
+    /// 0 for pairs without as2rel data
+    pub peers_count: u32,
+    /// Whether this hop breaks the valley-free property of the path
+    pub violation: bool,
+}
+
+/// An AS path with the relationship of each hop
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnnotatedAsPath {
+    /// The path with prepends collapsed
+    pub asns: Vec<u32>,
+    /// Hops between adjacent ASNs, `asns.len() - 1` of them
+    pub hops: Vec<RelHop>,
+}
+
+impl AnnotatedAsPath {
+    /// Whether no hop breaks the valley-free property
+    pub fn is_valley_free(&self) -> bool {
+        !self.hops.iter().any(|hop| hop.violation)
+    }
+}
+
+/// `174 p2p 3356 >c 13335`, with `!` after the label of violating hops
+impl fmt::Display for AnnotatedAsPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(first) = self.asns.first() else {
+            return Ok(());
+        };
+        write!(f, "{first}")?;
+        for hop in &self.hops {
+            let mark = if hop.violation { "!" } else { "" };
+            write!(f, " {}{} {}", hop.rel, mark, hop.asn2)?;
+        }
+        Ok(())
+    }
+}
+
+/// Observations of one AS pair, keyed by the lower ASN
+#[derive(Debug, Clone, Copy, Default)]
+struct PairCounts {
+    connected: u32,
+    low_provider: u32,
+    high_provider: u32,
+}
+
+impl PairCounts {
+    fn from_records(low: u32, records: &[As2relRecord]) -> Self {
+        let mut counts = Self::default();
+        for record in records {
+            // rel 1: asn1 is the provider of asn2, -1: asn2 is the provider of asn1
+            let provider = match record.rel {
+                0 => {
+                    counts.connected = counts.connected.max(record.peers_count);
+                    continue;
+                }
+                1 => record.asn1,
+                -1 => record.asn2,
+                _ => continue,
+            };
+            if provider == low {
+                counts.low_provider += record.peers_count;
+            } else {
+                counts.high_provider += record.peers_count;
+            }
+        }
+        counts
+    }
+
+    /// Relationship from `asn1` to `asn2` and the number of peers observing it
+    fn classify(&self, asn1: u32, asn2: u32, thresholds: &PathRelThresholds) -> (HopRel, u32) {
+        let (asn1_provider, asn2_provider) = match asn1 <= asn2 {
+            true => (self.low_provider, self.high_provider),
+            false => (self.high_provider, self.low_provider),
+        };
+        let total = self.connected.max(asn1_provider + asn2_provider);
+        let peering = total - asn1_provider - asn2_provider;
+        let (rel, count) = [
+            (HopRel::ProviderToCustomer, asn1_provider),
+            (HopRel::CustomerToProvider, asn2_provider),
+            (HopRel::PeerToPeer, peering),
+        ]
+        .into_iter()
+        .fold((HopRel::Unknown, 0), |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        });
+        let agreement_pct = match total {
+            0 => 0.0,
+            _ => count as f32 * 100.0 / total as f32,
+        };
+        if count == 0
+            || count < thresholds.min_peers_count
+            || agreement_pct < thresholds.min_agreement_pct
+        {
+            return (HopRel::Unknown, count);
+        }
+        (rel, count)
+    }
+}
+
+/// Mark the hops that break the valley-free property, see the [module docs](self)
+fn mark_violations(hops: &mut [RelHop]) {
+    // Last known hop: 0 towards a provider (or none yet), 1 peering, 2 towards a customer
+    let mut phase = 0;
+    for hop in hops {
+        let next = match hop.rel {
+            HopRel::CustomerToProvider => 0,
+            HopRel::PeerToPeer => 1,
+            HopRel::ProviderToCustomer => 2,
+            HopRel::Unknown => continue,
+        };
+        hop.violation = phase > 0 && (next < phase || (next == 1 && phase == 1));
+        phase = next;
+    }
+}
+
+/// Annotates AS paths with AS relationships, see the [module docs](self)
+///
+/// Cloning is cheap and clones share the cache. The database is only locked
+/// to look up AS pairs that are not cached yet.
+#[derive(Clone)]
+pub struct AsPathAnnotator {
+    db: Arc<Mutex<MonocleDatabase>>,
+    cache: Arc<RwLock<HashMap<(u32, u32), PairCounts>>>,
+    thresholds: PathRelThresholds,
+}
+
+impl fmt::Debug for AsPathAnnotator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsPathAnnotator")
+            .field("thresholds", &self.thresholds)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AsPathAnnotator {
+    /// Annotate from the as2rel data of `db`, which must not be empty
+    pub fn new(db: MonocleDatabase, thresholds: PathRelThresholds) -> Result<Self> {
+        thresholds.validate()?;
+        if db.as2rel().is_empty() {
+            return Err(anyhow!(
+                "AS2Rel data is empty, run `monocle config update --as2rel` first"
+            ));
+        }
+        Ok(Self {
+            db: Arc::new(Mutex::new(db)),
+            cache: Arc::new(RwLock::new(HashMap::new())),
+            thresholds,
+        })
+    }
+
+    /// Annotate from the database in the configured data directory, refreshing
+    /// the as2rel data first when it is empty or outdated
+    pub fn from_config(config: &MonocleConfig, thresholds: PathRelThresholds) -> Result<Self> {
+        let db = MonocleDatabase::open_in_dir(&config.data_dir)?;
+        if db.needs_as2rel_refresh(config.as2rel_cache_ttl()) {
+            info!("Refreshing AS2Rel data for AS path annotation");
+            db.refresh_as2rel()
+                .map_err(|e| anyhow!("Failed to refresh AS2Rel data: {}", e))?;
+        }
+        Self::new(db, thresholds)
+    }
+
+    /// The confidence required to label a hop
+    pub fn thresholds(&self) -> &PathRelThresholds {
+        &self.thresholds
+    }
+
+    /// Annotate `path`, `None` for empty paths and paths with an AS_SET of
+    /// more than one ASN or confederation segments
+    pub fn annotate(&self, path: &AsPath) -> Option<AnnotatedAsPath> {
+        let asns = path.to_u32_vec_opt(true)?;
+        Some(self.annotate_asns(&asns))
+    }
+
+    /// Annotate the path of `asns`, leftmost (neighbor) AS first
+    pub fn annotate_asns(&self, asns: &[u32]) -> AnnotatedAsPath {
+        let mut asns = asns.to_vec();
+        asns.dedup();
+        let mut hops: Vec<RelHop> = asns
+            .windows(2)
+            .map(|pair| {
+                let (asn1, asn2) = (pair[0], pair[1]);
+                let (rel, peers_count) =
+                    self.pair_counts(asn1, asn2)
+                        .classify(asn1, asn2, &self.thresholds);
+                RelHop {
+                    asn1,
+                    asn2,
+                    rel,
+                    peers_count,
+                    violation: false,
+                }
+            })
+            .collect();
+        mark_violations(&mut hops);
+        AnnotatedAsPath { asns, hops }
+    }
+
+    /// Observations of the pair of `asn1` and `asn2`, from the cache if possible
+    fn pair_counts(&self, asn1: u32, asn2: u32) -> PairCounts {
+        let key = (asn1.min(asn2), asn1.max(asn2));
+        if let Some(counts) = self
+            .cache
+            .read()
+            .ok()
+            .and_then(|cache| cache.get(&key).copied())
+        {
+            return counts;
+        }
+        let records = match self.db.lock() {
+            Ok(db) => db.as2rel().search_pair(key.0, key.1),
+            Err(_) => Err(anyhow!("database lock poisoned")),
+        };
+        match records {
+            Ok(records) => {
+                let counts = PairCounts::from_records(key.0, &records);
+                if let Ok(mut cache) = self.cache.write() {
+                    cache.insert(key, counts);
+                }
+                counts
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to look up the relationship of AS{} and AS{}: {}",
+                    key.0, key.1, e
+                );
+                PairCounts::default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn annotator(thresholds: PathRelThresholds) -> AsPathAnnotator {
+        let db = MonocleDatabase::open_in_memory().unwrap();
+        // 3356 provides transit to 13335 and 64500, 174 and 3356 peer, 64500
+        // is a customer of 174 as well, and few peers see 3356 provide transit
+        // to 64501
+        for (asn1, asn2, peers_count, rel) in [
+            (3356, 13335, 100, 0),
+            (3356, 13335, 90, 1),
+            (174, 3356, 80, 0),
+            (64500, 174, 50, 0),
+            (64500, 174, 45, -1),
+            (64500, 3356, 40, 0),
+            (64500, 3356, 40, -1),
+            (3356, 64501, 20, 0),
+            (3356, 64501, 5, 1),
+        ] {
+            db.connection()
+                .execute(
+                    "INSERT INTO as2rel (asn1, asn2, paths_count, peers_count, rel)
+                     VALUES (?1, ?2, 0, ?3, ?4)",
+                    rusqlite::params![asn1, asn2, peers_count, rel],
+                )
+                .unwrap();
+        }
+        AsPathAnnotator::new(db, thresholds).unwrap()
+    }
+
+    #[test]
+    fn test_annotate() {
+        let annotator = annotator(PathRelThresholds::default());
+
+        let path = annotator.annotate_asns(&[174, 3356, 13335, 13335]);
+        assert_eq!(path.to_string(), "174 p2p 3356 >c 13335");
+        assert_eq!(path.hops[1].peers_count, 90);
+        assert!(path.is_valley_free());
+
+        // Provider-to-customer seen from the customer side, then an unknown pair
+        let path = annotator.annotate_asns(&[13335, 3356, 64496]);
+        assert_eq!(path.to_string(), "13335 >p 3356 ? 64496");
+        assert!(path.is_valley_free());
+
+        let path: AsPath = AsPath::from_sequence([174, 3356, 13335]);
+        assert_eq!(
+            annotator.annotate(&path).unwrap().to_string(),
+            "174 p2p 3356 >c 13335"
+        );
+    }
+
+    #[test]
+    fn test_valley_free_violations() {
+        let annotator = annotator(PathRelThresholds::default());
+
+        // 64500 leaks routes of its provider 174 to its provider 3356
+        let path = annotator.annotate_asns(&[3356, 64500, 174]);
+        assert_eq!(path.to_string(), "3356 >c 64500 >p! 174");
+        assert!(!path.is_valley_free());
+        assert!(path.hops[1].violation);
+
+        // Routes learned over one peering link announced over another
+        let path = annotator.annotate_asns(&[64501, 3356, 174]);
+        assert_eq!(path.to_string(), "64501 p2p 3356 p2p! 174");
+
+        // Down, across a peering link and up again is fine
+        let path = annotator.annotate_asns(&[13335, 3356, 174, 64500]);
+        assert_eq!(path.to_string(), "13335 >p 3356 p2p 174 >c 64500");
+        assert!(path.is_valley_free());
+    }
+
+    #[test]
+    fn test_thresholds() {
+        // The 3356-64501 link is only seen as a provider link by 5 of 20 peers
+        let path = annotator(PathRelThresholds::default()).annotate_asns(&[3356, 64501]);
+        assert_eq!(path.to_string(), "3356 p2p 64501");
+
+        let strict = annotator(PathRelThresholds {
+            min_peers_count: 30,
+            min_agreement_pct: 0.0,
+        });
+        assert_eq!(
+            strict.annotate_asns(&[174, 3356, 13335]).to_string(),
+            "174 p2p 3356 >c 13335"
+        );
+        assert_eq!(
+            strict.annotate_asns(&[3356, 64501]).to_string(),
+            "3356 ? 64501"
+        );
+
+        let agreeing = annotator(PathRelThresholds {
+            min_peers_count: 1,
+            min_agreement_pct: 95.0,
+        });
+        assert_eq!(
+            agreeing
+                .annotate_asns(&[64500, 174, 3356, 13335])
+                .to_string(),
+            "64500 ? 174 p2p 3356 ? 13335"
+        );
+
+        assert!(AsPathAnnotator::new(
+            MonocleDatabase::open_in_memory().unwrap(),
+            PathRelThresholds::default()
+        )
+        .is_err());
+        assert!(PathRelThresholds {
+            min_peers_count: 1,
+            min_agreement_pct: 120.0,
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_failed_lookups_not_cached() {
+        let annotator = annotator(PathRelThresholds::default());
+        let rename = |from: &str, to: &str| {
+            annotator
+                .db
+                .lock()
+                .unwrap()
+                .connection()
+                .execute(&format!("ALTER TABLE {from} RENAME TO {to}"), [])
+                .unwrap();
+        };
+
+        rename("as2rel", "as2rel_hidden");
+        assert_eq!(
+            annotator.annotate_asns(&[174, 3356]).to_string(),
+            "174 ? 3356"
+        );
+        rename("as2rel_hidden", "as2rel");
+        assert_eq!(
+            annotator.annotate_asns(&[174, 3356]).to_string(),
+            "174 p2p 3356"
+        );
+    }
+}
@@ -251,6 +251,51 @@ impl CountryLens {
         entries
     }
 
+    /// Resolve a country code or name to a two-letter country code
+    ///
+    /// A name must match exactly or be the only match. An unknown two-letter
+    /// input is taken as a code as is, since country data may be unavailable.
+    pub fn resolve_code(&self, input: &str) -> Result<String> {
+        let matches = self.lookup(input);
+
+        if matches.is_empty() {
+            if input.len() == 2 {
+                return Ok(input.to_uppercase());
+            }
+            return Err(anyhow!("Unknown country '{}'", input));
+        }
+
+        let exact_name_matches: Vec<_> = matches
+            .iter()
+            .filter(|entry| entry.name.eq_ignore_ascii_case(input))
+            .collect();
+        if exact_name_matches.len() == 1 {
+            return Ok(exact_name_matches[0].code.clone());
+        }
+
+        let exact_code_matches: Vec<_> = matches
+            .iter()
+            .filter(|entry| entry.code.eq_ignore_ascii_case(input))
+            .collect();
+        if exact_code_matches.len() == 1 {
+            return Ok(exact_code_matches[0].code.clone());
+        }
+
+        if matches.len() == 1 {
+            return Ok(matches[0].code.clone());
+        }
+
+        Err(anyhow!(
+            "Country '{}' is ambiguous; matches: {}",
+            input,
+            matches
+                .iter()
+                .map(|entry| format!("{} ({})", entry.name, entry.code))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+
     /// Get all countries sorted by code
     pub fn all(&self) -> Vec<CountryEntry> {
         let mut entries = self.data().entries.clone();
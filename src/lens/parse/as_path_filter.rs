@@ -0,0 +1,312 @@
+//! Structural AS path filters.
+//!
+//! The `--as-path` regex matches the text of a path. These filters match its
+//! shape instead, for hunting heavy prepending, long paths, AS_SETs, bogon
+//! ASNs and loops:
+//!
+//! | Filter | Values | Matches |
+//! |--------|--------|---------|
+//! | `path_len` | `N`, `A..B`, `A..` or `..B` | hops in the path, an AS_SET counts as one |
+//! | `unique_path_len` | same | hops once prepends are collapsed |
+//! | `prepend_count` | same | `path_len - unique_path_len` |
+//! | `as_set` | `true` or `false` | the path has an AS_SET or AS_CONFED_SET |
+//! | `private_asn` | `true` or `false` | the path has a private ASN (RFC 6996) |
+//! | `reserved_asn` | `true` or `false` | the path has a reserved or documentation ASN |
+//! | `path_loop` | `true` or `false` | an ASN appears again after a different one |
+//!
+//! Confederation segments do not count towards the lengths and are not
+//! checked for private ASNs, which they typically use by design. Elements
+//! without an AS path (withdrawals) never match a positive filter and always
+//! pass a negated one.
+
+use anyhow::Result;
+use bgpkit_parser::models::{AsPath, AsPathSegment};
+use bgpkit_parser::BgpElem;
+use serde::{Deserialize, Serialize};
+
+use super::attr_filter::{ValueRange, ValueSet};
+
+#[cfg(feature = "cli")]
+use clap::Args;
+
+/// Structural AS path filters, shared by [`ParseFilters`](super::ParseFilters)
+/// and [`RibFilters`](crate::lens::rib::RibFilters)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(Args))]
+pub struct AsPathFilters {
+    /// Filter by AS path length, comma-separated values or inclusive ranges (e.g., `10..`).
+    /// Prefix with ! to exclude.
+    #[cfg_attr(feature = "cli", clap(long, value_delimiter = ','))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_len: Vec<String>,
+
+    /// Filter by AS path length without prepends, comma-separated values or inclusive ranges.
+    /// Prefix with ! to exclude.
+    #[cfg_attr(feature = "cli", clap(long, value_delimiter = ','))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unique_path_len: Vec<String>,
+
+    /// Filter by number of prepended hops, comma-separated values or inclusive ranges (e.g.,
+    /// `3..`). Prefix with ! to exclude.
+    #[cfg_attr(feature = "cli", clap(long, value_delimiter = ','))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prepend_count: Vec<String>,
+
+    /// Filter by whether the AS path contains an AS_SET: true or false
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub as_set: Option<bool>,
+
+    /// Filter by whether the AS path contains a private ASN: true or false
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_asn: Option<bool>,
+
+    /// Filter by whether the AS path contains a reserved ASN: true or false
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reserved_asn: Option<bool>,
+
+    /// Filter by whether the AS path has a loop: true or false
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_loop: Option<bool>,
+}
+
+impl AsPathFilters {
+    /// Whether no filter is set
+    pub fn is_empty(&self) -> bool {
+        self.path_len.is_empty()
+            && self.unique_path_len.is_empty()
+            && self.prepend_count.is_empty()
+            && self.as_set.is_none()
+            && self.private_asn.is_none()
+            && self.reserved_asn.is_none()
+            && self.path_loop.is_none()
+    }
+
+    /// Parse the filters into an [`AsPathMatcher`]
+    pub fn matcher(&self) -> Result<AsPathMatcher> {
+        Ok(AsPathMatcher {
+            path_len: ValueSet::parse(&self.path_len, "path-len", str::parse)?,
+            unique_path_len: ValueSet::parse(&self.unique_path_len, "unique-path-len", str::parse)?,
+            prepend_count: ValueSet::parse(&self.prepend_count, "prepend-count", str::parse)?,
+            as_set: self.as_set,
+            private_asn: self.private_asn,
+            reserved_asn: self.reserved_asn,
+            path_loop: self.path_loop,
+        })
+    }
+}
+
+/// Parsed [`AsPathFilters`]
+#[derive(Debug, Clone, Default)]
+pub struct AsPathMatcher {
+    path_len: Option<ValueSet<ValueRange>>,
+    unique_path_len: Option<ValueSet<ValueRange>>,
+    prepend_count: Option<ValueSet<ValueRange>>,
+    as_set: Option<bool>,
+    private_asn: Option<bool>,
+    reserved_asn: Option<bool>,
+    path_loop: Option<bool>,
+}
+
+impl AsPathMatcher {
+    /// Whether no filter is set
+    pub fn is_empty(&self) -> bool {
+        self.path_len.is_none()
+            && self.unique_path_len.is_none()
+            && self.prepend_count.is_none()
+            && self.as_set.is_none()
+            && self.private_asn.is_none()
+            && self.reserved_asn.is_none()
+            && self.path_loop.is_none()
+    }
+
+    /// Whether `elem` passes all filters
+    pub fn matches(&self, elem: &BgpElem) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        self.matches_stats(elem.as_path.as_ref().map(AsPathStats::new).as_ref())
+    }
+
+    /// Whether a path with `stats`, or no path for `None`, passes all filters
+    pub fn matches_stats(&self, stats: Option<&AsPathStats>) -> bool {
+        fn check(set: &Option<ValueSet<ValueRange>>, value: Option<u32>) -> bool {
+            set.as_ref().is_none_or(|set| {
+                set.matches(|range| value.is_some_and(|value| range.contains(value)))
+            })
+        }
+        fn check_flag(expected: Option<bool>, value: Option<bool>) -> bool {
+            // Without a path, a positive flag filter cannot match, and a
+            // negated one (`false`) passes
+            expected.is_none_or(|expected| value.unwrap_or(false) == expected)
+        }
+
+        check(&self.path_len, stats.map(|s| s.path_len))
+            && check(&self.unique_path_len, stats.map(|s| s.unique_path_len))
+            && check(&self.prepend_count, stats.map(|s| s.prepend_count))
+            && check_flag(self.as_set, stats.map(|s| s.has_as_set))
+            && check_flag(self.private_asn, stats.map(|s| s.has_private_asn))
+            && check_flag(self.reserved_asn, stats.map(|s| s.has_reserved_asn))
+            && check_flag(self.path_loop, stats.map(|s| s.has_loop))
+    }
+}
+
+/// Structural properties of an AS path, see the [module docs](self)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AsPathStats {
+    pub path_len: u32,
+    pub unique_path_len: u32,
+    pub prepend_count: u32,
+    pub has_as_set: bool,
+    pub has_private_asn: bool,
+    pub has_reserved_asn: bool,
+    pub has_loop: bool,
+}
+
+impl AsPathStats {
+    pub fn new(path: &AsPath) -> Self {
+        let mut stats = Self::default();
+        // Hops with prepends collapsed; `None` stands for an AS_SET
+        let mut hops: Vec<Option<u32>> = Vec::new();
+        for segment in path.iter_segments() {
+            match segment {
+                AsPathSegment::AsSequence(asns) => {
+                    for asn in asns {
+                        let asn = asn.to_u32();
+                        stats.path_len += 1;
+                        stats.check_asn(asn);
+                        if hops.last() != Some(&Some(asn)) {
+                            hops.push(Some(asn));
+                        }
+                    }
+                }
+                AsPathSegment::AsSet(asns) => {
+                    stats.path_len += 1;
+                    stats.has_as_set = true;
+                    asns.iter().for_each(|asn| stats.check_asn(asn.to_u32()));
+                    hops.push(None);
+                }
+                AsPathSegment::ConfedSet(_) => stats.has_as_set = true,
+                AsPathSegment::ConfedSequence(_) => {}
+            }
+        }
+        stats.unique_path_len = hops.len() as u32;
+        stats.prepend_count = stats.path_len - stats.unique_path_len;
+        let mut seen = std::collections::HashSet::new();
+        stats.has_loop = !hops.iter().flatten().all(|asn| seen.insert(*asn));
+        stats
+    }
+
+    fn check_asn(&mut self, asn: u32) {
+        self.has_private_asn |= is_private_asn(asn);
+        self.has_reserved_asn |= is_reserved_asn(asn);
+    }
+}
+
+/// Private use ASNs (RFC 6996)
+pub fn is_private_asn(asn: u32) -> bool {
+    matches!(asn, 64512..=65534 | 4_200_000_000..=4_294_967_294)
+}
+
+/// Reserved ASNs: 0 (RFC 7607), AS_TRANS 23456 (RFC 6793), documentation
+/// ASNs (RFC 5398), 65535 and 4294967295 (RFC 7300) and the IANA reserved
+/// block 65552-131071
+pub fn is_reserved_asn(asn: u32) -> bool {
+    matches!(
+        asn,
+        0 | 23456 | 64496..=64511 | 65535 | 65536..=65551 | 65552..=131071 | 4_294_967_295
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn stats(path: &[u32]) -> AsPathStats {
+        AsPathStats::new(&AsPath::from_sequence(path))
+    }
+
+    #[test]
+    fn test_as_path_stats() {
+        let prepended = stats(&[174, 3356, 13335, 13335, 13335]);
+        assert_eq!(prepended.path_len, 5);
+        assert_eq!(prepended.unique_path_len, 3);
+        assert_eq!(prepended.prepend_count, 2);
+        assert!(!prepended.has_loop);
+        assert!(!prepended.has_private_asn && !prepended.has_reserved_asn);
+
+        assert!(stats(&[174, 3356, 174]).has_loop);
+        assert!(stats(&[174, 64512]).has_private_asn);
+        assert!(stats(&[174, 4_200_000_001]).has_private_asn);
+        assert!(stats(&[174, 23456]).has_reserved_asn);
+        assert!(stats(&[64496]).has_reserved_asn);
+
+        let mut path = AsPath::from_sequence([174, 3356]);
+        path.append_segment(AsPathSegment::set([64500, 64501]));
+        let with_set = AsPathStats::new(&path);
+        assert!(with_set.has_as_set);
+        assert_eq!(with_set.path_len, 3);
+        assert_eq!(with_set.unique_path_len, 3);
+        assert!(with_set.has_reserved_asn && !with_set.has_private_asn);
+    }
+
+    #[test]
+    fn test_as_path_matcher() {
+        let elem = BgpElem {
+            as_path: Some(AsPath::from_sequence([174, 3356, 13335, 13335, 13335])),
+            ..Default::default()
+        };
+        let matches = |filters: AsPathFilters| filters.matcher().unwrap().matches(&elem);
+        let ranges = |values: &[&str]| values.iter().map(ToString::to_string).collect();
+
+        assert!(matches(AsPathFilters::default()));
+        assert!(matches(AsPathFilters {
+            path_len: ranges(&["5.."]),
+            unique_path_len: ranges(&["..3"]),
+            prepend_count: ranges(&["2"]),
+            ..Default::default()
+        }));
+        assert!(!matches(AsPathFilters {
+            prepend_count: ranges(&["3.."]),
+            ..Default::default()
+        }));
+        assert!(!matches(AsPathFilters {
+            path_len: ranges(&["!5"]),
+            ..Default::default()
+        }));
+        assert!(matches(AsPathFilters {
+            as_set: Some(false),
+            path_loop: Some(false),
+            private_asn: Some(false),
+            ..Default::default()
+        }));
+        assert!(!matches(AsPathFilters {
+            path_loop: Some(true),
+            ..Default::default()
+        }));
+
+        let withdrawal = BgpElem::default();
+        let filters = AsPathFilters {
+            path_len: ranges(&["!10.."]),
+            as_set: Some(false),
+            ..Default::default()
+        };
+        assert!(filters.matcher().unwrap().matches(&withdrawal));
+        let filters = AsPathFilters {
+            path_len: ranges(&["0.."]),
+            ..Default::default()
+        };
+        assert!(!filters.matcher().unwrap().matches(&withdrawal));
+
+        assert!(AsPathFilters {
+            prepend_count: ranges(&["many"]),
+            ..Default::default()
+        }
+        .matcher()
+        .is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! Router-style AS path regular expressions.
+//!
+//! `--as-path` patterns are matched against the text of an AS path, e.g.
+//! `174 3356 {64500,64501}`. By default ([`AsPathStyle::Raw`]) the pattern is
+//! a plain [`regex`] over that text, so `174` also matches inside `1174`.
+//! Operators usually paste patterns written for their routers instead, which
+//! [`translate`] rewrites into an equivalent raw regex:
+//!
+//! | Style | Example | Semantics |
+//! |-------|---------|-----------|
+//! | `raw` | `^174 ` | regex over characters, unchanged |
+//! | `cisco` | `_174_`, `^3356_`, `_13335$` | `_` matches a delimiter: start or end of the path, a space, or the braces and commas of an AS_SET; everything else is a character regex |
+//! | `junos` | `.* 13335`, `3356 .{1,3}`, `[64512-65534]` | terms are whole ASNs: a number, an inclusive range `A-B`, a set `[A B-C]` or `.` (any ASN, or an AS_SET); `*`, `+`, `?`, `{n,m}`, `\|` and parentheses apply to terms; the pattern must match the whole path |
+//!
+//! Withdrawals have no AS path and never match an `--as-path` filter.
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+
+/// Dialect of an `--as-path` pattern, see the [module docs](self)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum AsPathStyle {
+    /// Plain regex over the AS path text
+    #[default]
+    Raw,
+    /// Cisco IOS: `_` matches an ASN boundary
+    Cisco,
+    /// Junos: terms match whole ASNs, anchored to the full path
+    Junos,
+}
+
+impl std::fmt::Display for AsPathStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AsPathStyle::Raw => "raw",
+            AsPathStyle::Cisco => "cisco",
+            AsPathStyle::Junos => "junos",
+        })
+    }
+}
+
+/// Characters between ASNs in the AS path text, besides start and end
+const CISCO_DELIMITER: &str = r"(?:^|$|[ {},])";
+
+/// Start of an ASN token in the AS path text
+const JUNOS_TOKEN_START: &str = "(?:^| )";
+
+/// Translate `pattern` in `style` into a raw regex over the AS path text
+pub fn translate(pattern: &str, style: AsPathStyle) -> Result<String> {
+    match style {
+        AsPathStyle::Raw => Ok(pattern.to_string()),
+        AsPathStyle::Cisco => Ok(translate_cisco(pattern)),
+        AsPathStyle::Junos => translate_junos(pattern),
+    }
+}
+
+/// Translate and compile `pattern`
+pub fn compile(pattern: &str, style: AsPathStyle) -> Result<Regex> {
+    let translated = translate(pattern, style)
+        .map_err(|e| anyhow!("Invalid --as-path regex '{}': {}", pattern, e))?;
+    Regex::new(&translated).map_err(|e| anyhow!("Invalid --as-path regex '{}': {}", pattern, e))
+}
+
+fn translate_cisco(pattern: &str) -> String {
+    let mut out = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                out.push(c);
+                out.extend(chars.next());
+            }
+            '[' if !in_class => {
+                in_class = true;
+                out.push(c);
+            }
+            ']' if in_class => {
+                in_class = false;
+                out.push(c);
+            }
+            '_' if !in_class => out.push_str(CISCO_DELIMITER),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn translate_junos(pattern: &str) -> Result<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut body = String::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let c = chars[pos];
+        match c {
+            c if c.is_whitespace() => pos += 1,
+            // Junos patterns always match the whole path
+            '^' | '$' => pos += 1,
+            '(' | ')' | '|' | '*' | '+' | '?' => {
+                body.push(c);
+                pos += 1;
+            }
+            '{' => {
+                let end = chars[pos..]
+                    .iter()
+                    .position(|c| *c == '}')
+                    .map(|offset| pos + offset)
+                    .ok_or_else(|| anyhow!("unclosed '{{' at column {}", pos + 1))?;
+                let repeat: String = chars[pos + 1..end].iter().collect();
+                if repeat.is_empty() || !repeat.chars().all(|c| c.is_ascii_digit() || c == ',') {
+                    return Err(anyhow!("invalid repeat '{{{}}}'", repeat));
+                }
+                body.push_str(&format!("{{{repeat}}}"));
+                pos = end + 1;
+            }
+            '.' => {
+                body.push_str(&format!("(?:{JUNOS_TOKEN_START}[^ ]+)"));
+                pos += 1;
+            }
+            '[' => {
+                let end = chars[pos..]
+                    .iter()
+                    .position(|c| *c == ']')
+                    .map(|offset| pos + offset)
+                    .ok_or_else(|| anyhow!("unclosed '[' at column {}", pos + 1))?;
+                let set: String = chars[pos + 1..end].iter().collect();
+                let alternatives = set
+                    .split_whitespace()
+                    .map(asn_term)
+                    .collect::<Result<Vec<_>>>()?;
+                if alternatives.is_empty() {
+                    return Err(anyhow!("empty ASN set at column {}", pos + 1));
+                }
+                body.push_str(&format!(
+                    "(?:{JUNOS_TOKEN_START}(?:{}))",
+                    alternatives.join("|")
+                ));
+                pos = end + 1;
+            }
+            c if c.is_ascii_digit() => {
+                let end = chars[pos..]
+                    .iter()
+                    .position(|c| !(c.is_ascii_digit() || *c == '-'))
+                    .map_or(chars.len(), |offset| pos + offset);
+                let term: String = chars[pos..end].iter().collect();
+                body.push_str(&format!("(?:{JUNOS_TOKEN_START}(?:{}))", asn_term(&term)?));
+                pos = end;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "unsupported character '{}' at column {} in a junos pattern",
+                    c,
+                    pos + 1
+                ))
+            }
+        }
+    }
+    Ok(format!("^(?:{body})$"))
+}
+
+/// Regex for one ASN or an inclusive `A-B` range of ASNs
+fn asn_term(term: &str) -> Result<String> {
+    let parse = |value: &str| {
+        value
+            .parse::<u32>()
+            .map_err(|_| anyhow!("invalid ASN '{}'", value))
+    };
+    match term.split_once('-') {
+        Some((low, high)) => {
+            let (low, high) = (parse(low)?, parse(high)?);
+            if low > high {
+                return Err(anyhow!("empty ASN range '{}'", term));
+            }
+            Ok(range_regex(low, high))
+        }
+        None => Ok(parse(term)?.to_string()),
+    }
+}
+
+/// Regex matching the decimal numbers from `low` to `high`, without leading
+/// zeros
+fn range_regex(low: u32, high: u32) -> String {
+    let (mut start, high) = (u64::from(low), u64::from(high));
+    let mut alternatives = Vec::new();
+    if start == 0 {
+        alternatives.push("0".to_string());
+        start = 1;
+    }
+    while start <= high {
+        // Largest power of ten block that starts at `start` and fits
+        let mut step = 1u64;
+        while start % (step * 10) == 0 && start + step * 10 - 1 <= high {
+            step *= 10;
+        }
+        let first_digit = (start / step) % 10;
+        let count = ((high + 1 - start) / step).min(10 - first_digit);
+        let last_digit = first_digit + count - 1;
+
+        let prefix = start / (step * 10);
+        let mut alternative = if prefix > 0 {
+            prefix.to_string()
+        } else {
+            String::new()
+        };
+        if first_digit == last_digit {
+            alternative.push_str(&first_digit.to_string());
+        } else {
+            alternative.push_str(&format!("[{first_digit}-{last_digit}]"));
+        }
+        let trailing = step.ilog10();
+        if trailing > 0 {
+            alternative.push_str(&format!("[0-9]{{{trailing}}}"));
+        }
+        alternatives.push(alternative);
+        start += count * step;
+    }
+    alternatives.join("|")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn is_match(pattern: &str, style: AsPathStyle, path: &str) -> bool {
+        compile(pattern, style).unwrap().is_match(path)
+    }
+
+    #[test]
+    fn test_range_regex() {
+        for (low, high) in [
+            (0, 0),
+            (0, 9),
+            (1, 1000),
+            (7, 93),
+            (64512, 65534),
+            (99, 101),
+        ] {
+            let re = Regex::new(&format!("^(?:{})$", range_regex(low, high))).unwrap();
+            for n in 0..=70000u32 {
+                assert_eq!(
+                    re.is_match(&n.to_string()),
+                    (low..=high).contains(&n),
+                    "{n}"
+                );
+            }
+            assert!(!re.is_match(&format!("0{low}")));
+        }
+        let re = Regex::new(&format!("^(?:{})$", range_regex(4_200_000_000, u32::MAX))).unwrap();
+        assert!(re.is_match("4200000000") && re.is_match("4294967295"));
+        assert!(!re.is_match("4199999999") && !re.is_match("4294967296"));
+    }
+
+    #[test]
+    fn test_cisco_style() {
+        let cisco = AsPathStyle::Cisco;
+        assert!(is_match("_174_", cisco, "3356 174 13335"));
+        assert!(is_match("_174_", cisco, "174"));
+        assert!(!is_match("_174_", cisco, "1174 3356"));
+        assert!(is_match("^3356_", cisco, "3356 174"));
+        assert!(!is_match("^3356_", cisco, "174 3356"));
+        assert!(is_match("_13335$", cisco, "174 13335"));
+        assert!(!is_match("_13335$", cisco, "174 113335"));
+        assert!(is_match("_174_3356_", cisco, "6939 174 3356 13335"));
+        assert!(is_match("_64501_", cisco, "174 {64500,64501}"));
+        assert!(is_match("^[0-9]+_13335$", cisco, "174 13335"));
+        assert!(
+            !is_match("174", AsPathStyle::Raw, "3356") && is_match("174", AsPathStyle::Raw, "1174")
+        );
+    }
+
+    #[test]
+    fn test_junos_style() {
+        let junos = AsPathStyle::Junos;
+        assert!(is_match(".* 13335", junos, "174 3356 13335"));
+        assert!(!is_match(".* 13335", junos, "174 113335"));
+        assert!(is_match("3356 .*", junos, "3356"));
+        assert!(!is_match("3356 .*", junos, "174 3356"));
+        assert!(is_match("174 .{1,2} 13335", junos, "174 3356 13335"));
+        assert!(!is_match("174 .{1,2} 13335", junos, "174 13335"));
+        assert!(is_match(".* [64512-65534] .*", junos, "174 64600 13335"));
+        assert!(!is_match(".* [64512-65534] .*", junos, "174 65535 13335"));
+        assert!(is_match("(174|3356) 13335+", junos, "3356 13335 13335"));
+        assert!(is_match("^174 .*$", junos, "174 {64500,64501}"));
+        assert!(is_match(".*", junos, ""));
+        assert!(compile("174 #", junos).is_err());
+        assert!(compile("174 {a}", junos).is_err());
+        assert!(compile("[65534-64512]", junos).is_err());
+    }
+}
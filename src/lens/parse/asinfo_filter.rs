@@ -0,0 +1,259 @@
+//! AS registration country and organization filters.
+//!
+//! `--origin-country` and `--peer-country` keep elements whose origin or peer
+//! AS is registered in one of the given countries (codes or names), and
+//! `--origin-org` those whose origin AS belongs to one of the given CAIDA
+//! AS2Org organization IDs. Like the other [`ParseFilters`](super::ParseFilters),
+//! multiple values match if any of them matches and values prefixed with `!`
+//! exclude matching elements. An element matches an origin filter if any of
+//! its origin ASNs does; withdrawals have no origin, so they never match a
+//! positive origin filter and always pass a negated one.
+//!
+//! Each value is resolved to a set of ASNs from the ASInfo data with
+//! [`ParseFilters::load_asinfo`](super::ParseFilters::load_asinfo) before
+//! parsing, so that elements are only matched against ASN sets.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use bgpkit_parser::BgpElem;
+use serde::{Deserialize, Serialize};
+
+use super::attr_filter::ValueSet;
+use crate::database::MonocleDatabase;
+use crate::lens::country::CountryLens;
+
+#[cfg(feature = "cli")]
+use clap::Args;
+
+/// Country and organization filter options of [`ParseFilters`](super::ParseFilters)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(Args))]
+pub struct AsinfoFilters {
+    /// Filter by registration country of the origin AS: country codes or names,
+    /// comma-separated. Prefix with ! to exclude
+    #[cfg_attr(feature = "cli", clap(long, value_delimiter = ','))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub origin_country: Vec<String>,
+
+    /// Filter by registration country of the peer AS: country codes or names,
+    /// comma-separated. Prefix with ! to exclude
+    #[cfg_attr(feature = "cli", clap(long, value_delimiter = ','))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub peer_country: Vec<String>,
+
+    /// Filter by organization of the origin AS: CAIDA AS2Org organization IDs
+    /// (e.g. CLOUD14-ARIN), comma-separated. Prefix with ! to exclude
+    #[cfg_attr(feature = "cli", clap(long, value_delimiter = ','))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub origin_org: Vec<String>,
+
+    /// ASN sets resolved by [`ParseFilters::load_asinfo`](super::ParseFilters::load_asinfo)
+    #[cfg_attr(feature = "cli", clap(skip))]
+    #[serde(skip)]
+    pub resolved: Option<AsinfoMatcher>,
+}
+
+impl AsinfoFilters {
+    /// Whether no country or organization filter is set
+    pub fn is_empty(&self) -> bool {
+        self.origin_country.is_empty() && self.peer_country.is_empty() && self.origin_org.is_empty()
+    }
+
+    /// Check that no value is empty and negation is consistent in each filter
+    pub fn validate(&self) -> Result<()> {
+        let not_empty = |value: &str| match value.is_empty() {
+            true => Err(anyhow!("must not be empty")),
+            false => Ok(()),
+        };
+        ValueSet::parse(&self.origin_country, "origin-country", not_empty)?;
+        ValueSet::parse(&self.peer_country, "peer-country", not_empty)?;
+        ValueSet::parse(&self.origin_org, "origin-org", not_empty)?;
+        Ok(())
+    }
+
+    /// Resolve the filter values to ASN sets from the ASInfo data of `db`
+    ///
+    /// Fails for values without any ASN, such as an unknown organization ID.
+    pub fn load(&mut self, db: &MonocleDatabase) -> Result<()> {
+        let countries = CountryLens::new();
+        let country_asns = |value: &str| -> Result<HashSet<u32>> {
+            let code = countries.resolve_code(value)?;
+            let asns: HashSet<u32> = db
+                .asinfo()
+                .search_by_country(&code, u32::MAX as usize)?
+                .into_iter()
+                .map(|record| record.asn)
+                .collect();
+            match asns.is_empty() {
+                true => Err(anyhow!("no ASNs registered in {}", code)),
+                false => Ok(asns),
+            }
+        };
+        let org_asns = |value: &str| -> Result<HashSet<u32>> {
+            let asns: HashSet<u32> = db
+                .asinfo()
+                .search_by_org_id(value, u32::MAX as usize)?
+                .into_iter()
+                .map(|record| record.asn)
+                .collect();
+            match asns.is_empty() {
+                true => Err(anyhow!("no ASNs of this AS2Org organization ID")),
+                false => Ok(asns),
+            }
+        };
+
+        self.resolved = Some(AsinfoMatcher {
+            origin_country: ValueSet::parse(&self.origin_country, "origin-country", country_asns)?
+                .map(Arc::new),
+            peer_country: ValueSet::parse(&self.peer_country, "peer-country", country_asns)?
+                .map(Arc::new),
+            origin_org: ValueSet::parse(&self.origin_org, "origin-org", org_asns)?.map(Arc::new),
+        });
+        Ok(())
+    }
+
+    /// The resolved filters, `None` without country or organization filters
+    ///
+    /// Fails if a filter is set but its values have not been resolved.
+    pub fn matcher(&self) -> Result<Option<AsinfoMatcher>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        self.resolved.clone().map(Some).ok_or_else(|| {
+            anyhow!(
+                "country and organization filters need ASInfo data, load it with ParseFilters::load_asinfo first"
+            )
+        })
+    }
+}
+
+/// [`AsinfoFilters`] resolved to ASN sets
+#[derive(Debug, Clone, Default)]
+pub struct AsinfoMatcher {
+    origin_country: Option<Arc<ValueSet<HashSet<u32>>>>,
+    peer_country: Option<Arc<ValueSet<HashSet<u32>>>>,
+    origin_org: Option<Arc<ValueSet<HashSet<u32>>>>,
+}
+
+impl AsinfoMatcher {
+    /// Whether `elem` passes all country and organization filters
+    pub fn matches(&self, elem: &BgpElem) -> bool {
+        let origin_in = |asns: &HashSet<u32>| {
+            elem.origin_asns
+                .as_ref()
+                .is_some_and(|origins| origins.iter().any(|asn| asns.contains(&asn.to_u32())))
+        };
+        let peer_asn = elem.peer_asn.to_u32();
+        self.origin_country
+            .as_ref()
+            .is_none_or(|set| set.matches(origin_in))
+            && self
+                .peer_country
+                .as_ref()
+                .is_none_or(|set| set.matches(|asns| asns.contains(&peer_asn)))
+            && self
+                .origin_org
+                .as_ref()
+                .is_none_or(|set| set.matches(origin_in))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::database::JsonlRecord;
+    use bgpkit_parser::models::{AsPath, ElemType, NetworkPrefix};
+    use serde_json::json;
+
+    fn database() -> MonocleDatabase {
+        let db = MonocleDatabase::open_in_memory().unwrap();
+        let org = |asn: u32, org_id: &str, country: &str| {
+            json!({
+                "asn": asn,
+                "name": format!("AS{asn}"),
+                "country": country,
+                "as2org": {"country": country, "name": format!("AS{asn}"),
+                           "org_id": org_id, "org_name": org_id}
+            })
+        };
+        let records: Vec<JsonlRecord> = serde_json::from_value(json!([
+            org(13335, "CLOUD14-ARIN", "US"),
+            org(209242, "CLOUD14-ARIN", "US"),
+            org(3320, "DTAG-RIPE", "DE"),
+            org(174, "COGC-ARIN", "US"),
+        ]))
+        .unwrap();
+        db.asinfo()
+            .store_from_jsonl(&records, "test://source")
+            .unwrap();
+        db
+    }
+
+    fn elem(peer: u32, origin: Option<u32>) -> BgpElem {
+        BgpElem {
+            elem_type: match origin {
+                Some(_) => ElemType::ANNOUNCE,
+                None => ElemType::WITHDRAW,
+            },
+            peer_asn: peer.into(),
+            prefix: NetworkPrefix::new("1.1.1.0/24".parse().unwrap(), None),
+            as_path: origin.map(|asn| AsPath::from_sequence([peer, asn])),
+            origin_asns: origin.map(|asn| vec![asn.into()]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_asinfo_filter() {
+        let db = database();
+        let matcher = |filters: AsinfoFilters| {
+            let mut filters = filters;
+            filters.load(&db).unwrap();
+            filters.matcher().unwrap().unwrap()
+        };
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+
+        let us_origin = matcher(AsinfoFilters {
+            origin_country: strings(&["us"]),
+            ..Default::default()
+        });
+        assert!(us_origin.matches(&elem(3320, Some(13335))));
+        assert!(!us_origin.matches(&elem(174, Some(3320))));
+        assert!(!us_origin.matches(&elem(174, None)));
+
+        let not_de_peer = matcher(AsinfoFilters {
+            peer_country: strings(&["!DE"]),
+            ..Default::default()
+        });
+        assert!(!not_de_peer.matches(&elem(3320, Some(13335))));
+        assert!(not_de_peer.matches(&elem(174, Some(3320))));
+
+        let org = matcher(AsinfoFilters {
+            origin_org: strings(&["CLOUD14-ARIN", "DTAG-RIPE"]),
+            peer_country: strings(&["US"]),
+            ..Default::default()
+        });
+        assert!(org.matches(&elem(174, Some(209242))));
+        assert!(org.matches(&elem(13335, Some(3320))));
+        assert!(!org.matches(&elem(3320, Some(13335))));
+        assert!(!org.matches(&elem(174, Some(174))));
+    }
+
+    #[test]
+    fn test_asinfo_filter_errors() {
+        let db = database();
+        let filters = |org: &[&str]| AsinfoFilters {
+            origin_org: org.iter().map(|v| v.to_string()).collect(),
+            ..Default::default()
+        };
+        assert!(filters(&["CLOUD14-ARIN"]).validate().is_ok());
+        assert!(filters(&["CLOUD14-ARIN", "!DTAG-RIPE"]).validate().is_err());
+        assert!(filters(&["NOSUCH-ORG"]).load(&db).is_err());
+        // Filters must be resolved before matching
+        assert!(filters(&["CLOUD14-ARIN"]).matcher().is_err());
+        assert!(AsinfoFilters::default().matcher().unwrap().is_none());
+    }
+}
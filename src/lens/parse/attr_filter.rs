@@ -0,0 +1,459 @@
+//! Path attribute filters.
+//!
+//! bgpkit-parser's [`Filter`](bgpkit_parser::parser::filter::Filter)s cover
+//! prefixes, ASNs, peers, communities, the AS path and time. The attributes
+//! below are filtered by monocle itself, after the parser:
+//!
+//! | Filter | Values |
+//! |--------|--------|
+//! | `ext_communities` | `rt:A:B`, `soo:A:B` or `*:A:B`; `A` is an ASN or IP address, each part may be `*` |
+//! | `med`, `local_pref` | `N`, `A..B`, `A..` or `..B` (inclusive) |
+//! | `next_hop` | IP address or prefix |
+//! | `origin` | `igp`, `egp` or `incomplete` |
+//! | `aggr_asn` | ASN |
+//! | `atomic` | `true` or `false` |
+//!
+//! The structural AS path filters of [`as_path_filter`](super::as_path_filter)
+//! are applied together with these.
+//!
+//! Like the other [`ParseFilters`], multiple values of one filter match if any
+//! of them matches, and values prefixed with `!` exclude matching elements.
+//! Elements without the attribute (such as withdrawals) never match a positive
+//! filter and always pass a negated one.
+
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use bgpkit_parser::models::{ExtendedCommunity, MetaCommunity, Origin};
+use bgpkit_parser::BgpElem;
+use ipnet::IpNet;
+
+use super::as_path_filter::AsPathMatcher;
+use super::ParseFilters;
+
+/// Extended community subtype of Route Targets (RFC 4360)
+const ROUTE_TARGET_SUBTYPE: u8 = 0x02;
+
+/// Extended community subtype of Route Origins / Site of Origin (RFC 4360)
+const ROUTE_ORIGIN_SUBTYPE: u8 = 0x03;
+
+/// Inclusive range of attribute values: `N`, `A..B`, `A..` or `..B`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl ValueRange {
+    pub fn contains(&self, value: u32) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+impl FromStr for ValueRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let bound = |value: &str, default: u32| -> Result<u32> {
+            match value.trim() {
+                "" => Ok(default),
+                value => value
+                    .parse()
+                    .map_err(|_| anyhow!("'{}' is not a 32-bit unsigned integer", value)),
+            }
+        };
+        let (min, max) = match s.split_once("..") {
+            Some((min, max)) => (bound(min, 0)?, bound(max, u32::MAX)?),
+            None if s.trim().is_empty() => return Err(anyhow!("empty range")),
+            None => {
+                let value = bound(s, 0)?;
+                (value, value)
+            }
+        };
+        if min > max {
+            return Err(anyhow!("range start {} is above its end {}", min, max));
+        }
+        Ok(Self { min, max })
+    }
+}
+
+/// Extended community pattern: `KIND:GLOBAL:LOCAL`
+///
+/// `KIND` is `rt` (route target), `soo` (site of origin) or `*`; `GLOBAL` is
+/// the global administrator, an ASN or IP address; `LOCAL` the local
+/// administrator number. Any part can be `*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtCommunityPattern {
+    subtype: Option<u8>,
+    global: Option<IpOrAsn>,
+    local: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IpOrAsn {
+    Asn(u32),
+    Ip(IpAddr),
+}
+
+impl FromStr for ExtCommunityPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // IPv6 global administrators contain colons, so split off the ends
+        let (kind, rest) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("must be KIND:GLOBAL:LOCAL, e.g. rt:64500:100"))?;
+        let (global, local) = rest
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("must be KIND:GLOBAL:LOCAL, e.g. rt:64500:100"))?;
+        let subtype = match kind.to_lowercase().as_str() {
+            "rt" | "target" => Some(ROUTE_TARGET_SUBTYPE),
+            "soo" | "origin" => Some(ROUTE_ORIGIN_SUBTYPE),
+            "*" => None,
+            _ => return Err(anyhow!("unknown type '{}': must be rt, soo or *", kind)),
+        };
+        let global = match global {
+            "*" => None,
+            global => Some(if let Ok(asn) = global.parse() {
+                IpOrAsn::Asn(asn)
+            } else {
+                IpOrAsn::Ip(
+                    global
+                        .parse()
+                        .map_err(|_| anyhow!("'{}' is not an ASN or IP address", global))?,
+                )
+            }),
+        };
+        let local = match local {
+            "*" => None,
+            local => Some(
+                local
+                    .parse()
+                    .map_err(|_| anyhow!("'{}' is not a 32-bit unsigned integer", local))?,
+            ),
+        };
+        Ok(Self {
+            subtype,
+            global,
+            local,
+        })
+    }
+}
+
+impl ExtCommunityPattern {
+    fn matches(&self, community: &MetaCommunity) -> bool {
+        let Some((subtype, global, local)) = ext_community_parts(community) else {
+            return false;
+        };
+        self.subtype.is_none_or(|want| want == subtype)
+            && self.global.is_none_or(|want| want == global)
+            && self.local.is_none_or(|want| want == local)
+    }
+}
+
+/// Subtype, global and local administrator of an AS or IP address specific
+/// extended community
+fn ext_community_parts(community: &MetaCommunity) -> Option<(u8, IpOrAsn, u32)> {
+    match community {
+        MetaCommunity::Extended(
+            ExtendedCommunity::TransitiveTwoOctetAs(ec)
+            | ExtendedCommunity::NonTransitiveTwoOctetAs(ec),
+        ) => Some((
+            ec.subtype,
+            IpOrAsn::Asn(ec.global_admin.into()),
+            u32::from_be_bytes(ec.local_admin),
+        )),
+        MetaCommunity::Extended(
+            ExtendedCommunity::TransitiveFourOctetAs(ec)
+            | ExtendedCommunity::NonTransitiveFourOctetAs(ec),
+        ) => Some((
+            ec.subtype,
+            IpOrAsn::Asn(ec.global_admin.into()),
+            u16::from_be_bytes(ec.local_admin).into(),
+        )),
+        MetaCommunity::Extended(
+            ExtendedCommunity::TransitiveIpv4Addr(ec)
+            | ExtendedCommunity::NonTransitiveIpv4Addr(ec),
+        ) => Some((
+            ec.subtype,
+            IpOrAsn::Ip(ec.global_admin.into()),
+            u16::from_be_bytes(ec.local_admin).into(),
+        )),
+        MetaCommunity::Ipv6Extended(ec) => Some((
+            ec.subtype,
+            IpOrAsn::Ip(ec.global_admin.into()),
+            u16::from_be_bytes(ec.local_admin).into(),
+        )),
+        _ => None,
+    }
+}
+
+/// Parse an ORIGIN attribute name
+pub fn parse_origin(value: &str) -> Result<Origin> {
+    match value.trim().to_lowercase().as_str() {
+        "igp" | "i" => Ok(Origin::IGP),
+        "egp" | "e" => Ok(Origin::EGP),
+        "incomplete" | "?" => Ok(Origin::INCOMPLETE),
+        _ => Err(anyhow!(
+            "must be igp, egp or incomplete (use --origin-asn for origin AS numbers)"
+        )),
+    }
+}
+
+/// Values of one filter, matched with OR logic and optionally negated
+#[derive(Debug, Clone)]
+pub(super) struct ValueSet<T> {
+    values: Vec<T>,
+    negated: bool,
+}
+
+impl<T> ValueSet<T> {
+    /// Parse `values`, with `None` for an unset filter
+    pub(super) fn parse<E: Display>(
+        values: &[String],
+        name: &str,
+        parse: impl Fn(&str) -> Result<T, E>,
+    ) -> Result<Option<Self>> {
+        if values.is_empty() {
+            return Ok(None);
+        }
+        ParseFilters::check_negation_consistency(values, name)?;
+        let negated = values[0].starts_with('!');
+        let values = values
+            .iter()
+            .map(|value| {
+                let raw = value.strip_prefix('!').unwrap_or(value).trim();
+                parse(raw).map_err(|e| anyhow!("Invalid {} '{}': {}", name, value, e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(Self { values, negated }))
+    }
+
+    pub(super) fn matches(&self, predicate: impl Fn(&T) -> bool) -> bool {
+        self.values.iter().any(predicate) != self.negated
+    }
+}
+
+/// Path attribute filters of [`ParseFilters`], see the [module docs](self)
+#[derive(Debug, Clone, Default)]
+pub struct AttributeFilters {
+    ext_communities: Option<ValueSet<ExtCommunityPattern>>,
+    med: Option<ValueSet<ValueRange>>,
+    local_pref: Option<ValueSet<ValueRange>>,
+    next_hop: Option<ValueSet<IpNet>>,
+    origin: Option<ValueSet<Origin>>,
+    aggr_asn: Option<ValueSet<u32>>,
+    atomic: Option<bool>,
+    as_path: AsPathMatcher,
+}
+
+impl AttributeFilters {
+    /// Parse the attribute filters of `filters`
+    pub fn new(filters: &ParseFilters) -> Result<Self> {
+        Ok(Self {
+            ext_communities: ValueSet::parse(
+                &filters.ext_communities,
+                "ext-community",
+                str::parse,
+            )?,
+            med: ValueSet::parse(&filters.med, "med", str::parse)?,
+            local_pref: ValueSet::parse(&filters.local_pref, "local-pref", str::parse)?,
+            next_hop: ValueSet::parse(&filters.next_hop, "next-hop", |value| {
+                value
+                    .parse::<IpNet>()
+                    .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| anyhow!("must be an IP address or prefix"))
+            })?,
+            origin: ValueSet::parse(&filters.origin, "origin", parse_origin)?,
+            aggr_asn: ValueSet::parse(&filters.aggr_asn, "aggr-asn", |value| {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| anyhow!("must be a valid 32-bit unsigned integer"))
+            })?,
+            atomic: filters.atomic,
+            as_path: filters.as_path_filters.matcher()?,
+        })
+    }
+
+    /// Whether no attribute filter is set
+    pub fn is_empty(&self) -> bool {
+        self.ext_communities.is_none()
+            && self.med.is_none()
+            && self.local_pref.is_none()
+            && self.next_hop.is_none()
+            && self.origin.is_none()
+            && self.aggr_asn.is_none()
+            && self.atomic.is_none()
+            && self.as_path.is_empty()
+    }
+
+    /// Whether `elem` passes all attribute filters
+    pub fn matches(&self, elem: &BgpElem) -> bool {
+        fn check<T>(set: &Option<ValueSet<T>>, predicate: impl Fn(&T) -> bool) -> bool {
+            set.as_ref().is_none_or(|set| set.matches(predicate))
+        }
+
+        let communities = elem.communities.as_deref().unwrap_or_default();
+        check(&self.ext_communities, |pattern| {
+            communities
+                .iter()
+                .any(|community| pattern.matches(community))
+        }) && check(&self.med, |range| {
+            elem.med.is_some_and(|med| range.contains(med))
+        }) && check(&self.local_pref, |range| {
+            elem.local_pref.is_some_and(|pref| range.contains(pref))
+        }) && check(&self.next_hop, |net| {
+            elem.next_hop
+                .is_some_and(|next_hop| net.contains(&next_hop))
+        }) && check(&self.origin, |origin| elem.origin == Some(*origin))
+            && check(&self.aggr_asn, |asn| {
+                elem.aggr_asn.is_some_and(|aggr| u32::from(aggr) == *asn)
+            })
+            && self.atomic.is_none_or(|atomic| elem.atomic == atomic)
+            && self.as_path.matches(elem)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use bgpkit_parser::models::{Community, Ipv4AddrExtCommunity, TwoOctetAsExtCommunity};
+
+    fn route_target(asn: u32, value: u32) -> MetaCommunity {
+        MetaCommunity::Extended(ExtendedCommunity::TransitiveTwoOctetAs(
+            TwoOctetAsExtCommunity {
+                subtype: ROUTE_TARGET_SUBTYPE,
+                global_admin: asn.into(),
+                local_admin: value.to_be_bytes(),
+            },
+        ))
+    }
+
+    fn elem() -> BgpElem {
+        BgpElem {
+            next_hop: Some("192.0.2.1".parse().unwrap()),
+            origin: Some(Origin::IGP),
+            local_pref: Some(150),
+            med: Some(10),
+            communities: Some(vec![
+                MetaCommunity::Plain(Community::Custom(64500.into(), 1)),
+                route_target(64500, 100),
+                MetaCommunity::Extended(ExtendedCommunity::TransitiveIpv4Addr(
+                    Ipv4AddrExtCommunity {
+                        subtype: ROUTE_ORIGIN_SUBTYPE,
+                        global_admin: "198.51.100.1".parse().unwrap(),
+                        local_admin: 7u16.to_be_bytes(),
+                    },
+                )),
+            ]),
+            aggr_asn: Some(64501.into()),
+            ..Default::default()
+        }
+    }
+
+    fn matches(filters: ParseFilters) -> bool {
+        AttributeFilters::new(&filters).unwrap().matches(&elem())
+    }
+
+    #[test]
+    fn test_value_range() {
+        assert_eq!(
+            "100..200".parse::<ValueRange>().unwrap(),
+            ValueRange { min: 100, max: 200 }
+        );
+        assert_eq!(
+            "100".parse::<ValueRange>().unwrap(),
+            ValueRange { min: 100, max: 100 }
+        );
+        assert_eq!(
+            "..50".parse::<ValueRange>().unwrap(),
+            ValueRange { min: 0, max: 50 }
+        );
+        assert_eq!(
+            "50..".parse::<ValueRange>().unwrap(),
+            ValueRange {
+                min: 50,
+                max: u32::MAX
+            }
+        );
+        assert!("200..100".parse::<ValueRange>().is_err());
+        assert!("".parse::<ValueRange>().is_err());
+        assert!("a..b".parse::<ValueRange>().is_err());
+    }
+
+    #[test]
+    fn test_numeric_filters() {
+        let filters = |med: &[&str], local_pref: &[&str]| ParseFilters {
+            med: med.iter().map(ToString::to_string).collect(),
+            local_pref: local_pref.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        assert!(matches(filters(&["0..10"], &["100..200"])));
+        assert!(matches(filters(&["5", "10"], &[])));
+        assert!(!matches(filters(&["11.."], &[])));
+        assert!(!matches(filters(&[], &["!100..200"])));
+        assert!(matches(filters(&[], &["!0..99"])));
+
+        let withdrawal = BgpElem::default();
+        let filter = AttributeFilters::new(&filters(&["0.."], &[])).unwrap();
+        assert!(!filter.matches(&withdrawal));
+        let filter = AttributeFilters::new(&filters(&["!0.."], &[])).unwrap();
+        assert!(filter.matches(&withdrawal));
+    }
+
+    #[test]
+    fn test_ext_community_filters() {
+        let filters = |patterns: &[&str]| ParseFilters {
+            ext_communities: patterns.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        assert!(matches(filters(&["rt:64500:100"])));
+        assert!(matches(filters(&["rt:64500:*"])));
+        assert!(matches(filters(&["soo:198.51.100.1:7"])));
+        assert!(matches(filters(&["*:*:7"])));
+        assert!(!matches(filters(&["soo:64500:100"])));
+        assert!(!matches(filters(&["rt:64501:*", "rt:*:101"])));
+        assert!(!matches(filters(&["!rt:64500:*"])));
+        assert!(matches(filters(&["!rt:64501:*"])));
+        assert!("rt:64500".parse::<ExtCommunityPattern>().is_err());
+        assert!("xx:64500:1".parse::<ExtCommunityPattern>().is_err());
+        assert!("soo:2001:db8::1:5".parse::<ExtCommunityPattern>().is_ok());
+    }
+
+    #[test]
+    fn test_other_attribute_filters() {
+        let next_hop = |values: &[&str]| ParseFilters {
+            next_hop: values.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        assert!(matches(next_hop(&["192.0.2.1"])));
+        assert!(matches(next_hop(&["192.0.2.0/24"])));
+        assert!(!matches(next_hop(&["!192.0.2.0/24"])));
+        assert!(!matches(next_hop(&["2001:db8::/32"])));
+
+        let origin = |values: &[&str]| ParseFilters {
+            origin: values.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        assert!(matches(origin(&["IGP"])));
+        assert!(!matches(origin(&["egp", "incomplete"])));
+        assert!(AttributeFilters::new(&origin(&["13335"])).is_err());
+
+        let aggr = |values: &[&str], atomic: Option<bool>| ParseFilters {
+            aggr_asn: values.iter().map(ToString::to_string).collect(),
+            atomic,
+            ..Default::default()
+        };
+        assert!(matches(aggr(&["64501"], Some(false))));
+        assert!(!matches(aggr(&["!64501"], None)));
+        assert!(!matches(aggr(&[], Some(true))));
+
+        assert!(AttributeFilters::new(&next_hop(&["1.1.1.1", "!2.2.2.2"])).is_err());
+        assert!(AttributeFilters::new(&ParseFilters::default())
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,529 @@
+//! BMP (RFC 7854) input.
+//!
+//! Reads a stream of raw BMP messages, as captured from a router's BMP
+//! session or received live by `monocle bmp-listen`, and converts it into
+//! [`BmpRecord`]s:
+//!
+//! - Route Monitoring messages become one [`BgpElem`] per announced or
+//!   withdrawn prefix, with peer address, ASN and BGP ID taken from the
+//!   per-peer header, so they can be filtered, formatted and exported like
+//!   elements parsed from MRT files.
+//! - Peer Up and Peer Down notifications become [`BmpPeerEvent`]s.
+//! - Initiation, Termination, Statistics Report and Route Mirroring messages
+//!   are skipped.
+//!
+//! Streams are detected by their first bytes ([`detect_bmp`]): BMP version 3,
+//! a plausible message length and a known message type. MRT files start with
+//! a timestamp and never match.
+
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use bgpkit_parser::parser::bmp::messages::{
+    BmpMessage, BmpMessageBody, BmpPerPeerHeader, PeerDownReason,
+};
+use bgpkit_parser::parser::filter::{Filter, Filterable};
+use bgpkit_parser::{parse_bmp_msg, BgpElem, Elementor};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use super::time_window::TimeWindows;
+use super::{ElemFilter, ParseFilters};
+
+/// Size of the BMP common header: version, message length and type
+const BMP_COMMON_HEADER_LEN: usize = 6;
+
+/// Largest BMP message accepted; bounds the allocation for a corrupt length
+const BMP_MAX_MESSAGE_LEN: usize = 1 << 24;
+
+/// Highest BMP message type defined by RFC 7854 (Route Mirroring)
+const BMP_MAX_MESSAGE_TYPE: u8 = 6;
+
+/// Whether `buf`, the first bytes of a stream, looks like a BMP message
+pub fn detect_bmp(buf: &[u8]) -> bool {
+    if buf.len() < BMP_COMMON_HEADER_LEN || buf[0] != 3 {
+        return false;
+    }
+    let msg_len = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]) as usize;
+    (BMP_COMMON_HEADER_LEN..=BMP_MAX_MESSAGE_LEN).contains(&msg_len)
+        && buf[5] <= BMP_MAX_MESSAGE_TYPE
+}
+
+/// Kind of peer state change reported over BMP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BmpPeerEventType {
+    /// Peer Up notification: the BGP session was established
+    PeerUp,
+    /// Peer Down notification: the BGP session went down
+    PeerDown,
+}
+
+impl std::fmt::Display for BmpPeerEventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PeerUp => write!(f, "peer_up"),
+            Self::PeerDown => write!(f, "peer_down"),
+        }
+    }
+}
+
+/// A Peer Up or Peer Down notification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BmpPeerEvent {
+    /// Event type, serialized as `type` like element types
+    #[serde(rename = "type")]
+    pub event_type: BmpPeerEventType,
+    /// Time from the per-peer header (Unix seconds)
+    pub timestamp: f64,
+    /// Address of the monitored peer
+    pub peer_ip: IpAddr,
+    /// AS number of the monitored peer
+    pub peer_asn: u32,
+    /// BGP identifier of the monitored peer
+    pub peer_bgp_id: Ipv4Addr,
+    /// Local address of the session (Peer Up only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_ip: Option<IpAddr>,
+    /// Local port of the session (Peer Up only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_port: Option<u16>,
+    /// Remote port of the session (Peer Up only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_port: Option<u16>,
+    /// Why the session went down (Peer Down only), e.g. `remote_no_data`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// One record read from a BMP stream
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::large_enum_variant)] // elements dominate; boxing them costs more
+pub enum BmpRecord {
+    /// A BGP element from a Route Monitoring message
+    Elem(BgpElem),
+    /// A Peer Up or Peer Down notification
+    PeerEvent(BmpPeerEvent),
+}
+
+/// Short name of a Peer Down reason code
+fn peer_down_reason(reason: PeerDownReason) -> &'static str {
+    match reason {
+        PeerDownReason::Reserved => "reserved",
+        PeerDownReason::LocalSystemClosedNotificationPduFollows => "local_notification",
+        PeerDownReason::LocalSystemClosedFsmEvenFollows => "local_no_notification",
+        PeerDownReason::RemoteSystemClosedNotificationPduFollows => "remote_notification",
+        PeerDownReason::RemoteSystemsClosedNoData => "remote_no_data",
+        PeerDownReason::PeerDeConfigured => "peer_deconfigured",
+        PeerDownReason::LocalSystemClosedTlvDataFollows => "local_tlv",
+    }
+}
+
+/// Current time as Unix seconds
+fn now_secs() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default()
+}
+
+/// Convert a parsed BMP message into records.
+///
+/// `fallback_timestamp` replaces per-peer header timestamps of zero, which
+/// routers send when they do not timestamp their messages.
+pub fn bmp_message_to_records(msg: BmpMessage, fallback_timestamp: Option<f64>) -> Vec<BmpRecord> {
+    let Some(peer) = msg.per_peer_header else {
+        return Vec::new();
+    };
+    let timestamp = match fallback_timestamp {
+        Some(fallback) if peer.timestamp == 0.0 => fallback,
+        _ => peer.timestamp,
+    };
+    let event = |event_type: BmpPeerEventType, peer: &BmpPerPeerHeader| BmpPeerEvent {
+        event_type,
+        timestamp,
+        peer_ip: peer.peer_ip,
+        peer_asn: peer.peer_asn.to_u32(),
+        peer_bgp_id: peer.peer_bgp_id,
+        local_ip: None,
+        local_port: None,
+        remote_port: None,
+        reason: None,
+    };
+
+    match msg.message_body {
+        BmpMessageBody::RouteMonitoring(monitoring) => Elementor::bgp_to_elems(
+            monitoring.bgp_message,
+            timestamp,
+            &peer.peer_ip,
+            &peer.peer_asn,
+        )
+        .into_iter()
+        .map(|mut elem| {
+            elem.peer_bgp_id = Some(peer.peer_bgp_id);
+            BmpRecord::Elem(elem)
+        })
+        .collect(),
+        BmpMessageBody::PeerUpNotification(up) => {
+            vec![BmpRecord::PeerEvent(BmpPeerEvent {
+                local_ip: Some(up.local_addr),
+                local_port: Some(up.local_port),
+                remote_port: Some(up.remote_port),
+                ..event(BmpPeerEventType::PeerUp, &peer)
+            })]
+        }
+        BmpMessageBody::PeerDownNotification(down) => {
+            vec![BmpRecord::PeerEvent(BmpPeerEvent {
+                reason: Some(peer_down_reason(down.reason).to_string()),
+                ..event(BmpPeerEventType::PeerDown, &peer)
+            })]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Reader for a stream of raw BMP messages
+///
+/// Iterating yields records in stream order. A message that fails to parse
+/// yields an error and is skipped. A stream that ends in the middle of a
+/// message or has an invalid common header yields an error and ends the
+/// iteration, since the message boundaries can no longer be trusted.
+pub struct BmpReader<R> {
+    reader: R,
+    live: bool,
+    pending: VecDeque<BmpRecord>,
+    finished: bool,
+    messages: u64,
+}
+
+impl<R: Read> BmpReader<R> {
+    /// Read BMP messages from `reader`
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            live: false,
+            pending: VecDeque::new(),
+            finished: false,
+            messages: 0,
+        }
+    }
+
+    /// Use the time of arrival for messages without a per-peer timestamp,
+    /// for reading from a live session
+    pub fn live(mut self) -> Self {
+        self.live = true;
+        self
+    }
+
+    /// Number of BMP messages read so far
+    pub fn messages(&self) -> u64 {
+        self.messages
+    }
+
+    /// Read and parse the next BMP message, or `None` at the end of the stream
+    pub fn next_message(&mut self) -> Result<Option<BmpMessage>> {
+        match self.read_frame()? {
+            Some(frame) => self.parse_frame(frame).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the bytes of the next message, common header included
+    fn read_frame(&mut self) -> Result<Option<Bytes>> {
+        let mut header = [0u8; BMP_COMMON_HEADER_LEN];
+        let mut filled = 0;
+        while filled < header.len() {
+            match self.reader.read(&mut header[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(anyhow!("BMP stream ended inside a message header")),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        if header[0] != 3 {
+            return Err(anyhow!("unsupported BMP version {}", header[0]));
+        }
+        let msg_len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        if !(BMP_COMMON_HEADER_LEN..=BMP_MAX_MESSAGE_LEN).contains(&msg_len) {
+            return Err(anyhow!("invalid BMP message length {}", msg_len));
+        }
+
+        let mut data = vec![0u8; msg_len];
+        data[..BMP_COMMON_HEADER_LEN].copy_from_slice(&header);
+        self.reader
+            .read_exact(&mut data[BMP_COMMON_HEADER_LEN..])
+            .map_err(|e| match e.kind() {
+                ErrorKind::UnexpectedEof => anyhow!("BMP stream ended inside a message"),
+                _ => e.into(),
+            })?;
+        self.messages += 1;
+        Ok(Some(Bytes::from(data)))
+    }
+
+    fn parse_frame(&self, mut frame: Bytes) -> Result<BmpMessage> {
+        parse_bmp_msg(&mut frame)
+            .map_err(|e| anyhow!("invalid BMP message {}: {}", self.messages, e))
+    }
+}
+
+impl<R: Read> Iterator for BmpReader<R> {
+    type Item = Result<BmpRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.pending.pop_front() {
+                return Some(Ok(record));
+            }
+            if self.finished {
+                return None;
+            }
+            let frame = match self.read_frame() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => {
+                    self.finished = true;
+                    continue;
+                }
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            };
+            match self.parse_frame(frame) {
+                Ok(msg) => {
+                    let fallback = self.live.then(now_secs);
+                    self.pending.extend(bmp_message_to_records(msg, fallback));
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// [`ParseFilters`] applied to BMP records
+///
+/// Elements must match all filters and fall into the time windows. Peer
+/// events are only checked against the time range and the peer filters
+/// (`--peer-ip`, `--peer-asn`), since they carry no prefixes or attributes.
+pub struct BmpRecordFilter {
+    elem_filter: ElemFilter,
+    event_filters: Vec<Filter>,
+    windows: TimeWindows,
+}
+
+impl BmpRecordFilter {
+    /// Build the record filter from parse filters
+    pub fn new(filters: &ParseFilters) -> Result<Self> {
+        let event_filters = filters
+            .filter_specs()?
+            .into_iter()
+            .filter(|(filter_type, _)| {
+                matches!(
+                    *filter_type,
+                    "peer_ips" | "peer_asns" | "start_ts" | "end_ts"
+                )
+            })
+            .map(|(filter_type, value)| Filter::new(filter_type, &value))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            elem_filter: filters.to_elem_filter()?,
+            event_filters,
+            windows: filters.time_windows()?,
+        })
+    }
+
+    /// Whether the record passes the filters
+    pub fn matches(&self, record: &BmpRecord) -> bool {
+        match record {
+            BmpRecord::Elem(elem) => self.elem_filter.matches(elem),
+            BmpRecord::PeerEvent(event) => {
+                // Evaluate peer and time filters on a stand-in element
+                let elem = BgpElem {
+                    timestamp: event.timestamp,
+                    peer_ip: event.peer_ip,
+                    peer_asn: event.peer_asn.into(),
+                    ..Default::default()
+                };
+                elem.match_filters(&self.event_filters) && self.windows.contains(event.timestamp)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// BMP per-peer header for an IPv4 peer with 4-byte ASNs
+    fn per_peer_header(peer_asn: u32, timestamp: u32) -> Vec<u8> {
+        let mut buf = vec![0u8, 0u8];
+        buf.extend([0u8; 8]);
+        buf.extend([0u8; 12]);
+        buf.extend([192, 0, 2, 1]);
+        buf.extend(peer_asn.to_be_bytes());
+        buf.extend([10, 0, 0, 1]);
+        buf.extend(timestamp.to_be_bytes());
+        buf.extend(0u32.to_be_bytes());
+        buf
+    }
+
+    fn bmp_message(msg_type: u8, body: Vec<u8>) -> Vec<u8> {
+        let mut buf = vec![3u8];
+        buf.extend(((body.len() + BMP_COMMON_HEADER_LEN) as u32).to_be_bytes());
+        buf.push(msg_type);
+        buf.extend(body);
+        buf
+    }
+
+    fn bgp_message(msg_type: u8, body: Vec<u8>) -> Vec<u8> {
+        let mut buf = vec![0xffu8; 16];
+        buf.extend(((body.len() + 19) as u16).to_be_bytes());
+        buf.push(msg_type);
+        buf.extend(body);
+        buf
+    }
+
+    /// Route Monitoring message announcing `10.<n>.0.0/16` via `peer_asn 13335`
+    fn route_monitoring(peer_asn: u32, timestamp: u32, n: u8) -> Vec<u8> {
+        let mut attrs = vec![0x40, 1, 1, 0];
+        attrs.extend([0x40, 2, 10, 2, 2]);
+        attrs.extend(peer_asn.to_be_bytes());
+        attrs.extend(13335u32.to_be_bytes());
+        attrs.extend([0x40, 3, 4, 192, 0, 2, 1]);
+        let mut update = 0u16.to_be_bytes().to_vec();
+        update.extend((attrs.len() as u16).to_be_bytes());
+        update.extend(attrs);
+        update.extend([16, 10, n]);
+
+        let mut body = per_peer_header(peer_asn, timestamp);
+        body.extend(bgp_message(2, update));
+        bmp_message(0, body)
+    }
+
+    /// Peer Up notification with minimal OPEN messages
+    fn peer_up(peer_asn: u32, timestamp: u32) -> Vec<u8> {
+        let open = |asn: u16| {
+            let mut open = vec![4u8];
+            open.extend(asn.to_be_bytes());
+            open.extend(180u16.to_be_bytes());
+            open.extend([10, 0, 0, 1, 0]);
+            bgp_message(1, open)
+        };
+        let mut body = per_peer_header(peer_asn, timestamp);
+        body.extend([0u8; 12]);
+        body.extend([192, 0, 2, 254]);
+        body.extend(179u16.to_be_bytes());
+        body.extend(40000u16.to_be_bytes());
+        body.extend(open(65000));
+        body.extend(open(peer_asn as u16));
+        bmp_message(3, body)
+    }
+
+    /// Peer Down notification with reason 4 (remote closed, no data)
+    fn peer_down(peer_asn: u32, timestamp: u32) -> Vec<u8> {
+        let mut body = per_peer_header(peer_asn, timestamp);
+        body.push(4);
+        bmp_message(2, body)
+    }
+
+    fn records(data: &[u8]) -> Vec<Result<BmpRecord>> {
+        BmpReader::new(data).collect()
+    }
+
+    #[test]
+    fn test_detect_bmp() {
+        assert!(detect_bmp(&route_monitoring(64500, 1704067200, 1)));
+        // MRT records start with a timestamp
+        assert!(!detect_bmp(&[0x65, 0x92, 0x00, 0x80, 0, 16, 0, 4]));
+        assert!(!detect_bmp(&[3, 0, 0, 0]));
+        assert!(!detect_bmp(&[3, 0, 0, 0, 6, 9]));
+    }
+
+    #[test]
+    fn test_read_bmp_stream() {
+        let mut data = peer_up(64500, 1704067200);
+        data.extend(route_monitoring(64500, 1704067201, 1));
+        data.extend(route_monitoring(64500, 1704067202, 2));
+        data.extend(peer_down(64500, 1704067203));
+
+        let records: Vec<BmpRecord> = records(&data).into_iter().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 4);
+        let BmpRecord::PeerEvent(up) = &records[0] else {
+            panic!("expected peer up, got {:?}", records[0]);
+        };
+        assert_eq!(up.event_type, BmpPeerEventType::PeerUp);
+        assert_eq!(up.peer_ip, "192.0.2.1".parse::<IpAddr>().unwrap());
+        assert_eq!(up.local_port, Some(179));
+        let BmpRecord::Elem(elem) = &records[1] else {
+            panic!("expected element, got {:?}", records[1]);
+        };
+        assert_eq!(elem.prefix.to_string(), "10.1.0.0/16");
+        assert_eq!(elem.peer_asn.to_u32(), 64500);
+        assert_eq!(elem.timestamp, 1704067201.0);
+        assert_eq!(elem.peer_bgp_id, Some(Ipv4Addr::new(10, 0, 0, 1)));
+        assert_eq!(elem.as_path.as_ref().unwrap().to_string(), "64500 13335");
+        let BmpRecord::PeerEvent(down) = &records[3] else {
+            panic!("expected peer down, got {:?}", records[3]);
+        };
+        assert_eq!(down.reason.as_deref(), Some("remote_no_data"));
+    }
+
+    #[test]
+    fn test_truncated_bmp_stream() {
+        let mut data = route_monitoring(64500, 1704067201, 1);
+        data.extend(&route_monitoring(64500, 1704067202, 2)[..20]);
+        let records = records(&data);
+        assert_eq!(records.len(), 2);
+        assert!(records[0].is_ok());
+        assert!(records[1].is_err());
+    }
+
+    #[test]
+    fn test_invalid_bmp_message_is_skipped() {
+        // Peer Down with an undefined reason code
+        let mut data = peer_down(64500, 1704067200);
+        let reason = data.len() - 1;
+        data[reason] = 99;
+        data.extend(route_monitoring(64500, 1704067201, 1));
+        let records = records(&data);
+        assert_eq!(records.len(), 2);
+        assert!(records[0].is_err());
+        assert!(matches!(records[1], Ok(BmpRecord::Elem(_))));
+    }
+
+    #[test]
+    fn test_live_timestamp_fallback() {
+        let data = route_monitoring(64500, 0, 1);
+        let elem = |reader: BmpReader<&[u8]>| match reader.into_iter().next() {
+            Some(Ok(BmpRecord::Elem(elem))) => elem,
+            other => panic!("expected element, got {:?}", other),
+        };
+        assert_eq!(elem(BmpReader::new(data.as_slice())).timestamp, 0.0);
+        assert!(elem(BmpReader::new(data.as_slice()).live()).timestamp > 1_700_000_000.0);
+    }
+
+    #[test]
+    fn test_bmp_record_filter() {
+        let filters = ParseFilters {
+            peer_asn: vec!["64500".to_string()],
+            prefix: vec!["10.1.0.0/16".to_string()],
+            ..Default::default()
+        };
+        let filter = BmpRecordFilter::new(&filters).unwrap();
+        let mut data = peer_up(64500, 1704067200);
+        data.extend(peer_up(64501, 1704067200));
+        data.extend(route_monitoring(64500, 1704067201, 1));
+        data.extend(route_monitoring(64500, 1704067201, 2));
+        let kept: Vec<BmpRecord> = records(&data)
+            .into_iter()
+            .map(Result::unwrap)
+            .filter(|record| filter.matches(record))
+            .collect();
+        assert_eq!(kept.len(), 2);
+        assert!(matches!(&kept[0], BmpRecord::PeerEvent(e) if e.peer_asn == 64500));
+        assert!(matches!(&kept[1], BmpRecord::Elem(e) if e.prefix.to_string() == "10.1.0.0/16"));
+    }
+}
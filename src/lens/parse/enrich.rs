@@ -0,0 +1,265 @@
+//! AS information enrichment of BGP elements.
+//!
+//! [`AsInfoEnricher`] adds the name of the peer AS and the name, registration
+//! country and organization of the origin AS to elements, from the ASInfo
+//! data of the local database. Each ASN is looked up once per run: lookups go
+//! to SQLite in batches and the results, including ASNs without ASInfo data,
+//! are cached in memory.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use bgpkit_parser::BgpElem;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::config::MonocleConfig;
+use crate::database::MonocleDatabase;
+
+/// Output fields provided by [`AsInfoEnricher`]
+pub const AS_INFO_FIELDS: &[&str] = &["peer_name", "origin_name", "origin_country", "origin_org"];
+
+/// Largest number of ASNs per SQLite lookup
+const LOOKUP_BATCH_SIZE: usize = 500;
+
+/// AS information of one element
+///
+/// The origin is the first origin AS, as in the `origin_asns` column of
+/// [`MsgStore`](crate::database::MsgStore). Fields are `None` when the
+/// element has no origin or the ASN has no ASInfo data.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ElemAsInfo {
+    /// Preferred name of the peer AS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peer_name: Option<String>,
+    /// Preferred name of the origin AS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin_name: Option<String>,
+    /// Registration country of the origin AS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin_country: Option<String>,
+    /// Organization (CAIDA AS2Org) of the origin AS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin_org: Option<String>,
+}
+
+impl ElemAsInfo {
+    /// Value of one of the [`AS_INFO_FIELDS`]
+    pub fn field(&self, field: &str) -> Option<&str> {
+        match field {
+            "peer_name" => self.peer_name.as_deref(),
+            "origin_name" => self.origin_name.as_deref(),
+            "origin_country" => self.origin_country.as_deref(),
+            "origin_org" => self.origin_org.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Cached ASInfo data of one ASN
+#[derive(Debug, Clone, Default)]
+struct AsLabel {
+    name: Option<String>,
+    country: Option<String>,
+    org: Option<String>,
+}
+
+struct EnricherState {
+    db: MonocleDatabase,
+    cache: HashMap<u32, AsLabel>,
+}
+
+impl EnricherState {
+    /// Look up all ASNs of `asns` that are not cached yet
+    fn fetch(&mut self, asns: impl IntoIterator<Item = u32>) {
+        let mut missing: Vec<u32> = asns
+            .into_iter()
+            .filter(|asn| !self.cache.contains_key(asn))
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+
+        for chunk in missing.chunks(LOOKUP_BATCH_SIZE) {
+            let asinfo = self.db.asinfo();
+            let mut names = asinfo.lookup_preferred_names_batch(chunk);
+            let mut countries = asinfo.lookup_countries_batch(chunk);
+            let mut orgs = asinfo.lookup_orgs_batch(chunk);
+            for asn in chunk {
+                let label = AsLabel {
+                    name: names.remove(asn),
+                    country: countries.remove(asn).filter(|c| !c.is_empty()),
+                    org: orgs.remove(asn).filter(|o| !o.is_empty()),
+                };
+                self.cache.insert(*asn, label);
+            }
+        }
+    }
+
+    fn elem_info(&self, elem: &BgpElem) -> ElemAsInfo {
+        let peer = self.cache.get(&elem.peer_asn.to_u32());
+        let origin = origin_asn(elem).and_then(|asn| self.cache.get(&asn));
+        ElemAsInfo {
+            peer_name: peer.and_then(|label| label.name.clone()),
+            origin_name: origin.and_then(|label| label.name.clone()),
+            origin_country: origin.and_then(|label| label.country.clone()),
+            origin_org: origin.and_then(|label| label.org.clone()),
+        }
+    }
+}
+
+/// Open the database in the configured data directory, refreshing the ASInfo
+/// data first when it is empty or outdated
+pub fn open_asinfo_database(config: &MonocleConfig) -> Result<MonocleDatabase> {
+    let db = MonocleDatabase::open_in_dir(&config.data_dir)?;
+    let asinfo = db.asinfo();
+    if asinfo.is_empty() || asinfo.needs_refresh(config.asinfo_cache_ttl()) {
+        info!("Refreshing ASInfo data");
+        let counts = db
+            .refresh_asinfo()
+            .map_err(|e| anyhow!("Failed to refresh ASInfo data: {}", e))?;
+        info!(
+            "Loaded {} core, {} as2org ASInfo records",
+            counts.core, counts.as2org
+        );
+    }
+    Ok(db)
+}
+
+fn origin_asn(elem: &BgpElem) -> Option<u32> {
+    elem.origin_asns
+        .as_ref()
+        .and_then(|asns| asns.first())
+        .map(|asn| asn.to_u32())
+}
+
+fn elem_asns(elem: &BgpElem) -> impl Iterator<Item = u32> {
+    std::iter::once(elem.peer_asn.to_u32()).chain(origin_asn(elem))
+}
+
+/// Adds AS names, countries and organizations to elements, see the
+/// [module docs](self)
+///
+/// Cloning is cheap and clones share the cache.
+#[derive(Clone)]
+pub struct AsInfoEnricher {
+    state: Arc<Mutex<EnricherState>>,
+}
+
+impl std::fmt::Debug for AsInfoEnricher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsInfoEnricher").finish_non_exhaustive()
+    }
+}
+
+impl AsInfoEnricher {
+    /// Enrich from the ASInfo data of `db`, which must not be empty
+    pub fn new(db: MonocleDatabase) -> Result<Self> {
+        if db.asinfo().is_empty() {
+            return Err(anyhow!(
+                "ASInfo data is empty, run `monocle config update --asinfo` first"
+            ));
+        }
+        Ok(Self {
+            state: Arc::new(Mutex::new(EnricherState {
+                db,
+                cache: HashMap::new(),
+            })),
+        })
+    }
+
+    /// Enrich from the database in the configured data directory, refreshing
+    /// the ASInfo data first when it is empty or outdated
+    pub fn from_config(config: &MonocleConfig) -> Result<Self> {
+        Self::new(open_asinfo_database(config)?)
+    }
+
+    /// Look up the peer and origin ASNs of `elems` that are not cached yet,
+    /// in batches
+    pub fn prefetch<'a>(&self, elems: impl IntoIterator<Item = &'a BgpElem>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.fetch(elems.into_iter().flat_map(elem_asns));
+        }
+    }
+
+    /// AS information of `elem`
+    pub fn lookup(&self, elem: &BgpElem) -> ElemAsInfo {
+        let Ok(mut state) = self.state.lock() else {
+            return ElemAsInfo::default();
+        };
+        state.fetch(elem_asns(elem));
+        state.elem_info(elem)
+    }
+
+    /// AS information of each of `elems`, in order
+    pub fn lookup_all(&self, elems: &[BgpElem]) -> Vec<ElemAsInfo> {
+        let Ok(mut state) = self.state.lock() else {
+            return vec![ElemAsInfo::default(); elems.len()];
+        };
+        state.fetch(elems.iter().flat_map(elem_asns));
+        elems.iter().map(|elem| state.elem_info(elem)).collect()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::database::JsonlRecord;
+    use bgpkit_parser::models::{AsPath, NetworkPrefix};
+    use serde_json::json;
+
+    fn enricher() -> AsInfoEnricher {
+        let db = MonocleDatabase::open_in_memory().unwrap();
+        let records: Vec<JsonlRecord> = serde_json::from_value(json!([
+            {
+                "asn": 13335,
+                "name": "CLOUDFLARENET",
+                "country": "US",
+                "as2org": {
+                    "country": "US",
+                    "name": "CLOUDFLARENET",
+                    "org_id": "CLOUD14-ARIN",
+                    "org_name": "Cloudflare, Inc."
+                }
+            },
+            {"asn": 3320, "name": "DTAG", "country": "DE"}
+        ]))
+        .unwrap();
+        db.asinfo()
+            .store_from_jsonl(&records, "test://source")
+            .unwrap();
+        AsInfoEnricher::new(db).unwrap()
+    }
+
+    fn elem(peer: u32, origin: Option<u32>) -> BgpElem {
+        BgpElem {
+            peer_asn: peer.into(),
+            prefix: NetworkPrefix::new("1.1.1.0/24".parse().unwrap(), None),
+            as_path: origin.map(|asn| AsPath::from_sequence([peer, asn])),
+            origin_asns: origin.map(|asn| vec![asn.into()]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let enricher = enricher();
+        let info = enricher.lookup(&elem(3320, Some(13335)));
+        assert_eq!(info.peer_name.as_deref(), Some("DTAG"));
+        assert_eq!(info.origin_name.as_deref(), Some("Cloudflare, Inc."));
+        assert_eq!(info.origin_country.as_deref(), Some("US"));
+        assert_eq!(info.origin_org.as_deref(), Some("Cloudflare, Inc."));
+        assert_eq!(info.field("origin_country"), Some("US"));
+
+        // Unknown ASNs and withdrawals have no information
+        let all = enricher.lookup_all(&[elem(64496, Some(3320)), elem(13335, None)]);
+        assert_eq!(all[0].peer_name, None);
+        assert_eq!(all[0].origin_name.as_deref(), Some("DTAG"));
+        assert_eq!(all[0].origin_org, None);
+        assert_eq!(all[1].peer_name.as_deref(), Some("Cloudflare, Inc."));
+        assert_eq!(all[1].origin_name, None);
+
+        assert!(AsInfoEnricher::new(MonocleDatabase::open_in_memory().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,870 @@
+//! Boolean filter expressions (`--where`).
+//!
+//! [`ParseFilters`](super::ParseFilters) AND across filter dimensions and OR
+//! within one, so they cannot express conditions such as
+//! `origin_asns == 64496 or (communities == 64496:666 and type == w)`. An
+//! [`ElemExpr`] combines conditions on any element field freely. It is
+//! compiled once and evaluated for each element after the parser filters.
+//!
+//! | Syntax | Matches if |
+//! |--------|------------|
+//! | `A and B`, `A && B` | both `A` and `B` match |
+//! | `A or B`, `A \|\| B` | `A` or `B` matches (`and` binds tighter) |
+//! | `not A`, `!A` | `A` does not match |
+//! | `(A)` | grouping |
+//! | `FIELD == V`, `!=`, `<`, `<=`, `>`, `>=` | comparison (`=` is the same as `==`) |
+//! | `FIELD ~ "REGEX"`, `FIELD !~ "REGEX"` | regex match on the field's text |
+//! | `FIELD in PREFIX` | the address or prefix is within `PREFIX` (inclusive) |
+//! | `FIELD in (V, ...)` | the field equals any of the values |
+//! | `FIELD contains V` | the prefix covers `V`, the AS path contains ASN `V`, or a list field has item `V` |
+//! | `FIELD` | a boolean field is true, any other field is present |
+//!
+//! Keywords are case-insensitive. Values are bare words such as `64496`,
+//! `64496:100`, `192.0.2.0/24` or `igp`, or single- or double-quoted strings.
+//!
+//! | Field | Values |
+//! |-------|--------|
+//! | `type` | `a`/`announce`, `w`/`withdraw` |
+//! | `timestamp` | Unix timestamp or time string |
+//! | `peer_ip`, `next_hop`, `aggr_ip` | IP address |
+//! | `peer_asn`, `aggr_asn`, `only_to_customer` | ASN |
+//! | `prefix` | prefix (an address is a host prefix) |
+//! | `path_id`, `local_pref`, `med` | number |
+//! | `as_path_len`, `unique_path_len`, `prepend_count` | number, see [`AsPathStats`] |
+//! | `as_path` | AS path text, e.g. `"174 64496"` |
+//! | `origin_asns` (list) | ASN |
+//! | `origin` | `igp`, `egp` or `incomplete` |
+//! | `communities` (list) | community text, e.g. `64496:100`, `no-export` |
+//! | `atomic` | `true` or `false` |
+//!
+//! A comparison with a list field matches if any item matches; `!=` and `!~`
+//! match if the field is present and no item matches. Comparisons with an
+//! attribute the element does not have, such as the `med` of a withdrawal,
+//! do not match.
+
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::net::IpAddr;
+
+use bgpkit_parser::models::{Asn, ElemType, Origin};
+use bgpkit_parser::BgpElem;
+use ipnet::IpNet;
+use regex::Regex;
+
+use super::as_path_filter::AsPathStats;
+use super::attr_filter::parse_origin;
+use crate::lens::time::TimeLens;
+
+/// Syntax or type error in an expression, with the position it was found at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExprError {
+    pub message: String,
+    /// Character offset into the expression
+    pub position: usize,
+    pub expr: String,
+}
+
+impl Display for ExprError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "invalid where expression at column {}: {}",
+            self.position + 1,
+            self.message
+        )?;
+        writeln!(f, "  {}", self.expr)?;
+        write!(f, "  {}^", " ".repeat(self.position))
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+/// A compiled filter expression
+#[derive(Debug, Clone)]
+pub struct ElemExpr {
+    root: Node,
+}
+
+impl ElemExpr {
+    /// Parse and type-check an expression
+    pub fn parse(expr: &str) -> Result<Self, ExprError> {
+        let error = |message: String, offset: usize| ExprError {
+            message,
+            position: expr[..offset].chars().count(),
+            expr: expr.to_string(),
+        };
+        let tokens = tokenize(expr).map_err(|(message, offset)| error(message, offset))?;
+        let mut parser = Parser {
+            tokens,
+            index: 0,
+            end: expr.len(),
+        };
+        let root = parser
+            .parse_or()
+            .and_then(|root| match parser.peek() {
+                None => Ok(root),
+                Some(token) => Err((
+                    format!(
+                        "expected 'and', 'or' or the end of the expression, found {}",
+                        token.kind
+                    ),
+                    token.offset,
+                )),
+            })
+            .map_err(|(message, offset)| error(message, offset))?;
+        Ok(Self { root })
+    }
+
+    /// Whether `elem` matches the expression
+    pub fn matches(&self, elem: &BgpElem) -> bool {
+        self.root.eval(elem)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Test(Field, Test),
+}
+
+impl Node {
+    fn eval(&self, elem: &BgpElem) -> bool {
+        match self {
+            Node::And(a, b) => a.eval(elem) && b.eval(elem),
+            Node::Or(a, b) => a.eval(elem) || b.eval(elem),
+            Node::Not(a) => !a.eval(elem),
+            Node::Test(field, test) => test.eval(*field, elem),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Test {
+    Present,
+    Compare(CmpOp, Value),
+    Regex { regex: Regex, negated: bool },
+    Within(IpNet),
+    OneOf(Vec<Value>),
+    Covers(IpNet),
+    HasAsn(Asn),
+    HasItem(Value),
+}
+
+impl Test {
+    fn eval(&self, field: Field, elem: &BgpElem) -> bool {
+        if let Test::HasAsn(asn) = self {
+            return elem
+                .as_path
+                .as_ref()
+                .is_some_and(|path| path.contains_asn(*asn));
+        }
+        let values = field.values(elem);
+        match self {
+            Test::Present => match values.as_slice() {
+                [Value::Bool(value)] => *value,
+                values => !values.is_empty(),
+            },
+            Test::Compare(CmpOp::Ne, expected) => !values.is_empty() && !values.contains(expected),
+            Test::Compare(op, expected) => {
+                values.iter().any(|value| op.holds(value.compare(expected)))
+            }
+            Test::Regex { regex, negated } => {
+                let matched = values.iter().any(|value| regex.is_match(&value.text()));
+                !values.is_empty() && matched != *negated
+            }
+            Test::Within(net) => values.iter().any(|value| match value {
+                Value::Ip(ip) => net.contains(ip),
+                Value::Prefix(prefix) => net.contains(prefix),
+                _ => false,
+            }),
+            Test::OneOf(expected) => values.iter().any(|value| expected.contains(value)),
+            Test::Covers(net) => values.iter().any(|value| match value {
+                Value::Prefix(prefix) => prefix.contains(net),
+                _ => false,
+            }),
+            Test::HasItem(expected) => values.contains(expected),
+            Test::HasAsn(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    fn holds(self, ordering: Option<Ordering>) -> bool {
+        let Some(ordering) = ordering else {
+            return false;
+        };
+        match self {
+            CmpOp::Eq => ordering.is_eq(),
+            CmpOp::Ne => ordering.is_ne(),
+            CmpOp::Lt => ordering.is_lt(),
+            CmpOp::Le => ordering.is_le(),
+            CmpOp::Gt => ordering.is_gt(),
+            CmpOp::Ge => ordering.is_ge(),
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        !matches!(self, CmpOp::Eq | CmpOp::Ne)
+    }
+}
+
+/// A field value or a typed literal
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Num(f64),
+    Ip(IpAddr),
+    Prefix(IpNet),
+    Text(String),
+    Bool(bool),
+    ElemType(ElemType),
+    Origin(Origin),
+}
+
+impl Value {
+    fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Num(a), Value::Num(b)) => a.partial_cmp(b),
+            (Value::Ip(a), Value::Ip(b)) => Some(a.cmp(b)),
+            (a, b) if a == b => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+
+    /// Text the value is regex-matched against, as printed by `parse`
+    fn text(&self) -> String {
+        match self {
+            Value::Num(n) if n.fract() == 0.0 => format!("{n:.0}"),
+            Value::Num(n) => n.to_string(),
+            Value::Ip(ip) => ip.to_string(),
+            Value::Prefix(prefix) => prefix.to_string(),
+            Value::Text(text) => text.clone(),
+            Value::Bool(value) => value.to_string(),
+            Value::ElemType(ElemType::ANNOUNCE) => "A".to_string(),
+            Value::ElemType(ElemType::WITHDRAW) => "W".to_string(),
+            Value::Origin(origin) => origin.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Num,
+    Asn,
+    Timestamp,
+    Ip,
+    Prefix,
+    Text,
+    Bool,
+    ElemType,
+    Origin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Type,
+    Timestamp,
+    PeerIp,
+    PeerAsn,
+    Prefix,
+    PathId,
+    AsPath,
+    AsPathLen,
+    UniquePathLen,
+    PrependCount,
+    OriginAsns,
+    Origin,
+    NextHop,
+    LocalPref,
+    Med,
+    Communities,
+    Atomic,
+    AggrAsn,
+    AggrIp,
+    OnlyToCustomer,
+}
+
+/// Field names and their aliases
+const FIELDS: &[(&str, Field)] = &[
+    ("type", Field::Type),
+    ("timestamp", Field::Timestamp),
+    ("peer_ip", Field::PeerIp),
+    ("peer_asn", Field::PeerAsn),
+    ("prefix", Field::Prefix),
+    ("path_id", Field::PathId),
+    ("as_path", Field::AsPath),
+    ("as_path_len", Field::AsPathLen),
+    ("path_len", Field::AsPathLen),
+    ("unique_path_len", Field::UniquePathLen),
+    ("prepend_count", Field::PrependCount),
+    ("origin_asns", Field::OriginAsns),
+    ("origin_asn", Field::OriginAsns),
+    ("origin", Field::Origin),
+    ("next_hop", Field::NextHop),
+    ("local_pref", Field::LocalPref),
+    ("med", Field::Med),
+    ("communities", Field::Communities),
+    ("community", Field::Communities),
+    ("atomic", Field::Atomic),
+    ("aggr_asn", Field::AggrAsn),
+    ("aggr_ip", Field::AggrIp),
+    ("only_to_customer", Field::OnlyToCustomer),
+];
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        FIELDS
+            .iter()
+            .find(|(field_name, _)| *field_name == name)
+            .map(|(_, field)| *field)
+    }
+
+    fn name(self) -> &'static str {
+        FIELDS
+            .iter()
+            .find(|(_, field)| *field == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+
+    fn kind(self) -> Kind {
+        match self {
+            Field::Type => Kind::ElemType,
+            Field::Timestamp => Kind::Timestamp,
+            Field::PeerIp | Field::NextHop | Field::AggrIp => Kind::Ip,
+            Field::PeerAsn | Field::OriginAsns | Field::AggrAsn | Field::OnlyToCustomer => {
+                Kind::Asn
+            }
+            Field::Prefix => Kind::Prefix,
+            Field::PathId
+            | Field::AsPathLen
+            | Field::UniquePathLen
+            | Field::PrependCount
+            | Field::LocalPref
+            | Field::Med => Kind::Num,
+            Field::AsPath | Field::Communities => Kind::Text,
+            Field::Origin => Kind::Origin,
+            Field::Atomic => Kind::Bool,
+        }
+    }
+
+    fn is_list(self) -> bool {
+        matches!(self, Field::OriginAsns | Field::Communities)
+    }
+
+    fn values(self, elem: &BgpElem) -> Vec<Value> {
+        let asn = |asn: &Asn| Value::Num(asn.to_u32() as f64);
+        let num = |value: &u32| Value::Num(*value as f64);
+        match self {
+            Field::Type => vec![Value::ElemType(elem.elem_type)],
+            Field::Timestamp => vec![Value::Num(elem.timestamp)],
+            Field::PeerIp => vec![Value::Ip(elem.peer_ip)],
+            Field::PeerAsn => vec![asn(&elem.peer_asn)],
+            Field::Prefix => vec![Value::Prefix(elem.prefix.prefix)],
+            Field::PathId => elem.prefix.path_id.iter().map(num).collect(),
+            Field::AsPath => elem
+                .as_path
+                .iter()
+                .map(|path| Value::Text(path.to_string()))
+                .collect(),
+            Field::AsPathLen | Field::UniquePathLen | Field::PrependCount => elem
+                .as_path
+                .iter()
+                .map(|path| {
+                    let stats = AsPathStats::new(path);
+                    Value::Num(match self {
+                        Field::UniquePathLen => stats.unique_path_len,
+                        Field::PrependCount => stats.prepend_count,
+                        _ => stats.path_len,
+                    } as f64)
+                })
+                .collect(),
+            Field::OriginAsns => elem.origin_asns.iter().flatten().map(asn).collect(),
+            Field::Origin => elem.origin.iter().map(|o| Value::Origin(*o)).collect(),
+            Field::NextHop => elem.next_hop.iter().map(|ip| Value::Ip(*ip)).collect(),
+            Field::LocalPref => elem.local_pref.iter().map(num).collect(),
+            Field::Med => elem.med.iter().map(num).collect(),
+            Field::Communities => elem
+                .communities
+                .iter()
+                .flatten()
+                .map(|c| Value::Text(c.to_string()))
+                .collect(),
+            Field::Atomic => vec![Value::Bool(elem.atomic)],
+            Field::AggrAsn => elem.aggr_asn.iter().map(asn).collect(),
+            Field::AggrIp => elem
+                .aggr_ip
+                .iter()
+                .map(|ip| Value::Ip(IpAddr::V4(*ip)))
+                .collect(),
+            Field::OnlyToCustomer => elem.only_to_customer.iter().map(asn).collect(),
+        }
+    }
+
+    /// Parse a literal compared with this field
+    fn literal(self, text: &str) -> Result<Value, String> {
+        let invalid = |expected: &str| format!("'{}' is not {} ({})", text, expected, self.name());
+        match self.kind() {
+            Kind::Num => text
+                .parse::<u32>()
+                .map(|n| Value::Num(n as f64))
+                .map_err(|_| invalid("a number")),
+            Kind::Asn => parse_asn(text)
+                .map(|asn| Value::Num(asn as f64))
+                .ok_or_else(|| invalid("an ASN")),
+            Kind::Timestamp => match text.parse::<f64>() {
+                Ok(ts) => Ok(Value::Num(ts)),
+                Err(_) => TimeLens::new()
+                    .parse_time_string(text)
+                    .map(|time| Value::Num(time.timestamp() as f64))
+                    .map_err(|_| invalid("a timestamp or time string")),
+            },
+            Kind::Ip => text
+                .parse::<IpAddr>()
+                .map(Value::Ip)
+                .map_err(|_| invalid("an IP address")),
+            Kind::Prefix => parse_net(text)
+                .map(Value::Prefix)
+                .ok_or_else(|| invalid("a prefix")),
+            Kind::Text => Ok(Value::Text(text.to_string())),
+            Kind::Bool => match text.to_lowercase().as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                _ => Err(invalid("true or false")),
+            },
+            Kind::ElemType => match text.to_lowercase().as_str() {
+                "a" | "announce" | "announcement" => Ok(Value::ElemType(ElemType::ANNOUNCE)),
+                "w" | "withdraw" | "withdrawal" => Ok(Value::ElemType(ElemType::WITHDRAW)),
+                _ => Err(invalid("an element type (a or w)")),
+            },
+            Kind::Origin => parse_origin(text)
+                .map(Value::Origin)
+                .map_err(|_| invalid("igp, egp or incomplete")),
+        }
+    }
+}
+
+/// Parse an ASN, with or without an `AS` prefix
+fn parse_asn(text: &str) -> Option<u32> {
+    let digits = match text.get(..2) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("as") => &text[2..],
+        _ => text,
+    };
+    digits.parse().ok()
+}
+
+/// Parse a prefix, or an address as a host prefix
+fn parse_net(text: &str) -> Option<IpNet> {
+    text.parse::<IpNet>()
+        .ok()
+        .or_else(|| text.parse::<IpAddr>().ok().map(IpNet::from))
+}
+
+// =============================================================================
+// Tokenizer
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    Comma,
+    Cmp(CmpOp),
+    Match { negated: bool },
+    Not,
+    And,
+    Or,
+    Word(String),
+    Quoted(String),
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::LParen => f.write_str("'('"),
+            TokenKind::RParen => f.write_str("')'"),
+            TokenKind::Comma => f.write_str("','"),
+            TokenKind::Cmp(_) | TokenKind::Match { .. } => f.write_str("an operator"),
+            TokenKind::Not => f.write_str("'!'"),
+            TokenKind::And => f.write_str("'&&'"),
+            TokenKind::Or => f.write_str("'||'"),
+            TokenKind::Word(word) => write!(f, "'{word}'"),
+            TokenKind::Quoted(text) => write!(f, "\"{text}\""),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    /// Byte offset into the expression
+    offset: usize,
+}
+
+/// Characters that end a bare word
+const SPECIAL_CHARS: &str = "()!,=<>~&|\"'";
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, (String, usize)> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        let mut next_is = |expected: char| chars.next_if(|(_, c)| *c == expected).is_some();
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            ',' => TokenKind::Comma,
+            '=' => {
+                next_is('=');
+                TokenKind::Cmp(CmpOp::Eq)
+            }
+            '!' if next_is('=') => TokenKind::Cmp(CmpOp::Ne),
+            '!' if next_is('~') => TokenKind::Match { negated: true },
+            '!' => TokenKind::Not,
+            '<' if next_is('=') => TokenKind::Cmp(CmpOp::Le),
+            '<' => TokenKind::Cmp(CmpOp::Lt),
+            '>' if next_is('=') => TokenKind::Cmp(CmpOp::Ge),
+            '>' => TokenKind::Cmp(CmpOp::Gt),
+            '~' => TokenKind::Match { negated: false },
+            '&' if next_is('&') => TokenKind::And,
+            '|' if next_is('|') => TokenKind::Or,
+            '&' | '|' => return Err((format!("expected '{c}{c}'"), offset)),
+            '"' | '\'' => {
+                let mut text = String::new();
+                let mut closed = false;
+                while let Some((_, next)) = chars.next() {
+                    match next {
+                        '\\' => {
+                            if let Some((_, escaped)) = chars.next() {
+                                if escaped != c && escaped != '\\' {
+                                    text.push('\\');
+                                }
+                                text.push(escaped);
+                            }
+                        }
+                        next if next == c => {
+                            closed = true;
+                            break;
+                        }
+                        next => text.push(next),
+                    }
+                }
+                if !closed {
+                    return Err(("unterminated string".to_string(), offset));
+                }
+                TokenKind::Quoted(text)
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some((_, next)) =
+                    chars.next_if(|(_, c)| !c.is_whitespace() && !SPECIAL_CHARS.contains(*c))
+                {
+                    word.push(next);
+                }
+                match word.to_lowercase().as_str() {
+                    "and" => TokenKind::And,
+                    "or" => TokenKind::Or,
+                    "not" => TokenKind::Not,
+                    _ => TokenKind::Word(word),
+                }
+            }
+        };
+        tokens.push(Token { kind, offset });
+    }
+    Ok(tokens)
+}
+
+// =============================================================================
+// Parser
+// =============================================================================
+
+type ParseResult<T> = Result<T, (String, usize)>;
+
+struct Parser {
+    tokens: Vec<Token>,
+    index: usize,
+    /// Byte offset of the end of the expression
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn next_if(&mut self, kind: &TokenKind) -> bool {
+        let matched = self.peek().is_some_and(|token| token.kind == *kind);
+        if matched {
+            self.index += 1;
+        }
+        matched
+    }
+
+    /// Offset of the next token, or the end of the expression
+    fn offset(&self) -> usize {
+        self.peek().map(|token| token.offset).unwrap_or(self.end)
+    }
+
+    fn parse_or(&mut self) -> ParseResult<Node> {
+        let mut node = self.parse_and()?;
+        while self.next_if(&TokenKind::Or) {
+            node = Node::Or(Box::new(node), Box::new(self.parse_and()?));
+        }
+        Ok(node)
+    }
+
+    fn parse_and(&mut self) -> ParseResult<Node> {
+        let mut node = self.parse_unary()?;
+        while self.next_if(&TokenKind::And) {
+            node = Node::And(Box::new(node), Box::new(self.parse_unary()?));
+        }
+        Ok(node)
+    }
+
+    fn parse_unary(&mut self) -> ParseResult<Node> {
+        if self.next_if(&TokenKind::Not) {
+            return Ok(Node::Not(Box::new(self.parse_unary()?)));
+        }
+        let offset = self.offset();
+        match self.next().map(|token| token.kind) {
+            Some(TokenKind::LParen) => {
+                let node = self.parse_or()?;
+                if !self.next_if(&TokenKind::RParen) {
+                    return Err(("expected ')'".to_string(), self.offset()));
+                }
+                Ok(node)
+            }
+            Some(TokenKind::Word(name)) => {
+                let field = Field::from_name(&name).ok_or_else(|| {
+                    let names = FIELDS.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+                    (
+                        format!(
+                            "unknown field '{}', expected one of: {}",
+                            name,
+                            names.join(", ")
+                        ),
+                        offset,
+                    )
+                })?;
+                let test = self.parse_test(field)?;
+                Ok(Node::Test(field, test))
+            }
+            Some(kind) => Err((
+                format!("expected a field name or '(', found {kind}"),
+                offset,
+            )),
+            None => Err(("expected a field name or '('".to_string(), offset)),
+        }
+    }
+
+    fn parse_test(&mut self, field: Field) -> ParseResult<Test> {
+        let Some(token) = self.peek().cloned() else {
+            return Ok(Test::Present);
+        };
+        match token.kind {
+            TokenKind::Cmp(op) => {
+                self.index += 1;
+                if op.is_ordering()
+                    && !matches!(
+                        field.kind(),
+                        Kind::Num | Kind::Asn | Kind::Timestamp | Kind::Ip
+                    )
+                {
+                    return Err((
+                        format!("{} cannot be compared with '<' or '>'", field.name()),
+                        token.offset,
+                    ));
+                }
+                Ok(Test::Compare(op, self.parse_literal(field)?))
+            }
+            TokenKind::Match { negated } => {
+                self.index += 1;
+                let (pattern, offset) = self.parse_value()?;
+                let regex =
+                    Regex::new(&pattern).map_err(|e| (format!("invalid regex: {e}"), offset))?;
+                Ok(Test::Regex { regex, negated })
+            }
+            TokenKind::Word(word) if word.eq_ignore_ascii_case("in") => {
+                self.index += 1;
+                if self.next_if(&TokenKind::LParen) {
+                    let mut values = vec![self.parse_literal(field)?];
+                    while self.next_if(&TokenKind::Comma) {
+                        values.push(self.parse_literal(field)?);
+                    }
+                    if !self.next_if(&TokenKind::RParen) {
+                        return Err(("expected ',' or ')'".to_string(), self.offset()));
+                    }
+                    return Ok(Test::OneOf(values));
+                }
+                if !matches!(field.kind(), Kind::Ip | Kind::Prefix) {
+                    return Err((
+                        format!("{} in takes a list of values: (V, ...)", field.name()),
+                        self.offset(),
+                    ));
+                }
+                let (text, offset) = self.parse_value()?;
+                let net = parse_net(&text)
+                    .ok_or_else(|| (format!("'{text}' is not a prefix"), offset))?;
+                Ok(Test::Within(net))
+            }
+            TokenKind::Word(word) if word.eq_ignore_ascii_case("contains") => {
+                self.index += 1;
+                let (text, offset) = self.parse_value()?;
+                match field {
+                    Field::Prefix => parse_net(&text)
+                        .map(Test::Covers)
+                        .ok_or_else(|| (format!("'{text}' is not a prefix or address"), offset)),
+                    Field::AsPath => parse_asn(&text)
+                        .map(|asn| Test::HasAsn(Asn::new_32bit(asn)))
+                        .ok_or_else(|| (format!("'{text}' is not an ASN (as_path)"), offset)),
+                    field if field.is_list() => field
+                        .literal(&text)
+                        .map(Test::HasItem)
+                        .map_err(|message| (message, offset)),
+                    field => Err((
+                        format!("{} does not support 'contains'", field.name()),
+                        token.offset,
+                    )),
+                }
+            }
+            _ => Ok(Test::Present),
+        }
+    }
+
+    /// A bare word or quoted string, with its offset
+    fn parse_value(&mut self) -> ParseResult<(String, usize)> {
+        let offset = self.offset();
+        match self.next().map(|token| token.kind) {
+            Some(TokenKind::Word(text)) | Some(TokenKind::Quoted(text)) => Ok((text, offset)),
+            Some(kind) => Err((format!("expected a value, found {kind}"), offset)),
+            None => Err(("expected a value".to_string(), offset)),
+        }
+    }
+
+    fn parse_literal(&mut self, field: Field) -> ParseResult<Value> {
+        let (text, offset) = self.parse_value()?;
+        field.literal(&text).map_err(|message| (message, offset))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use bgpkit_parser::models::{AsPath, Community, MetaCommunity, NetworkPrefix};
+    use std::str::FromStr;
+
+    fn elem() -> BgpElem {
+        BgpElem {
+            timestamp: 1704067200.0,
+            peer_ip: "192.0.2.1".parse().unwrap(),
+            peer_asn: Asn::new_32bit(174),
+            prefix: NetworkPrefix::from_str("198.51.100.0/24").unwrap(),
+            as_path: Some(AsPath::from_sequence([174, 3356, 64496])),
+            origin_asns: Some(vec![Asn::new_32bit(64496)]),
+            origin: Some(Origin::IGP),
+            next_hop: Some("192.0.2.1".parse().unwrap()),
+            local_pref: Some(100),
+            communities: Some(vec![
+                MetaCommunity::Plain(Community::Custom(Asn::new_16bit(64496), 666)),
+                MetaCommunity::Plain(Community::NoExport),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn matches(expr: &str) -> bool {
+        ElemExpr::parse(expr).unwrap().matches(&elem())
+    }
+
+    #[test]
+    fn test_boolean_logic() {
+        assert!(matches("origin_asns == 64496"));
+        assert!(matches(
+            "origin_asn == 1 or (communities == 64496:666 and type == a)"
+        ));
+        assert!(!matches(
+            "origin_asn == 1 or communities == 64496:666 and type == w"
+        ));
+        assert!(matches("not (type == w) && !atomic"));
+        assert!(matches("peer_asn = 174 AND NOT med"));
+        assert!(!matches("(peer_asn == 174 or peer_asn == 3356) and med"));
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert!(matches("local_pref >= 100 and local_pref < 200"));
+        assert!(!matches("local_pref > 100"));
+        assert!(matches("as_path_len == 3 and peer_asn in (AS174, 6939)"));
+        assert!(matches(
+            "path_len == 3 and unique_path_len == 3 and prepend_count == 0"
+        ));
+        assert!(matches("timestamp >= 2024-01-01T00:00:00Z"));
+        assert!(matches("origin == igp and next_hop == 192.0.2.1"));
+        assert!(!matches("med != 0"), "missing attributes never match");
+        assert!(matches("communities != 64496:1"));
+    }
+
+    #[test]
+    fn test_regex_and_containment() {
+        assert!(matches(r#"as_path ~ "^174 3356 ""#));
+        assert!(matches("as_path !~ '^3356'"));
+        assert!(matches("communities ~ 'no-export'"));
+        assert!(matches(
+            "as_path contains 3356 and origin_asns contains 64496"
+        ));
+        assert!(matches(
+            "prefix in 198.51.0.0/16 and peer_ip in 192.0.2.0/24"
+        ));
+        assert!(!matches("prefix in 198.51.100.0/25"));
+        assert!(matches(
+            "prefix contains 198.51.100.128/25 and prefix contains 198.51.100.1"
+        ));
+        assert!(matches("prefix == 198.51.100.0/24"));
+    }
+
+    #[test]
+    fn test_errors_have_positions() {
+        let cases = [
+            ("origin_asn == ", 14, "expected a value"),
+            ("origin_asn == 1 or (type == a", 29, "expected ')'"),
+            ("bogus == 1", 0, "unknown field 'bogus'"),
+            ("med == abc", 7, "'abc' is not a number"),
+            ("type > a", 5, "cannot be compared"),
+            ("med == 1 med == 2", 9, "expected 'and', 'or'"),
+            ("as_path ~ '('", 10, "invalid regex"),
+            ("as_path ~ 'abc", 10, "unterminated string"),
+            ("med in 10.0.0.0/8", 7, "takes a list of values"),
+            ("atomic contains 1", 7, "does not support 'contains'"),
+        ];
+        for (expr, position, message) in cases {
+            let err = ElemExpr::parse(expr).unwrap_err();
+            assert_eq!(err.position, position, "{expr}: {err}");
+            assert!(err.message.contains(message), "{expr}: {err}");
+        }
+
+        let err = ElemExpr::parse("med == abc").unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "invalid where expression at column 8: 'abc' is not a number (med)\n  med == abc\n         ^"
+        );
+    }
+}
@@ -37,7 +37,21 @@
 //!   "peer_asns": ["174", "6939"],
 //!   "peer_ips": ["192.0.2.1"],
 //!   "communities": ["64496:100", "*:200"],
-//!   "as_path_regex": "174 64496$",
+//!   "ext_communities": ["rt:64496:1"],
+//!   "local_pref": ["100..200"],
+//!   "med": ["!1000.."],
+//!   "next_hops": ["192.0.2.0/24"],
+//!   "origins": ["igp"],
+//!   "aggr_asns": ["64496"],
+//!   "atomic": false,
+//!   "rpki": ["invalid"],
+//!   "origin_countries": ["!US"],
+//!   "peer_countries": ["DE", "Netherlands"],
+//!   "origin_orgs": ["CLOUD14-ARIN"],
+//!   "as_path_regex": "_174_64496$",
+//!   "as_path_style": "cisco",
+//!   "prepend_count": ["3.."],
+//!   "where": "med < 100 or origin == igp",
 //!   "elem_type": "w",
 //!   "include_super": false,
 //!   "include_sub": true,
@@ -46,9 +60,11 @@
 //! }
 //! ```
 //!
-//! String-typed fields (`prefixes`, `origin_asns`, `peer_asns`, `communities`)
-//! use the same syntax as CLI flags, including `!` prefix for negation. See the
-//! [`ParseFilters`] docs for details on negation semantics.
+//! String-typed fields (`prefixes`, `origin_asns`, `peer_asns`, `communities`
+//! and the path attribute filters) use the same syntax as CLI flags, including
+//! `!` prefix for negation. See the [`ParseFilters`] docs for details on
+//! negation semantics and the [`attr_filter`](super::attr_filter) module for
+//! the attribute filter syntax.
 //!
 //! ## Example: Withdrawal investigation for a specific AS
 //!
@@ -63,6 +79,23 @@
 //!
 //! This matches all BGP withdrawals originated by AS64496 within a 2-hour window.
 //!
+//! ## Example: The same prefixes around several incidents
+//!
+//! ```json
+//! {
+//!   "prefixes": ["192.0.2.0/24"],
+//!   "windows": [
+//!     { "label": "outage", "start_ts": "2025-03-01T00:00:00Z", "duration": "2h" },
+//!     { "label": "leak", "start_ts": "2025-04-12T09:30:00Z", "end_ts": "2025-04-12T10:00:00Z" }
+//!   ]
+//! }
+//! ```
+//!
+//! Each window takes two of `start_ts`, `end_ts` and `duration`. A search lists
+//! the files of all windows in one plan and tags every element with the labels
+//! of the windows it falls into. See the
+//! [`time_window`](crate::lens::parse::time_window) module for details.
+//!
 //! ## Example: Country-level investigation with community filter
 //!
 //! ```json
@@ -83,9 +116,12 @@
 //! where `filters.json` contains `"prefixes": ["192.0.2.0/24"]` results in a
 //! prefix filter matching **both** `10.0.0.0/8` and `192.0.2.0/24`.
 //!
-//! For scalar fields (`as_path_regex`, `elem_type`, `start_ts`, `end_ts`,
-//! `duration`), CLI flags take precedence over file values — if you pass
-//! `--as-path` on the command line, the file's `as_path_regex` is ignored.
+//! For scalar fields (`as_path_regex`, `atomic`, `where`, `elem_type`, the
+//! AS path flags such as `as_set`, `rpki_date`, `start_ts`, `end_ts`, `duration`), CLI flags take precedence over file values — if you pass
+//! `--as-path` on the command line, the file's `as_path_regex` and
+//! `as_path_style` are ignored, and likewise `rpki_source` follows
+//! `rpki_date`.
+//! File `windows` are appended to any `--window` CLI values.
 //! Boolean fields (`include_super`, `include_sub`) are OR-ed: the file can
 //! enable them but cannot disable a CLI-enabled flag.
 //!
@@ -165,13 +201,33 @@
 //! Here `as_filters.json` might specify origin ASNs and communities, while
 //! `extra_prefixes.txt` adds more prefixes on top of any `prefixes` in the JSON
 //! file. The `-c rrc00` CLI flag adds a collector filter (search command only).
+//!
+//! # Search and RIB Fields
+//!
+//! `collector`, `project` and `dump_type` only apply to `search`
+//! ([`FilterFile::merge_into_search`]); `rib` takes `collector` and `project`
+//! ([`FilterFile::merge_into_rib`]). [`FilterFile::merge_into`], used by
+//! `parse`, rejects a file that sets any of them with an error.
+//!
+//! # Saved Queries
+//!
+//! The same schema is used for named queries in `monocle.toml`
+//! (`[queries.<name>]` tables), looked up with
+//! [`MonocleConfig::saved_query`](crate::config::MonocleConfig::saved_query).
 
 use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use std::path::Path;
 
+use super::as_path_filter::AsPathFilters;
+use super::as_path_regex::AsPathStyle;
+use super::time_window::TimeWindowSpec;
 use super::{ParseElemType, ParseFilters};
+use crate::lens::rib::RibFilters;
+use crate::lens::rpki::RpkiDataSource;
+use crate::lens::search::{SearchDumpType, SearchFilters};
 
 /// Structured JSON filter file.
 ///
@@ -250,6 +306,136 @@ pub struct FilterFile {
     #[serde(default)]
     pub as_path_regex: Option<String>,
 
+    /// Dialect of `as_path_regex`: `raw` (default), `cisco` or `junos`.
+    ///
+    /// Follows the file's `as_path_regex`: ignored when `--as-path` is set on
+    /// the CLI, and `--as-path-style` takes precedence.
+    #[serde(default)]
+    pub as_path_style: Option<AsPathStyle>,
+
+    /// AS path length values or inclusive ranges (`"10.."`).
+    ///
+    /// Unioned with any `--path-len` CLI values, like the other ranges below.
+    #[serde(default)]
+    pub path_len: Vec<String>,
+
+    /// AS path length without prepends, values or inclusive ranges.
+    #[serde(default)]
+    pub unique_path_len: Vec<String>,
+
+    /// Number of prepended hops, values or inclusive ranges (`"3.."`).
+    #[serde(default)]
+    pub prepend_count: Vec<String>,
+
+    /// Whether the AS path contains an AS_SET.
+    ///
+    /// Only applied if `--as-set` is not set on the CLI (CLI takes precedence),
+    /// like the other AS path flags below.
+    #[serde(default)]
+    pub as_set: Option<bool>,
+
+    /// Whether the AS path contains a private ASN.
+    #[serde(default)]
+    pub private_asn: Option<bool>,
+
+    /// Whether the AS path contains a reserved ASN.
+    #[serde(default)]
+    pub reserved_asn: Option<bool>,
+
+    /// Whether the AS path has a loop.
+    #[serde(default)]
+    pub path_loop: Option<bool>,
+
+    /// Extended community patterns (`"rt:A:B"`, `"soo:A:B"`, `"*:A:B"`, `!`
+    /// prefix for negation).
+    ///
+    /// Unioned with any `--ext-community` CLI values.
+    #[serde(default)]
+    pub ext_communities: Vec<String>,
+
+    /// MED values or inclusive ranges (`"50"`, `"0..100"`, `"!1000.."`).
+    ///
+    /// Unioned with any `--med` CLI values.
+    #[serde(default)]
+    pub med: Vec<String>,
+
+    /// LOCAL_PREF values or inclusive ranges (`"100..200"`).
+    ///
+    /// Unioned with any `--local-pref` CLI values.
+    #[serde(default)]
+    pub local_pref: Vec<String>,
+
+    /// Next hop IP addresses or prefixes (`!` prefix for negation).
+    ///
+    /// Unioned with any `--next-hop` CLI values.
+    #[serde(default)]
+    pub next_hops: Vec<String>,
+
+    /// ORIGIN attribute values: `"igp"`, `"egp"` or `"incomplete"`.
+    ///
+    /// Unioned with any `--origin` CLI values.
+    #[serde(default)]
+    pub origins: Vec<String>,
+
+    /// Aggregator ASN filters (numeric string, `!` prefix for negation).
+    ///
+    /// Unioned with any `--aggr-asn` CLI values.
+    #[serde(default)]
+    pub aggr_asns: Vec<String>,
+
+    /// ATOMIC_AGGREGATE filter.
+    ///
+    /// Only applied if `--atomic` is not set on the CLI (CLI takes precedence).
+    #[serde(default)]
+    pub atomic: Option<bool>,
+
+    /// RPKI validation states: `"valid"`, `"invalid"` or `"not-found"` (`!`
+    /// prefix for negation).
+    ///
+    /// Unioned with any `--rpki` CLI values.
+    #[serde(default)]
+    pub rpki: Vec<String>,
+
+    /// Validate `rpki` against the historical RPKI data of this date.
+    ///
+    /// Only applied if `--rpki-date` is not set on the CLI (CLI takes precedence).
+    #[serde(default)]
+    pub rpki_date: Option<NaiveDate>,
+
+    /// Source of the `rpki_date` data: `"Ripe"`, `"RpkiViews"` or
+    /// `"RpkiSpools"` (default).
+    ///
+    /// Follows the file's `rpki_date`, and `--rpki-source` takes precedence.
+    #[serde(default)]
+    pub rpki_source: Option<RpkiDataSource>,
+
+    /// Origin AS registration countries, as codes or names (`!` prefix for
+    /// negation).
+    ///
+    /// Unioned with any `--origin-country` CLI values.
+    #[serde(default)]
+    pub origin_countries: Vec<String>,
+
+    /// Peer AS registration countries, as codes or names (`!` prefix for
+    /// negation).
+    ///
+    /// Unioned with any `--peer-country` CLI values.
+    #[serde(default)]
+    pub peer_countries: Vec<String>,
+
+    /// Origin AS organizations, as CAIDA AS2Org organization IDs (`!` prefix
+    /// for negation).
+    ///
+    /// Unioned with any `--origin-org` CLI values.
+    #[serde(default)]
+    pub origin_orgs: Vec<String>,
+
+    /// Filter expression (same syntax as `--where`), in the `"where"` key.
+    ///
+    /// Only applied if `--where` is not set on the CLI (CLI takes precedence).
+    #[serde(default, rename = "where")]
+    pub where_expr: Option<String>,
+
     /// Element type filter: `"a"` (announce) or `"w"` (withdraw).
     ///
     /// Only applied if `--elem-type` is not set on the CLI (CLI takes precedence).
@@ -287,6 +473,33 @@ pub struct FilterFile {
     /// Only applied if `--duration` is not set on the CLI (CLI takes precedence).
     #[serde(default)]
     pub duration: Option<String>,
+
+    /// Time windows to search instead of a single start/end/duration.
+    ///
+    /// Appended to any `--window` CLI values.
+    #[serde(default)]
+    pub windows: Vec<TimeWindowSpec>,
+
+    /// Collector(s), comma-separated (e.g. `"rrc00,route-views2"`).
+    ///
+    /// Used by `search` and `rib` only. Only applied if `--collector` is not set
+    /// on the CLI (CLI takes precedence).
+    #[serde(default)]
+    pub collector: Option<String>,
+
+    /// Route collection project (`"riperis"` or `"routeviews"`).
+    ///
+    /// Used by `search` and `rib` only. Only applied if `--project` is not set
+    /// on the CLI (CLI takes precedence).
+    #[serde(default)]
+    pub project: Option<String>,
+
+    /// Dump type to search: `"updates"`, `"rib"` or `"rib_updates"`.
+    ///
+    /// Used by `search` only. Only applied if `--dump-type` is left at its
+    /// default (`updates`).
+    #[serde(default)]
+    pub dump_type: Option<String>,
 }
 
 impl FilterFile {
@@ -326,10 +539,14 @@ impl FilterFile {
     ///
     /// | Field type | Merge rule |
     /// |------------|------------|
-    /// | Vec fields (prefixes, ASNs, communities, peer_ips) | **Union** — file values appended to CLI values |
-    /// | Scalar fields (as_path, elem_type, start_ts, end_ts, duration) | **CLI precedence** — file value used only if CLI didn't set it |
+    /// | Vec fields (prefixes, ASNs, communities, attribute filters, countries, orgs, peer_ips, windows) | **Union** — file values appended to CLI values |
+    /// | Scalar fields (as_path, atomic, rpki_date, where, elem_type, start_ts, end_ts, duration) | **CLI precedence** — file value used only if CLI didn't set it |
     /// | Boolean fields (include_super, include_sub) | **OR** — file can enable, cannot disable |
     ///
+    /// `collector`, `project` and `dump_type` only apply to searches (see
+    /// [`merge_into_search`](Self::merge_into_search)); setting them in a file
+    /// merged here is an error rather than being silently ignored.
+    ///
     /// # Example
     ///
     /// ```
@@ -357,6 +574,23 @@ impl FilterFile {
     /// assert!(filters.include_sub);
     /// ```
     pub fn merge_into(self, filters: &mut ParseFilters) -> Result<()> {
+        let unsupported: Vec<&str> = [
+            ("collector", self.collector.is_some()),
+            ("project", self.project.is_some()),
+            ("dump_type", self.dump_type.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(field, set)| set.then_some(field))
+        .collect();
+        if !unsupported.is_empty() {
+            return Err(anyhow!(
+                "Filter file fields only supported by search and rib: {}",
+                unsupported.join(", ")
+            ));
+        }
+
+        merge_as_path_filters(self.as_path_filters(), &mut filters.as_path_filters);
+
         // Vec fields: union CLI + file values, trimming whitespace and dropping empties
         filters.prefix.extend(
             self.prefixes
@@ -382,6 +616,31 @@ impl FilterFile {
                 .map(|s| s.trim().to_string())
                 .filter(|s| !s.is_empty()),
         );
+        for (cli_values, file_values) in [
+            (&mut filters.ext_communities, self.ext_communities),
+            (&mut filters.med, self.med),
+            (&mut filters.local_pref, self.local_pref),
+            (&mut filters.next_hop, self.next_hops),
+            (&mut filters.origin, self.origins),
+            (&mut filters.aggr_asn, self.aggr_asns),
+            (&mut filters.rpki_filters.rpki, self.rpki),
+            (
+                &mut filters.asinfo_filters.origin_country,
+                self.origin_countries,
+            ),
+            (
+                &mut filters.asinfo_filters.peer_country,
+                self.peer_countries,
+            ),
+            (&mut filters.asinfo_filters.origin_org, self.origin_orgs),
+        ] {
+            cli_values.extend(
+                file_values
+                    .into_iter()
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty()),
+            );
+        }
 
         // peer_ip is Vec<IpAddr> — trim, skip empties, parse, error on invalid
         for ip_str in self.peer_ips {
@@ -401,6 +660,21 @@ impl FilterFile {
         // Scalar/Option fields: only set from file if CLI didn't set them
         if filters.as_path.is_none() {
             filters.as_path = self.as_path_regex;
+            filters.as_path_style = filters.as_path_style.or(self.as_path_style);
+        }
+        if filters.atomic.is_none() {
+            filters.atomic = self.atomic;
+        }
+        if filters.rpki_filters.rpki_date.is_none() {
+            filters.rpki_filters.rpki_date = self.rpki_date;
+            filters.rpki_filters.rpki_source = filters
+                .rpki_filters
+                .rpki_source
+                .clone()
+                .or(self.rpki_source);
+        }
+        if filters.where_expr.is_none() {
+            filters.where_expr = self.where_expr;
         }
         if filters.elem_type.is_none() {
             // Map string "a"/"w" to ParseElemType, error on unrecognized
@@ -435,9 +709,155 @@ impl FilterFile {
         if filters.duration.is_none() {
             filters.duration = self.duration;
         }
+        filters.windows.extend(self.windows);
 
         Ok(())
     }
+
+    /// Merge this filter file's values into search filters.
+    ///
+    /// Same rules as [`merge_into`](Self::merge_into), plus `collector` and
+    /// `project` (CLI precedence) and `dump_type` (applied while the CLI value
+    /// is the default, `updates`).
+    pub fn merge_into_search(mut self, filters: &mut SearchFilters) -> Result<()> {
+        let collector = self.collector.take();
+        if filters.collector.is_none() {
+            filters.collector = collector;
+        }
+        let project = self.project.take();
+        if filters.project.is_none() {
+            filters.project = project;
+        }
+        if let Some(dump_type) = self.dump_type.take() {
+            if filters.dump_type == SearchDumpType::default() {
+                filters.dump_type = dump_type
+                    .parse()
+                    .map_err(|e| anyhow!("Invalid dump_type in filter file: {}", e))?;
+            }
+        }
+        self.merge_into(&mut filters.parse_filters)
+    }
+
+    /// Merge this filter file's values into `rib` filters.
+    ///
+    /// `rib` supports prefixes, origin and peer ASNs, the AS path regex and
+    /// structural AS path filters, `include_super`/`include_sub`, `collector`
+    /// and `project`, merged with the
+    /// same rules as [`merge_into`](Self::merge_into). Any other field set in
+    /// the file is an error rather than being silently ignored.
+    pub fn merge_into_rib(self, filters: &mut RibFilters) -> Result<()> {
+        let unsupported: Vec<&str> = [
+            ("peer_ips", !self.peer_ips.is_empty()),
+            ("communities", !self.communities.is_empty()),
+            ("ext_communities", !self.ext_communities.is_empty()),
+            ("med", !self.med.is_empty()),
+            ("local_pref", !self.local_pref.is_empty()),
+            ("next_hops", !self.next_hops.is_empty()),
+            ("origins", !self.origins.is_empty()),
+            ("aggr_asns", !self.aggr_asns.is_empty()),
+            ("atomic", self.atomic.is_some()),
+            ("rpki", !self.rpki.is_empty()),
+            ("rpki_date", self.rpki_date.is_some()),
+            ("rpki_source", self.rpki_source.is_some()),
+            ("origin_countries", !self.origin_countries.is_empty()),
+            ("peer_countries", !self.peer_countries.is_empty()),
+            ("origin_orgs", !self.origin_orgs.is_empty()),
+            ("where", self.where_expr.is_some()),
+            ("elem_type", self.elem_type.is_some()),
+            ("start_ts", self.start_ts.is_some()),
+            ("end_ts", self.end_ts.is_some()),
+            ("duration", self.duration.is_some()),
+            ("windows", !self.windows.is_empty()),
+            ("dump_type", self.dump_type.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(field, set)| set.then_some(field))
+        .collect();
+        if !unsupported.is_empty() {
+            return Err(anyhow!(
+                "Filter file fields not supported by rib: {}",
+                unsupported.join(", ")
+            ));
+        }
+
+        merge_as_path_filters(self.as_path_filters(), &mut filters.as_path_filters);
+        let trimmed = |values: Vec<String>| {
+            values
+                .into_iter()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+        };
+        filters.prefix.extend(trimmed(self.prefixes));
+        filters.origin_asn.extend(trimmed(self.origin_asns));
+        filters.peer_asn.extend(trimmed(self.peer_asns));
+        if filters.as_path.is_none() {
+            filters.as_path = self.as_path_regex;
+            filters.as_path_style = filters.as_path_style.or(self.as_path_style);
+        }
+        if self.include_super {
+            filters.include_super = true;
+        }
+        if self.include_sub {
+            filters.include_sub = true;
+        }
+        if filters.collector.is_none() {
+            filters.collector = self.collector;
+        }
+        if filters.project.is_none() {
+            filters.project = self.project;
+        }
+        Ok(())
+    }
+
+    fn as_path_filters(&self) -> AsPathFilters {
+        AsPathFilters {
+            path_len: self.path_len.clone(),
+            unique_path_len: self.unique_path_len.clone(),
+            prepend_count: self.prepend_count.clone(),
+            as_set: self.as_set,
+            private_asn: self.private_asn,
+            reserved_asn: self.reserved_asn,
+            path_loop: self.path_loop,
+        }
+    }
+
+    /// Check that the file's values are valid filters
+    ///
+    /// Time ranges are only checked for valid time strings, so a file without
+    /// `start_ts`/`end_ts` (to be given on the command line) is valid.
+    pub fn validate(&self) -> Result<()> {
+        let mut filters = SearchFilters::default();
+        self.clone().merge_into_search(&mut filters)?;
+        filters.parse_filters.validate()
+    }
+}
+
+/// Merge AS path filters from a file into CLI values: ranges are unioned, and
+/// flags are only taken from the file if the CLI did not set them
+fn merge_as_path_filters(file: AsPathFilters, filters: &mut AsPathFilters) {
+    for (cli_values, file_values) in [
+        (&mut filters.path_len, file.path_len),
+        (&mut filters.unique_path_len, file.unique_path_len),
+        (&mut filters.prepend_count, file.prepend_count),
+    ] {
+        cli_values.extend(
+            file_values
+                .into_iter()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+        );
+    }
+    for (cli_value, file_value) in [
+        (&mut filters.as_set, file.as_set),
+        (&mut filters.private_asn, file.private_asn),
+        (&mut filters.reserved_asn, file.reserved_asn),
+        (&mut filters.path_loop, file.path_loop),
+    ] {
+        if cli_value.is_none() {
+            *cli_value = file_value;
+        }
+    }
 }
 
 /// Load a newline-delimited prefix list file.
@@ -669,6 +1089,28 @@ mod tests {
         assert!(matches!(filters.elem_type, Some(ParseElemType::W)));
     }
 
+    #[test]
+    fn test_merge_into_as_path_style_follows_pattern() {
+        let file = FilterFile {
+            as_path_regex: Some("_174_".to_string()),
+            as_path_style: Some(AsPathStyle::Cisco),
+            ..Default::default()
+        };
+
+        let mut filters = ParseFilters::default();
+        file.clone().merge_into(&mut filters).unwrap();
+        assert_eq!(filters.as_path.as_deref(), Some("_174_"));
+        assert_eq!(filters.as_path_style, Some(AsPathStyle::Cisco));
+
+        let mut filters = ParseFilters {
+            as_path: Some("^174 ".to_string()),
+            ..Default::default()
+        };
+        file.merge_into(&mut filters).unwrap();
+        assert_eq!(filters.as_path.as_deref(), Some("^174 "));
+        assert_eq!(filters.as_path_style, None);
+    }
+
     #[test]
     fn test_merge_into_elem_type_variants() {
         for (input, is_a) in [
@@ -795,6 +1237,273 @@ mod tests {
         assert_eq!(filters.end_ts.as_deref(), Some("2025-01-01T01:00:00Z"));
     }
 
+    #[test]
+    fn test_merge_into_windows_union() {
+        let mut filters = ParseFilters {
+            windows: vec!["cli=1704067200/1h".parse().unwrap()],
+            ..Default::default()
+        };
+        let json = r#"{"windows": [
+            {"label": "file", "start_ts": "2024-02-01T00:00:00Z", "duration": "30m"}
+        ]}"#;
+        let file: FilterFile = serde_json::from_str(json).unwrap();
+        file.merge_into(&mut filters).unwrap();
+
+        let labels: Vec<String> = filters
+            .time_windows()
+            .unwrap()
+            .windows()
+            .iter()
+            .map(|w| w.label.clone())
+            .collect();
+        assert_eq!(labels, vec!["cli", "file"]);
+        assert_eq!(
+            filters.parse_start_end_strings().unwrap(),
+            (1704067200, 1706747400)
+        );
+
+        // A single start/end cannot be combined with windows
+        filters.start_ts = Some("2024-01-01T00:00:00Z".to_string());
+        assert!(filters.validate().is_err());
+    }
+
+    #[test]
+    fn test_merge_into_search_collector_and_dump_type() {
+        let json = r#"{
+            "prefixes": ["192.0.2.0/24"],
+            "collector": "rrc00,route-views2",
+            "project": "riperis",
+            "dump_type": "rib"
+        }"#;
+        let file: FilterFile = serde_json::from_str(json).unwrap();
+
+        let mut filters = SearchFilters {
+            project: Some("routeviews".to_string()), // CLI value
+            ..Default::default()
+        };
+        file.clone().merge_into_search(&mut filters).unwrap();
+        assert_eq!(filters.collector.as_deref(), Some("rrc00,route-views2"));
+        assert_eq!(filters.project.as_deref(), Some("routeviews"));
+        assert_eq!(filters.dump_type, SearchDumpType::Rib);
+        assert_eq!(filters.parse_filters.prefix, vec!["192.0.2.0/24"]);
+
+        // A non-default CLI dump type wins
+        let mut filters = SearchFilters {
+            dump_type: SearchDumpType::RibUpdates,
+            ..Default::default()
+        };
+        file.merge_into_search(&mut filters).unwrap();
+        assert_eq!(filters.dump_type, SearchDumpType::RibUpdates);
+
+        let file: FilterFile = serde_json::from_str(r#"{"dump_type": "bview"}"#).unwrap();
+        assert!(file
+            .merge_into_search(&mut SearchFilters::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_merge_into_rejects_search_fields() {
+        let json = r#"{"origin_asns": ["64496"], "collector": "rrc00", "dump_type": "rib"}"#;
+        let file: FilterFile = serde_json::from_str(json).unwrap();
+        let err = file
+            .merge_into(&mut ParseFilters::default())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("collector, dump_type"), "{}", err);
+    }
+
+    #[test]
+    fn test_merge_into_rib_rejects_unsupported_fields() {
+        let json = r#"{"origin_asns": ["64496"], "collector": "rrc00", "include_sub": true}"#;
+        let file: FilterFile = serde_json::from_str(json).unwrap();
+        let mut filters = RibFilters::default();
+        file.merge_into_rib(&mut filters).unwrap();
+        assert_eq!(filters.origin_asn, vec!["64496"]);
+        assert_eq!(filters.collector.as_deref(), Some("rrc00"));
+        assert!(filters.include_sub);
+
+        let json = r#"{"origin_asns": ["64496"], "elem_type": "w", "communities": ["64496:1"]}"#;
+        let file: FilterFile = serde_json::from_str(json).unwrap();
+        let err = file
+            .merge_into_rib(&mut RibFilters::default())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("communities, elem_type"), "{}", err);
+    }
+
+    #[test]
+    fn test_filter_file_validate() {
+        let valid: FilterFile =
+            serde_json::from_str(r#"{"origin_asns": ["64496"], "elem_type": "w"}"#).unwrap();
+        assert!(valid.validate().is_ok());
+
+        let invalid: FilterFile =
+            serde_json::from_str(r#"{"prefixes": ["not-a-prefix"]}"#).unwrap();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_merge_into_attribute_filters() {
+        let json = r#"{
+            "ext_communities": ["rt:64496:*"],
+            "local_pref": [" 100..200 "],
+            "med": ["!1000.."],
+            "next_hops": ["192.0.2.0/24"],
+            "origins": ["igp"],
+            "aggr_asns": ["64496"],
+            "atomic": true
+        }"#;
+        let file: FilterFile = serde_json::from_str(json).unwrap();
+        let mut filters = ParseFilters {
+            local_pref: vec!["50".to_string()],
+            atomic: Some(false),
+            ..Default::default()
+        };
+        file.clone().merge_into(&mut filters).unwrap();
+        assert_eq!(filters.ext_communities, vec!["rt:64496:*"]);
+        assert_eq!(filters.local_pref, vec!["50", "100..200"]);
+        assert_eq!(filters.med, vec!["!1000.."]);
+        assert_eq!(filters.next_hop, vec!["192.0.2.0/24"]);
+        assert_eq!(filters.origin, vec!["igp"]);
+        assert_eq!(filters.aggr_asn, vec!["64496"]);
+        assert_eq!(filters.atomic, Some(false), "CLI takes precedence");
+        assert!(filters.validate().is_ok());
+
+        let err = file
+            .merge_into_rib(&mut RibFilters::default())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("ext_communities, med, local_pref"), "{}", err);
+
+        let invalid = FilterFile {
+            origins: vec!["13335".to_string()],
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_merge_as_path_filters() {
+        let file: FilterFile = serde_json::from_str(
+            r#"{"path_len": ["10.."], "prepend_count": ["3.."], "as_set": true, "path_loop": false}"#,
+        )
+        .unwrap();
+        let mut filters = ParseFilters {
+            as_path_filters: AsPathFilters {
+                path_len: vec!["..2".to_string()],
+                as_set: Some(false),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        file.clone().merge_into(&mut filters).unwrap();
+        let merged = &filters.as_path_filters;
+        assert_eq!(merged.path_len, vec!["..2", "10.."]);
+        assert_eq!(merged.prepend_count, vec!["3.."]);
+        assert_eq!(merged.as_set, Some(false), "CLI takes precedence");
+        assert_eq!(merged.path_loop, Some(false));
+
+        let mut rib = RibFilters::default();
+        file.merge_into_rib(&mut rib).unwrap();
+        assert_eq!(rib.as_path_filters.path_len, vec!["10.."]);
+        assert_eq!(rib.as_path_filters.as_set, Some(true));
+    }
+
+    #[test]
+    fn test_merge_into_where_expr() {
+        let file: FilterFile =
+            serde_json::from_str(r#"{"where": "origin_asn == 64496 or type == w"}"#).unwrap();
+        let mut filters = ParseFilters::default();
+        file.clone().merge_into(&mut filters).unwrap();
+        assert_eq!(
+            filters.where_expr.as_deref(),
+            Some("origin_asn == 64496 or type == w")
+        );
+
+        let mut filters = ParseFilters {
+            where_expr: Some("med > 0".to_string()),
+            ..Default::default()
+        };
+        file.merge_into(&mut filters).unwrap();
+        assert_eq!(filters.where_expr.as_deref(), Some("med > 0"));
+
+        let invalid: FilterFile = serde_json::from_str(r#"{"where": "med >"}"#).unwrap();
+        let err = invalid.validate().unwrap_err().to_string();
+        assert!(err.contains("column 6: expected a value"), "{}", err);
+    }
+
+    #[test]
+    fn test_merge_into_rpki() {
+        let file: FilterFile = serde_json::from_str(
+            r#"{"rpki": ["invalid"], "rpki_date": "2024-01-01", "rpki_source": "Ripe"}"#,
+        )
+        .unwrap();
+        assert!(file.validate().is_ok());
+
+        let mut filters = ParseFilters::default();
+        filters.rpki_filters.rpki = vec!["not-found".to_string()];
+        file.clone().merge_into(&mut filters).unwrap();
+        assert_eq!(filters.rpki_filters.rpki, vec!["not-found", "invalid"]);
+        assert_eq!(
+            filters.rpki_filters.rpki_date,
+            NaiveDate::from_ymd_opt(2024, 1, 1)
+        );
+        assert!(matches!(
+            filters.rpki_filters.rpki_source,
+            Some(RpkiDataSource::Ripe)
+        ));
+
+        // The file's source follows its date
+        let mut filters = ParseFilters::default();
+        filters.rpki_filters.rpki_date = NaiveDate::from_ymd_opt(2025, 1, 1);
+        file.clone().merge_into(&mut filters).unwrap();
+        assert_eq!(
+            filters.rpki_filters.rpki_date,
+            NaiveDate::from_ymd_opt(2025, 1, 1)
+        );
+        assert!(filters.rpki_filters.rpki_source.is_none());
+
+        let err = file
+            .merge_into_rib(&mut RibFilters::default())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("rpki, rpki_date, rpki_source"), "{}", err);
+
+        let invalid: FilterFile = serde_json::from_str(r#"{"rpki": ["bogus"]}"#).unwrap();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_merge_into_asinfo() {
+        let file: FilterFile = serde_json::from_str(
+            r#"{"origin_countries": ["US", " "], "peer_countries": ["!DE"],
+                "origin_orgs": ["CLOUD14-ARIN"]}"#,
+        )
+        .unwrap();
+        assert!(file.validate().is_ok());
+
+        let mut filters = ParseFilters::default();
+        filters.asinfo_filters.origin_country = vec!["NL".to_string()];
+        file.clone().merge_into(&mut filters).unwrap();
+        assert_eq!(filters.asinfo_filters.origin_country, vec!["NL", "US"]);
+        assert_eq!(filters.asinfo_filters.peer_country, vec!["!DE"]);
+        assert_eq!(filters.asinfo_filters.origin_org, vec!["CLOUD14-ARIN"]);
+
+        let err = file
+            .merge_into_rib(&mut RibFilters::default())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("origin_countries, peer_countries, origin_orgs"),
+            "{}",
+            err
+        );
+
+        let invalid: FilterFile =
+            serde_json::from_str(r#"{"origin_orgs": ["CLOUD14-ARIN", "!DTAG-RIPE"]}"#).unwrap();
+        assert!(invalid.validate().is_err());
+    }
+
     #[test]
     fn test_merge_into_communities_union() {
         let mut filters = ParseFilters {
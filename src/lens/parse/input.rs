@@ -0,0 +1,249 @@
+//! Content-sniffed parse input.
+//!
+//! Files opened by path are decompressed by oneio according to their
+//! extension, which stdin and arbitrary readers do not have. [`ParseInput`]
+//! instead looks at the first bytes of the data: gzip, bzip2, xz and zstd
+//! streams are recognized by their magic bytes and decompressed, and the
+//! decompressed content is then classified as MRT, BMP, RIS Live JSON or a
+//! router table dump ([`InputFormat`]). The sniffed bytes are replayed, so
+//! the returned reader yields the whole (decompressed) input.
+
+use std::io::{BufRead, BufReader, Cursor, Read};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::text_dump::{self, TextDumpFormat};
+use super::{bmp, ris_live};
+
+/// Path that selects standard input
+pub const STDIN_PATH: &str = "-";
+
+/// Bytes of decompressed content inspected to detect the input format
+const FORMAT_SNIFF_LEN: usize = 256;
+
+/// Longest compression magic number (xz)
+const MAGIC_SNIFF_LEN: usize = 6;
+
+/// Whether `path` selects standard input
+pub fn is_stdin(path: &str) -> bool {
+    path == STDIN_PATH
+}
+
+/// Compression detected from the magic bytes of an input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputCompression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl InputCompression {
+    /// Detect compression from the first bytes of a stream
+    pub fn detect(buf: &[u8]) -> Self {
+        if buf.starts_with(&[0x1f, 0x8b]) {
+            Self::Gzip
+        } else if buf.starts_with(b"BZh") {
+            Self::Bzip2
+        } else if buf.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Self::Xz
+        } else if buf.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+
+    /// Compression implied by the extension of a path, which oneio
+    /// decompresses before the content is sniffed
+    pub fn from_extension(path: &str) -> Self {
+        let path = path.to_ascii_lowercase();
+        if path.ends_with(".gz") || path.ends_with(".gzip") {
+            Self::Gzip
+        } else if path.ends_with(".bz2") || path.ends_with(".bz") {
+            Self::Bzip2
+        } else if path.ends_with(".xz") || path.ends_with(".lzma") {
+            Self::Xz
+        } else if path.ends_with(".zst") || path.ends_with(".zstd") {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+
+    /// Wrap `reader` in a decoder for this compression. Concatenated gzip,
+    /// bzip2, xz and zstd members are all decoded.
+    fn decoder<R: Read + Send + 'static>(self, reader: R) -> Result<Box<dyn Read + Send>> {
+        Ok(match self {
+            Self::None => Box::new(reader),
+            Self::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+            Self::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+            Self::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(reader)),
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::new(reader)?),
+        })
+    }
+}
+
+/// Content of an input, detected from its first decompressed bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// Binary MRT, also the fallback for unrecognized content
+    Mrt,
+    /// Raw BMP messages
+    Bmp,
+    /// Newline-delimited RIS Live JSON
+    RisLive,
+    /// Router table dump
+    TextDump(TextDumpFormat),
+}
+
+impl InputFormat {
+    /// Detect the format from the first decompressed bytes of an input
+    pub fn detect(buf: &[u8]) -> Self {
+        if let Some(format) = text_dump::detect_text_dump_format(buf) {
+            Self::TextDump(format)
+        } else if bmp::detect_bmp(buf) {
+            Self::Bmp
+        } else if ris_live::detect_ris_live(buf) {
+            Self::RisLive
+        } else {
+            Self::Mrt
+        }
+    }
+}
+
+/// A decompressed input with its detected format
+pub struct ParseInput {
+    pub format: InputFormat,
+    pub compression: InputCompression,
+    reader: Box<dyn BufRead + Send>,
+}
+
+impl ParseInput {
+    /// Open a local path or URL, or standard input for `-`
+    pub fn open(path: &str) -> Result<Self> {
+        if is_stdin(path) {
+            return Self::from_reader(std::io::stdin());
+        }
+        Self::from_reader(oneio::get_reader(path)?)
+    }
+
+    /// Sniff the compression and format of any reader
+    pub fn from_reader<R: Read + Send + 'static>(mut reader: R) -> Result<Self> {
+        let magic = read_prefix(&mut reader, MAGIC_SNIFF_LEN)?;
+        let compression = InputCompression::detect(&magic);
+        let mut decoded = compression.decoder(Cursor::new(magic).chain(reader))?;
+
+        let head = read_prefix(&mut decoded, FORMAT_SNIFF_LEN)?;
+        let format = InputFormat::detect(&head);
+        Ok(Self {
+            format,
+            compression,
+            reader: Box::new(BufReader::new(Cursor::new(head).chain(decoded))),
+        })
+    }
+
+    /// The whole decompressed input, including the sniffed bytes
+    pub fn into_reader(self) -> Box<dyn BufRead + Send> {
+        self.reader
+    }
+}
+
+/// Read up to `len` bytes, fewer only at the end of the input
+fn read_prefix<R: Read>(reader: &mut R, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(len);
+    reader.take(len as u64).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const CISCO_DUMP: &str = "BGP table version is 1, local router ID is 10.0.0.1\n\
+        Status codes: s suppressed, d damped, h history, * valid, > best, i - internal\n\
+        \n     Network          Next Hop            Metric LocPrf Weight Path\n\
+        *>   1.0.0.0/24       192.0.2.1                0             0 13335 i\n";
+
+    fn read_all(input: ParseInput) -> String {
+        let mut text = String::new();
+        input.into_reader().read_to_string(&mut text).unwrap();
+        text
+    }
+
+    #[test]
+    fn test_detect_compression() {
+        assert_eq!(
+            InputCompression::detect(&[0x1f, 0x8b, 8]),
+            InputCompression::Gzip
+        );
+        assert_eq!(
+            InputCompression::detect(b"BZh91AY"),
+            InputCompression::Bzip2
+        );
+        assert_eq!(
+            InputCompression::detect(&[0xfd, b'7', b'z', b'X', b'Z', 0]),
+            InputCompression::Xz
+        );
+        assert_eq!(
+            InputCompression::detect(&[0x28, 0xb5, 0x2f, 0xfd, 0]),
+            InputCompression::Zstd
+        );
+        assert_eq!(InputCompression::detect(b"BGP"), InputCompression::None);
+        assert_eq!(InputCompression::detect(&[]), InputCompression::None);
+    }
+
+    #[test]
+    fn test_parse_input_decompresses_by_content() {
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gzip.write_all(CISCO_DUMP.as_bytes()).unwrap();
+        let gzip = gzip.finish().unwrap();
+
+        let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+        bzip2.write_all(CISCO_DUMP.as_bytes()).unwrap();
+        let bzip2 = bzip2.finish().unwrap();
+
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 1);
+        xz.write_all(CISCO_DUMP.as_bytes()).unwrap();
+        let xz = xz.finish().unwrap();
+
+        let zstd = zstd::encode_all(CISCO_DUMP.as_bytes(), 1).unwrap();
+
+        for (data, compression) in [
+            (CISCO_DUMP.as_bytes().to_vec(), InputCompression::None),
+            (gzip, InputCompression::Gzip),
+            (bzip2, InputCompression::Bzip2),
+            (xz, InputCompression::Xz),
+            (zstd, InputCompression::Zstd),
+        ] {
+            let input = ParseInput::from_reader(Cursor::new(data)).unwrap();
+            assert_eq!(input.compression, compression);
+            assert_eq!(input.format, InputFormat::TextDump(TextDumpFormat::Cisco));
+            assert_eq!(read_all(input), CISCO_DUMP, "{compression:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_input_formats() {
+        let ris = r#"{"type":"ris_message","data":{"host":"rrc00"}}"#;
+        let input = ParseInput::from_reader(Cursor::new(ris.as_bytes().to_vec())).unwrap();
+        assert_eq!(input.format, InputFormat::RisLive);
+        assert_eq!(read_all(input), ris);
+
+        let bmp = vec![3, 0, 0, 0, 6, 4];
+        let input = ParseInput::from_reader(Cursor::new(bmp)).unwrap();
+        assert_eq!(input.format, InputFormat::Bmp);
+
+        let input = ParseInput::from_reader(Cursor::new(vec![0x65, 0x9c, 0x1a, 0x00])).unwrap();
+        assert_eq!(input.format, InputFormat::Mrt);
+
+        let input = ParseInput::from_reader(std::io::empty()).unwrap();
+        assert_eq!(input.format, InputFormat::Mrt);
+        assert_eq!(read_all(input), "");
+    }
+}
@@ -64,23 +64,48 @@
 //! let elems = lens.parse_with_progress(&filters, "file.mrt", Some(callback))?;
 //! ```
 
+pub mod as_path_filter;
+pub mod as_path_regex;
+pub mod asinfo_filter;
+pub mod attr_filter;
+pub mod bmp;
+pub mod enrich;
+pub mod expr;
 pub mod filter_file;
+pub mod input;
+pub mod ris_live;
+pub mod rpki_filter;
+pub mod sort;
+pub mod stats;
 pub mod text_dump;
+pub mod time_window;
 
+use crate::database::MonocleDatabase;
+use crate::lens::rpki::RpkiValidator;
+use crate::lens::search::TimeHistogram;
 use crate::lens::time::TimeLens;
+use crate::utils::GroupByField;
 use anyhow::anyhow;
 use anyhow::Result;
-use bgpkit_parser::parser::filter::Filter;
+use as_path_filter::AsPathFilters;
+use as_path_regex::AsPathStyle;
+use asinfo_filter::{AsinfoFilters, AsinfoMatcher};
+use attr_filter::AttributeFilters;
+use bgpkit_parser::parser::filter::{Filter, Filterable};
 use bgpkit_parser::BgpElem;
 use bgpkit_parser::BgpkitParser;
+use expr::ElemExpr;
+use input::{InputCompression, InputFormat, ParseInput};
 use ipnet::IpNet;
 use itertools::Itertools;
+use rpki_filter::{RpkiFilters, RpkiMatcher};
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use std::io::Read;
 use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::Instant;
+use time_window::{TimeWindowSpec, TimeWindows};
 
 #[cfg(feature = "cli")]
 use clap::{Args, ValueEnum};
@@ -274,16 +299,106 @@ pub struct ParseFilters {
     #[cfg_attr(feature = "cli", clap(short = 'd', long))]
     pub duration: Option<String>,
 
+    /// Time window as `[LABEL=]START/END` or `[LABEL=]START/DURATION`; repeat for
+    /// several windows. Replaces --start-ts, --end-ts and --duration.
+    #[cfg_attr(feature = "cli", clap(long = "window"))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<TimeWindowSpec>,
+
     /// Filter by AS path regex string
     #[cfg_attr(feature = "cli", clap(short = 'a', long))]
     pub as_path: Option<String>,
+
+    /// Dialect of --as-path: raw regex (default), cisco (`_174_`) or junos (`.* 13335`)
+    #[cfg_attr(feature = "cli", clap(long, value_enum, value_name = "STYLE"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub as_path_style: Option<AsPathStyle>,
+
+    /// Structural AS path filters: length, prepends, AS_SETs, bogon ASNs, loops
+    #[cfg_attr(feature = "cli", clap(flatten))]
+    #[serde(flatten)]
+    pub as_path_filters: AsPathFilters,
+
+    /// Filter by extended community, comma-separated `rt:A:B`, `soo:A:B` or `*:A:B`, where A is
+    /// an ASN or IP address and any part can be `*` (e.g., `rt:64500:100`, `soo:192.0.2.1:*`).
+    /// Prefix with ! to exclude.
+    #[cfg_attr(
+        feature = "cli",
+        clap(
+            long = "ext-community",
+            visible_alias = "ext-communities",
+            value_delimiter = ','
+        )
+    )]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ext_communities: Vec<String>,
+
+    /// Filter by MED, comma-separated values or inclusive ranges (`N`, `A..B`, `A..`, `..B`).
+    /// Prefix with ! to exclude.
+    #[cfg_attr(feature = "cli", clap(long, value_delimiter = ','))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub med: Vec<String>,
+
+    /// Filter by LOCAL_PREF, comma-separated values or inclusive ranges (e.g., `100..200`).
+    /// Prefix with ! to exclude.
+    #[cfg_attr(feature = "cli", clap(long, value_delimiter = ','))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub local_pref: Vec<String>,
+
+    /// Filter by next hop IP address(es) or prefix(es), comma-separated. Prefix with ! to exclude.
+    #[cfg_attr(feature = "cli", clap(long, value_delimiter = ','))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub next_hop: Vec<String>,
+
+    /// Filter by ORIGIN attribute: igp, egp or incomplete, comma-separated. Prefix with ! to
+    /// exclude.
+    #[cfg_attr(feature = "cli", clap(long, value_delimiter = ','))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub origin: Vec<String>,
+
+    /// Filter by aggregator AS Number(s), comma-separated. Prefix with ! to exclude.
+    #[cfg_attr(feature = "cli", clap(long, value_delimiter = ','))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aggr_asn: Vec<String>,
+
+    /// Filter by ATOMIC_AGGREGATE: true or false
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub atomic: Option<bool>,
+
+    /// RPKI route origin validation filter and its data source
+    #[cfg_attr(feature = "cli", clap(flatten))]
+    #[serde(flatten)]
+    pub rpki_filters: RpkiFilters,
+
+    /// Origin and peer AS country and origin AS organization filters
+    #[cfg_attr(feature = "cli", clap(flatten))]
+    #[serde(flatten)]
+    pub asinfo_filters: AsinfoFilters,
+
+    /// Filter by an expression over element fields with and/or/not, parentheses,
+    /// comparisons, regex (`~`) and prefix containment (`in`), e.g.
+    /// `origin_asn == 64496 or (community == 64496:666 and type == w)`
+    #[cfg_attr(feature = "cli", clap(long = "where", value_name = "EXPR"))]
+    #[serde(default, rename = "where", skip_serializing_if = "Option::is_none")]
+    pub where_expr: Option<String>,
 }
 
 type FilterSpec = (&'static str, String);
 
 impl ParseFilters {
     /// Parse start and end time strings into Unix timestamps
+    ///
+    /// With [`windows`](Self::windows) set, this is the span from the earliest
+    /// window start to the latest window end.
     pub fn parse_start_end_strings(&self) -> Result<(i64, i64)> {
+        if !self.windows.is_empty() {
+            return self
+                .time_windows()?
+                .span()
+                .ok_or_else(|| anyhow!("no time windows specified"));
+        }
+
         let time_lens = TimeLens::new();
         let mut start_ts = None;
         let mut end_ts = None;
@@ -352,13 +467,32 @@ impl ParseFilters {
         Err(anyhow!("unexpected time-string parsing result"))
     }
 
+    /// Resolve the [`windows`](Self::windows) list; empty when no windows are set
+    pub fn time_windows(&self) -> Result<TimeWindows> {
+        if !self.windows.is_empty()
+            && (self.start_ts.is_some() || self.end_ts.is_some() || self.duration.is_some())
+        {
+            return Err(anyhow!(
+                "time windows cannot be combined with start_ts, end_ts or duration"
+            ));
+        }
+        TimeWindows::resolve(&self.windows)
+    }
+
     /// Validate the filters
     ///
     /// Checks:
-    /// - Time strings are valid
+    /// - Time strings and time windows are valid
     /// - ASN values are valid 32-bit unsigned integers (with optional `!` prefix)
     /// - Prefix values are valid CIDR notation (with optional `!` prefix)
     /// - Negation is consistent within each filter (all positive or all negative)
+    /// - The AS path regex is valid in its `as_path_style` (see [`as_path_regex`])
+    /// - Path attribute and AS path filters are valid (see [`attr_filter`]
+    ///   and [`as_path_filter`])
+    /// - RPKI filter values and data source are valid (see [`rpki_filter`])
+    /// - Country and organization filters are consistently negated (see
+    ///   [`asinfo_filter`])
+    /// - The `where` expression is valid (see [`expr`])
     pub fn validate(&self) -> Result<()> {
         let time_lens = TimeLens::new();
         if let Some(ts) = &self.start_ts {
@@ -371,6 +505,7 @@ impl ParseFilters {
                 return Err(anyhow!("end-ts is not a valid time string: {}", ts));
             }
         }
+        self.time_windows()?;
 
         // Validate origin ASNs
         for asn in &self.origin_asn {
@@ -398,6 +533,16 @@ impl ParseFilters {
         Self::check_negation_consistency(&self.prefix, "prefix")?;
         Self::check_negation_consistency(&self.communities, "community")?;
 
+        if let Some(as_path) = &self.as_path {
+            as_path_regex::compile(as_path, self.as_path_style.unwrap_or_default())?;
+        }
+
+        // Validate path attribute filters
+        AttributeFilters::new(self)?;
+        self.rpki_filters.validate()?;
+        self.asinfo_filters.validate()?;
+        self.where_expr()?;
+
         Ok(())
     }
 
@@ -529,8 +674,8 @@ impl ParseFilters {
     fn filter_specs(&self) -> Result<Vec<FilterSpec>> {
         let mut specs = Vec::new();
 
-        if let Some(value) = &self.as_path {
-            specs.push(("as_path", value.clone()));
+        if let Some(value) = self.as_path_regex()? {
+            specs.push(("as_path", value));
         }
 
         // Origin ASN filter - always use plural filter key for consistency.
@@ -604,14 +749,197 @@ impl ParseFilters {
     ///
     /// This method creates a parser with all filters applied. Multi-value filters
     /// use OR logic (matches ANY of the specified values). Negated values (prefixed
-    /// with `!`) exclude matching elements.
+    /// with `!`) exclude matching elements. Path attribute filters and time
+    /// windows are not applied by the parser; use
+    /// [`to_elem_iter`](Self::to_elem_iter) for those.
     pub fn to_parser(&self, file_path: &str) -> Result<BgpkitParser<Box<dyn Read + Send>>> {
-        let mut parser = BgpkitParser::new(file_path)?.disable_warnings();
+        self.add_to_parser(BgpkitParser::new(file_path)?)
+    }
+
+    /// Convert filters to a BgpkitParser reading MRT data from `reader`.
+    pub fn to_reader_parser<R: Read>(&self, reader: R) -> Result<BgpkitParser<R>> {
+        self.add_to_parser(BgpkitParser::from_reader(reader))
+    }
+
+    fn add_to_parser<R>(&self, parser: BgpkitParser<R>) -> Result<BgpkitParser<R>> {
+        let mut parser = parser.disable_warnings();
         for (filter_type, filter_value) in self.filter_specs()? {
             parser = parser.add_filter(filter_type, &filter_value)?;
         }
         Ok(parser)
     }
+
+    /// The [`as_path`](Self::as_path) pattern as a raw regex over the AS
+    /// path text, translated from its [`as_path_style`](Self::as_path_style)
+    pub fn as_path_regex(&self) -> Result<Option<String>> {
+        self.as_path
+            .as_deref()
+            .map(|pattern| {
+                as_path_regex::translate(pattern, self.as_path_style.unwrap_or_default())
+                    .map_err(|e| anyhow!("Invalid --as-path regex '{}': {}", pattern, e))
+            })
+            .transpose()
+    }
+
+    /// Load the ROAs for the [`rpki_filters`](Self::rpki_filters) and return
+    /// them, e.g. for an `rpki` output field
+    ///
+    /// With `rpki_date` set, the historical RPKI data of that date is
+    /// downloaded; otherwise the ROAs come from the RPKI cache of `db`, which
+    /// callers should refresh first (see [`RpkiLens`](crate::lens::rpki::RpkiLens)).
+    pub fn load_rpki(&mut self, db: Option<&MonocleDatabase>) -> Result<&RpkiValidator> {
+        self.rpki_filters.load(db)
+    }
+
+    /// Resolve the [`asinfo_filters`](Self::asinfo_filters) to ASN sets from
+    /// the ASInfo data of `db`, which callers should refresh first
+    pub fn load_asinfo(&mut self, db: &MonocleDatabase) -> Result<()> {
+        self.asinfo_filters.load(db)
+    }
+
+    /// Compile the [`where_expr`](Self::where_expr), if any
+    pub fn where_expr(&self) -> Result<Option<ElemExpr>> {
+        Ok(self
+            .where_expr
+            .as_deref()
+            .map(ElemExpr::parse)
+            .transpose()?)
+    }
+
+    /// Convert filters to an iterator over the matching elements of a file.
+    ///
+    /// Like [`to_parser`](Self::to_parser), but also drops elements between
+    /// the [`windows`](Self::windows): the parser itself only filters on the
+    /// span covering all windows. Path attribute filters (see [`attr_filter`])
+    /// and the `where` expression, which the parser does not support, are
+    /// applied here as well.
+    pub fn to_elem_iter(&self, file_path: &str) -> Result<impl Iterator<Item = BgpElem> + Send> {
+        let post_filter = self.post_parser_filter()?;
+        let parser = self.to_parser(file_path)?;
+        Ok(parser
+            .into_iter()
+            .filter(move |elem| post_filter.matches(elem)))
+    }
+
+    /// Like [`to_elem_iter`](Self::to_elem_iter), for MRT data read from `reader`.
+    pub fn to_reader_elem_iter<R: Read + Send>(
+        &self,
+        reader: R,
+    ) -> Result<impl Iterator<Item = BgpElem> + Send> {
+        let post_filter = self.post_parser_filter()?;
+        let parser = self.to_reader_parser(reader)?;
+        Ok(parser
+            .into_iter()
+            .filter(move |elem| post_filter.matches(elem)))
+    }
+
+    /// Like [`to_elem_iter`](Self::to_elem_iter), for a local path or URL in
+    /// any input format.
+    ///
+    /// Compression and format are detected from the content as for
+    /// [`ParseLens::parse_reader`], so MRT, BMP, RIS Live JSON and router table
+    /// dumps are all parsed. Table dump timestamps are inferred from the file
+    /// name (see [`text_dump::infer_timestamp_from_path`]).
+    pub fn to_input_elem_iter(
+        &self,
+        path: &str,
+    ) -> Result<Box<dyn Iterator<Item = BgpElem> + Send>> {
+        let timestamp = text_dump::infer_timestamp_from_path(path).unwrap_or(0.0);
+        self.input_elem_iter(ParseInput::open(path)?, timestamp)
+    }
+
+    /// Elements of `input` matching these filters, with `timestamp` for table
+    /// dump elements
+    fn input_elem_iter(
+        &self,
+        input: ParseInput,
+        timestamp: f64,
+    ) -> Result<Box<dyn Iterator<Item = BgpElem> + Send>> {
+        let format = input.format;
+        let reader = input.into_reader();
+        Ok(match format {
+            InputFormat::Mrt => Box::new(self.to_reader_elem_iter(reader)?),
+            InputFormat::Bmp => {
+                let record_filter = bmp::BmpRecordFilter::new(self)?;
+                Box::new(
+                    bmp::BmpReader::new(reader).filter_map(move |record| match record.ok()? {
+                        record if !record_filter.matches(&record) => None,
+                        bmp::BmpRecord::Elem(elem) => Some(elem),
+                        bmp::BmpRecord::PeerEvent(_) => None,
+                    }),
+                )
+            }
+            InputFormat::RisLive => {
+                let elem_filter = self.to_elem_filter()?;
+                Box::new(
+                    ris_live::RisLiveReader::new(reader).filter_map(move |elem| {
+                        let (elem, _host) = elem.ok()?;
+                        elem_filter.matches(&elem).then_some(elem)
+                    }),
+                )
+            }
+            InputFormat::TextDump(_) => {
+                let elem_filter = self.to_elem_filter()?;
+                let elems = text_dump::parse_text_dump_with_timestamp(reader, timestamp)?;
+                Box::new(
+                    elems
+                        .into_iter()
+                        .filter(move |elem| elem_filter.matches(elem)),
+                )
+            }
+        })
+    }
+
+    /// Convert filters into one predicate for elements that did not come from
+    /// a [`BgpkitParser`], such as BMP, RIS Live and table dump elements.
+    pub fn to_elem_filter(&self) -> Result<ElemFilter> {
+        Ok(ElemFilter {
+            filters: self.to_filters()?,
+            ..self.post_parser_filter()?
+        })
+    }
+
+    /// The filters a [`BgpkitParser`] does not apply itself
+    fn post_parser_filter(&self) -> Result<ElemFilter> {
+        Ok(ElemFilter {
+            filters: Vec::new(),
+            attributes: AttributeFilters::new(self)?,
+            rpki: self.rpki_filters.matcher()?,
+            asinfo: self.asinfo_filters.matcher()?,
+            where_expr: self.where_expr()?,
+            windows: self.time_windows()?,
+        })
+    }
+}
+
+/// All of [`ParseFilters`] as one element predicate, see
+/// [`ParseFilters::to_elem_filter`]
+#[derive(Debug, Clone)]
+pub struct ElemFilter {
+    filters: Vec<Filter>,
+    attributes: AttributeFilters,
+    rpki: Option<RpkiMatcher>,
+    asinfo: Option<AsinfoMatcher>,
+    where_expr: Option<ElemExpr>,
+    windows: TimeWindows,
+}
+
+impl ElemFilter {
+    /// Whether `elem` passes all filters
+    pub fn matches(&self, elem: &BgpElem) -> bool {
+        elem.match_filters(&self.filters)
+            && self.windows.contains(elem.timestamp)
+            && self.attributes.matches(elem)
+            && self.rpki.as_ref().is_none_or(|rpki| rpki.matches(elem))
+            && self
+                .asinfo
+                .as_ref()
+                .is_none_or(|asinfo| asinfo.matches(elem))
+            && self
+                .where_expr
+                .as_ref()
+                .is_none_or(|expr| expr.matches(elem))
+    }
 }
 
 // =============================================================================
@@ -633,8 +961,7 @@ impl ParseFilters {
 /// let filters = ParseFilters::default();
 ///
 /// // Simple parsing without progress tracking
-/// let parser = lens.create_parser(&filters, "path/to/file.mrt")?;
-/// for elem in parser {
+/// for elem in filters.to_elem_iter("path/to/file.mrt")? {
 ///     println!("{}", elem);
 /// }
 ///
@@ -656,6 +983,13 @@ impl ParseLens {
     ///
     /// This returns a streaming parser that yields BGP elements one at a time.
     /// For progress tracking, use `parse_with_progress` instead.
+    ///
+    /// The parser does not apply time windows, path attribute filters or the
+    /// `where` expression, see [`ParseFilters::to_parser`].
+    #[deprecated(
+        since = "1.5.0",
+        note = "Use ParseFilters::to_elem_iter, which applies all filters"
+    )]
     pub fn create_parser(
         &self,
         filters: &ParseFilters,
@@ -664,6 +998,66 @@ impl ParseLens {
         filters.to_parser(file_path)
     }
 
+    /// Parse any reader, such as stdin or a network stream, with `filters` applied
+    ///
+    /// Unlike the path-based methods, compression and format are detected from
+    /// the content (see [`ParseInput`]): gzip, bzip2, xz and zstd streams are
+    /// decompressed, and MRT, BMP, RIS Live JSON and router table dumps are all
+    /// parsed into elements. BMP peer events are dropped, and unparsable BMP
+    /// and RIS Live messages are skipped. Table dumps are read completely before
+    /// the first element is returned, with timestamps of 0.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use monocle::lens::parse::{ParseLens, ParseFilters};
+    ///
+    /// let lens = ParseLens::new();
+    /// for elem in lens.parse_reader(&ParseFilters::default(), std::io::stdin())? {
+    ///     println!("{}", elem);
+    /// }
+    /// ```
+    pub fn parse_reader<R: Read + Send + 'static>(
+        &self,
+        filters: &ParseFilters,
+        reader: R,
+    ) -> Result<Box<dyn Iterator<Item = BgpElem> + Send>> {
+        filters.input_elem_iter(ParseInput::from_reader(reader)?, 0.0)
+    }
+
+    /// Summarize an MRT file without filters: record types, peer index table,
+    /// per-peer element counts, time span and truncated or corrupt records.
+    /// See [`MrtStats`](stats::MrtStats).
+    ///
+    /// `-` reads standard input. Compression is detected from the content like
+    /// for [`parse_reader`](Self::parse_reader); other input formats are an
+    /// error.
+    pub fn stats(&self, file_path: &str) -> Result<stats::MrtStats> {
+        let mut stats = self.stats_input(ParseInput::open(file_path)?)?;
+        if stats.compression == Some(InputCompression::None) && !input::is_stdin(file_path) {
+            stats.compression = Some(InputCompression::from_extension(file_path));
+        }
+        Ok(stats)
+    }
+
+    /// Summarize an MRT stream from any reader, see [`stats`](Self::stats)
+    pub fn stats_reader<R: Read + Send + 'static>(&self, reader: R) -> Result<stats::MrtStats> {
+        self.stats_input(ParseInput::from_reader(reader)?)
+    }
+
+    fn stats_input(&self, input: ParseInput) -> Result<stats::MrtStats> {
+        if input.format != InputFormat::Mrt {
+            return Err(anyhow!(
+                "statistics are only available for MRT input, found {:?}",
+                input.format
+            ));
+        }
+        let compression = input.compression;
+        let mut stats = stats::MrtStats::from_reader(input.into_reader());
+        stats.compression = Some(compression);
+        Ok(stats)
+    }
+
     /// Validate filters
     pub fn validate_filters(&self, filters: &ParseFilters) -> Result<()> {
         filters.validate()
@@ -715,7 +1109,7 @@ impl ParseLens {
         file_path: &str,
         callback: Option<ParseProgressCallback>,
     ) -> Result<Vec<BgpElem>> {
-        let parser = self.create_parser(filters, file_path)?;
+        let parser = filters.to_elem_iter(file_path)?;
 
         // Notify start
         if let Some(ref cb) = callback {
@@ -823,7 +1217,7 @@ impl ParseLens {
     where
         F: FnMut(BgpElem),
     {
-        let parser = self.create_parser(filters, file_path)?;
+        let parser = filters.to_elem_iter(file_path)?;
 
         // Notify start
         if let Some(ref cb) = progress_callback {
@@ -876,6 +1270,26 @@ impl ParseLens {
 
         Ok(messages_processed)
     }
+
+    /// Parse a file and count elements per time bucket
+    ///
+    /// Elements are counted in `bucket_secs` wide buckets, split into one series
+    /// per combination of `split_by` values. The returned histogram spans the
+    /// buckets between the first and last element.
+    pub fn parse_histogram(
+        &self,
+        filters: &ParseFilters,
+        file_path: &str,
+        bucket_secs: u64,
+        split_by: Vec<GroupByField>,
+        progress_callback: Option<ParseProgressCallback>,
+    ) -> Result<TimeHistogram> {
+        let mut histogram = TimeHistogram::new(bucket_secs, split_by);
+        self.parse_with_handler(filters, file_path, progress_callback, |elem| {
+            histogram.add(&elem, None)
+        })?;
+        Ok(histogram)
+    }
 }
 
 impl Default for ParseLens {
@@ -1090,6 +1504,23 @@ mod tests {
         assert_eq!(actual.len(), 3);
     }
 
+    #[test]
+    fn test_as_path_style() {
+        let mut filters = ParseFilters {
+            as_path: Some("_13335$".to_string()),
+            as_path_style: Some(AsPathStyle::Cisco),
+            ..Default::default()
+        };
+        let regex = filters.as_path_regex().unwrap().unwrap();
+        let expected = Filter::new("as_path", &regex).unwrap();
+        assert!(filters.to_filters().unwrap().contains(&expected));
+
+        filters.as_path_style = Some(AsPathStyle::Junos);
+        assert!(filters.validate().is_err());
+        filters.as_path = Some(".* 13335".to_string());
+        assert!(filters.validate().is_ok());
+    }
+
     #[test]
     fn test_parse_filters_validate() {
         // Valid filters
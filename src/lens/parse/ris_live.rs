@@ -0,0 +1,327 @@
+//! RIS Live JSON input.
+//!
+//! Reads [RIS Live](https://ris-live.ripe.net/manual/) messages, either
+//! archived as newline-delimited JSON ([`RisLiveReader`]) or streamed from a
+//! RIS Live websocket ([`RisLiveClient`]), and converts the UPDATEs in
+//! `ris_message` messages into [`BgpElem`]s paired with the RIS collector
+//! (`host`) that saw them. Other message types (`ris_subscribe_ok`, `pong`,
+//! `RIS_PEER_STATE`, ...) carry no routes and are skipped; `ris_error`
+//! messages are reported as errors.
+
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::net::TcpStream;
+
+use anyhow::{anyhow, Result};
+use bgpkit_parser::parser::rislive::error::ParserRisliveError;
+use bgpkit_parser::{
+    parse_ris_live_message, BgpElem, RisLiveClientMessage, RisSubscribe, RisSubscribeType,
+};
+use serde::Deserialize;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+use super::ParseFilters;
+
+/// Public RIS Live websocket endpoint
+pub const RIS_LIVE_URL: &str = "wss://ris-live.ripe.net/v1/ws/?client=monocle";
+
+/// An element and the RIS collector (`host`) it was collected by
+pub type RisLiveElem = (BgpElem, String);
+
+/// Whether `buf`, the first bytes of a file, looks like RIS Live JSON messages
+pub fn detect_ris_live(buf: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(buf);
+    let text = text.trim_start();
+    text.starts_with('{') && text.contains("\"type\"") && text.contains("\"ris_")
+}
+
+/// Whether `path` is a websocket URL rather than a file path or HTTP URL
+pub fn is_websocket_url(path: &str) -> bool {
+    path.starts_with("ws://") || path.starts_with("wss://")
+}
+
+/// Envelope fields needed before handing a message to bgpkit-parser
+#[derive(Deserialize)]
+struct RisLiveEnvelope {
+    #[serde(rename = "type")]
+    msg_type: String,
+    #[serde(default)]
+    data: Option<RisLiveEnvelopeData>,
+}
+
+#[derive(Deserialize)]
+struct RisLiveEnvelopeData {
+    #[serde(default)]
+    host: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// Parse one RIS Live message into elements
+///
+/// Returns no elements for messages other than `ris_message` UPDATEs,
+/// including End-of-RIB markers.
+pub fn parse_ris_live_line(line: &str) -> Result<Vec<RisLiveElem>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(Vec::new());
+    }
+    let envelope: RisLiveEnvelope =
+        serde_json::from_str(line).map_err(|e| anyhow!("invalid RIS Live message: {}", e))?;
+    let data = envelope.data;
+    match envelope.msg_type.as_str() {
+        "ris_message" => {}
+        "ris_error" => {
+            let message = data.and_then(|data| data.message).unwrap_or_default();
+            return Err(anyhow!("RIS Live error: {}", message));
+        }
+        _ => return Ok(Vec::new()),
+    }
+
+    let elems = match parse_ris_live_message(line) {
+        Ok(elems) => elems,
+        Err(ParserRisliveError::ElemEndOfRibPrefix) => return Ok(Vec::new()),
+        Err(e) => return Err(anyhow!("invalid RIS Live message: {}", e)),
+    };
+    let host = data.and_then(|data| data.host).unwrap_or_default();
+    Ok(elems.into_iter().map(|elem| (elem, host.clone())).collect())
+}
+
+/// Reader for newline-delimited RIS Live JSON, e.g. an archived websocket
+/// session or stdin
+///
+/// Yields an error for each line that cannot be parsed and continues with
+/// the next one; a read error ends the iteration.
+pub struct RisLiveReader<R: BufRead> {
+    reader: R,
+    line: String,
+    line_number: u64,
+    pending: VecDeque<RisLiveElem>,
+    done: bool,
+}
+
+impl<R: BufRead> RisLiveReader<R> {
+    /// Create a reader over newline-delimited RIS Live messages
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            line_number: 0,
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for RisLiveReader<R> {
+    type Item = Result<RisLiveElem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(elem) = self.pending.pop_front() {
+                return Some(Ok(elem));
+            }
+            if self.done {
+                return None;
+            }
+
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(_) => self.line_number += 1,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(anyhow!("failed to read RIS Live messages: {}", e)));
+                }
+            }
+            match parse_ris_live_line(&self.line) {
+                Ok(elems) => self.pending.extend(elems),
+                Err(e) => return Some(Err(anyhow!("line {}: {}", self.line_number, e))),
+            }
+        }
+    }
+}
+
+/// Build the RIS Live subscription for `filters`
+///
+/// Subscribes to UPDATE messages. A single peer IP and a single prefix
+/// filter are passed on to the server to cut down the stream; all filters
+/// still have to be applied to the received elements.
+pub fn ris_subscribe(filters: &ParseFilters) -> RisSubscribe {
+    let mut subscribe = RisSubscribe::new().data_type(RisSubscribeType::UPDATE);
+    if let [peer_ip] = filters.peer_ip.as_slice() {
+        subscribe = subscribe.peer(*peer_ip);
+    }
+    if let [prefix] = filters.prefix.as_slice() {
+        if let Ok(prefix) = prefix.parse() {
+            subscribe = subscribe
+                .prefix(prefix)
+                .more_specific(filters.include_sub)
+                .less_specific(filters.include_super);
+        }
+    }
+    subscribe
+}
+
+/// Client for a RIS Live websocket
+///
+/// Yields elements until the server closes the connection. Unparsable
+/// messages and `ris_error` messages are yielded as errors without ending
+/// the stream.
+pub struct RisLiveClient {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    pending: VecDeque<RisLiveElem>,
+    closed: bool,
+}
+
+impl RisLiveClient {
+    /// Connect to a RIS Live websocket at `url` and send `subscribe`
+    pub fn connect(url: &str, subscribe: &RisSubscribe) -> Result<Self> {
+        let (mut socket, _response) = tungstenite::connect(url)
+            .map_err(|e| anyhow!("failed to connect to {}: {}", url, e))?;
+        socket
+            .send(Message::Text(subscribe.to_json_string().into()))
+            .map_err(|e| anyhow!("failed to subscribe to {}: {}", url, e))?;
+        Ok(Self {
+            socket,
+            pending: VecDeque::new(),
+            closed: false,
+        })
+    }
+}
+
+impl Iterator for RisLiveClient {
+    type Item = Result<RisLiveElem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(elem) = self.pending.pop_front() {
+                return Some(Ok(elem));
+            }
+            if self.closed {
+                return None;
+            }
+
+            match self.socket.read() {
+                Ok(Message::Text(text)) => match parse_ris_live_line(text.as_str()) {
+                    Ok(elems) => self.pending.extend(elems),
+                    Err(e) => return Some(Err(e)),
+                },
+                Ok(Message::Close(_)) => self.closed = true,
+                Ok(_) => {}
+                Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                    self.closed = true
+                }
+                Err(e) => {
+                    self.closed = true;
+                    return Some(Err(anyhow!("RIS Live connection failed: {}", e)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgpkit_parser::models::ElemType;
+    use std::io::Cursor;
+    use std::net::TcpListener;
+
+    const UPDATE: &str = r#"{"type":"ris_message","data":{"timestamp":1704067201.5,"peer":"192.0.2.1","peer_asn":"64500","id":"1","host":"rrc21","type":"UPDATE","path":[64500,13335],"community":[[64500,100]],"origin":"igp","announcements":[{"next_hop":"192.0.2.1","prefixes":["1.1.1.0/24","1.0.0.0/24"]}],"withdrawals":["10.0.0.0/8"]}}"#;
+
+    #[test]
+    fn test_detect_ris_live() {
+        assert!(detect_ris_live(UPDATE.as_bytes()));
+        assert!(detect_ris_live(b"\n  {\"type\": \"ris_message\", \"data\""));
+        assert!(!detect_ris_live(b"{\"prefix\": \"1.1.1.0/24\"}"));
+        assert!(!detect_ris_live(&[0x03, 0x00, 0x00, 0x00, 0x06, 0x04]));
+        assert!(is_websocket_url("wss://ris-live.ripe.net/v1/ws/"));
+        assert!(!is_websocket_url("https://example.com/updates.gz"));
+    }
+
+    #[test]
+    fn test_parse_ris_live_line() {
+        let elems = parse_ris_live_line(UPDATE).unwrap();
+        assert_eq!(elems.len(), 3);
+        let (announce, host) = &elems[0];
+        assert_eq!(host, "rrc21");
+        assert_eq!(announce.elem_type, ElemType::ANNOUNCE);
+        assert_eq!(announce.prefix.to_string(), "1.1.1.0/24");
+        assert_eq!(announce.peer_asn.to_u32(), 64500);
+        assert_eq!(announce.timestamp, 1704067201.5);
+        assert!(announce.communities.is_some());
+        assert_eq!(elems[2].0.elem_type, ElemType::WITHDRAW);
+
+        let pong = r#"{"type":"pong","data":null}"#;
+        assert!(parse_ris_live_line(pong).unwrap().is_empty());
+        assert!(parse_ris_live_line("").unwrap().is_empty());
+        let error = r#"{"type":"ris_error","data":{"message":"Unknown host"}}"#;
+        assert!(parse_ris_live_line(error)
+            .unwrap_err()
+            .to_string()
+            .contains("Unknown host"));
+    }
+
+    #[test]
+    fn test_reader_skips_invalid_lines() {
+        let input = format!("{UPDATE}\nnot json\n\n{UPDATE}\n");
+        let results: Vec<_> = RisLiveReader::new(Cursor::new(input)).collect();
+        assert_eq!(results.len(), 7);
+        let error = results.iter().find_map(|r| r.as_ref().err()).unwrap();
+        assert!(error.to_string().starts_with("line 2:"));
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 6);
+    }
+
+    #[test]
+    fn test_ris_subscribe_pushdown() {
+        let filters = ParseFilters {
+            prefix: vec!["1.1.1.0/24".to_string()],
+            include_sub: true,
+            peer_ip: vec!["192.0.2.1".parse().unwrap()],
+            ..Default::default()
+        };
+        let json = ris_subscribe(&filters).to_json_string();
+        assert!(json.contains("\"type\":\"UPDATE\""));
+        assert!(json.contains("\"prefix\":\"1.1.1.0/24\""));
+        assert!(json.contains("\"moreSpecific\":true"));
+        assert!(json.contains("\"peer\":\"192.0.2.1\""));
+
+        let filters = ParseFilters {
+            prefix: vec!["1.1.1.0/24".to_string(), "8.8.8.0/24".to_string()],
+            ..Default::default()
+        };
+        assert!(!ris_subscribe(&filters).to_json_string().contains("prefix"));
+    }
+
+    #[test]
+    fn test_client_with_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut socket = tungstenite::accept(stream).unwrap();
+            let subscribe = socket.read().unwrap().into_text().unwrap();
+            socket
+                .send(Message::Text(
+                    r#"{"type":"ris_subscribe_ok","data":{}}"#.into(),
+                ))
+                .unwrap();
+            socket.send(Message::Text(UPDATE.into())).unwrap();
+            socket.close(None).unwrap();
+            // Drain until the client acknowledges the close
+            while socket.read().is_ok() {}
+            subscribe.to_string()
+        });
+
+        let client = RisLiveClient::connect(&format!("ws://{addr}"), &RisSubscribe::new()).unwrap();
+        let elems: Vec<_> = client.collect::<Result<_>>().unwrap();
+        assert_eq!(elems.len(), 3);
+        assert_eq!(elems[0].1, "rrc21");
+        assert!(server.join().unwrap().contains("ris_subscribe"));
+    }
+}
@@ -0,0 +1,211 @@
+//! RPKI route origin validation filter.
+//!
+//! `--rpki` keeps elements by the RFC 6811 validation state of their prefix
+//! and origin AS: `valid`, `invalid` or `not-found`. Like the other
+//! [`ParseFilters`](super::ParseFilters), multiple values match if any of them
+//! matches and values prefixed with `!` exclude matching elements. Elements
+//! without an origin (withdrawals) have no state: they never match a positive
+//! filter and always pass a negated one.
+//!
+//! The ROAs come from an [`RpkiValidator`], which is not part of the filter
+//! itself: load it with [`ParseFilters::load_rpki`](super::ParseFilters::load_rpki)
+//! from the local RPKI cache, or from the historical data of `rpki_date`.
+
+use anyhow::{anyhow, Result};
+use bgpkit_parser::BgpElem;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::attr_filter::ValueSet;
+use crate::database::MonocleDatabase;
+use crate::lens::rpki::{RpkiDataSource, RpkiValidationState, RpkiValidator};
+
+#[cfg(feature = "cli")]
+use clap::Args;
+
+/// RPKI filter options of [`ParseFilters`](super::ParseFilters)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(Args))]
+pub struct RpkiFilters {
+    /// Filter by RPKI validation state of the prefix and origin: valid, invalid or not-found,
+    /// comma-separated. Prefix with ! to exclude. Uses the local RPKI cache unless --rpki-date
+    /// is set
+    #[cfg_attr(feature = "cli", clap(long, value_delimiter = ','))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rpki: Vec<String>,
+
+    /// Validate --rpki and the rpki output field against historical RPKI data of this date
+    /// (YYYY-MM-DD) instead of the cache
+    #[cfg_attr(feature = "cli", clap(long, value_name = "DATE"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpki_date: Option<NaiveDate>,
+
+    /// Source of --rpki-date data: ripe, rpki-views or rpki-spools (default)
+    #[cfg_attr(
+        feature = "cli",
+        clap(long, value_enum, value_name = "SOURCE", hide_possible_values = true)
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpki_source: Option<RpkiDataSource>,
+
+    /// ROAs loaded by [`ParseFilters::load_rpki`](super::ParseFilters::load_rpki)
+    #[cfg_attr(feature = "cli", clap(skip))]
+    #[serde(skip)]
+    pub validator: Option<RpkiValidator>,
+}
+
+impl RpkiFilters {
+    /// Whether no RPKI filter is set
+    pub fn is_empty(&self) -> bool {
+        self.rpki.is_empty()
+    }
+
+    /// Check the filter values and the data source options
+    pub fn validate(&self) -> Result<()> {
+        parse_states(&self.rpki)?;
+        if self.rpki_source.is_some() && self.rpki_date.is_none() {
+            return Err(anyhow!("--rpki-source requires --rpki-date"));
+        }
+        if matches!(self.rpki_source, Some(RpkiDataSource::Cloudflare)) {
+            return Err(anyhow!(
+                "--rpki-source cloudflare has no historical data, use ripe, rpki-views or rpki-spools"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Load the ROAs to validate against: the historical data of
+    /// [`rpki_date`](Self::rpki_date), or else the local RPKI cache of `db`
+    pub fn load(&mut self, db: Option<&MonocleDatabase>) -> Result<&RpkiValidator> {
+        let validator = match (self.rpki_date, db) {
+            (Some(date), _) => RpkiValidator::from_historical(date, self.rpki_source.as_ref())?,
+            (None, Some(db)) => RpkiValidator::from_cache(db)?,
+            (None, None) => return Err(anyhow!("--rpki needs the RPKI cache or --rpki-date")),
+        };
+        Ok(self.validator.insert(validator))
+    }
+
+    /// Parse the filters into an [`RpkiMatcher`], `None` without `--rpki`
+    ///
+    /// Fails if the filter is set but no validator has been loaded.
+    pub fn matcher(&self) -> Result<Option<RpkiMatcher>> {
+        let Some(states) = parse_states(&self.rpki)? else {
+            return Ok(None);
+        };
+        let validator = self.validator.clone().ok_or_else(|| {
+            anyhow!("--rpki needs RPKI data, load it with ParseFilters::load_rpki first")
+        })?;
+        Ok(Some(RpkiMatcher { states, validator }))
+    }
+}
+
+fn parse_states(values: &[String]) -> Result<Option<ValueSet<RpkiValidationState>>> {
+    ValueSet::parse(values, "rpki", parse_state)
+}
+
+/// Parse an RPKI validation state: `valid`, `invalid` or `not-found`
+pub fn parse_state(value: &str) -> Result<RpkiValidationState> {
+    match value.trim().to_lowercase().as_str() {
+        "valid" => Ok(RpkiValidationState::Valid),
+        "invalid" => Ok(RpkiValidationState::Invalid),
+        "not-found" | "not_found" | "notfound" | "unknown" => Ok(RpkiValidationState::NotFound),
+        _ => Err(anyhow!("must be valid, invalid or not-found")),
+    }
+}
+
+/// Parsed [`RpkiFilters`] with the ROAs to validate against
+#[derive(Debug, Clone)]
+pub struct RpkiMatcher {
+    states: ValueSet<RpkiValidationState>,
+    validator: RpkiValidator,
+}
+
+impl RpkiMatcher {
+    /// Whether `elem` passes the filter
+    pub fn matches(&self, elem: &BgpElem) -> bool {
+        let state = self.validator.validate_elem(elem);
+        self.states.matches(|wanted| state == Some(*wanted))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use bgpkit_commons::rpki::{Roa, RpkiTrie};
+    use bgpkit_parser::models::{AsPath, ElemType, NetworkPrefix};
+
+    fn validator() -> RpkiValidator {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roa(Roa {
+            prefix: "1.1.1.0/24".parse().unwrap(),
+            asn: 13335,
+            max_length: 24,
+            rir: None,
+            not_before: None,
+            not_after: None,
+        });
+        RpkiValidator::from_trie(trie, "test")
+    }
+
+    fn elem(prefix: &str, origin: Option<u32>) -> BgpElem {
+        BgpElem {
+            elem_type: match origin {
+                Some(_) => ElemType::ANNOUNCE,
+                None => ElemType::WITHDRAW,
+            },
+            prefix: NetworkPrefix::new(prefix.parse().unwrap(), None),
+            as_path: origin.map(|asn| AsPath::from_sequence([174, asn])),
+            origin_asns: origin.map(|asn| vec![asn.into()]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rpki_filter() {
+        let matches = |values: &[&str], elem: &BgpElem| {
+            let filters = RpkiFilters {
+                rpki: values.iter().map(|v| v.to_string()).collect(),
+                validator: Some(validator()),
+                ..Default::default()
+            };
+            filters.matcher().unwrap().unwrap().matches(elem)
+        };
+        let valid = elem("1.1.1.0/24", Some(13335));
+        let invalid = elem("1.1.1.0/24", Some(64496));
+        let not_found = elem("8.8.8.0/24", Some(15169));
+        let withdrawal = elem("1.1.1.0/24", None);
+
+        assert!(matches(&["valid"], &valid) && !matches(&["valid"], &invalid));
+        assert!(matches(&["invalid"], &invalid));
+        assert!(matches(&["not-found"], &not_found) && !matches(&["not-found"], &valid));
+        assert!(matches(&["invalid", "not_found"], &not_found));
+        assert!(!matches(&["!valid"], &valid) && matches(&["!valid"], &invalid));
+        assert!(!matches(&["invalid"], &withdrawal) && matches(&["!invalid"], &withdrawal));
+    }
+
+    #[test]
+    fn test_rpki_filter_validate() {
+        let filters = |rpki: &str| RpkiFilters {
+            rpki: vec![rpki.to_string()],
+            ..Default::default()
+        };
+        assert!(filters("invalid").validate().is_ok());
+        assert!(filters("bogus").validate().is_err());
+        assert!(RpkiFilters {
+            rpki: vec!["valid".to_string(), "!invalid".to_string()],
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(RpkiFilters {
+            rpki_source: Some(RpkiDataSource::Ripe),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        // A filter without loaded ROAs cannot be matched
+        assert!(filters("invalid").matcher().is_err());
+        assert!(RpkiFilters::default().matcher().unwrap().is_none());
+    }
+}
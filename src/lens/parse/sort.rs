@@ -0,0 +1,474 @@
+//! Ordering of BGP elements for `--order-by`, spilling to disk when needed.
+//!
+//! [`ElemOrder`] compares elements by one or more [`OrderByField`]s, e.g.
+//! `prefix` then `timestamp`. [`ElemSorter`] collects elements and sorts them
+//! with an external merge sort: elements are buffered in memory until their
+//! estimated size exceeds the memory budget, then the buffer is sorted and
+//! written to a temporary file as one sorted run. [`ElemSorter::finish`]
+//! merges all runs (and whatever is still buffered) into a single sorted
+//! stream, so only one element per run is held in memory while merging.
+//!
+//! Runs are written as JSON lines of `(BgpElem, collector)` pairs into a
+//! temporary directory that is removed once the sorted stream is dropped.
+//! If everything fits into the budget, nothing is written to disk.
+//!
+//! The sort is stable: elements comparing equal keep the order in which they
+//! were pushed, with or without spilling.
+//!
+//! ```rust,ignore
+//! use monocle::lens::parse::sort::{ElemOrder, ElemSorter, DEFAULT_SORT_MEMORY_BYTES};
+//! use monocle::utils::{OrderByField, OrderDirection};
+//!
+//! let order = ElemOrder::new(
+//!     vec![OrderByField::Prefix, OrderByField::Timestamp],
+//!     OrderDirection::Asc,
+//! );
+//! let mut sorter = ElemSorter::new(order, DEFAULT_SORT_MEMORY_BYTES);
+//! for elem in elems {
+//!     sorter.push(elem, None)?;
+//! }
+//! for item in sorter.finish()? {
+//!     let (elem, collector) = item?;
+//!     println!("{}", elem);
+//! }
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use bgpkit_parser::models::{AttrRaw, MetaCommunity};
+use bgpkit_parser::BgpElem;
+use tempfile::TempDir;
+
+use crate::utils::{OrderByField, OrderDirection};
+
+/// Default memory budget for buffered elements before spilling (512 MiB)
+pub const DEFAULT_SORT_MEMORY_BYTES: usize = 512 * 1024 * 1024;
+
+/// One element to sort, with the collector it was seen on (if known)
+pub type SortItem = (BgpElem, Option<String>);
+
+/// Ordering of BGP elements by a list of fields
+///
+/// Later fields break ties of earlier ones. The direction applies to all
+/// fields. Prefixes, IP addresses and AS paths compare as strings, matching
+/// how they are printed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElemOrder {
+    fields: Vec<OrderByField>,
+    direction: OrderDirection,
+}
+
+impl ElemOrder {
+    /// Order by `fields` in `direction`; an empty list keeps input order
+    pub fn new(fields: Vec<OrderByField>, direction: OrderDirection) -> Self {
+        Self { fields, direction }
+    }
+
+    /// Fields compared, in order of precedence
+    pub fn fields(&self) -> &[OrderByField] {
+        &self.fields
+    }
+
+    /// Compare two elements
+    pub fn compare(&self, a: &BgpElem, b: &BgpElem) -> Ordering {
+        let cmp = self
+            .fields
+            .iter()
+            .map(|field| compare_field(*field, a, b))
+            .find(|cmp| cmp.is_ne())
+            .unwrap_or(Ordering::Equal);
+        match self.direction {
+            OrderDirection::Asc => cmp,
+            OrderDirection::Desc => cmp.reverse(),
+        }
+    }
+
+    /// Sort a slice in place (stable)
+    pub fn sort(&self, elems: &mut [SortItem]) {
+        elems.sort_by(|(a, _), (b, _)| self.compare(a, b));
+    }
+}
+
+fn compare_field(field: OrderByField, a: &BgpElem, b: &BgpElem) -> Ordering {
+    match field {
+        OrderByField::Timestamp => a
+            .timestamp
+            .partial_cmp(&b.timestamp)
+            .unwrap_or(Ordering::Equal),
+        OrderByField::Prefix => a.prefix.to_string().cmp(&b.prefix.to_string()),
+        OrderByField::PeerIp => a.peer_ip.to_string().cmp(&b.peer_ip.to_string()),
+        OrderByField::PeerAsn => a.peer_asn.cmp(&b.peer_asn),
+        OrderByField::AsPath => {
+            let a_path = a
+                .as_path
+                .as_ref()
+                .map(|p| p.to_string())
+                .unwrap_or_default();
+            let b_path = b
+                .as_path
+                .as_ref()
+                .map(|p| p.to_string())
+                .unwrap_or_default();
+            a_path.cmp(&b_path)
+        }
+        OrderByField::NextHop => {
+            let a_hop = a
+                .next_hop
+                .as_ref()
+                .map(|h| h.to_string())
+                .unwrap_or_default();
+            let b_hop = b
+                .next_hop
+                .as_ref()
+                .map(|h| h.to_string())
+                .unwrap_or_default();
+            a_hop.cmp(&b_hop)
+        }
+    }
+}
+
+/// Approximate heap and inline size of a buffered element in bytes
+fn estimated_size(item: &SortItem) -> usize {
+    let (elem, collector) = item;
+    let as_path = elem.as_path.as_ref().map_or(0, |path| {
+        path.segments
+            .iter()
+            .map(|segment| segment.len() * 4)
+            .sum::<usize>()
+    });
+    let origin_asns = elem.origin_asns.as_ref().map_or(0, |asns| asns.len() * 4);
+    let communities = elem.communities.as_ref().map_or(0, |communities| {
+        communities.len() * std::mem::size_of::<MetaCommunity>()
+    });
+    let raw_attrs = [&elem.unknown, &elem.deprecated]
+        .into_iter()
+        .flatten()
+        .map(|attrs| attrs.len() * std::mem::size_of::<AttrRaw>())
+        .sum::<usize>();
+    std::mem::size_of::<SortItem>()
+        + collector.as_ref().map_or(0, String::len)
+        + as_path
+        + origin_asns
+        + communities
+        + raw_attrs
+}
+
+/// External merge sort for BGP elements
+///
+/// See the [module documentation](self) for how spilling works.
+pub struct ElemSorter {
+    order: ElemOrder,
+    memory_budget: usize,
+    spill_dir: Option<PathBuf>,
+    buffer: Vec<SortItem>,
+    buffered_bytes: usize,
+    temp_dir: Option<TempDir>,
+    runs: Vec<PathBuf>,
+    total: u64,
+}
+
+impl ElemSorter {
+    /// Create a sorter keeping up to `memory_budget` bytes of elements in memory
+    pub fn new(order: ElemOrder, memory_budget: usize) -> Self {
+        Self {
+            order,
+            memory_budget,
+            spill_dir: None,
+            buffer: Vec::new(),
+            buffered_bytes: 0,
+            temp_dir: None,
+            runs: Vec::new(),
+            total: 0,
+        }
+    }
+
+    /// Write sorted runs below `dir` instead of the system temporary directory
+    pub fn with_spill_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.spill_dir = Some(dir.into());
+        self
+    }
+
+    /// Add an element, spilling the buffer to disk if it exceeds the budget
+    pub fn push(&mut self, elem: BgpElem, collector: Option<String>) -> Result<()> {
+        let item = (elem, collector);
+        self.buffered_bytes += estimated_size(&item);
+        self.buffer.push(item);
+        self.total += 1;
+        if self.buffered_bytes > self.memory_budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Number of elements pushed so far
+    pub fn len(&self) -> u64 {
+        self.total
+    }
+
+    /// Whether no elements were pushed
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// Number of sorted runs written to disk so far
+    pub fn spilled_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Sort the buffer and write it to a new run file
+    fn spill(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let dir = match self.temp_dir {
+            Some(ref dir) => dir,
+            None => {
+                let dir = match self.spill_dir {
+                    Some(ref parent) => tempfile::Builder::new()
+                        .prefix("monocle-sort-")
+                        .tempdir_in(parent),
+                    None => tempfile::Builder::new().prefix("monocle-sort-").tempdir(),
+                }
+                .context("failed to create directory for sort runs")?;
+                self.temp_dir.insert(dir)
+            }
+        };
+        let path = dir.path().join(format!("run-{}.jsonl", self.runs.len()));
+
+        self.order.sort(&mut self.buffer);
+        let file = File::create(&path)
+            .with_context(|| format!("failed to create sort run {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        for item in self.buffer.drain(..) {
+            serde_json::to_writer(&mut writer, &item)?;
+            writer.write_all(b"\n")?;
+        }
+        writer
+            .flush()
+            .with_context(|| format!("failed to write sort run {}", path.display()))?;
+
+        self.buffered_bytes = 0;
+        self.runs.push(path);
+        Ok(())
+    }
+
+    /// Finish sorting and return the elements in order
+    pub fn finish(mut self) -> Result<SortedElems> {
+        if self.runs.is_empty() {
+            self.order.sort(&mut self.buffer);
+            return Ok(SortedElems {
+                inner: SortedInner::Memory(self.buffer.into_iter()),
+            });
+        }
+        self.spill()?;
+
+        let mut runs = Vec::with_capacity(self.runs.len());
+        for path in &self.runs {
+            let file = File::open(path)
+                .with_context(|| format!("failed to open sort run {}", path.display()))?;
+            runs.push(BufReader::new(file).lines());
+        }
+        let mut merge = RunMerge {
+            order: self.order,
+            runs,
+            heap: BinaryHeap::new(),
+            _temp_dir: self.temp_dir,
+        };
+        for index in 0..merge.runs.len() {
+            merge.advance(index)?;
+        }
+        Ok(SortedElems {
+            inner: SortedInner::Merge(Box::new(merge)),
+        })
+    }
+}
+
+/// Sorted elements returned by [`ElemSorter::finish`]
+///
+/// Reading a spilled run can fail, so items are results.
+pub struct SortedElems {
+    inner: SortedInner,
+}
+
+enum SortedInner {
+    Memory(std::vec::IntoIter<SortItem>),
+    Merge(Box<RunMerge>),
+}
+
+impl Iterator for SortedElems {
+    type Item = Result<SortItem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.inner {
+            SortedInner::Memory(ref mut iter) => iter.next().map(Ok),
+            SortedInner::Merge(ref mut merge) => merge.next(),
+        }
+    }
+}
+
+/// K-way merge over sorted run files
+struct RunMerge {
+    order: ElemOrder,
+    runs: Vec<Lines<BufReader<File>>>,
+    heap: BinaryHeap<RunHead>,
+    // Keeps the run files around until the merge is dropped
+    _temp_dir: Option<TempDir>,
+}
+
+impl RunMerge {
+    /// Read the next element of run `index` into the heap
+    fn advance(&mut self, index: usize) -> Result<()> {
+        let Some(line) = self.runs[index].next() else {
+            return Ok(());
+        };
+        let line = line.context("failed to read sort run")?;
+        let item: SortItem =
+            serde_json::from_str(&line).map_err(|e| anyhow!("corrupt sort run: {}", e))?;
+        self.heap.push(RunHead {
+            item,
+            run: index,
+            order: self.order.clone(),
+        });
+        Ok(())
+    }
+
+    fn next(&mut self) -> Option<Result<SortItem>> {
+        let head = self.heap.pop()?;
+        if let Err(e) = self.advance(head.run) {
+            self.heap.clear();
+            return Some(Err(e));
+        }
+        Some(Ok(head.item))
+    }
+}
+
+/// Next element of one run; [`BinaryHeap`] pops the first element in sort
+/// order, with ties going to the earlier run to keep the sort stable
+struct RunHead {
+    item: SortItem,
+    run: usize,
+    order: ElemOrder,
+}
+
+impl PartialEq for RunHead {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RunHead {}
+
+impl Ord for RunHead {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order
+            .compare(&other.item.0, &self.item.0)
+            .then_with(|| other.run.cmp(&self.run))
+    }
+}
+
+impl PartialOrd for RunHead {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn elem(timestamp: f64, prefix: &str, peer_asn: u32) -> BgpElem {
+        BgpElem {
+            timestamp,
+            prefix: bgpkit_parser::models::NetworkPrefix::from_str(prefix).unwrap(),
+            peer_asn: peer_asn.into(),
+            as_path: Some(bgpkit_parser::models::AsPath::from_sequence([
+                peer_asn, 13335,
+            ])),
+            ..Default::default()
+        }
+    }
+
+    fn sample() -> Vec<BgpElem> {
+        (0..200u32)
+            .map(|i| elem(f64::from(1000 - i % 7), &format!("10.{}.0.0/16", i % 13), i))
+            .collect()
+    }
+
+    fn sorted(mut sorter: ElemSorter, elems: Vec<BgpElem>) -> Vec<SortItem> {
+        for elem in elems {
+            let collector = Some(format!("rrc{:02}", elem.peer_asn.to_u32() % 3));
+            sorter.push(elem, collector).unwrap();
+        }
+        sorter.finish().unwrap().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn test_multi_key_order() {
+        let order = ElemOrder::new(
+            vec![OrderByField::Prefix, OrderByField::Timestamp],
+            OrderDirection::Asc,
+        );
+        let a = elem(2.0, "10.0.0.0/8", 1);
+        let b = elem(1.0, "10.0.0.0/8", 2);
+        let c = elem(0.0, "11.0.0.0/8", 3);
+        assert_eq!(order.compare(&b, &a), Ordering::Less);
+        assert_eq!(order.compare(&a, &c), Ordering::Less);
+
+        let desc = ElemOrder::new(order.fields().to_vec(), OrderDirection::Desc);
+        assert_eq!(desc.compare(&b, &a), Ordering::Greater);
+        assert_eq!(
+            ElemOrder::new(Vec::new(), OrderDirection::Asc).compare(&a, &c),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_spilled_sort_matches_in_memory_sort() {
+        let order = ElemOrder::new(
+            vec![OrderByField::Prefix, OrderByField::Timestamp],
+            OrderDirection::Desc,
+        );
+        let in_memory = sorted(
+            ElemSorter::new(order.clone(), DEFAULT_SORT_MEMORY_BYTES),
+            sample(),
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut sorter =
+            ElemSorter::new(order, 10 * std::mem::size_of::<SortItem>()).with_spill_dir(dir.path());
+        for elem in sample() {
+            sorter.push(elem, None).unwrap();
+        }
+        assert!(sorter.spilled_runs() > 1);
+        assert_eq!(sorter.len(), 200);
+        let spilled: Vec<SortItem> = sorter.finish().unwrap().map(Result::unwrap).collect();
+        assert_eq!(
+            spilled.iter().map(|(e, _)| e).collect::<Vec<_>>(),
+            in_memory.iter().map(|(e, _)| e).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_spilled_sort_is_stable_and_keeps_collectors() {
+        let order = ElemOrder::new(vec![OrderByField::Timestamp], OrderDirection::Asc);
+        let spilled = sorted(
+            ElemSorter::new(order, 5 * std::mem::size_of::<SortItem>()),
+            sample(),
+        );
+        assert_eq!(spilled.len(), 200);
+        for pair in spilled.windows(2) {
+            let ((a, _), (b, _)) = (&pair[0], &pair[1]);
+            assert!(a.timestamp < b.timestamp || a.peer_asn < b.peer_asn);
+        }
+        for (elem, collector) in &spilled {
+            let expected = format!("rrc{:02}", elem.peer_asn.to_u32() % 3);
+            assert_eq!(collector.as_deref(), Some(expected.as_str()));
+        }
+    }
+}
@@ -0,0 +1,487 @@
+//! MRT file statistics and integrity report.
+//!
+//! [`MrtStats`] reads an MRT file record by record, without filters, and
+//! collects what is in it: record type and subtype counts, the TABLE_DUMP_V2
+//! peer index table, element counts per peer, the time span of the records,
+//! the IPv4/IPv6 split and ADD-PATH usage. Records that cannot be parsed are
+//! reported as [`RecordIssue`]s with their byte offset in the decompressed
+//! file, instead of being silently skipped like in normal parsing.
+//!
+//! A record whose body fails to parse is skipped and reading continues with
+//! the next one. A corrupt common header or an input that ends inside a
+//! record stops the report, since the following record boundaries are
+//! unknown.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr};
+
+use bgpkit_parser::models::{ElemType, EntryType, MrtMessage, PeerIndexTable, TableDumpV2Message};
+use bgpkit_parser::{chunk_mrt_record, BgpElem, Elementor, ParserError, RawMrtRecord};
+use serde::{Deserialize, Serialize};
+
+use super::input::InputCompression;
+
+/// Summary of an MRT file, see the [module docs](self)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MrtStats {
+    /// Compression detected from the content, if read through
+    /// [`ParseLens::stats`](super::ParseLens::stats)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<InputCompression>,
+    /// Decompressed bytes read
+    pub bytes: u64,
+    pub records: u64,
+    pub elements: u64,
+    pub announcements: u64,
+    pub withdrawals: u64,
+    pub ipv4_elements: u64,
+    pub ipv6_elements: u64,
+    /// Records with an ADD-PATH subtype (RFC 8050)
+    pub add_path_records: u64,
+    /// Elements with a path identifier
+    pub add_path_elements: u64,
+    /// Earliest record timestamp
+    pub first_timestamp: Option<f64>,
+    /// Latest record timestamp
+    pub last_timestamp: Option<f64>,
+    /// Records per type and subtype, in numeric order
+    pub record_types: Vec<RecordTypeStats>,
+    /// TABLE_DUMP_V2 peer index table, for RIB dumps
+    pub peer_index: Option<PeerIndexStats>,
+    /// Elements per peer, most elements first
+    pub peers: Vec<PeerStats>,
+    /// Truncated and corrupt records, in file order
+    pub issues: Vec<RecordIssue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordTypeStats {
+    pub entry_type: String,
+    pub subtype: String,
+    pub records: u64,
+    /// Bytes of these records, including common headers
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerIndexStats {
+    pub collector_bgp_id: Ipv4Addr,
+    pub view_name: String,
+    /// Peers by index
+    pub peers: Vec<PeerIndexEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerIndexEntry {
+    pub index: u16,
+    pub peer_ip: IpAddr,
+    pub peer_asn: u32,
+    pub peer_bgp_id: Ipv4Addr,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerStats {
+    pub peer_ip: IpAddr,
+    pub peer_asn: u32,
+    pub elements: u64,
+    pub announcements: u64,
+    pub withdrawals: u64,
+}
+
+/// Why a record could not be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordIssueKind {
+    /// The record, or a length inside it, extends past the available data
+    Truncated,
+    /// The record is malformed or unsupported
+    Corrupt,
+}
+
+impl std::fmt::Display for RecordIssueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RecordIssueKind::Truncated => "truncated",
+            RecordIssueKind::Corrupt => "corrupt",
+        })
+    }
+}
+
+/// A record that could not be read
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordIssue {
+    /// Byte offset of the record in the decompressed input
+    pub offset: u64,
+    pub kind: RecordIssueKind,
+    /// Type and subtype from the common header, when it could be read
+    pub entry_type: Option<String>,
+    pub subtype: Option<String>,
+    /// Body length from the common header
+    pub length: Option<u32>,
+    pub message: String,
+}
+
+/// Counts bytes read, for record offsets
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+impl MrtStats {
+    /// Read a decompressed MRT stream to its end. Read errors are reported as
+    /// [`issues`](Self::issues) rather than returned.
+    pub fn from_reader<R: Read>(reader: R) -> Self {
+        let mut stats = Self::default();
+        let mut reader = CountingReader {
+            inner: reader,
+            count: 0,
+        };
+        let mut elementor = Elementor::new();
+        let mut record_types: BTreeMap<(u16, u16), (u64, u64)> = BTreeMap::new();
+        let mut peers: HashMap<(IpAddr, u32), PeerStats> = HashMap::new();
+
+        loop {
+            let offset = reader.count;
+            let raw = match chunk_mrt_record(&mut reader) {
+                Ok(raw) => raw,
+                Err(e) => {
+                    let consumed = reader.count - offset;
+                    let kind = match &e.error {
+                        ParserError::EofExpected if consumed == 0 => break,
+                        ParserError::EofExpected | ParserError::EofError(_) => {
+                            RecordIssueKind::Truncated
+                        }
+                        ParserError::IoError(io)
+                            if io.kind() == std::io::ErrorKind::UnexpectedEof =>
+                        {
+                            RecordIssueKind::Truncated
+                        }
+                        _ => RecordIssueKind::Corrupt,
+                    };
+                    let message = match kind {
+                        RecordIssueKind::Truncated => {
+                            format!("input ends {consumed} bytes into the record")
+                        }
+                        RecordIssueKind::Corrupt => e.error.to_string(),
+                    };
+                    stats.issues.push(RecordIssue {
+                        offset,
+                        kind,
+                        entry_type: None,
+                        subtype: None,
+                        length: None,
+                        message,
+                    });
+                    break;
+                }
+            };
+
+            let header = raw.common_header;
+            let entry_type = header.entry_type as u16;
+            stats.records += 1;
+            let counts = record_types
+                .entry((entry_type, header.entry_subtype))
+                .or_default();
+            counts.0 += 1;
+            counts.1 += raw.total_bytes_len() as u64;
+            if is_add_path(header.entry_type, header.entry_subtype) {
+                stats.add_path_records += 1;
+            }
+            let timestamp = header.timestamp as f64
+                + header.microsecond_timestamp.unwrap_or(0) as f64 / 1_000_000.0;
+            stats.first_timestamp = Some(
+                stats
+                    .first_timestamp
+                    .map_or(timestamp, |t| t.min(timestamp)),
+            );
+            stats.last_timestamp =
+                Some(stats.last_timestamp.map_or(timestamp, |t| t.max(timestamp)));
+
+            if let Err((kind, message)) = stats.add_record(raw, &mut elementor, &mut peers) {
+                stats.issues.push(RecordIssue {
+                    offset,
+                    kind,
+                    entry_type: Some(format!("{:?}", header.entry_type)),
+                    subtype: Some(subtype_name(header.entry_type, header.entry_subtype)),
+                    length: Some(header.length),
+                    message,
+                });
+            }
+        }
+
+        stats.bytes = reader.count;
+        stats.record_types = record_types
+            .into_iter()
+            .map(|((entry_type, subtype), (records, bytes))| {
+                let entry_type = EntryType::try_from(entry_type).unwrap_or(EntryType::NULL);
+                RecordTypeStats {
+                    entry_type: format!("{entry_type:?}"),
+                    subtype: subtype_name(entry_type, subtype),
+                    records,
+                    bytes,
+                }
+            })
+            .collect();
+        stats.peers = peers.into_values().collect();
+        stats.peers.sort_by(|a, b| {
+            b.elements
+                .cmp(&a.elements)
+                .then(a.peer_ip.cmp(&b.peer_ip))
+                .then(a.peer_asn.cmp(&b.peer_asn))
+        });
+        stats
+    }
+
+    /// Parse one record and count its elements
+    fn add_record(
+        &mut self,
+        raw: RawMrtRecord,
+        elementor: &mut Elementor,
+        peers: &mut HashMap<(IpAddr, u32), PeerStats>,
+    ) -> Result<(), (RecordIssueKind, String)> {
+        let record = raw.parse().map_err(|e| {
+            let kind = match e {
+                ParserError::TruncatedMsg(_)
+                | ParserError::TruncatedPrefix
+                | ParserError::TruncatedLabeledNlri
+                | ParserError::EofError(_) => RecordIssueKind::Truncated,
+                _ => RecordIssueKind::Corrupt,
+            };
+            (kind, e.to_string())
+        })?;
+
+        if let MrtMessage::TableDumpV2Message(TableDumpV2Message::PeerIndexTable(table)) =
+            &record.message
+        {
+            self.peer_index = Some(PeerIndexStats::new(table));
+            return elementor
+                .set_peer_table(record)
+                .map_err(|e| (RecordIssueKind::Corrupt, e.to_string()));
+        }
+
+        let elems = elementor
+            .record_to_elems_iter(record)
+            .map_err(|e| (RecordIssueKind::Corrupt, e.to_string()))?;
+        for elem in elems {
+            self.add_elem(&elem, peers);
+        }
+        Ok(())
+    }
+
+    fn add_elem(&mut self, elem: &BgpElem, peers: &mut HashMap<(IpAddr, u32), PeerStats>) {
+        let announce = elem.elem_type == ElemType::ANNOUNCE;
+        self.elements += 1;
+        if announce {
+            self.announcements += 1;
+        } else {
+            self.withdrawals += 1;
+        }
+        if elem.prefix.prefix.addr().is_ipv4() {
+            self.ipv4_elements += 1;
+        } else {
+            self.ipv6_elements += 1;
+        }
+        if elem.prefix.path_id.is_some() {
+            self.add_path_elements += 1;
+        }
+
+        let peer_asn = elem.peer_asn.to_u32();
+        let peer = peers
+            .entry((elem.peer_ip, peer_asn))
+            .or_insert_with(|| PeerStats {
+                peer_ip: elem.peer_ip,
+                peer_asn,
+                elements: 0,
+                announcements: 0,
+                withdrawals: 0,
+            });
+        peer.elements += 1;
+        if announce {
+            peer.announcements += 1;
+        } else {
+            peer.withdrawals += 1;
+        }
+    }
+
+    /// Seconds between the first and last record
+    pub fn duration(&self) -> Option<f64> {
+        Some(self.last_timestamp? - self.first_timestamp?)
+    }
+
+    /// Records that could not be read because they are truncated
+    pub fn truncated_records(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.kind == RecordIssueKind::Truncated)
+            .count()
+    }
+
+    /// Records that could not be read because they are corrupt
+    pub fn corrupt_records(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.kind == RecordIssueKind::Corrupt)
+            .count()
+    }
+}
+
+impl PeerIndexStats {
+    fn new(table: &PeerIndexTable) -> Self {
+        let mut peers: Vec<PeerIndexEntry> = table
+            .id_peer_map
+            .iter()
+            .map(|(index, peer)| PeerIndexEntry {
+                index: *index,
+                peer_ip: peer.peer_ip,
+                peer_asn: peer.peer_asn.to_u32(),
+                peer_bgp_id: peer.peer_bgp_id,
+            })
+            .collect();
+        peers.sort_by_key(|peer| peer.index);
+        Self {
+            collector_bgp_id: table.collector_bgp_id,
+            view_name: table.view_name.clone(),
+            peers,
+        }
+    }
+}
+
+/// Whether a record subtype carries ADD-PATH path identifiers (RFC 8050)
+fn is_add_path(entry_type: EntryType, subtype: u16) -> bool {
+    match entry_type {
+        EntryType::TABLE_DUMP_V2 => (8..=12).contains(&subtype),
+        EntryType::BGP4MP | EntryType::BGP4MP_ET => (8..=11).contains(&subtype),
+        _ => false,
+    }
+}
+
+/// RFC 6396 and RFC 8050 name of a record subtype, or its number
+fn subtype_name(entry_type: EntryType, subtype: u16) -> String {
+    let name = match entry_type {
+        EntryType::TABLE_DUMP => match subtype {
+            1 => Some("AFI_IPV4"),
+            2 => Some("AFI_IPV6"),
+            _ => None,
+        },
+        EntryType::TABLE_DUMP_V2 => match subtype {
+            1 => Some("PEER_INDEX_TABLE"),
+            2 => Some("RIB_IPV4_UNICAST"),
+            3 => Some("RIB_IPV4_MULTICAST"),
+            4 => Some("RIB_IPV6_UNICAST"),
+            5 => Some("RIB_IPV6_MULTICAST"),
+            6 => Some("RIB_GENERIC"),
+            7 => Some("GEO_PEER_TABLE"),
+            8 => Some("RIB_IPV4_UNICAST_ADDPATH"),
+            9 => Some("RIB_IPV4_MULTICAST_ADDPATH"),
+            10 => Some("RIB_IPV6_UNICAST_ADDPATH"),
+            11 => Some("RIB_IPV6_MULTICAST_ADDPATH"),
+            12 => Some("RIB_GENERIC_ADDPATH"),
+            _ => None,
+        },
+        EntryType::BGP4MP | EntryType::BGP4MP_ET => match subtype {
+            0 => Some("STATE_CHANGE"),
+            1 => Some("MESSAGE"),
+            4 => Some("MESSAGE_AS4"),
+            5 => Some("STATE_CHANGE_AS4"),
+            6 => Some("MESSAGE_LOCAL"),
+            7 => Some("MESSAGE_AS4_LOCAL"),
+            8 => Some("MESSAGE_ADDPATH"),
+            9 => Some("MESSAGE_AS4_ADDPATH"),
+            10 => Some("MESSAGE_LOCAL_ADDPATH"),
+            11 => Some("MESSAGE_AS4_LOCAL_ADDPATH"),
+            _ => None,
+        },
+        _ => None,
+    };
+    name.map_or_else(|| subtype.to_string(), str::to_string)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use bgpkit_parser::encoder::MrtUpdatesEncoder;
+    use bgpkit_parser::models::{AsPath, NetworkPrefix};
+    use std::io::Cursor;
+    use std::str::FromStr;
+
+    fn elem(timestamp: f64, peer: &str, prefix: &str, elem_type: ElemType) -> BgpElem {
+        BgpElem {
+            timestamp,
+            elem_type,
+            peer_ip: peer.parse().unwrap(),
+            peer_asn: 64496.into(),
+            prefix: NetworkPrefix::from_str(prefix).unwrap(),
+            as_path: Some(AsPath::from_sequence([64496, 13335])),
+            next_hop: Some(peer.parse().unwrap()),
+            ..Default::default()
+        }
+    }
+
+    fn updates() -> Vec<u8> {
+        let mut encoder = MrtUpdatesEncoder::new();
+        encoder.process_elem(&elem(100.0, "192.0.2.1", "1.0.0.0/24", ElemType::ANNOUNCE));
+        encoder.process_elem(&elem(
+            160.0,
+            "192.0.2.1",
+            "2001:db8::/32",
+            ElemType::ANNOUNCE,
+        ));
+        encoder.process_elem(&elem(130.0, "192.0.2.2", "1.0.0.0/24", ElemType::WITHDRAW));
+        encoder.export_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_stats_counts() {
+        let data = updates();
+        let stats = MrtStats::from_reader(Cursor::new(data.clone()));
+        assert_eq!(stats.bytes, data.len() as u64);
+        assert_eq!(stats.records, 3);
+        assert_eq!(stats.record_types.len(), 1);
+        assert_eq!(stats.record_types[0].entry_type, "BGP4MP_ET");
+        assert_eq!(stats.record_types[0].subtype, "MESSAGE_AS4");
+        assert_eq!(stats.record_types[0].bytes, data.len() as u64);
+        assert_eq!(
+            (stats.elements, stats.announcements, stats.withdrawals),
+            (3, 2, 1)
+        );
+        assert_eq!((stats.ipv4_elements, stats.ipv6_elements), (2, 1));
+        assert_eq!(stats.first_timestamp, Some(100.0));
+        assert_eq!(stats.duration(), Some(60.0));
+        assert_eq!(stats.peers.len(), 2);
+        assert_eq!(stats.peers[0].peer_ip.to_string(), "192.0.2.1");
+        assert_eq!(stats.peers[0].elements, 2);
+        assert!(stats.issues.is_empty() && stats.peer_index.is_none());
+    }
+
+    #[test]
+    fn test_stats_issues() {
+        let data = updates();
+        let first_len = 12 + u32::from_be_bytes(data[8..12].try_into().unwrap()) as usize;
+
+        // Input ends inside the last record
+        let stats = MrtStats::from_reader(Cursor::new(data[..data.len() - 3].to_vec()));
+        assert_eq!(stats.records, 2);
+        assert_eq!(stats.truncated_records(), 1);
+        assert!(stats.issues[0].offset > first_len as u64);
+
+        // Garbage in the body of the first record is skipped
+        let mut corrupt = data.clone();
+        corrupt[12..first_len].fill(0xff);
+        let stats = MrtStats::from_reader(Cursor::new(corrupt));
+        assert_eq!(stats.records, 3);
+        assert_eq!(stats.elements, 2);
+        assert_eq!(stats.issues.len(), 1);
+        assert_eq!(stats.issues[0].offset, 0);
+        assert_eq!(stats.issues[0].subtype.as_deref(), Some("MESSAGE_AS4"));
+    }
+}
@@ -0,0 +1,332 @@
+//! BIRD 2 `show route all` parser.
+//!
+//! ```text
+//! BIRD 2.0.12 ready.
+//! Table master4:
+//! 1.0.0.0/24           unicast [peer_13335 2024-01-01 from 192.0.2.1] * (100) [AS13335i]
+//!     via 192.0.2.1 on eth0
+//!     Type: BGP univ
+//!     BGP.origin: IGP
+//!     BGP.as_path: 13335
+//!     BGP.next_hop: 192.0.2.1
+//!     BGP.med: 0
+//!     BGP.local_pref: 100
+//!     BGP.community: (13335,10) (65000,100)
+//!     BGP.large_community: (13335, 1, 2)
+//!                      unicast [peer_6939 2024-01-01] (100) [AS13335i]
+//!     via 192.0.2.2 on eth0
+//!     ...
+//! ```
+//!
+//! Each route starts with a line naming the route type (`unicast`, ...) and,
+//! for the first route of a network, the prefix. BIRD does not print the
+//! neighbor's address or AS: the peer address is taken from `from` in the
+//! route line when present and from `BGP.next_hop` otherwise, and the peer AS
+//! is the first AS of the path (AS0 for routes with an empty path). Routes
+//! without BGP attributes (static, device, ...) are skipped. `birdc -v` reply
+//! codes (`1007-`) are stripped.
+
+use super::{parse_community, parse_origin_name, split_as_path, DumpRoute};
+use bgpkit_parser::models::BgpElem;
+use ipnet::IpNet;
+use std::io::BufRead;
+use std::net::IpAddr;
+
+/// BIRD route types that can start a route line
+const ROUTE_TYPES: [&str; 4] = ["unicast", "blackhole", "unreachable", "prohibit"];
+
+/// Whether the start of a dump looks like BIRD `show route` output
+pub(super) fn detect(text: &str) -> bool {
+    text.starts_with("BIRD ")
+        || text.starts_with("Table master")
+        || text.lines().take(4).any(|line| {
+            let line = strip_reply_code(line);
+            line.starts_with("Table ") && line.trim_end().ends_with(':')
+                || parse_route_line(line).is_some_and(|(prefix, _, _)| prefix.is_some())
+        })
+}
+
+/// Strip a `birdc -v` reply code (`1007-`, `0000 `) from a line
+fn strip_reply_code(line: &str) -> &str {
+    let bytes = line.as_bytes();
+    if bytes.len() >= 5
+        && bytes[..4].iter().all(u8::is_ascii_digit)
+        && matches!(bytes[4], b'-' | b' ')
+    {
+        &line[5..]
+    } else {
+        line
+    }
+}
+
+/// Split a route line into its prefix (first route of a network only), route
+/// type and the route description after the type
+///
+/// BIRD 1 style lines (`PREFIX via GATEWAY on IFACE [...]`) have `via` in
+/// place of the route type.
+fn parse_route_line(line: &str) -> Option<(Option<IpNet>, &str, &str)> {
+    let mut tokens = line.split_whitespace();
+    let first = tokens.next()?;
+    let (prefix, route_type) = match first.parse::<IpNet>() {
+        Ok(prefix) => (Some(prefix), tokens.next()?),
+        Err(_) => (None, first),
+    };
+    let is_route_type =
+        ROUTE_TYPES.contains(&route_type) || (route_type == "via" && prefix.is_some());
+    if !is_route_type {
+        return None;
+    }
+    let rest = line.split_once(route_type)?.1;
+    Some((prefix, route_type, rest))
+}
+
+/// Address after `from` in a route line's `[protocol time from ADDR]`
+fn route_line_source(rest: &str) -> Option<IpAddr> {
+    let bracket = rest.split_once('[')?.1.split_once(']')?.0;
+    let mut tokens = bracket.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if token == "from" {
+            return tokens.next()?.parse().ok();
+        }
+    }
+    None
+}
+
+/// Route being read, with the BIRD-specific bits needed to finish it
+struct BirdRoute {
+    route: DumpRoute,
+    source: Option<IpAddr>,
+    gateway: Option<IpAddr>,
+    is_bgp: bool,
+}
+
+impl BirdRoute {
+    fn finish(mut self, timestamp: f64) -> Option<BgpElem> {
+        if !self.is_bgp {
+            return None;
+        }
+        if let Some(peer_ip) = self.source.or(self.route.next_hop).or(self.gateway) {
+            self.route.peer_ip = peer_ip;
+        }
+        self.route.peer_asn = self.route.first_asn().unwrap_or(0);
+        if self.route.next_hop.is_none() {
+            self.route.next_hop = self.gateway;
+        }
+        Some(self.route.into_elem(timestamp))
+    }
+}
+
+pub(super) fn parse_bird_dump<R: BufRead>(
+    mut reader: R,
+    timestamp: f64,
+) -> std::io::Result<Vec<BgpElem>> {
+    let mut elems = Vec::new();
+    let mut prefix: Option<IpNet> = None;
+    let mut current: Option<BirdRoute> = None;
+    let mut buf = String::new();
+
+    while reader.read_line(&mut buf)? > 0 {
+        let line = strip_reply_code(buf.trim_end()).to_string();
+        buf.clear();
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if let Some((line_prefix, route_type, rest)) = parse_route_line(&line) {
+            if let Some(route) = current.take() {
+                elems.extend(route.finish(timestamp));
+            }
+            if line_prefix.is_some() {
+                prefix = line_prefix;
+            }
+            let Some(prefix) = prefix else {
+                continue;
+            };
+            let gateway = match route_type {
+                "via" => rest
+                    .split_whitespace()
+                    .next()
+                    .and_then(|gateway| gateway.parse().ok()),
+                _ => None,
+            };
+            current = Some(BirdRoute {
+                route: DumpRoute::new(prefix),
+                source: route_line_source(rest),
+                gateway,
+                is_bgp: false,
+            });
+            continue;
+        }
+
+        let Some(route) = current.as_mut() else {
+            continue;
+        };
+        if let Some(gateway) = trimmed.strip_prefix("via ") {
+            route.gateway = gateway
+                .split_whitespace()
+                .next()
+                .and_then(|gateway| gateway.parse().ok());
+            continue;
+        }
+        if let Some(route_type) = trimmed.strip_prefix("Type: ") {
+            route.is_bgp |= route_type.starts_with("BGP");
+            continue;
+        }
+        let Some((name, value)) = trimmed
+            .strip_prefix("BGP.")
+            .and_then(|attr| attr.split_once(':'))
+        else {
+            continue;
+        };
+        route.is_bgp = true;
+        let value = value.trim();
+        let route = &mut route.route;
+        match name {
+            "origin" => route.origin = parse_origin_name(value),
+            "as_path" => route.as_path = split_as_path(value).0,
+            "next_hop" => {
+                route.next_hop = value
+                    .split_whitespace()
+                    .next()
+                    .and_then(|next_hop| next_hop.parse().ok())
+            }
+            "med" => route.med = value.parse().ok(),
+            "local_pref" => route.local_pref = value.parse().ok(),
+            "community" | "large_community" => route.communities.extend(
+                value
+                    .split(')')
+                    .filter_map(|group| group.trim().strip_prefix('('))
+                    .filter_map(|group| {
+                        let parts: Vec<&str> = group
+                            .split([',', ' '])
+                            .filter(|part| !part.is_empty())
+                            .collect();
+                        parse_community(&parts.join(":"))
+                    }),
+            ),
+            _ => {}
+        }
+    }
+    if let Some(route) = current.take() {
+        elems.extend(route.finish(timestamp));
+    }
+
+    Ok(elems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgpkit_parser::models::{Asn, Community, LargeCommunity, MetaCommunity, Origin};
+
+    const DUMP: &str = "\
+BIRD 2.0.12 ready.
+Table master4:
+1.0.0.0/24           unicast [peer_13335 2024-01-01 from 192.0.2.1] * (100) [AS13335i]
+\tvia 192.0.2.1 on eth0
+\tType: BGP univ
+\tBGP.origin: IGP
+\tBGP.as_path: 13335
+\tBGP.next_hop: 192.0.2.1
+\tBGP.med: 10
+\tBGP.local_pref: 200
+\tBGP.community: (13335,10) (65535,65281)
+\tBGP.large_community: (13335, 1, 2)
+                     unicast [peer_6939 2024-01-01] (100) [AS13335i]
+\tvia 192.0.2.2 on eth0
+\tType: BGP univ
+\tBGP.origin: Incomplete
+\tBGP.as_path: 6939 {64512 64513} 13335
+\tBGP.next_hop: 192.0.2.2
+\tBGP.local_pref: 100
+10.0.0.0/8           unicast [static1 2024-01-01] * (200)
+\tvia 10.1.1.1 on eth1
+\tType: static univ
+
+Table master6:
+2001:db8::/32        unicast [peer_v6 2024-01-01] * (100) [AS64500i]
+\tvia 2001:db8::1 on eth0
+\tType: BGP univ
+\tBGP.origin: IGP
+\tBGP.as_path: 64500
+\tBGP.next_hop: 2001:db8::1 fe80::1
+\tBGP.local_pref: 100
+";
+
+    fn parse(dump: &str) -> Vec<BgpElem> {
+        match parse_bird_dump(dump.as_bytes(), 1704067200.0) {
+            Ok(elems) => elems,
+            Err(error) => panic!("BIRD dump parsing failed: {error}"),
+        }
+    }
+
+    #[test]
+    fn test_detect_bird() {
+        assert!(detect(DUMP));
+        assert!(detect(
+            "Table master6:\n2001:db8::/32 unicast [p 10:00] * (100)\n"
+        ));
+        assert!(detect("1007-1.0.0.0/24 unicast [p 10:00] * (100)\n"));
+        assert!(!detect("inet.0: 10 destinations, 12 routes\n"));
+    }
+
+    #[test]
+    fn test_parse_bird_dump() {
+        let elems = parse(DUMP);
+        assert_eq!(elems.len(), 3, "the static route is skipped");
+
+        let first = &elems[0];
+        assert_eq!(first.prefix.to_string(), "1.0.0.0/24");
+        assert_eq!(first.peer_ip.to_string(), "192.0.2.1");
+        assert_eq!(first.peer_asn, Asn::from(13335u32));
+        assert_eq!(first.origin, Some(Origin::IGP));
+        assert_eq!(first.med, Some(10));
+        assert_eq!(first.local_pref, Some(200));
+        assert_eq!(first.timestamp, 1704067200.0);
+        assert_eq!(
+            first.communities,
+            Some(vec![
+                MetaCommunity::Plain(Community::Custom(Asn::from(13335u32), 10)),
+                MetaCommunity::Plain(Community::NoExport),
+                MetaCommunity::Large(LargeCommunity::new(13335, [1, 2])),
+            ])
+        );
+
+        let second = &elems[1];
+        assert_eq!(second.prefix.to_string(), "1.0.0.0/24");
+        assert_eq!(second.peer_ip.to_string(), "192.0.2.2");
+        assert_eq!(second.peer_asn, Asn::from(6939u32));
+        assert_eq!(second.origin, Some(Origin::INCOMPLETE));
+        assert_eq!(second.origin_asns, Some(vec![Asn::from(13335u32)]));
+        assert_eq!(
+            second.as_path.as_ref().map(|path| path.to_string()),
+            Some("6939 64512 64513 13335".to_string())
+        );
+        assert!(second.communities.is_none());
+
+        let v6 = &elems[2];
+        assert_eq!(v6.prefix.to_string(), "2001:db8::/32");
+        assert_eq!(
+            v6.next_hop.map(|ip| ip.to_string()),
+            Some("2001:db8::1".to_string())
+        );
+        assert_eq!(v6.peer_ip.to_string(), "2001:db8::1");
+    }
+
+    #[test]
+    fn test_parse_bird_reply_codes() {
+        let dump = "\
+0001 BIRD 2.0.12 ready.
+1007-Table master4:
+ 1.0.0.0/24           unicast [peer1 2024-01-01] * (100) [AS13335i]
+1008-\tType: BGP univ
+ \tBGP.as_path: 13335
+ \tBGP.next_hop: 192.0.2.1
+0000
+";
+        let elems = parse(dump);
+        assert_eq!(elems.len(), 1);
+        assert_eq!(elems[0].peer_ip.to_string(), "192.0.2.1");
+    }
+}
@@ -0,0 +1,274 @@
+//! FRR `show bgp ipv4 unicast json` parser.
+//!
+//! ```text
+//! {
+//!  "vrfId": 0,
+//!  "vrfName": "default",
+//!  "routerId": "10.0.0.1",
+//!  "localAS": 65001,
+//!  "routes": { "1.0.0.0/24": [
+//!   {"valid":true,"bestpath":true,"pathFrom":"external","metric":0,"locPrf":100,
+//!    "peerId":"192.0.2.1","path":"13335","origin":"IGP",
+//!    "nexthops":[{"ip":"192.0.2.1","afi":"ipv4","used":true}]}
+//!  ] }
+//! }
+//! ```
+//!
+//! Both the summary schema above and the `detail json` schema (paths under
+//! `"paths"`, with `aspath`, `med`, `localpref`, `community` and
+//! `largeCommunity` objects) are accepted. The summary schema has no
+//! communities. FRR does not print the neighbor's AS: routes learned over
+//! eBGP get the first AS of the path, internal routes the local AS.
+
+use super::{parse_community, parse_origin_name, split_as_path, DumpRoute};
+use bgpkit_parser::models::BgpElem;
+use ipnet::IpNet;
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::io::BufRead;
+use std::net::IpAddr;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrrTable {
+    #[serde(rename = "localAS", default)]
+    local_as: Option<u32>,
+    #[serde(default)]
+    routes: FrrRouteMap,
+}
+
+/// `routes` object, kept in the order FRR printed the prefixes
+#[derive(Default)]
+struct FrrRouteMap(Vec<(String, FrrRoutes)>);
+
+impl<'de> Deserialize<'de> for FrrRouteMap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RouteMapVisitor;
+
+        impl<'de> Visitor<'de> for RouteMapVisitor {
+            type Value = FrrRouteMap;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of prefixes to paths")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut routes = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    routes.push(entry);
+                }
+                Ok(FrrRouteMap(routes))
+            }
+        }
+
+        deserializer.deserialize_map(RouteMapVisitor)
+    }
+}
+
+/// Paths of one prefix: a plain array, or an object with `paths` in `detail json`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FrrRoutes {
+    Paths(Vec<FrrPath>),
+    Detail { paths: Vec<FrrPath> },
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct FrrPath {
+    valid: Option<bool>,
+    path_from: Option<String>,
+    // Summary schema
+    path: Option<String>,
+    metric: Option<u32>,
+    loc_prf: Option<u32>,
+    peer_id: Option<String>,
+    // Detail schema
+    aspath: Option<FrrText>,
+    med: Option<u32>,
+    localpref: Option<u32>,
+    peer: Option<FrrPeer>,
+    community: Option<FrrText>,
+    large_community: Option<FrrText>,
+    // Both
+    origin: Option<String>,
+    nexthops: Vec<FrrNexthop>,
+}
+
+/// Attribute object whose `string` member holds FRR's textual rendering
+#[derive(Deserialize)]
+struct FrrText {
+    #[serde(default)]
+    string: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrrPeer {
+    #[serde(default)]
+    peer_id: Option<String>,
+    #[serde(rename = "type", default)]
+    peer_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FrrNexthop {
+    #[serde(default)]
+    ip: Option<IpAddr>,
+}
+
+impl FrrPath {
+    fn into_route(self, prefix: IpNet, local_as: u32) -> DumpRoute {
+        let mut route = DumpRoute::new(prefix);
+        let as_path = self
+            .aspath
+            .map(|aspath| aspath.string)
+            .or(self.path)
+            .unwrap_or_default();
+        let (as_path, path_origin) = split_as_path(&as_path);
+        route.as_path = as_path;
+        route.origin = self
+            .origin
+            .as_deref()
+            .and_then(parse_origin_name)
+            .or(path_origin);
+        route.med = self.med.or(self.metric);
+        route.local_pref = self.localpref.or(self.loc_prf);
+        route.next_hop = self.nexthops.iter().find_map(|nexthop| nexthop.ip);
+
+        let (peer_id, peer_type) = match self.peer {
+            Some(peer) => (peer.peer_id, peer.peer_type),
+            None => (self.peer_id, self.path_from),
+        };
+        // Locally originated paths have `peerId` "(unspec)"
+        if let Some(peer_ip) = peer_id
+            .and_then(|peer_id| peer_id.parse().ok())
+            .or(route.next_hop)
+        {
+            route.peer_ip = peer_ip;
+        }
+        route.peer_asn = match peer_type.as_deref() {
+            Some("internal") => local_as,
+            _ => route.first_asn().unwrap_or(local_as),
+        };
+
+        for communities in [self.community, self.large_community].into_iter().flatten() {
+            route.communities.extend(
+                communities
+                    .string
+                    .split_whitespace()
+                    .filter_map(parse_community),
+            );
+        }
+        route
+    }
+}
+
+pub(super) fn parse_frr_json<R: BufRead>(
+    reader: R,
+    timestamp: f64,
+) -> std::io::Result<Vec<BgpElem>> {
+    let table: FrrTable = serde_json::from_reader(reader)?;
+    let local_as = table.local_as.unwrap_or(0);
+
+    let mut elems = Vec::new();
+    for (prefix, routes) in table.routes.0 {
+        let Ok(prefix) = prefix.parse::<IpNet>() else {
+            continue;
+        };
+        let paths = match routes {
+            FrrRoutes::Paths(paths) | FrrRoutes::Detail { paths } => paths,
+        };
+        elems.extend(
+            paths
+                .into_iter()
+                .filter(|path| path.valid != Some(false))
+                .map(|path| path.into_route(prefix, local_as).into_elem(timestamp)),
+        );
+    }
+    Ok(elems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgpkit_parser::models::{Asn, Community, LargeCommunity, MetaCommunity, Origin};
+
+    fn parse(dump: &str) -> Vec<BgpElem> {
+        match parse_frr_json(dump.as_bytes(), 0.0) {
+            Ok(elems) => elems,
+            Err(error) => panic!("FRR JSON parsing failed: {error}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_frr_summary_json() {
+        let dump = r#"{
+ "vrfId": 0,
+ "vrfName": "default",
+ "tableVersion": 3,
+ "routerId": "10.0.0.1",
+ "defaultLocPrf": 100,
+ "localAS": 65001,
+ "routes": { "10.0.0.0/8": [
+  {"valid":true,"bestpath":true,"pathFrom":"external","prefix":"10.0.0.0","prefixLen":8,"network":"10.0.0.0/8","metric":5,"locPrf":150,"weight":0,"peerId":"192.0.2.1","path":"13335 6939","origin":"IGP","nexthops":[{"ip":"192.0.2.1","afi":"ipv4","used":true}]},
+  {"valid":true,"pathFrom":"internal","network":"10.0.0.0/8","locPrf":100,"peerId":"10.0.0.2","path":"","origin":"incomplete","nexthops":[{"ip":"10.0.0.2","afi":"ipv4","used":true}]}
+ ],"1.0.0.0/24": [
+  {"valid":true,"pathFrom":"external","peerId":"192.0.2.1","path":"13335","origin":"IGP","nexthops":[{"ip":"192.0.2.1","afi":"ipv4","used":true}]}
+ ] }
+}"#;
+        let elems = parse(dump);
+        assert_eq!(elems.len(), 3);
+        assert_eq!(elems[0].prefix.to_string(), "10.0.0.0/8");
+        assert_eq!(elems[0].peer_ip.to_string(), "192.0.2.1");
+        assert_eq!(elems[0].peer_asn, Asn::from(13335u32));
+        assert_eq!(elems[0].med, Some(5));
+        assert_eq!(elems[0].local_pref, Some(150));
+        assert_eq!(elems[0].origin, Some(Origin::IGP));
+        assert_eq!(elems[0].origin_asns, Some(vec![Asn::from(6939u32)]));
+        assert_eq!(elems[1].peer_asn, Asn::from(65001u32));
+        assert_eq!(elems[1].origin, Some(Origin::INCOMPLETE));
+        assert_eq!(
+            elems[2].prefix.to_string(),
+            "1.0.0.0/24",
+            "file order is kept"
+        );
+    }
+
+    #[test]
+    fn test_parse_frr_detail_json() {
+        let dump = r#"{
+ "vrfId": 0,
+ "vrfName": "default",
+ "routerId": "10.0.0.1",
+ "localAS": 65001,
+ "routes": { "2001:db8::/32": {
+  "prefix": "2001:db8::/32",
+  "paths": [
+   {"aspath":{"string":"64500 {64511,64512}","segments":[],"length":2},"origin":"IGP","med":20,"localpref":120,"valid":true,
+    "community":{"string":"64500:1 no-export","list":["64500:1","noExport"]},
+    "largeCommunity":{"string":"64500:1:2","list":["64500:1:2"]},
+    "nexthops":[{"ip":"2001:db8::1","afi":"ipv6","used":true}],
+    "peer":{"peerId":"2001:db8::1","routerId":"192.0.2.9","type":"external"}},
+   {"aspath":{"string":"64501"},"origin":"IGP","valid":false,
+    "peer":{"peerId":"2001:db8::2","type":"external"}}
+  ] } }
+}"#;
+        let elems = parse(dump);
+        assert_eq!(elems.len(), 1, "invalid paths are skipped");
+        let elem = &elems[0];
+        assert_eq!(elem.peer_ip.to_string(), "2001:db8::1");
+        assert_eq!(elem.peer_asn, Asn::from(64500u32));
+        assert_eq!(elem.med, Some(20));
+        assert_eq!(elem.local_pref, Some(120));
+        assert_eq!(
+            elem.communities,
+            Some(vec![
+                MetaCommunity::Plain(Community::Custom(Asn::from(64500u32), 1)),
+                MetaCommunity::Plain(Community::NoExport),
+                MetaCommunity::Large(LargeCommunity::new(64500, [1, 2])),
+            ])
+        );
+    }
+}
@@ -0,0 +1,449 @@
+//! Junos `show route detail` and `show route detail | display json` parsers.
+//!
+//! ```text
+//! inet.0: 3 destinations, 4 routes (3 active, 0 holddown, 0 hidden)
+//! 1.0.0.0/24 (2 entries, 1 announced)
+//!         *BGP    Preference: 170/-101
+//!                 Source: 192.0.2.1
+//!                 Next hop: 192.0.2.1 via xe-0/0/0.0, selected
+//!                 Local AS: 65001 Peer AS: 13335
+//!                 Age: 3w0d 12:00:00     Metric: 10
+//!                 AS path: 13335 I
+//!                 Communities: 13335:10 large:13335:1:2
+//!                 Localpref: 100
+//!          BGP    Preference: 170/-101
+//!                 ...
+//! ```
+//!
+//! Only BGP entries are read; static, direct and other protocol entries are
+//! skipped. The peer is the entry's `Source` and `Peer AS`, and the next hop
+//! the BGP protocol next hop when shown, the forwarding next hop otherwise.
+
+use super::{parse_community, split_as_path, DumpRoute};
+use bgpkit_parser::models::BgpElem;
+use ipnet::IpNet;
+use serde::Deserialize;
+use std::io::BufRead;
+use std::net::IpAddr;
+
+/// Whether the start of a dump looks like Junos `show route` output
+pub(super) fn detect(text: &str) -> bool {
+    text.lines()
+        .take(4)
+        .any(|line| line.contains(".0: ") && line.contains(" destinations, "))
+}
+
+/// First token of `value` as an IP address
+fn first_ip(value: &str) -> Option<IpAddr> {
+    value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .next()
+        .and_then(|ip| ip.parse().ok())
+}
+
+/// Value of `Metric:` in a line, which Junos may print after other fields
+fn metric_in_line(line: &str) -> Option<u32> {
+    let mut tokens = line.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if token == "Metric:" {
+            return tokens.next()?.parse().ok();
+        }
+    }
+    None
+}
+
+/// Entry being read, with the Junos-specific bits needed to finish it
+struct JunosEntry {
+    route: DumpRoute,
+    protocol_next_hop: Option<IpAddr>,
+}
+
+impl JunosEntry {
+    fn finish(mut self, timestamp: f64) -> BgpElem {
+        if self.protocol_next_hop.is_some() {
+            self.route.next_hop = self.protocol_next_hop;
+        }
+        self.route.into_elem(timestamp)
+    }
+}
+
+pub(super) fn parse_junos_text<R: BufRead>(
+    mut reader: R,
+    timestamp: f64,
+) -> std::io::Result<Vec<BgpElem>> {
+    let mut elems = Vec::new();
+    let mut prefix: Option<IpNet> = None;
+    let mut current: Option<JunosEntry> = None;
+    let mut buf = String::new();
+
+    while reader.read_line(&mut buf)? > 0 {
+        let line = buf.trim_end().to_string();
+        buf.clear();
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        // `PREFIX (N entries, M announced)` starts a destination
+        if !line.starts_with(char::is_whitespace) {
+            if let Some(entry) = current.take() {
+                elems.push(entry.finish(timestamp));
+            }
+            prefix = trimmed
+                .split_whitespace()
+                .next()
+                .and_then(|prefix| prefix.parse().ok());
+            continue;
+        }
+
+        // `*BGP    Preference: 170/-101` starts an entry
+        if trimmed.contains("Preference:") {
+            if let Some(entry) = current.take() {
+                elems.push(entry.finish(timestamp));
+            }
+            let protocol = trimmed.trim_start_matches(['*', '-', '+']);
+            if let (Some(prefix), true) = (prefix, protocol.starts_with("BGP ")) {
+                current = Some(JunosEntry {
+                    route: DumpRoute::new(prefix),
+                    protocol_next_hop: None,
+                });
+            }
+            continue;
+        }
+
+        let Some(entry) = current.as_mut() else {
+            continue;
+        };
+        let route = &mut entry.route;
+        if let Some(source) = trimmed.strip_prefix("Source: ") {
+            if let Some(peer_ip) = first_ip(source) {
+                route.peer_ip = peer_ip;
+            }
+        } else if let Some(next_hop) = trimmed.strip_prefix("Next hop: ") {
+            route.next_hop = route.next_hop.or(first_ip(next_hop));
+        } else if let Some(next_hop) = trimmed.strip_prefix("Protocol next hop: ") {
+            entry.protocol_next_hop = entry.protocol_next_hop.or(first_ip(next_hop));
+        } else if let Some(as_path) = trimmed.strip_prefix("AS path: ") {
+            // Junos appends notes such as `(Originator)` or `Aggregator: ...`
+            let as_path = as_path.split(" (").next().unwrap_or_default();
+            let as_path = as_path.split("Aggregator").next().unwrap_or_default();
+            let (as_path, origin) = split_as_path(as_path);
+            route.as_path = as_path;
+            route.origin = origin;
+        } else if let Some(communities) = trimmed.strip_prefix("Communities: ") {
+            route
+                .communities
+                .extend(communities.split_whitespace().filter_map(parse_community));
+        } else if let Some(local_pref) = trimmed.strip_prefix("Localpref: ") {
+            route.local_pref = local_pref.trim().parse().ok();
+        } else if let Some(peer_as) = trimmed.split("Peer AS: ").nth(1) {
+            route.peer_asn = peer_as.trim().parse().unwrap_or_default();
+        }
+        if let Some(med) = metric_in_line(trimmed) {
+            route.med = Some(med);
+        }
+    }
+    if let Some(entry) = current.take() {
+        elems.push(entry.finish(timestamp));
+    }
+
+    Ok(elems)
+}
+
+// ── `| display json` ───────────────────────────────────────────────
+
+/// Junos wraps every value as `[{"data": "..."}]`
+#[derive(Deserialize)]
+struct JunosData {
+    data: String,
+}
+
+type JunosValue = Vec<JunosData>;
+
+fn first_data(value: &JunosValue) -> Option<&str> {
+    value.first().map(|value| value.data.trim())
+}
+
+#[derive(Deserialize)]
+struct JunosJson {
+    #[serde(rename = "route-information", default)]
+    route_information: Vec<JunosRouteInformation>,
+}
+
+#[derive(Deserialize)]
+struct JunosRouteInformation {
+    #[serde(rename = "route-table", default)]
+    route_table: Vec<JunosRouteTable>,
+}
+
+#[derive(Deserialize)]
+struct JunosRouteTable {
+    #[serde(default)]
+    rt: Vec<JunosRt>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct JunosRt {
+    #[serde(default)]
+    rt_destination: JunosValue,
+    #[serde(default)]
+    rt_prefix_length: JunosValue,
+    #[serde(default)]
+    rt_entry: Vec<JunosRtEntry>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case", default)]
+struct JunosRtEntry {
+    protocol_name: JunosValue,
+    /// BGP peer address (`Source:` in text output)
+    gateway: JunosValue,
+    learned_from: JunosValue,
+    peer_as: JunosValue,
+    as_path: JunosValue,
+    local_preference: JunosValue,
+    med: JunosValue,
+    metric: JunosValue,
+    nh: Vec<JunosNextHop>,
+    protocol_nh: Vec<JunosNextHop>,
+    communities: Vec<JunosCommunities>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct JunosNextHop {
+    to: JunosValue,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct JunosCommunities {
+    community: JunosValue,
+}
+
+impl JunosRt {
+    fn prefix(&self) -> Option<IpNet> {
+        let destination = first_data(&self.rt_destination)?;
+        if destination.contains('/') {
+            return destination.parse().ok();
+        }
+        let length = first_data(&self.rt_prefix_length)?;
+        format!("{destination}/{length}").parse().ok()
+    }
+}
+
+impl JunosRtEntry {
+    fn into_route(self, prefix: IpNet) -> DumpRoute {
+        let mut route = DumpRoute::new(prefix);
+        let next_hop = |hops: &[JunosNextHop]| {
+            hops.iter()
+                .find_map(|hop| first_data(&hop.to).and_then(first_ip))
+        };
+        route.next_hop = next_hop(&self.protocol_nh).or_else(|| next_hop(&self.nh));
+        if let Some(peer_ip) = [&self.gateway, &self.learned_from]
+            .into_iter()
+            .find_map(|value| first_data(value).and_then(first_ip))
+            .or(route.next_hop)
+        {
+            route.peer_ip = peer_ip;
+        }
+        route.peer_asn = first_data(&self.peer_as)
+            .and_then(|asn| asn.parse().ok())
+            .unwrap_or_default();
+        if let Some(as_path) = first_data(&self.as_path) {
+            let as_path = as_path.strip_prefix("AS path:").unwrap_or(as_path);
+            let as_path = as_path.split(" (").next().unwrap_or_default();
+            let (as_path, origin) = split_as_path(as_path);
+            route.as_path = as_path;
+            route.origin = origin;
+        }
+        route.local_pref = first_data(&self.local_preference).and_then(|pref| pref.parse().ok());
+        route.med = first_data(&self.med)
+            .or(first_data(&self.metric))
+            .and_then(|med| med.parse().ok());
+        route.communities = self
+            .communities
+            .iter()
+            .flat_map(|communities| &communities.community)
+            .filter_map(|community| parse_community(&community.data))
+            .collect();
+        route
+    }
+}
+
+pub(super) fn parse_junos_json<R: BufRead>(
+    reader: R,
+    timestamp: f64,
+) -> std::io::Result<Vec<BgpElem>> {
+    let dump: JunosJson = serde_json::from_reader(reader)?;
+
+    let mut elems = Vec::new();
+    let rts = dump
+        .route_information
+        .into_iter()
+        .flat_map(|info| info.route_table)
+        .flat_map(|table| table.rt);
+    for rt in rts {
+        let Some(prefix) = rt.prefix() else {
+            continue;
+        };
+        elems.extend(
+            rt.rt_entry
+                .into_iter()
+                .filter(|entry| first_data(&entry.protocol_name) == Some("BGP"))
+                .map(|entry| entry.into_route(prefix).into_elem(timestamp)),
+        );
+    }
+    Ok(elems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgpkit_parser::models::{Asn, Community, LargeCommunity, MetaCommunity, Origin};
+
+    const TEXT_DUMP: &str = "
+inet.0: 3 destinations, 4 routes (3 active, 0 holddown, 0 hidden)
+1.0.0.0/24 (2 entries, 1 announced)
+        *BGP    Preference: 170/-101
+                Next hop type: Router, Next hop index: 0
+                Address: 0x9b5e1c4
+                Next-hop reference count: 4
+                Source: 192.0.2.1
+                Next hop: 192.0.2.1 via xe-0/0/0.0, selected
+                Session Id: 0x0
+                State: <Active Ext>
+                Local AS: 65001 Peer AS: 13335
+                Age: 3w0d 12:00:00 \tMetric: 10
+                Validation State: unverified
+                Task: BGP_13335.192.0.2.1
+                AS path: 13335 I
+                Communities: 13335:10 no-export large:13335:1:2 target:65000:1
+                Accepted
+                Localpref: 200
+                Router ID: 192.0.2.1
+         BGP    Preference: 170/-101
+                Source: 10.0.0.2
+                Next hop: 10.1.1.1 via ae0.0
+                Protocol next hop: 10.0.0.2
+                Local AS: 65001 Peer AS: 65001
+                AS path: 6939 {64512 64513} 13335 ? (Originator)
+                Localpref: 100
+10.0.0.0/8 (1 entry, 1 announced)
+        *Static Preference: 5
+                Next hop: 10.9.9.9 via ae1.0, selected
+2001:db8::/32 (1 entry, 1 announced)
+        *BGP    Preference: 170/-101
+                Source: 2001:db8::1
+                Next hop: 2001:db8::1 via xe-0/0/1.0, selected
+                Local AS: 65001 Peer AS: 64500
+                AS path: 64500 I
+";
+
+    const JSON_DUMP: &str = r#"{
+    "route-information" : [
+    {
+        "attributes" : {"xmlns" : "http://xml.juniper.net/junos/21.4R0/junos-routing"},
+        "route-table" : [
+        {
+            "table-name" : [{"data" : "inet.0"}],
+            "rt" : [
+            {
+                "attributes" : {"junos:style" : "detail"},
+                "rt-destination" : [{"data" : "1.0.0.0"}],
+                "rt-prefix-length" : [{"data" : "24"}],
+                "rt-entry" : [
+                {
+                    "active-tag" : [{"data" : "*"}],
+                    "protocol-name" : [{"data" : "BGP"}],
+                    "gateway" : [{"data" : "192.0.2.1"}],
+                    "nh" : [{"to" : [{"data" : "192.0.2.1"}], "via" : [{"data" : "xe-0/0/0.0"}]}],
+                    "local-as" : [{"data" : "65001"}],
+                    "peer-as" : [{"data" : "13335"}],
+                    "as-path" : [{"data" : "13335 I\n"}],
+                    "communities" : [{"community" : [{"data" : "13335:10"}, {"data" : "large:13335:1:2"}]}],
+                    "local-preference" : [{"data" : "200"}],
+                    "med" : [{"data" : "10"}]
+                },
+                {
+                    "protocol-name" : [{"data" : "Static"}],
+                    "nh" : [{"to" : [{"data" : "10.9.9.9"}]}]
+                }
+                ]
+            }
+            ]
+        }
+        ]
+    }
+    ]
+}"#;
+
+    fn expected_communities() -> Vec<MetaCommunity> {
+        vec![
+            MetaCommunity::Plain(Community::Custom(Asn::from(13335u32), 10)),
+            MetaCommunity::Large(LargeCommunity::new(13335, [1, 2])),
+        ]
+    }
+
+    #[test]
+    fn test_detect_junos() {
+        assert!(detect(TEXT_DUMP.trim_start()));
+        assert!(!detect("BIRD 2.0.12 ready.\n"));
+    }
+
+    #[test]
+    fn test_parse_junos_text() {
+        let elems = match parse_junos_text(TEXT_DUMP.as_bytes(), 0.0) {
+            Ok(elems) => elems,
+            Err(error) => panic!("Junos text parsing failed: {error}"),
+        };
+        assert_eq!(elems.len(), 3, "the static route is skipped");
+
+        let first = &elems[0];
+        assert_eq!(first.prefix.to_string(), "1.0.0.0/24");
+        assert_eq!(first.peer_ip.to_string(), "192.0.2.1");
+        assert_eq!(first.peer_asn, Asn::from(13335u32));
+        assert_eq!(
+            first.next_hop.map(|ip| ip.to_string()),
+            Some("192.0.2.1".to_string())
+        );
+        assert_eq!(first.med, Some(10));
+        assert_eq!(first.local_pref, Some(200));
+        assert_eq!(first.origin, Some(Origin::IGP));
+        let mut communities = expected_communities();
+        communities.insert(1, MetaCommunity::Plain(Community::NoExport));
+        assert_eq!(first.communities, Some(communities));
+
+        let second = &elems[1];
+        assert_eq!(second.peer_ip.to_string(), "10.0.0.2");
+        assert_eq!(second.peer_asn, Asn::from(65001u32));
+        assert_eq!(
+            second.next_hop.map(|ip| ip.to_string()),
+            Some("10.0.0.2".to_string())
+        );
+        assert_eq!(second.origin, Some(Origin::INCOMPLETE));
+        assert_eq!(second.origin_asns, Some(vec![Asn::from(13335u32)]));
+        assert_eq!(second.med, None);
+
+        assert_eq!(elems[2].prefix.to_string(), "2001:db8::/32");
+        assert_eq!(elems[2].peer_asn, Asn::from(64500u32));
+    }
+
+    #[test]
+    fn test_parse_junos_json() {
+        let elems = match parse_junos_json(JSON_DUMP.as_bytes(), 0.0) {
+            Ok(elems) => elems,
+            Err(error) => panic!("Junos JSON parsing failed: {error}"),
+        };
+        assert_eq!(elems.len(), 1);
+        let elem = &elems[0];
+        assert_eq!(elem.prefix.to_string(), "1.0.0.0/24");
+        assert_eq!(elem.peer_ip.to_string(), "192.0.2.1");
+        assert_eq!(elem.peer_asn, Asn::from(13335u32));
+        assert_eq!(elem.local_pref, Some(200));
+        assert_eq!(elem.med, Some(10));
+        assert_eq!(elem.origin, Some(Origin::IGP));
+        assert_eq!(elem.communities, Some(expected_communities()));
+    }
+}
@@ -1,4 +1,21 @@
-//! Cisco `sh ip bgp` text dump parser.
+//! Router BGP table dump parser.
+//!
+//! Recognizes ([`detect_text_dump_format`]) and parses the table dumps of
+//! several router implementations into [`BgpElem`]s:
+//!
+//! - Cisco `sh ip bgp` fixed-width output (this module)
+//! - BIRD 2 `show route all` ([`TextDumpFormat::Bird`])
+//! - FRR `show bgp ipv4 unicast json`, with or without `detail`
+//!   ([`TextDumpFormat::FrrJson`])
+//! - Junos `show route detail` and `show route detail | display json`
+//!   ([`TextDumpFormat::JunosText`], [`TextDumpFormat::JunosJson`])
+//!
+//! Next hop, origin, AS path, MED and local preference are kept for every
+//! format; communities are kept where the dump shows them (all formats but
+//! Cisco). Dumps carry no timestamps, so all elements get the timestamp
+//! passed to [`parse_text_dump_with_timestamp`].
+//!
+//! # Cisco
 //!
 //! Parses the fixed-width column format produced by Cisco IOS routers.
 //! Extracts field boundaries from the table header so numeric attributes do not
@@ -25,6 +42,10 @@ use ipnet::IpNet;
 use std::io::{BufRead, Read};
 use std::net::IpAddr;
 
+mod bird;
+mod frr;
+mod junos;
+
 /// Byte offsets for the Cisco `Next Hop`, `Metric`, `LocPrf`, `Weight`, and
 /// `Path` columns, respectively.
 type ColumnPositions = (usize, usize, usize, usize, usize);
@@ -40,17 +61,59 @@ pub struct TextDumpHeader {
 
 // ── Detection ──────────────────────────────────────────────────────
 
-/// Detect whether a reader contains a Cisco `sh ip bgp` text dump.
+/// Router table dump formats understood by [`parse_text_dump`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDumpFormat {
+    /// Cisco `sh ip bgp` fixed-width table
+    Cisco,
+    /// BIRD 2 `show route all`
+    Bird,
+    /// FRR `show bgp ipv4 unicast json` (or `ipv6`, optionally `detail`)
+    FrrJson,
+    /// Junos `show route detail`
+    JunosText,
+    /// Junos `show route detail | display json`
+    JunosJson,
+}
+
+/// Detect the table dump format from the first bytes of a file.
+pub fn detect_text_dump_format(buf: &[u8]) -> Option<TextDumpFormat> {
+    let first = *buf.first()?;
+    if first.is_ascii_graphic()
+        && std::str::from_utf8(&buf[..buf.len().min(128)]).is_ok_and(|s| {
+            s.contains("BGP table") || s.contains("Next Hop") || s.contains("Status codes:")
+        })
+    {
+        return Some(TextDumpFormat::Cisco);
+    }
+
+    let text = String::from_utf8_lossy(buf);
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('{') {
+        if trimmed.contains("\"route-information\"") {
+            return Some(TextDumpFormat::JunosJson);
+        }
+        if trimmed.contains("\"routerId\"") || trimmed.contains("\"vrfName\"") {
+            return Some(TextDumpFormat::FrrJson);
+        }
+        return None;
+    }
+    if bird::detect(trimmed) {
+        return Some(TextDumpFormat::Bird);
+    }
+    if junos::detect(trimmed) {
+        return Some(TextDumpFormat::JunosText);
+    }
+    None
+}
+
+/// Detect whether a reader contains a router table dump in any supported format.
 pub fn detect_text_dump<R: Read>(mut reader: R) -> std::io::Result<(bool, Vec<u8>)> {
     let mut buf = vec![0u8; 256];
     let n = reader.read(&mut buf)?;
     buf.truncate(n);
 
-    let is_text = n > 0
-        && buf[0].is_ascii_graphic()
-        && std::str::from_utf8(&buf[..n.min(128)]).is_ok_and(|s| {
-            s.contains("BGP table") || s.contains("Next Hop") || s.contains("Status codes:")
-        });
+    let is_text = detect_text_dump_format(&buf).is_some();
 
     Ok((is_text, buf))
 }
@@ -303,6 +366,152 @@ fn as_path_from_tokens(tokens: &[String]) -> AsPath {
     }
 }
 
+/// Split a router's AS path text into ASNs and a trailing origin code.
+///
+/// Accepts the BIRD, FRR and Junos spellings: whitespace-separated ASNs,
+/// AS-sets in `{}` (flattened, as in [`as_path_from_tokens`]), confederation
+/// segments in `()` and Junos' bracketed local AS (`[65001]`, dropped), and a
+/// final `i`/`e`/`?` origin letter in either case.
+fn split_as_path(text: &str) -> (Vec<String>, Option<Origin>) {
+    let mut tokens = Vec::new();
+    let mut origin = None;
+    let mut in_local_as = false;
+    for token in text.split(|c: char| c.is_whitespace() || c == ',') {
+        if token.starts_with('[') {
+            in_local_as = !token.ends_with(']');
+            continue;
+        }
+        if in_local_as {
+            in_local_as = !token.ends_with(']');
+            continue;
+        }
+        let token = token.trim_matches(|c| matches!(c, '{' | '}' | '(' | ')'));
+        match token {
+            "" => {}
+            "i" | "I" => origin = Some(Origin::IGP),
+            "e" | "E" => origin = Some(Origin::EGP),
+            "?" => origin = Some(Origin::INCOMPLETE),
+            _ if token.parse::<u32>().is_ok() => tokens.push(token.to_string()),
+            _ => {}
+        }
+    }
+    (tokens, origin)
+}
+
+/// Parse an origin attribute spelled out as `IGP`, `EGP` or `incomplete`.
+fn parse_origin_name(name: &str) -> Option<Origin> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "igp" | "i" => Some(Origin::IGP),
+        "egp" | "e" => Some(Origin::EGP),
+        "incomplete" | "?" => Some(Origin::INCOMPLETE),
+        _ => None,
+    }
+}
+
+/// Parse a standard (`ASN:VALUE`), large (`ASN:DATA1:DATA2`, optionally with
+/// Junos' `large:` prefix) or well-known community.
+///
+/// Extended communities (`target:...`, `origin:...`) have no textual form
+/// shared between implementations and are skipped.
+fn parse_community(text: &str) -> Option<MetaCommunity> {
+    let text = text.trim();
+    match text {
+        "no-export" => return Some(MetaCommunity::Plain(Community::NoExport)),
+        "no-advertise" => return Some(MetaCommunity::Plain(Community::NoAdvertise)),
+        "no-export-subconfed" | "no-export-sub-confed" | "local-AS" => {
+            return Some(MetaCommunity::Plain(Community::NoExportSubConfed))
+        }
+        _ => {}
+    }
+    let text = text.strip_prefix("large:").unwrap_or(text);
+    let parts: Vec<&str> = text.split(':').collect();
+    match parts.as_slice() {
+        // Well-known communities map to the variants MRT parsing produces
+        [asn, value] => Some(MetaCommunity::Plain(
+            match (asn.parse::<u32>().ok()?, value.parse::<u16>().ok()?) {
+                (65535, 65281) => Community::NoExport,
+                (65535, 65282) => Community::NoAdvertise,
+                (65535, 65283) => Community::NoExportSubConfed,
+                (asn, value) => Community::Custom(Asn::from(asn), value),
+            },
+        )),
+        [global, data1, data2] => Some(MetaCommunity::Large(LargeCommunity::new(
+            global.parse().ok()?,
+            [data1.parse().ok()?, data2.parse().ok()?],
+        ))),
+        _ => None,
+    }
+}
+
+/// One route read from a table dump, in the attributes all formats share
+#[derive(Debug, Clone)]
+struct DumpRoute {
+    prefix: IpNet,
+    peer_ip: IpAddr,
+    peer_asn: u32,
+    next_hop: Option<IpAddr>,
+    as_path: Vec<String>,
+    origin: Option<Origin>,
+    local_pref: Option<u32>,
+    med: Option<u32>,
+    communities: Vec<MetaCommunity>,
+}
+
+impl DumpRoute {
+    fn new(prefix: IpNet) -> Self {
+        Self {
+            prefix,
+            peer_ip: IpAddr::from([0, 0, 0, 0]),
+            peer_asn: 0,
+            next_hop: None,
+            as_path: Vec::new(),
+            origin: None,
+            local_pref: None,
+            med: None,
+            communities: Vec::new(),
+        }
+    }
+
+    /// First ASN of the AS path: the neighbor's AS for routes learned over eBGP
+    fn first_asn(&self) -> Option<u32> {
+        self.as_path.first().and_then(|asn| asn.parse().ok())
+    }
+
+    fn into_elem(self, timestamp: f64) -> BgpElem {
+        let as_path = as_path_from_tokens(&self.as_path);
+        let origin_asns: Option<Vec<Asn>> = as_path
+            .segments
+            .last()
+            .and_then(|seg| match seg {
+                AsPathSegment::AsSequence(asns) => asns.last().copied(),
+                _ => None,
+            })
+            .map(|asn| vec![asn]);
+
+        BgpElem {
+            timestamp,
+            elem_type: ElemType::ANNOUNCE,
+            peer_ip: self.peer_ip,
+            peer_asn: Asn::from(self.peer_asn),
+            prefix: NetworkPrefix::new(self.prefix, None),
+            next_hop: self.next_hop,
+            as_path: Some(as_path),
+            origin_asns,
+            origin: self.origin,
+            local_pref: self.local_pref,
+            med: self.med,
+            communities: (!self.communities.is_empty()).then_some(self.communities),
+            atomic: false,
+            aggr_asn: None,
+            aggr_ip: None,
+            only_to_customer: None,
+            unknown: None,
+            deprecated: None,
+            peer_bgp_id: None,
+        }
+    }
+}
+
 fn entry_to_elem(
     entry: &RouteEntry,
     prefix_str: &str,
@@ -315,44 +524,17 @@ fn entry_to_elem(
         Err(_) => return None,
     };
 
-    let network_prefix = NetworkPrefix::new(prefix, None);
-    let next_hop: Option<IpAddr> = entry.next_hop.parse().ok();
-    let as_path = Some(as_path_from_tokens(&entry.as_path));
-    let origin = entry.origin;
-    let local_pref = entry.local_pref;
-    let med = entry.metric;
-
-    let origin_asns: Option<Vec<Asn>> = as_path.as_ref().and_then(|ap| {
-        ap.segments
-            .last()
-            .and_then(|seg| match seg {
-                AsPathSegment::AsSequence(asns) => asns.last().copied(),
-                _ => None,
-            })
-            .map(|asn| vec![asn])
-    });
-
-    Some(BgpElem {
-        timestamp,
-        elem_type: ElemType::ANNOUNCE,
+    let route = DumpRoute {
         peer_ip,
-        peer_asn: Asn::from(peer_asn),
-        prefix: network_prefix,
-        next_hop,
-        as_path,
-        origin_asns,
-        origin,
-        local_pref,
-        med,
-        communities: None,
-        atomic: false,
-        aggr_asn: None,
-        aggr_ip: None,
-        only_to_customer: None,
-        unknown: None,
-        deprecated: None,
-        peer_bgp_id: None,
-    })
+        peer_asn,
+        next_hop: entry.next_hop.parse().ok(),
+        as_path: entry.as_path.clone(),
+        origin: entry.origin,
+        local_pref: entry.local_pref,
+        med: entry.metric,
+        ..DumpRoute::new(prefix)
+    };
+    Some(route.into_elem(timestamp))
 }
 
 // ── Timestamp inference ────────────────────────────────────────────
@@ -446,10 +628,25 @@ pub fn parse_text_dump<R: BufRead>(reader: R) -> std::io::Result<Vec<BgpElem>> {
     parse_text_dump_with_timestamp(reader, 0.0)
 }
 
+/// Parse a table dump in any [`TextDumpFormat`], giving every element `timestamp`.
+///
+/// The format is detected from the start of the reader; dumps that match no
+/// format are parsed as Cisco tables.
 pub fn parse_text_dump_with_timestamp<R: BufRead>(
     mut reader: R,
     timestamp: f64,
 ) -> std::io::Result<Vec<BgpElem>> {
+    let format = detect_text_dump_format(reader.fill_buf()?).unwrap_or(TextDumpFormat::Cisco);
+    match format {
+        TextDumpFormat::Cisco => parse_cisco_dump(reader, timestamp),
+        TextDumpFormat::Bird => bird::parse_bird_dump(reader, timestamp),
+        TextDumpFormat::FrrJson => frr::parse_frr_json(reader, timestamp),
+        TextDumpFormat::JunosText => junos::parse_junos_text(reader, timestamp),
+        TextDumpFormat::JunosJson => junos::parse_junos_json(reader, timestamp),
+    }
+}
+
+fn parse_cisco_dump<R: BufRead>(mut reader: R, timestamp: f64) -> std::io::Result<Vec<BgpElem>> {
     let header = parse_header(&mut reader)?;
     let column_positions = match header.column_positions {
         Some(positions) => positions,
@@ -555,7 +752,7 @@ mod tests {
 
     #[test]
     fn test_detect_text_dump_negative() {
-        let data = vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x0d, 0x00, 0x01];
+        let data = [0x00, 0x00, 0x00, 0x00, 0x00, 0x0d, 0x00, 0x01];
         let (is_text, _buf) = match detect_text_dump(&data[..]) {
             Ok(result) => result,
             Err(error) => panic!("text dump detection failed: {error}"),
@@ -563,6 +760,36 @@ mod tests {
         assert!(!is_text);
     }
 
+    #[test]
+    fn test_detect_text_dump_format() {
+        let cases = [
+            (
+                "BGP table version is 1, local router ID is 10.0.0.1\n",
+                Some(TextDumpFormat::Cisco),
+            ),
+            (
+                "BIRD 2.0.12 ready.\nTable master4:\n",
+                Some(TextDumpFormat::Bird),
+            ),
+            (
+                "{\n \"vrfId\": 0,\n \"vrfName\": \"default\",\n",
+                Some(TextDumpFormat::FrrJson),
+            ),
+            (
+                "\ninet.0: 3 destinations, 4 routes (3 active, 0 holddown, 0 hidden)\n",
+                Some(TextDumpFormat::JunosText),
+            ),
+            (
+                "{\n    \"route-information\" : [\n",
+                Some(TextDumpFormat::JunosJson),
+            ),
+            ("{\"type\":\"ris_message\",\"data\":{}}\n", None),
+        ];
+        for (dump, expected) in cases {
+            assert_eq!(detect_text_dump_format(dump.as_bytes()), expected, "{dump}");
+        }
+    }
+
     #[test]
     fn test_parse_header() {
         let preamble = "\
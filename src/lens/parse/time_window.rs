@@ -0,0 +1,308 @@
+//! Multiple time windows for `parse` and `search`.
+//!
+//! A single `start_ts`/`end_ts`/`duration` triple describes one contiguous
+//! time range. To look at the same filters around several separate incidents,
+//! a list of [`TimeWindowSpec`]s can be given instead, either as a repeated
+//! `--window` flag or as a `windows` array in a JSON filter file:
+//!
+//! ```text
+//! --window 2024-01-01T00:00:00Z/2024-01-01T02:00:00Z
+//! --window leak=2024-03-05T10:00:00Z/30m
+//! --window 1h/2024-06-01T12:00:00Z
+//! ```
+//!
+//! Each window is `[LABEL=]START/END`, `[LABEL=]START/DURATION` or
+//! `[LABEL=]DURATION/END`. Unlabeled windows are labeled by their 1-based
+//! position in the list. Both ends are inclusive, like `--start-ts` and
+//! `--end-ts`.
+//!
+//! Windows are resolved into [`TimeWindows`], which
+//!
+//! - merges overlapping windows into the time ranges listed from the broker
+//!   ([`TimeWindows::merged_ranges`]), so a file covering several windows is
+//!   only fetched once,
+//! - keeps elements inside at least one window ([`TimeWindows::contains`]),
+//! - tags each element with the labels of the windows it matched
+//!   ([`TimeWindows::labels_at`]).
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use super::ParseFilters;
+use crate::lens::time::TimeLens;
+
+/// One time window as written by the user.
+///
+/// Uses the same `start_ts`/`end_ts`/`duration` rules as [`ParseFilters`]:
+/// exactly two of the three must be set.
+///
+/// ```json
+/// { "label": "leak", "start_ts": "2024-03-05T10:00:00Z", "duration": "30m" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeWindowSpec {
+    /// Label used to tag matching elements; defaults to the window's position
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Start of the window (unix timestamp or time string)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_ts: Option<String>,
+    /// End of the window (unix timestamp or time string)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_ts: Option<String>,
+    /// Window length from `start_ts` or to `end_ts`, e.g. `30m`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<String>,
+}
+
+impl TimeWindowSpec {
+    /// Resolve the window into Unix timestamps, labeling it with `default_label`
+    /// if it has no label of its own.
+    pub fn resolve(&self, default_label: &str) -> Result<TimeWindow> {
+        let label = match self.label.as_deref().map(str::trim) {
+            Some("") => return Err(anyhow!("time window label cannot be empty")),
+            Some(label) => label.to_string(),
+            None => default_label.to_string(),
+        };
+        let filters = ParseFilters {
+            start_ts: self.start_ts.clone(),
+            end_ts: self.end_ts.clone(),
+            duration: self.duration.clone(),
+            ..Default::default()
+        };
+        let (start, end) = filters
+            .parse_start_end_strings()
+            .map_err(|e| anyhow!("invalid time window '{}': {}", label, e))?;
+        if start > end {
+            return Err(anyhow!(
+                "invalid time window '{}': ends before it starts",
+                label
+            ));
+        }
+        Ok(TimeWindow { label, start, end })
+    }
+}
+
+impl FromStr for TimeWindowSpec {
+    type Err = anyhow::Error;
+
+    /// Parse `[LABEL=]START/END`, `[LABEL=]START/DURATION` or `[LABEL=]DURATION/END`
+    fn from_str(s: &str) -> Result<Self> {
+        let (label, range) = match s.split_once('=') {
+            Some((label, range)) => (Some(label.trim().to_string()), range),
+            None => (None, s),
+        };
+        let (first, second) = range.split_once('/').ok_or_else(|| {
+            anyhow!(
+                "invalid time window '{}': expected [LABEL=]START/END or [LABEL=]START/DURATION",
+                s
+            )
+        })?;
+        let (first, second) = (first.trim(), second.trim());
+
+        let mut spec = TimeWindowSpec {
+            label,
+            ..Default::default()
+        };
+        if is_duration(first) {
+            spec.duration = Some(first.to_string());
+            spec.end_ts = Some(second.to_string());
+        } else if is_duration(second) {
+            spec.start_ts = Some(first.to_string());
+            spec.duration = Some(second.to_string());
+        } else {
+            spec.start_ts = Some(first.to_string());
+            spec.end_ts = Some(second.to_string());
+        }
+        Ok(spec)
+    }
+}
+
+/// Whether a window bound is a duration (`30m`) rather than a time string
+fn is_duration(value: &str) -> bool {
+    TimeLens::new().parse_time_string(value).is_err() && humantime::parse_duration(value).is_ok()
+}
+
+/// One resolved time window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    /// Label elements matching this window are tagged with
+    pub label: String,
+    /// Start of the window (Unix seconds, inclusive)
+    pub start: i64,
+    /// End of the window (Unix seconds, inclusive)
+    pub end: i64,
+}
+
+impl TimeWindow {
+    /// Whether `timestamp` falls inside the window
+    pub fn contains(&self, timestamp: f64) -> bool {
+        timestamp >= self.start as f64 && timestamp <= self.end as f64
+    }
+}
+
+/// A set of resolved time windows
+///
+/// An empty set means no windows were given and places no restriction on
+/// element timestamps.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimeWindows {
+    windows: Vec<TimeWindow>,
+}
+
+impl TimeWindows {
+    /// Resolve window specs, labeling unlabeled windows by their 1-based position
+    pub fn resolve(specs: &[TimeWindowSpec]) -> Result<Self> {
+        let mut windows: Vec<TimeWindow> = Vec::with_capacity(specs.len());
+        for (index, spec) in specs.iter().enumerate() {
+            let window = spec.resolve(&(index + 1).to_string())?;
+            if windows.iter().any(|w| w.label == window.label) {
+                return Err(anyhow!("duplicate time window label '{}'", window.label));
+            }
+            windows.push(window);
+        }
+        Ok(Self { windows })
+    }
+
+    /// Resolved windows, in the order they were given
+    pub fn windows(&self) -> &[TimeWindow] {
+        &self.windows
+    }
+
+    /// Whether no windows were given
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// Earliest start and latest end over all windows
+    pub fn span(&self) -> Option<(i64, i64)> {
+        let start = self.windows.iter().map(|w| w.start).min()?;
+        let end = self.windows.iter().map(|w| w.end).max()?;
+        Some((start, end))
+    }
+
+    /// Time ranges covering all windows, with overlapping or touching
+    /// windows merged, sorted by start time
+    pub fn merged_ranges(&self) -> Vec<(i64, i64)> {
+        let mut ranges: Vec<(i64, i64)> = self.windows.iter().map(|w| (w.start, w.end)).collect();
+        ranges.sort_unstable();
+        let mut merged: Vec<(i64, i64)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+
+    /// Whether `timestamp` falls inside any window; always true for an empty set
+    pub fn contains(&self, timestamp: f64) -> bool {
+        self.windows.is_empty() || self.windows.iter().any(|w| w.contains(timestamp))
+    }
+
+    /// Labels of all windows containing `timestamp`, in the order windows were given
+    pub fn labels_at(&self, timestamp: f64) -> Vec<&str> {
+        self.windows
+            .iter()
+            .filter(|w| w.contains(timestamp))
+            .map(|w| w.label.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(s: &str) -> TimeWindowSpec {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_window_spec_forms() {
+        assert_eq!(
+            spec("100/200"),
+            TimeWindowSpec {
+                start_ts: Some("100".to_string()),
+                end_ts: Some("200".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            spec("leak=2024-03-05T10:00:00Z/30m"),
+            TimeWindowSpec {
+                label: Some("leak".to_string()),
+                start_ts: Some("2024-03-05T10:00:00Z".to_string()),
+                duration: Some("30m".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            spec("1h/2024-06-01T12:00:00Z"),
+            TimeWindowSpec {
+                end_ts: Some("2024-06-01T12:00:00Z".to_string()),
+                duration: Some("1h".to_string()),
+                ..Default::default()
+            }
+        );
+        assert!("2024-01-01T00:00:00Z".parse::<TimeWindowSpec>().is_err());
+    }
+
+    #[test]
+    fn test_resolve_windows() {
+        let windows =
+            TimeWindows::resolve(&[spec("1704067200/1h"), spec("b=1h/1704153600")]).unwrap();
+        assert_eq!(
+            windows.windows(),
+            &[
+                TimeWindow {
+                    label: "1".to_string(),
+                    start: 1704067200,
+                    end: 1704070800,
+                },
+                TimeWindow {
+                    label: "b".to_string(),
+                    start: 1704150000,
+                    end: 1704153600,
+                },
+            ]
+        );
+        assert_eq!(windows.span(), Some((1704067200, 1704153600)));
+
+        let duplicate = [spec("a=1704067200/1h"), spec("a=1704153600/1h")];
+        assert!(TimeWindows::resolve(&duplicate)
+            .unwrap_err()
+            .to_string()
+            .contains("duplicate"));
+        assert!(TimeWindows::resolve(&[spec("1704153600/1704067200")])
+            .unwrap_err()
+            .to_string()
+            .contains("ends before it starts"));
+        assert!(TimeWindows::resolve(&[spec("=1704067200/1h")]).is_err());
+    }
+
+    #[test]
+    fn test_merged_ranges_and_labels() {
+        let base = 1_700_000_000;
+        let windows = TimeWindows::resolve(
+            &[(300, 400), (100, 200), (150, 250), (250, 260)]
+                .map(|(start, end)| spec(&format!("{}/{}", base + start, base + end))),
+        )
+        .unwrap();
+        assert_eq!(
+            windows.merged_ranges(),
+            vec![(base + 100, base + 260), (base + 300, base + 400)]
+        );
+
+        let at = |offset: i64| (base + offset) as f64;
+        assert_eq!(windows.labels_at(at(175)), vec!["2", "3"]);
+        assert_eq!(windows.labels_at(at(400)), vec!["1"]);
+        assert!(windows.labels_at(at(270)).is_empty());
+        assert!(!windows.contains(at(270)));
+        assert!(TimeWindows::default().contains(at(270)));
+    }
+}
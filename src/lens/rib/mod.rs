@@ -10,7 +10,7 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
-use bgpkit_broker::{BgpkitBroker, BrokerItem};
+use bgpkit_broker::BrokerItem;
 use bgpkit_parser::models::ElemType;
 use bgpkit_parser::BgpElem;
 use chrono::{DateTime, Duration};
@@ -23,7 +23,10 @@ use crate::database::{
     MonocleDatabase, RibRouteKey, RibStateStore, StoredRibEntry, StoredRibUpdate,
 };
 use crate::lens::country::CountryLens;
+use crate::lens::parse::as_path_filter::{AsPathFilters, AsPathMatcher};
+use crate::lens::parse::as_path_regex::{self, AsPathStyle};
 use crate::lens::parse::ParseFilters;
+use crate::lens::search::{broker_backend_from_config, is_remote, BrokerBackend, BrokerQuery};
 use crate::lens::time::TimeLens;
 
 #[cfg(feature = "cli")]
@@ -71,6 +74,16 @@ pub struct RibFilters {
     #[cfg_attr(feature = "cli", clap(short = 'a', long))]
     pub as_path: Option<String>,
 
+    /// Dialect of --as-path: raw regex (default), cisco (`_174_`) or junos (`.* 13335`).
+    #[cfg_attr(feature = "cli", clap(long, value_enum, value_name = "STYLE"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub as_path_style: Option<AsPathStyle>,
+
+    /// Structural AS path filters: length, prepends, AS_SETs, bogon ASNs, loops.
+    #[cfg_attr(feature = "cli", clap(flatten))]
+    #[serde(flatten)]
+    pub as_path_filters: AsPathFilters,
+
     /// Filter by collector, e.g., rrc00 or route-views2.
     #[cfg_attr(feature = "cli", clap(short = 'c', long))]
     pub collector: Option<String>,
@@ -101,6 +114,14 @@ pub struct RibArgs {
     #[cfg_attr(feature = "cli", clap(long))]
     pub sqlite_path: Option<PathBuf>,
 
+    /// Apache Parquet output file path. Updates behind the 2nd and later
+    /// snapshots are written to a sibling `<name>.updates.parquet` file.
+    #[cfg_attr(
+        feature = "cli",
+        clap(long, value_name = "PATH", conflicts_with = "sqlite_path")
+    )]
+    pub parquet_path: Option<PathBuf>,
+
     /// Use the default XDG cache directory ($XDG_CACHE_HOME/monocle) for MRT files.
     /// Overridden by --cache-dir if both are specified.
     #[cfg_attr(feature = "cli", clap(long))]
@@ -146,17 +167,16 @@ impl RibArgs {
             include_sub: self.filters.include_sub,
             peer_asn: self.filters.peer_asn.clone(),
             as_path: self.filters.as_path.clone(),
+            as_path_style: self.filters.as_path_style,
+            as_path_filters: self.filters.as_path_filters.clone(),
             ..Default::default()
         };
         parse_filters.validate()?;
 
-        if let Some(as_path) = &self.filters.as_path {
-            Regex::new(as_path)
-                .map_err(|e| anyhow!("Invalid --as-path regex '{}': {}", as_path, e))?;
-        }
-
-        if normalized_ts.len() > 1 && self.sqlite_path.is_none() {
-            return Err(anyhow!("Multiple RIB timestamps require --sqlite-path."));
+        if normalized_ts.len() > 1 && self.sqlite_path.is_none() && self.parquet_path.is_none() {
+            return Err(anyhow!(
+                "Multiple RIB timestamps require --sqlite-path or --parquet-path."
+            ));
         }
 
         Ok(normalized_ts)
@@ -219,11 +239,16 @@ impl<'a> RibLens<'a> {
         let normalized_ts = args.validate()?;
         let country_asns = self.resolve_country_asns(args.filters.country.as_deref(), no_update)?;
         let origin_filter = Self::parse_origin_filter(&args.filters.origin_asn)?;
-        let as_path_regex = Self::compile_as_path_regex(args.filters.as_path.as_deref())?;
-        let groups = self.resolve_replay_groups(args, &normalized_ts)?;
+        let as_path_regex = Self::compile_as_path_regex(
+            args.filters.as_path.as_deref(),
+            args.filters.as_path_style.unwrap_or_default(),
+        )?;
+        let as_path_matcher = args.filters.as_path_filters.matcher()?;
+        let backend = broker_backend_from_config(self.config)?;
+        let groups = self.resolve_replay_groups(backend.as_ref(), args, &normalized_ts)?;
 
         let allowlists = if args.filters.full_feed_only {
-            self.build_full_feed_allowlists(&groups)?
+            self.build_full_feed_allowlists(backend.as_ref(), &groups)?
         } else {
             HashMap::new()
         };
@@ -252,6 +277,7 @@ impl<'a> RibLens<'a> {
                 country_asns.as_ref(),
                 origin_filter.as_ref(),
                 as_path_regex.as_ref(),
+                &as_path_matcher,
                 allowlists.get(group.collector.as_str()),
             )?;
 
@@ -283,6 +309,7 @@ impl<'a> RibLens<'a> {
                         country_asns.as_ref(),
                         origin_filter.as_ref(),
                         as_path_regex.as_ref(),
+                        &as_path_matcher,
                         allowlists.get(group.collector.as_str()),
                         &mut snapshot_visitor,
                     )?;
@@ -295,6 +322,7 @@ impl<'a> RibLens<'a> {
                     country_asns.as_ref(),
                     origin_filter.as_ref(),
                     as_path_regex.as_ref(),
+                    &as_path_matcher,
                     allowlists.get(group.collector.as_str()),
                     &mut snapshot_visitor,
                 )?;
@@ -353,7 +381,7 @@ impl<'a> RibLens<'a> {
             return Ok(None);
         };
 
-        let country_code = self.resolve_country_code(country)?;
+        let country_code = CountryLens::new().resolve_code(country)?;
         let asinfo = self.db.asinfo();
 
         if asinfo.is_empty() {
@@ -397,48 +425,6 @@ impl<'a> RibLens<'a> {
         Ok(Some(asns))
     }
 
-    fn resolve_country_code(&self, input: &str) -> Result<String> {
-        let lens = CountryLens::new();
-        let matches = lens.lookup(input);
-
-        if matches.is_empty() {
-            if input.len() == 2 {
-                return Ok(input.to_uppercase());
-            }
-            return Err(anyhow!("Unknown country filter '{}'", input));
-        }
-
-        let exact_name_matches: Vec<_> = matches
-            .iter()
-            .filter(|entry| entry.name.eq_ignore_ascii_case(input))
-            .collect();
-        if exact_name_matches.len() == 1 {
-            return Ok(exact_name_matches[0].code.clone());
-        }
-
-        let exact_code_matches: Vec<_> = matches
-            .iter()
-            .filter(|entry| entry.code.eq_ignore_ascii_case(input))
-            .collect();
-        if exact_code_matches.len() == 1 {
-            return Ok(exact_code_matches[0].code.clone());
-        }
-
-        if matches.len() == 1 {
-            return Ok(matches[0].code.clone());
-        }
-
-        Err(anyhow!(
-            "Country filter '{}' is ambiguous; matches: {}",
-            input,
-            matches
-                .iter()
-                .map(|entry| format!("{} ({})", entry.name, entry.code))
-                .collect::<Vec<_>>()
-                .join(", ")
-        ))
-    }
-
     fn parse_origin_filter(values: &[String]) -> Result<Option<OriginFilter>> {
         if values.is_empty() {
             return Ok(None);
@@ -464,17 +450,15 @@ impl<'a> RibLens<'a> {
         }))
     }
 
-    fn compile_as_path_regex(pattern: Option<&str>) -> Result<Option<Regex>> {
+    fn compile_as_path_regex(pattern: Option<&str>, style: AsPathStyle) -> Result<Option<Regex>> {
         pattern
-            .map(|pattern| {
-                Regex::new(pattern)
-                    .map_err(|e| anyhow!("Invalid --as-path regex '{}': {}", pattern, e))
-            })
+            .map(|pattern| as_path_regex::compile(pattern, style))
             .transpose()
     }
 
     fn resolve_replay_groups(
         &self,
+        backend: &dyn BrokerBackend,
         args: &RibArgs,
         normalized_ts: &[i64],
     ) -> Result<Vec<RibReplayGroup>> {
@@ -490,13 +474,14 @@ impl<'a> RibLens<'a> {
         // be excluded, forcing the code to use an earlier RIB and replay unnecessary
         // update files.
         let ribs = self
-            .base_broker(args)
-            .data_type("rib")
-            .ts_start(Self::timestamp_to_broker_string(
+            .query_files(
+                backend,
+                args,
+                args.filters.collector.as_deref(),
+                "rib",
                 first_ts - Duration::hours(RIB_LOOKBACK_HOURS).num_seconds(),
-            )?)
-            .ts_end(Self::timestamp_to_broker_string(last_ts + 1)?)
-            .query()
+                last_ts + 1,
+            )
             .map_err(|e| anyhow!("Failed to query broker for candidate RIB files: {}", e))?;
 
         let mut ribs_by_collector: BTreeMap<String, Vec<BrokerItem>> = BTreeMap::new();
@@ -543,7 +528,7 @@ impl<'a> RibLens<'a> {
                     group_max_ts - rib_item.ts_start.and_utc().timestamp()
                 );
                 let updates =
-                    self.resolve_group_updates(args, &collector, &rib_item, group_max_ts)?;
+                    self.resolve_group_updates(backend, args, &collector, &rib_item, group_max_ts)?;
 
                 groups.push(RibReplayGroup {
                     collector: collector.clone(),
@@ -571,6 +556,7 @@ impl<'a> RibLens<'a> {
 
     fn resolve_group_updates(
         &self,
+        backend: &dyn BrokerBackend,
         args: &RibArgs,
         collector: &str,
         rib_item: &BrokerItem,
@@ -578,24 +564,22 @@ impl<'a> RibLens<'a> {
     ) -> Result<Vec<BrokerItem>> {
         let rib_ts = rib_item.ts_start.and_utc().timestamp();
 
-        let mut broker = self
-            .base_broker(args)
-            .collector_id(collector)
-            .data_type("updates")
-            .ts_start(Self::timestamp_to_broker_string(rib_ts)?)
-            .ts_end(Self::timestamp_to_broker_string(group_max_ts)?);
-
-        if let Some(project) = &args.filters.project {
-            broker = broker.project(project);
-        }
-
-        let mut updates = broker.query().map_err(|e| {
-            anyhow!(
-                "Failed to query broker for updates for {}: {}",
-                collector,
-                e
+        let mut updates = self
+            .query_files(
+                backend,
+                args,
+                Some(collector),
+                "updates",
+                rib_ts,
+                group_max_ts,
             )
-        })?;
+            .map_err(|e| {
+                anyhow!(
+                    "Failed to query broker for updates for {}: {}",
+                    collector,
+                    e
+                )
+            })?;
 
         // Only keep update files that contain data up to and including the target timestamp.
         // An update file with ts_end <= group_max_ts has all elements with timestamp <= group_max_ts.
@@ -607,7 +591,11 @@ impl<'a> RibLens<'a> {
         Ok(updates)
     }
 
-    fn build_full_feed_allowlists(&self, groups: &[RibReplayGroup]) -> Result<FullFeedAllowlists> {
+    fn build_full_feed_allowlists(
+        &self,
+        backend: &dyn BrokerBackend,
+        groups: &[RibReplayGroup],
+    ) -> Result<FullFeedAllowlists> {
         let mut allowlists = HashMap::new();
 
         for collector in groups
@@ -615,16 +603,13 @@ impl<'a> RibLens<'a> {
             .map(|group| group.collector.as_str())
             .collect::<BTreeSet<_>>()
         {
-            let peers = BgpkitBroker::new()
-                .collector_id(collector)
-                .get_peers()
-                .map_err(|e| {
-                    anyhow!(
-                        "Failed to fetch broker peer metadata for {}: {}",
-                        collector,
-                        e
-                    )
-                })?;
+            let peers = backend.peers(collector).map_err(|e| {
+                anyhow!(
+                    "Failed to fetch broker peer metadata for {}: {}",
+                    collector,
+                    e
+                )
+            })?;
 
             let allowed = peers
                 .into_iter()
@@ -652,6 +637,7 @@ impl<'a> RibLens<'a> {
         country_asns: Option<&HashSet<u32>>,
         origin_filter: Option<&OriginFilter>,
         as_path_regex: Option<&Regex>,
+        as_path_matcher: &AsPathMatcher,
         full_feed_allowlist: Option<&HashSet<(String, u32)>>,
     ) -> Result<()> {
         let input_path = self.input_path(args, collector, &rib_item.url)?;
@@ -678,6 +664,7 @@ impl<'a> RibLens<'a> {
                 country_asns,
                 origin_filter,
                 as_path_regex,
+                as_path_matcher,
                 full_feed_allowlist,
             ) {
                 batch.push(StoredRibEntry::from_elem(Arc::clone(&collector_arc), elem));
@@ -697,6 +684,7 @@ impl<'a> RibLens<'a> {
         country_asns: Option<&HashSet<u32>>,
         origin_filter: Option<&OriginFilter>,
         as_path_regex: Option<&Regex>,
+        as_path_matcher: &AsPathMatcher,
         full_feed_allowlist: Option<&HashSet<(String, u32)>>,
         snapshot_visitor: &mut F,
     ) -> Result<()>
@@ -761,6 +749,7 @@ impl<'a> RibLens<'a> {
                     country_asns,
                     origin_filter,
                     as_path_regex,
+                    as_path_matcher,
                     full_feed_allowlist,
                 )?;
 
@@ -807,6 +796,7 @@ impl<'a> RibLens<'a> {
         country_asns: Option<&HashSet<u32>>,
         origin_filter: Option<&OriginFilter>,
         as_path_regex: Option<&Regex>,
+        as_path_matcher: &AsPathMatcher,
         full_feed_allowlist: Option<&HashSet<(String, u32)>>,
     ) -> Result<bool> {
         let route_key = RibRouteKey::from_elem(Arc::clone(&collector), elem);
@@ -827,6 +817,7 @@ impl<'a> RibLens<'a> {
                     country_asns,
                     origin_filter,
                     as_path_regex,
+                    as_path_matcher,
                     full_feed_allowlist,
                 );
 
@@ -888,6 +879,7 @@ impl<'a> RibLens<'a> {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn announce_matches(
         &self,
         collector: &str,
@@ -895,6 +887,7 @@ impl<'a> RibLens<'a> {
         country_asns: Option<&HashSet<u32>>,
         origin_filter: Option<&OriginFilter>,
         as_path_regex: Option<&Regex>,
+        as_path_matcher: &AsPathMatcher,
         full_feed_allowlist: Option<&HashSet<(String, u32)>>,
     ) -> bool {
         if collector.is_empty() {
@@ -947,6 +940,10 @@ impl<'a> RibLens<'a> {
             }
         }
 
+        if !as_path_matcher.matches(elem) {
+            return false;
+        }
+
         if let Some(full_feed_allowlist) = full_feed_allowlist {
             let peer_key = (elem.peer_ip.to_string(), elem.peer_asn.to_u32());
             if !full_feed_allowlist.contains(&peer_key) {
@@ -997,6 +994,10 @@ impl<'a> RibLens<'a> {
         let Some(cache_dir) = cache_dir else {
             return Ok(url.to_string());
         };
+        // Files from a local archive are read in place
+        if !is_remote(url) {
+            return Ok(url.to_string());
+        }
 
         let cache_path = Self::cache_path(&cache_dir, collector, url)
             .ok_or_else(|| anyhow!("Cannot construct cache path for {}", url))?;
@@ -1028,21 +1029,23 @@ impl<'a> RibLens<'a> {
         Some(cache_dir.join(relative_path))
     }
 
-    fn base_broker(&self, args: &RibArgs) -> BgpkitBroker {
-        let mut broker = BgpkitBroker::new().page_size(1000);
-        if let Some(collector) = &args.filters.collector {
-            broker = broker.collector_id(collector);
-        }
-        if let Some(project) = &args.filters.project {
-            broker = broker.project(project);
-        }
-        broker
-    }
-
-    fn timestamp_to_broker_string(ts: i64) -> Result<String> {
-        let timestamp = DateTime::from_timestamp(ts, 0)
-            .ok_or_else(|| anyhow!("Invalid Unix timestamp {} for broker query", ts))?;
-        Ok(timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+    /// List RIB or updates files in `[ts_start, ts_end)` from the broker backend.
+    fn query_files(
+        &self,
+        backend: &dyn BrokerBackend,
+        args: &RibArgs,
+        collector: Option<&str>,
+        data_type: &str,
+        ts_start: i64,
+        ts_end: i64,
+    ) -> Result<Vec<BrokerItem>> {
+        backend.query(&BrokerQuery {
+            ts_start,
+            ts_end,
+            collector: collector.map(str::to_string),
+            project: args.filters.project.clone(),
+            data_type: Some(data_type.to_string()),
+        })
     }
 
     fn format_rib_ts_for_filename(rib_ts: i64) -> Result<String> {
@@ -1098,7 +1101,13 @@ impl<'a> RibLens<'a> {
             parts.push(format!("prefix-{}", Self::hash8(&filters.prefix.join(","))));
         }
         if let Some(as_path) = &filters.as_path {
-            parts.push(format!("aspath-{}", Self::hash8(as_path)));
+            let style = filters.as_path_style.unwrap_or_default();
+            let as_path = as_path_regex::translate(as_path, style)?;
+            parts.push(format!("aspath-{}", Self::hash8(&as_path)));
+        }
+        if !filters.as_path_filters.is_empty() {
+            let shape = serde_json::to_string(&filters.as_path_filters)?;
+            parts.push(format!("pathshape-{}", Self::hash8(&shape)));
         }
         if filters.full_feed_only {
             parts.push("fullfeed".to_string());
@@ -1158,6 +1167,7 @@ mod tests {
                 ..Default::default()
             },
             sqlite_path: None,
+            parquet_path: None,
             use_cache: false,
             cache_dir: None,
             fields: None,
@@ -1178,6 +1188,10 @@ mod tests {
         args.sqlite_path = Some(PathBuf::from("/tmp/monocle-rib.sqlite3"));
         let values = args.validate()?;
         assert_eq!(values.len(), 2);
+
+        args.sqlite_path = None;
+        args.parquet_path = Some(PathBuf::from("/tmp/monocle-rib.parquet"));
+        assert_eq!(args.validate()?.len(), 2);
         Ok(())
     }
 
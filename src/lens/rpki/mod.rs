@@ -5,6 +5,7 @@
 //! - ASPA (Autonomous System Provider Authorization) data access
 //! - Historical RPKI data support via RIPE NCC, RPKIviews, and RPKISPOOL
 //! - RTR (RPKI-to-Router) protocol support for fetching ROAs
+//! - In-memory validation of parsed BGP elements ([`RpkiValidator`])
 //!
 //! The lens uses `RpkiRepository` for cached/current data operations,
 //! and bgpkit-commons for historical data loading (with date parameter).
@@ -14,10 +15,12 @@
 // Public modules (for advanced use cases like database refresh)
 pub mod commons;
 pub mod rtr;
+pub mod validator;
 
 // Re-export types needed for external use (input/output structs)
 pub use commons::{RpkiAspaEntry, RpkiAspaProvider, RpkiAspaTableEntry, RpkiRoaEntry};
 pub use rtr::RtrClient;
+pub use validator::RpkiValidator;
 
 use crate::database::MonocleDatabase;
 use crate::utils::option_u32_from_str;
@@ -0,0 +1,214 @@
+//! In-memory route origin validation of BGP elements.
+//!
+//! [`RpkiLens::validate`](super::RpkiLens::validate) queries the SQLite cache
+//! for every prefix, which is too slow for the millions of elements of an MRT
+//! file. [`RpkiValidator`] loads all ROAs once, from the cache or from a
+//! historical RPKI archive, into an [`RpkiTrie`] that is cheap to share
+//! between threads.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use bgpkit_commons::rpki::{Roa, RpkiTrie};
+use bgpkit_parser::BgpElem;
+use chrono::NaiveDate;
+use ipnet::IpNet;
+
+use super::{commons, RpkiDataSource, RpkiValidationState};
+use crate::database::MonocleDatabase;
+
+/// ROAs to validate elements against, see the [module docs](self)
+#[derive(Clone)]
+pub struct RpkiValidator {
+    trie: Arc<RpkiTrie>,
+    roa_count: usize,
+    source: String,
+}
+
+impl std::fmt::Debug for RpkiValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RpkiValidator")
+            .field("roa_count", &self.roa_count)
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl RpkiValidator {
+    /// Build a validator from ROAs of the local RPKI cache
+    ///
+    /// The cache must have been populated first, see
+    /// [`RpkiLens::refresh`](super::RpkiLens::refresh).
+    pub fn from_cache(db: &MonocleDatabase) -> Result<Self> {
+        let repo = db.rpki();
+        if repo.is_empty() {
+            return Err(anyhow!(
+                "RPKI cache is empty, run `monocle config update --rpki` first"
+            ));
+        }
+        let source = repo
+            .get_metadata()?
+            .map(|meta| format!("cache ({})", meta.format_source()))
+            .unwrap_or_else(|| "cache".to_string());
+
+        let mut trie = RpkiTrie::new(None);
+        let mut roa_count = 0;
+        for roa in repo.get_all_roas()? {
+            let Ok(prefix) = roa.prefix.parse::<IpNet>() else {
+                continue;
+            };
+            trie.insert_roa(Roa {
+                prefix,
+                asn: roa.origin_asn,
+                max_length: roa.max_length,
+                rir: None,
+                not_before: None,
+                not_after: None,
+            });
+            roa_count += 1;
+        }
+        Ok(Self {
+            trie: Arc::new(trie),
+            roa_count,
+            source,
+        })
+    }
+
+    /// Build a validator from the historical RPKI data of `date`
+    ///
+    /// `source` defaults to RPKISPOOL; Cloudflare only has current data.
+    pub fn from_historical(date: NaiveDate, source: Option<&RpkiDataSource>) -> Result<Self> {
+        let source_str = match source {
+            None | Some(RpkiDataSource::RpkiSpools) => "rpkispools",
+            Some(RpkiDataSource::Ripe) => "ripe",
+            Some(RpkiDataSource::RpkiViews) => "rpkiviews",
+            Some(RpkiDataSource::Cloudflare) => {
+                return Err(anyhow!(
+                    "Cloudflare has no historical RPKI data, use ripe, rpki-views or rpki-spools"
+                ))
+            }
+        };
+        let trie = commons::load_rpki_data(Some(date), Some(source_str), None)?;
+        Ok(Self::from_trie(trie, format!("{source_str} {date}")))
+    }
+
+    /// Wrap an already loaded [`RpkiTrie`]
+    pub fn from_trie(trie: RpkiTrie, source: impl Into<String>) -> Self {
+        let roa_count = trie.trie.iter().map(|(_, roas)| roas.len()).sum();
+        Self {
+            trie: Arc::new(trie),
+            roa_count,
+            source: source.into(),
+        }
+    }
+
+    /// Number of ROAs loaded
+    pub fn roa_count(&self) -> usize {
+        self.roa_count
+    }
+
+    /// Where the ROAs were loaded from, e.g. `cache (Cloudflare)` or
+    /// `ripe 2024-01-01`
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Validate the prefix and origin of `elem` (RFC 6811)
+    ///
+    /// Returns `None` for elements without an origin, such as withdrawals.
+    /// A path ending in an AS_SET has no single origin AS and is never valid.
+    pub fn validate_elem(&self, elem: &BgpElem) -> Option<RpkiValidationState> {
+        let origins = elem.origin_asns.as_ref()?;
+        let origin = match origins.as_slice() {
+            [origin] => Some(origin.to_u32()),
+            _ => None,
+        };
+        Some(self.validate(&elem.prefix.prefix, origin))
+    }
+
+    /// Validate `prefix` originated by `origin`, `None` for an AS_SET origin
+    pub fn validate(&self, prefix: &IpNet, origin: Option<u32>) -> RpkiValidationState {
+        let mut covered = false;
+        for (roa_prefix, roas) in self.trie.trie.matches(prefix) {
+            if !roa_prefix.contains(prefix) {
+                continue;
+            }
+            for roa in roas {
+                covered = true;
+                // AS0 ROAs (RFC 6483) only ever make a route invalid
+                if roa.asn != 0 && Some(roa.asn) == origin && prefix.prefix_len() <= roa.max_length
+                {
+                    return RpkiValidationState::Valid;
+                }
+            }
+        }
+        if covered {
+            RpkiValidationState::Invalid
+        } else {
+            RpkiValidationState::NotFound
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn validator() -> RpkiValidator {
+        let mut trie = RpkiTrie::new(None);
+        for (prefix, asn, max_length) in [
+            ("1.1.1.0/24", 13335, 24),
+            ("2001:db8::/32", 64500, 48),
+            ("203.0.113.0/24", 0, 24),
+        ] {
+            trie.insert_roa(Roa {
+                prefix: prefix.parse().unwrap(),
+                asn,
+                max_length,
+                rir: None,
+                not_before: None,
+                not_after: None,
+            });
+        }
+        RpkiValidator::from_trie(trie, "test")
+    }
+
+    #[test]
+    fn test_validate() {
+        let validator = validator();
+        let validate = |prefix: &str, origin: Option<u32>| {
+            validator.validate(&prefix.parse().unwrap(), origin)
+        };
+
+        assert_eq!(validator.roa_count(), 3);
+        assert_eq!(
+            validate("1.1.1.0/24", Some(13335)),
+            RpkiValidationState::Valid
+        );
+        assert_eq!(
+            validate("1.1.1.0/24", Some(64496)),
+            RpkiValidationState::Invalid
+        );
+        // Longer than max_length
+        assert_eq!(
+            validate("1.1.1.0/25", Some(13335)),
+            RpkiValidationState::Invalid
+        );
+        assert_eq!(
+            validate("2001:db8:1::/48", Some(64500)),
+            RpkiValidationState::Valid
+        );
+        assert_eq!(
+            validate("8.8.8.0/24", Some(15169)),
+            RpkiValidationState::NotFound
+        );
+        // AS0 ROAs and AS_SET origins never validate
+        assert_eq!(
+            validate("203.0.113.0/24", Some(0)),
+            RpkiValidationState::Invalid
+        );
+        assert_eq!(validate("1.1.1.0/24", None), RpkiValidationState::Invalid);
+        assert_eq!(validate("8.8.8.0/24", None), RpkiValidationState::NotFound);
+    }
+}
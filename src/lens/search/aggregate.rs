@@ -0,0 +1,355 @@
+//! Group-by aggregation of BGP elements
+//!
+//! Instead of emitting every matched element, aggregation mode counts elements
+//! per combination of [`GroupByField`] values and tracks when each group was
+//! first and last seen. Partial aggregates built on different worker threads
+//! (or from different files) are combined with [`ElemAggregator::merge`].
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use bgpkit_parser::models::ElemType;
+use bgpkit_parser::BgpElem;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    SearchControl, SearchElementBatch, SearchProgress, SearchProgressCallback, SearchSink,
+};
+use crate::utils::GroupByField;
+
+/// Count and first/last seen timestamps of one group
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GroupStats {
+    /// Number of elements in the group
+    pub count: u64,
+    /// Earliest element timestamp in the group
+    pub first_seen: f64,
+    /// Latest element timestamp in the group
+    pub last_seen: f64,
+}
+
+impl GroupStats {
+    fn new(timestamp: f64) -> Self {
+        Self {
+            count: 1,
+            first_seen: timestamp,
+            last_seen: timestamp,
+        }
+    }
+
+    fn add(&mut self, timestamp: f64) {
+        self.count += 1;
+        self.first_seen = self.first_seen.min(timestamp);
+        self.last_seen = self.last_seen.max(timestamp);
+    }
+
+    fn merge(&mut self, other: GroupStats) {
+        self.count += other.count;
+        self.first_seen = self.first_seen.min(other.first_seen);
+        self.last_seen = self.last_seen.max(other.last_seen);
+    }
+}
+
+/// One aggregated output row
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregateRow {
+    /// Group values, in the same order as the aggregator's group-by fields
+    pub key: Vec<String>,
+    #[serde(flatten)]
+    pub stats: GroupStats,
+}
+
+/// Accumulates group-by counts over BGP elements
+#[derive(Debug, Clone, Default)]
+pub struct ElemAggregator {
+    fields: Vec<GroupByField>,
+    groups: HashMap<Vec<String>, GroupStats>,
+}
+
+impl ElemAggregator {
+    /// Create an empty aggregator grouping by `fields`
+    pub fn new(fields: Vec<GroupByField>) -> Self {
+        Self {
+            fields,
+            groups: HashMap::new(),
+        }
+    }
+
+    /// Fields elements are grouped by
+    pub fn fields(&self) -> &[GroupByField] {
+        &self.fields
+    }
+
+    /// Number of distinct groups
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Whether no element has been added yet
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Add one element; `collector` is used for the `collector` field
+    pub fn add(&mut self, elem: &BgpElem, collector: Option<&str>) {
+        for key in group_keys(&self.fields, elem, collector) {
+            self.groups
+                .entry(key)
+                .and_modify(|stats| stats.add(elem.timestamp))
+                .or_insert_with(|| GroupStats::new(elem.timestamp));
+        }
+    }
+
+    /// Merge a partial aggregate (built with the same fields) into this one
+    pub fn merge(&mut self, mut other: ElemAggregator) {
+        if self.groups.len() < other.groups.len() {
+            std::mem::swap(&mut self.groups, &mut other.groups);
+        }
+        for (key, stats) in other.groups {
+            self.groups
+                .entry(key)
+                .and_modify(|existing| existing.merge(stats))
+                .or_insert(stats);
+        }
+    }
+
+    /// Aggregated rows, sorted by descending count and then by key
+    pub fn rows(&self) -> Vec<AggregateRow> {
+        let mut rows: Vec<AggregateRow> = self
+            .groups
+            .iter()
+            .map(|(key, stats)| AggregateRow {
+                key: key.clone(),
+                stats: *stats,
+            })
+            .collect();
+        rows.sort_by(|a, b| {
+            b.stats
+                .count
+                .cmp(&a.stats.count)
+                .then_with(|| a.key.cmp(&b.key))
+        });
+        rows
+    }
+}
+
+/// All group keys an element contributes to (more than one when grouping by community)
+pub(super) fn group_keys(
+    fields: &[GroupByField],
+    elem: &BgpElem,
+    collector: Option<&str>,
+) -> Vec<Vec<String>> {
+    let mut keys: Vec<Vec<String>> = vec![Vec::with_capacity(fields.len())];
+    for field in fields {
+        let values = field_values(*field, elem, collector);
+        if values.len() == 1 {
+            for key in keys.iter_mut() {
+                key.push(values[0].clone());
+            }
+        } else {
+            keys = keys
+                .into_iter()
+                .flat_map(|key| {
+                    values.iter().map(move |value| {
+                        let mut key = key.clone();
+                        key.push(value.clone());
+                        key
+                    })
+                })
+                .collect();
+        }
+    }
+    keys
+}
+
+fn field_values(field: GroupByField, elem: &BgpElem, collector: Option<&str>) -> Vec<String> {
+    let value = match field {
+        GroupByField::Prefix => elem.prefix.to_string(),
+        GroupByField::OriginAsns => elem
+            .origin_asns
+            .as_ref()
+            .map(|asns| {
+                asns.iter()
+                    .map(|asn| asn.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default(),
+        GroupByField::PeerAsn => elem.peer_asn.to_string(),
+        GroupByField::PeerIp => elem.peer_ip.to_string(),
+        GroupByField::Collector => collector.unwrap_or_default().to_string(),
+        GroupByField::Type => match elem.elem_type {
+            ElemType::ANNOUNCE => "A".to_string(),
+            ElemType::WITHDRAW => "W".to_string(),
+        },
+        GroupByField::Community => {
+            return match &elem.communities {
+                Some(communities) if !communities.is_empty() => {
+                    communities.iter().map(|c| c.to_string()).collect()
+                }
+                _ => vec![String::new()],
+            };
+        }
+    };
+    vec![value]
+}
+
+/// Search sink that aggregates elements instead of emitting them
+///
+/// Each batch is aggregated on the worker thread that produced it and the
+/// partial result is then merged into the shared aggregate, so the lock is
+/// taken once per batch rather than once per element.
+pub struct AggregateSink {
+    fields: Vec<GroupByField>,
+    aggregator: Mutex<ElemAggregator>,
+    progress_callback: Option<SearchProgressCallback>,
+}
+
+impl AggregateSink {
+    /// Create a sink grouping by `fields`
+    pub fn new(fields: Vec<GroupByField>) -> Self {
+        Self {
+            aggregator: Mutex::new(ElemAggregator::new(fields.clone())),
+            fields,
+            progress_callback: None,
+        }
+    }
+
+    /// Forward search progress updates to `callback`
+    pub fn with_progress(mut self, callback: SearchProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
+    }
+
+    /// Take the aggregate collected so far, leaving an empty one in its place
+    pub fn take(&self) -> ElemAggregator {
+        match self.aggregator.lock() {
+            Ok(mut aggregator) => {
+                std::mem::replace(&mut *aggregator, ElemAggregator::new(self.fields.clone()))
+            }
+            Err(_) => ElemAggregator::new(self.fields.clone()),
+        }
+    }
+}
+
+impl SearchSink for AggregateSink {
+    fn on_progress(&self, progress: SearchProgress) {
+        if let Some(ref cb) = self.progress_callback {
+            cb(progress);
+        }
+    }
+
+    fn on_elements(&self, batch: SearchElementBatch) -> SearchControl {
+        let mut partial = ElemAggregator::new(self.fields.clone());
+        for elem in &batch.elements {
+            partial.add(elem, Some(batch.collector.as_str()));
+        }
+        match self.aggregator.lock() {
+            Ok(mut aggregator) => {
+                aggregator.merge(partial);
+                SearchControl::Continue
+            }
+            Err(_) => SearchControl::Stop,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgpkit_parser::models::{Community, MetaCommunity, NetworkPrefix};
+    use std::str::FromStr;
+
+    fn elem(prefix: &str, peer_asn: u32, timestamp: f64, communities: &[u16]) -> BgpElem {
+        BgpElem {
+            timestamp,
+            prefix: NetworkPrefix::from_str(prefix).unwrap(),
+            peer_asn: peer_asn.into(),
+            origin_asns: Some(vec![65001.into()]),
+            communities: if communities.is_empty() {
+                None
+            } else {
+                Some(
+                    communities
+                        .iter()
+                        .map(|v| MetaCommunity::Plain(Community::Custom(65000.into(), *v)))
+                        .collect(),
+                )
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_aggregate_counts_and_seen_times() {
+        let mut agg = ElemAggregator::new(vec![GroupByField::Prefix, GroupByField::Collector]);
+        agg.add(&elem("10.0.0.0/8", 1, 20.0, &[]), Some("rrc00"));
+        agg.add(&elem("10.0.0.0/8", 2, 10.0, &[]), Some("rrc00"));
+        agg.add(&elem("10.0.0.0/8", 1, 30.0, &[]), Some("rrc01"));
+
+        let rows = agg.rows();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].key, vec!["10.0.0.0/8", "rrc00"]);
+        assert_eq!(rows[0].stats.count, 2);
+        assert_eq!(rows[0].stats.first_seen, 10.0);
+        assert_eq!(rows[0].stats.last_seen, 20.0);
+        assert_eq!(rows[1].key, vec!["10.0.0.0/8", "rrc01"]);
+    }
+
+    #[test]
+    fn test_aggregate_community_expands_groups() {
+        let mut agg = ElemAggregator::new(vec![GroupByField::PeerAsn, GroupByField::Community]);
+        agg.add(&elem("10.0.0.0/8", 1, 1.0, &[100, 200]), None);
+        agg.add(&elem("10.0.0.0/8", 1, 2.0, &[]), None);
+
+        let keys: Vec<Vec<String>> = agg.rows().into_iter().map(|r| r.key).collect();
+        assert_eq!(
+            keys,
+            vec![
+                vec!["1".to_string(), String::new()],
+                vec!["1".to_string(), "65000:100".to_string()],
+                vec!["1".to_string(), "65000:200".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aggregate_merge_matches_single_pass() {
+        let elems: Vec<BgpElem> = (0..20)
+            .map(|i| elem("192.0.2.0/24", i % 3, i as f64, &[]))
+            .collect();
+        let fields = vec![GroupByField::PeerAsn];
+
+        let mut single = ElemAggregator::new(fields.clone());
+        elems.iter().for_each(|e| single.add(e, None));
+
+        let mut merged = ElemAggregator::new(fields.clone());
+        for chunk in elems.chunks(7) {
+            let mut partial = ElemAggregator::new(fields.clone());
+            chunk.iter().for_each(|e| partial.add(e, None));
+            merged.merge(partial);
+        }
+
+        assert_eq!(single.rows(), merged.rows());
+    }
+
+    #[test]
+    fn test_aggregate_sink_merges_batches() {
+        let sink = AggregateSink::new(vec![GroupByField::Collector]);
+        for collector in ["rrc00", "rrc00", "route-views2"] {
+            let control = sink.on_elements(SearchElementBatch {
+                file_index: 0,
+                file_url: String::new(),
+                collector: collector.to_string(),
+                elements: vec![elem("10.0.0.0/8", 1, 1.0, &[])],
+            });
+            assert_eq!(control, SearchControl::Continue);
+        }
+
+        let rows = sink.take().rows();
+        assert_eq!(rows[0].key, vec!["rrc00"]);
+        assert_eq!(rows[0].stats.count, 2);
+        assert_eq!(rows[1].key, vec!["route-views2"]);
+        assert!(sink.take().is_empty());
+    }
+}
@@ -0,0 +1,581 @@
+//! Broker backends
+//!
+//! A [`BrokerBackend`] answers "which MRT files cover this time range,
+//! collector and dump type?". Search and RIB reconstruction only talk to this
+//! trait, so the file listing can come from:
+//!
+//! - [`BgpkitBrokerBackend`]: the public BGPKIT broker, or a self-hosted
+//!   instance set with `broker_url` in `monocle.toml`
+//! - [`ManifestBackend`]: a static JSON manifest of `BrokerItem`s set with
+//!   `broker_manifest` (the output of `monocle search --broker-files --json`
+//!   is a valid manifest)
+//! - [`LocalArchive`]: a RouteViews/RIS-style directory tree set with
+//!   `local_archive_dir`
+//!
+//! Use [`broker_backend_from_config`] to pick the backend configured in
+//! [`MonocleConfig`].
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use bgpkit_broker::{BgpkitBroker, BrokerItem, BrokerPeer};
+use serde::Deserialize;
+
+use super::LocalArchive;
+use crate::config::MonocleConfig;
+
+/// Page size used when querying a broker.
+pub const BROKER_PAGE_SIZE: i64 = 1000;
+
+/// Backend-neutral file listing query, mirroring the broker query parameters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BrokerQuery {
+    /// Start of the time range (Unix seconds, inclusive)
+    pub ts_start: i64,
+    /// End of the time range (Unix seconds, exclusive for file start times)
+    pub ts_end: i64,
+    /// Collector ID, e.g. `rrc00` or `route-views2`; comma-separated for several
+    pub collector: Option<String>,
+    /// Project name, `riperis` or `routeviews`
+    pub project: Option<String>,
+    /// Data type, `rib` or `updates`; `None` matches both
+    pub data_type: Option<String>,
+}
+
+impl BrokerQuery {
+    /// Check that `project`, if set, is a known project name
+    ///
+    /// Backends that filter in memory call this before [`matches`](Self::matches),
+    /// which would otherwise match nothing for an unknown project.
+    pub fn validate(&self) -> Result<()> {
+        match &self.project {
+            Some(project) if normalize_project(project).is_none() => Err(anyhow!(
+                "unknown project '{}': expected riperis or routeviews",
+                project
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether an item satisfies this query.
+    ///
+    /// Used by backends that filter in memory; a file matches when its time
+    /// range overlaps the query range.
+    pub fn matches(&self, item: &BrokerItem) -> bool {
+        let item_start = item.ts_start.and_utc().timestamp();
+        let item_end = item.ts_end.and_utc().timestamp();
+        if item_end < self.ts_start || item_start >= self.ts_end {
+            return false;
+        }
+        if let Some(collector) = &self.collector {
+            if !collector
+                .split(',')
+                .any(|c| c.trim() == item.collector_id.as_str())
+            {
+                return false;
+            }
+        }
+        if let Some(project) = &self.project {
+            if normalize_project(project) != Some(project_for_collector(&item.collector_id)) {
+                return false;
+            }
+        }
+        if let Some(data_type) = &self.data_type {
+            if data_type != &item.data_type {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Source of MRT file listings for search and RIB reconstruction.
+pub trait BrokerBackend: Send + Sync {
+    /// Human-readable description used in logs and error messages.
+    fn name(&self) -> String;
+
+    /// List all files matching the query, sorted by start time.
+    fn query(&self, query: &BrokerQuery) -> Result<Vec<BrokerItem>>;
+
+    /// List one page (1-based) of files matching the query.
+    ///
+    /// Backends without server-side paging return every match on page 1 and
+    /// nothing afterwards.
+    fn query_page(
+        &self,
+        query: &BrokerQuery,
+        page: i64,
+        _page_size: i64,
+    ) -> Result<Vec<BrokerItem>> {
+        if page == 1 {
+            self.query(query)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Collector peer metadata, used to detect full-feed peers.
+    fn peers(&self, collector: &str) -> Result<Vec<BrokerPeer>> {
+        Err(anyhow!(
+            "{} does not provide peer metadata for {}",
+            self.name(),
+            collector
+        ))
+    }
+
+    /// A `BgpkitBroker` for the query, `None` for backends that are not a
+    /// BGPKIT broker instance.
+    fn bgpkit_broker(&self, _query: &BrokerQuery) -> Option<BgpkitBroker> {
+        None
+    }
+
+    /// Whether listings may be stored in the shared broker cache used by
+    /// `search --use-cache`. The cache is not keyed by backend, so only the
+    /// public broker opts in.
+    fn cache_results(&self) -> bool {
+        false
+    }
+}
+
+// =============================================================================
+// BGPKIT broker
+// =============================================================================
+
+/// The BGPKIT broker API, public or self-hosted.
+#[derive(Debug, Clone, Default)]
+pub struct BgpkitBrokerBackend {
+    url: Option<String>,
+}
+
+impl BgpkitBrokerBackend {
+    /// Backend for the default broker (`BGPKIT_BROKER_URL` or the public API).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Backend for a broker instance at `url`, e.g. `https://broker.example.com/v3/broker`.
+    pub fn with_url(url: impl Into<String>) -> Self {
+        Self {
+            url: Some(url.into()),
+        }
+    }
+
+    /// Configured broker URL, `None` for the default broker.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Build a `BgpkitBroker` for the query.
+    pub fn build_broker(&self, query: &BrokerQuery) -> BgpkitBroker {
+        let mut broker = self
+            .base_broker()
+            .ts_start(query.ts_start)
+            .ts_end(query.ts_end)
+            .page_size(BROKER_PAGE_SIZE);
+
+        if let Some(project) = &query.project {
+            broker = broker.project(project.as_str());
+        }
+        if let Some(collector) = &query.collector {
+            broker = broker.collector_id(collector.as_str());
+        }
+        if let Some(data_type) = &query.data_type {
+            broker = broker.data_type(data_type.as_str());
+        }
+        broker
+    }
+
+    fn base_broker(&self) -> BgpkitBroker {
+        match &self.url {
+            Some(url) => BgpkitBroker::new().broker_url(url),
+            None => BgpkitBroker::new(),
+        }
+    }
+}
+
+impl BrokerBackend for BgpkitBrokerBackend {
+    fn name(&self) -> String {
+        match &self.url {
+            Some(url) => format!("broker {}", url),
+            None => "BGPKIT broker".to_string(),
+        }
+    }
+
+    fn query(&self, query: &BrokerQuery) -> Result<Vec<BrokerItem>> {
+        self.build_broker(query)
+            .query()
+            .map_err(|e| anyhow!("broker query error: {}", e))
+    }
+
+    fn query_page(
+        &self,
+        query: &BrokerQuery,
+        page: i64,
+        page_size: i64,
+    ) -> Result<Vec<BrokerItem>> {
+        self.build_broker(query)
+            .page_size(page_size)
+            .page(page)
+            .query_single_page()
+            .map_err(|e| anyhow!("broker query error: {}", e))
+    }
+
+    fn peers(&self, collector: &str) -> Result<Vec<BrokerPeer>> {
+        self.base_broker()
+            .collector_id(collector)
+            .get_peers()
+            .map_err(|e| anyhow!("broker peer query error: {}", e))
+    }
+
+    fn bgpkit_broker(&self, query: &BrokerQuery) -> Option<BgpkitBroker> {
+        Some(self.build_broker(query))
+    }
+
+    fn cache_results(&self) -> bool {
+        self.url.is_none()
+    }
+}
+
+// =============================================================================
+// Static manifest
+// =============================================================================
+
+/// A fixed list of files loaded from a JSON manifest.
+///
+/// The manifest is either a JSON array of broker items or a broker API
+/// response object with a `data` array. Relative `url` values in a local
+/// manifest are resolved against the manifest's directory.
+#[derive(Debug, Clone)]
+pub struct ManifestBackend {
+    source: String,
+    items: Vec<BrokerItem>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ManifestFile {
+    Items(Vec<BrokerItem>),
+    Response { data: Vec<BrokerItem> },
+}
+
+impl ManifestBackend {
+    /// Load a manifest from a local path or remote URL.
+    pub fn load(path: &str) -> Result<Self> {
+        let content = oneio::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read broker manifest {}: {}", path, e))?;
+        let mut items = match serde_json::from_str::<ManifestFile>(&content)
+            .map_err(|e| anyhow!("invalid broker manifest {}: {}", path, e))?
+        {
+            ManifestFile::Items(items) | ManifestFile::Response { data: items } => items,
+        };
+
+        let base_dir = Path::new(path).parent().filter(|_| !is_remote(path));
+        if let Some(base_dir) = base_dir {
+            for item in &mut items {
+                if !is_remote(&item.url) && Path::new(&item.url).is_relative() {
+                    item.url = base_dir.join(&item.url).to_string_lossy().to_string();
+                }
+            }
+        }
+
+        Ok(Self::from_items(path, items))
+    }
+
+    /// Build a manifest backend from items already in memory.
+    pub fn from_items(source: impl Into<String>, mut items: Vec<BrokerItem>) -> Self {
+        items.sort_by(|a, b| {
+            a.ts_start
+                .cmp(&b.ts_start)
+                .then_with(|| a.collector_id.cmp(&b.collector_id))
+        });
+        Self {
+            source: source.into(),
+            items,
+        }
+    }
+}
+
+impl BrokerBackend for ManifestBackend {
+    fn name(&self) -> String {
+        format!("manifest {}", self.source)
+    }
+
+    fn query(&self, query: &BrokerQuery) -> Result<Vec<BrokerItem>> {
+        query.validate()?;
+        Ok(self
+            .items
+            .iter()
+            .filter(|item| query.matches(item))
+            .cloned()
+            .collect())
+    }
+}
+
+// =============================================================================
+// Query plans
+// =============================================================================
+
+/// List the files matching any of several queries, such as one query per
+/// search time window.
+///
+/// Files matched by more than one query are only listed once.
+pub fn query_all(backend: &dyn BrokerBackend, queries: &[BrokerQuery]) -> Result<Vec<BrokerItem>> {
+    let mut listings = Vec::with_capacity(queries.len());
+    for query in queries {
+        listings.push(backend.query(query)?);
+    }
+    Ok(merge_broker_items(listings))
+}
+
+/// Merge several file listings into one sorted by start time, dropping files
+/// (by URL) that were already listed.
+pub fn merge_broker_items(listings: impl IntoIterator<Item = Vec<BrokerItem>>) -> Vec<BrokerItem> {
+    let mut seen = HashSet::new();
+    let mut items: Vec<BrokerItem> = listings
+        .into_iter()
+        .flatten()
+        .filter(|item| seen.insert(item.url.clone()))
+        .collect();
+    items.sort_by_key(|item| item.ts_start);
+    items
+}
+
+// =============================================================================
+// Configuration
+// =============================================================================
+
+/// Build the broker backend configured in `config`.
+///
+/// At most one of `local_archive_dir`, `broker_manifest` and `broker_url` may
+/// be set; with none set the default BGPKIT broker is used.
+pub fn broker_backend_from_config(config: &MonocleConfig) -> Result<Arc<dyn BrokerBackend>> {
+    let configured = [
+        ("local_archive_dir", config.local_archive_dir.as_deref()),
+        ("broker_manifest", config.broker_manifest.as_deref()),
+        ("broker_url", config.broker_url.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| value.map(|v| (key, v.trim())))
+    .filter(|(_, value)| !value.is_empty())
+    .collect::<Vec<_>>();
+
+    match configured.as_slice() {
+        [] => Ok(Arc::new(BgpkitBrokerBackend::new())),
+        [("local_archive_dir", dir)] => Ok(Arc::new(LocalArchive::new(*dir)?)),
+        [("broker_manifest", path)] => Ok(Arc::new(ManifestBackend::load(path)?)),
+        [(_, url)] => Ok(Arc::new(BgpkitBrokerBackend::with_url(*url))),
+        _ => Err(anyhow!(
+            "only one of {} may be set",
+            configured
+                .iter()
+                .map(|(key, _)| *key)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Project name used by the broker for a collector ID.
+fn project_for_collector(collector: &str) -> &'static str {
+    if collector.starts_with("rrc") {
+        "riperis"
+    } else {
+        "routeviews"
+    }
+}
+
+fn normalize_project(project: &str) -> Option<&'static str> {
+    match project.trim().to_lowercase().as_str() {
+        "riperis" | "ripe-ris" | "ris" => Some("riperis"),
+        "routeviews" | "route-views" | "rv" => Some("routeviews"),
+        _ => None,
+    }
+}
+
+/// Whether `path` is a URL such as `https://…` or `s3://…` rather than a local
+/// path, i.e. a file that is downloaded and can be cached
+pub fn is_remote(path: &str) -> bool {
+    path.contains("://")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    fn item(collector: &str, data_type: &str, ts_start: i64, ts_end: i64, url: &str) -> BrokerItem {
+        BrokerItem {
+            ts_start: DateTime::from_timestamp(ts_start, 0).unwrap().naive_utc(),
+            ts_end: DateTime::from_timestamp(ts_end, 0).unwrap().naive_utc(),
+            collector_id: collector.to_string(),
+            data_type: data_type.to_string(),
+            url: url.to_string(),
+            rough_size: 0,
+            exact_size: 0,
+        }
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let query = BrokerQuery {
+            ts_start: 1000,
+            ts_end: 2000,
+            collector: Some("rrc00, route-views2".to_string()),
+            project: None,
+            data_type: Some("updates".to_string()),
+        };
+        assert!(query.matches(&item("rrc00", "updates", 900, 1000, "a")));
+        assert!(query.matches(&item("route-views2", "updates", 1900, 2800, "b")));
+        assert!(!query.matches(&item("rrc00", "updates", 2000, 2300, "c")));
+        assert!(!query.matches(&item("rrc01", "updates", 1200, 1500, "d")));
+        assert!(!query.matches(&item("rrc00", "rib", 1200, 1200, "e")));
+
+        let query = BrokerQuery {
+            project: Some("routeviews".to_string()),
+            collector: None,
+            data_type: None,
+            ..query
+        };
+        assert!(!query.matches(&item("rrc00", "updates", 1200, 1500, "a")));
+        assert!(query.matches(&item("route-views.sg", "rib", 1200, 1200, "b")));
+        assert!(query.validate().is_ok());
+
+        let query = BrokerQuery {
+            project: Some("routeview".to_string()),
+            ..query
+        };
+        assert!(query.validate().is_err());
+        assert!(ManifestBackend::from_items("test", Vec::new())
+            .query(&query)
+            .is_err());
+    }
+
+    #[test]
+    fn test_manifest_backend_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let items = vec![
+            item("rrc00", "updates", 1300, 1600, "rrc00/updates.2.gz"),
+            item(
+                "rrc00",
+                "updates",
+                1000,
+                1300,
+                "https://example.com/updates.1.gz",
+            ),
+            item("rrc00", "rib", 1000, 1000, "/data/bview.gz"),
+        ];
+        let path = dir.path().join("manifest.json");
+        std::fs::write(&path, serde_json::to_string(&items).unwrap()).unwrap();
+
+        let backend = ManifestBackend::load(path.to_str().unwrap()).unwrap();
+        let found = backend
+            .query(&BrokerQuery {
+                ts_start: 1000,
+                ts_end: 2000,
+                data_type: Some("updates".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        let urls: Vec<_> = found.iter().map(|i| i.url.clone()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/updates.1.gz".to_string(),
+                dir.path()
+                    .join("rrc00/updates.2.gz")
+                    .to_string_lossy()
+                    .to_string(),
+            ]
+        );
+        assert_eq!(
+            backend
+                .query_page(&BrokerQuery::default(), 2, BROKER_PAGE_SIZE)
+                .unwrap()
+                .len(),
+            0
+        );
+        assert!(backend.peers("rrc00").is_err());
+        assert!(!backend.cache_results());
+    }
+
+    #[test]
+    fn test_manifest_backend_accepts_broker_response() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("response.json");
+        let response = serde_json::json!({
+            "count": 1,
+            "data": [item("rrc00", "rib", 1000, 1000, "https://example.com/bview.gz")],
+        });
+        std::fs::write(&path, response.to_string()).unwrap();
+
+        let backend = ManifestBackend::load(path.to_str().unwrap()).unwrap();
+        let found = backend
+            .query(&BrokerQuery {
+                ts_start: 0,
+                ts_end: 2000,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(found.len(), 1);
+    }
+
+    #[test]
+    fn test_query_all_lists_shared_files_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let items = vec![
+            item("rrc00", "updates", 1000, 1300, "/data/updates.1.gz"),
+            item("rrc00", "updates", 1300, 1600, "/data/updates.2.gz"),
+            item("rrc00", "updates", 5000, 5300, "/data/updates.3.gz"),
+        ];
+        let path = dir.path().join("manifest.json");
+        std::fs::write(&path, serde_json::to_string(&items).unwrap()).unwrap();
+        let backend = ManifestBackend::load(path.to_str().unwrap()).unwrap();
+
+        let query = |ts_start, ts_end| BrokerQuery {
+            ts_start,
+            ts_end,
+            ..Default::default()
+        };
+        let found = query_all(
+            &backend,
+            &[query(5000, 5100), query(1100, 1400), query(1500, 1550)],
+        )
+        .unwrap();
+        let urls: Vec<_> = found.iter().map(|i| i.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "/data/updates.1.gz",
+                "/data/updates.2.gz",
+                "/data/updates.3.gz"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_backend_from_config() {
+        let config = MonocleConfig::default();
+        let backend = broker_backend_from_config(&config).unwrap();
+        assert_eq!(backend.name(), "BGPKIT broker");
+        assert!(backend.cache_results());
+
+        let config = MonocleConfig {
+            broker_url: Some("http://127.0.0.1:40064/".to_string()),
+            ..Default::default()
+        };
+        let backend = broker_backend_from_config(&config).unwrap();
+        assert_eq!(backend.name(), "broker http://127.0.0.1:40064/");
+        assert!(!backend.cache_results());
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = MonocleConfig {
+            local_archive_dir: Some(dir.path().to_string_lossy().to_string()),
+            broker_url: Some("http://127.0.0.1:40064".to_string()),
+            ..Default::default()
+        };
+        let err = broker_backend_from_config(&config).err().unwrap();
+        assert!(err.to_string().contains("local_archive_dir, broker_url"));
+    }
+}
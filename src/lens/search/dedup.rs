@@ -0,0 +1,421 @@
+//! Cross-peer and cross-collector deduplication of BGP elements
+//!
+//! The same announcement is usually observed by many peers on many
+//! collectors. Deduplication collapses elements with the same prefix, AS path,
+//! communities and type into a single [`DedupEvent`] as long as consecutive
+//! observations are at most `window` seconds apart. Each event keeps its
+//! earliest observation together with the observing peers and collectors.
+//!
+//! Grouping only depends on the set of observed timestamps, not on the order
+//! elements arrive in, so partial results built on different worker threads
+//! can be combined with [`ElemDeduplicator::merge`].
+
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::ops::Range;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use bgpkit_parser::models::{Asn, ElemType};
+use bgpkit_parser::BgpElem;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    SearchControl, SearchElementBatch, SearchProgress, SearchProgressCallback, SearchSink,
+};
+
+/// Parse a dedup window such as `0`, `30`, `10s` or `1m` into seconds
+///
+/// Plain integers are taken as seconds. A window of `0` only collapses
+/// observations with the same timestamp.
+pub fn parse_dedup_window(window: &str) -> Result<u64> {
+    let window = window.trim();
+    match window.parse::<u64>() {
+        Ok(secs) => Ok(secs),
+        Err(_) => humantime::parse_duration(window)
+            .map(|duration| duration.as_secs())
+            .map_err(|e| anyhow!("invalid dedup window '{}': {}", window, e)),
+    }
+}
+
+/// A peer that observed a deduplicated event
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct DedupPeer {
+    pub peer_ip: IpAddr,
+    pub peer_asn: Asn,
+}
+
+/// Attributes that identify the same routing event across peers and collectors
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct DedupKey {
+    prefix: String,
+    as_path: String,
+    communities: Vec<String>,
+    announce: bool,
+}
+
+impl DedupKey {
+    fn new(elem: &BgpElem) -> Self {
+        let mut communities: Vec<String> = elem
+            .communities
+            .iter()
+            .flatten()
+            .map(|c| c.to_string())
+            .collect();
+        // Peers may reorder communities; compare them as a set
+        communities.sort_unstable();
+        communities.dedup();
+        Self {
+            prefix: elem.prefix.to_string(),
+            as_path: elem
+                .as_path
+                .as_ref()
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+            communities,
+            announce: elem.elem_type == ElemType::ANNOUNCE,
+        }
+    }
+}
+
+/// One deduplicated routing event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DedupEvent {
+    /// Earliest observation of the event
+    pub elem: BgpElem,
+    /// Collector of the earliest observation
+    pub collector: String,
+    /// Timestamp of the earliest observation
+    pub first_seen: f64,
+    /// Timestamp of the latest observation
+    pub last_seen: f64,
+    /// Number of collapsed elements
+    pub count: u64,
+    /// Distinct peers that observed the event
+    pub peers: BTreeSet<DedupPeer>,
+    /// Distinct collectors that observed the event
+    pub collectors: BTreeSet<String>,
+}
+
+impl DedupEvent {
+    fn new(elem: &BgpElem, collector: &str) -> Self {
+        Self {
+            elem: elem.clone(),
+            collector: collector.to_string(),
+            first_seen: elem.timestamp,
+            last_seen: elem.timestamp,
+            count: 1,
+            peers: BTreeSet::from([DedupPeer {
+                peer_ip: elem.peer_ip,
+                peer_asn: elem.peer_asn,
+            }]),
+            collectors: BTreeSet::from([collector.to_string()]),
+        }
+    }
+
+    /// Number of distinct observing peers
+    pub fn peer_count(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Number of distinct observing collectors
+    pub fn collector_count(&self) -> usize {
+        self.collectors.len()
+    }
+
+    /// Whether `elem` seen on `collector` should represent the event instead
+    /// of the current earliest observation. Ties are broken on collector and
+    /// peer so the result does not depend on arrival order.
+    fn precedes(&self, elem: &BgpElem, collector: &str) -> bool {
+        let candidate = (elem.timestamp, collector, elem.peer_ip, elem.peer_asn);
+        let current = (
+            self.elem.timestamp,
+            self.collector.as_str(),
+            self.elem.peer_ip,
+            self.elem.peer_asn,
+        );
+        candidate.partial_cmp(&current) == Some(std::cmp::Ordering::Less)
+    }
+
+    fn observe(&mut self, elem: &BgpElem, collector: &str) {
+        if self.precedes(elem, collector) {
+            self.elem = elem.clone();
+            self.collector = collector.to_string();
+        }
+        self.count += 1;
+        self.first_seen = self.first_seen.min(elem.timestamp);
+        self.last_seen = self.last_seen.max(elem.timestamp);
+        self.peers.insert(DedupPeer {
+            peer_ip: elem.peer_ip,
+            peer_asn: elem.peer_asn,
+        });
+        if !self.collectors.contains(collector) {
+            self.collectors.insert(collector.to_string());
+        }
+    }
+
+    fn absorb(&mut self, other: DedupEvent) {
+        if self.precedes(&other.elem, &other.collector) {
+            self.elem = other.elem;
+            self.collector = other.collector;
+        }
+        self.count += other.count;
+        self.first_seen = self.first_seen.min(other.first_seen);
+        self.last_seen = self.last_seen.max(other.last_seen);
+        self.peers.extend(other.peers);
+        self.collectors.extend(other.collectors);
+    }
+}
+
+/// Collapses duplicate observations of the same routing event
+#[derive(Debug, Clone, Default)]
+pub struct ElemDeduplicator {
+    window: f64,
+    /// Events per key, sorted by time and separated by more than `window`
+    events: HashMap<DedupKey, Vec<DedupEvent>>,
+}
+
+impl ElemDeduplicator {
+    /// Create an empty deduplicator collapsing observations up to `window_secs` apart
+    pub fn new(window_secs: u64) -> Self {
+        Self {
+            window: window_secs as f64,
+            events: HashMap::new(),
+        }
+    }
+
+    /// Window in seconds
+    pub fn window_secs(&self) -> u64 {
+        self.window as u64
+    }
+
+    /// Number of events
+    pub fn len(&self) -> usize {
+        self.events.values().map(|events| events.len()).sum()
+    }
+
+    /// Whether no element has been added yet
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Add one element observed on `collector`
+    pub fn add(&mut self, elem: &BgpElem, collector: Option<&str>) {
+        let collector = collector.unwrap_or_default();
+        let window = self.window;
+        let events = self.events.entry(DedupKey::new(elem)).or_default();
+        let range = overlapping(events, elem.timestamp, elem.timestamp, window);
+        if range.len() == 1 {
+            events[range.start].observe(elem, collector);
+            return;
+        }
+        // No event yet, or the element bridges the gap between two events
+        let mut event = DedupEvent::new(elem, collector);
+        for other in events.drain(range.clone()) {
+            event.absorb(other);
+        }
+        events.insert(range.start, event);
+    }
+
+    /// Merge a partial result (built with the same window) into this one
+    pub fn merge(&mut self, other: ElemDeduplicator) {
+        let window = self.window;
+        for (key, other_events) in other.events {
+            let events = self.events.entry(key).or_default();
+            for mut event in other_events {
+                let range = overlapping(events, event.first_seen, event.last_seen, window);
+                for existing in events.drain(range.clone()) {
+                    event.absorb(existing);
+                }
+                events.insert(range.start, event);
+            }
+        }
+    }
+
+    /// All events, sorted by first observation
+    pub fn events(&self) -> Vec<DedupEvent> {
+        let mut events: Vec<DedupEvent> = self.events.values().flatten().cloned().collect();
+        sort_events(&mut events);
+        events
+    }
+
+    /// Consume the deduplicator, returning its events sorted by first observation
+    pub fn into_events(self) -> Vec<DedupEvent> {
+        let mut events: Vec<DedupEvent> = self.events.into_values().flatten().collect();
+        sort_events(&mut events);
+        events
+    }
+}
+
+/// Index range of the events within `window` of the span `[first, last]`
+fn overlapping(events: &[DedupEvent], first: f64, last: f64, window: f64) -> Range<usize> {
+    let start = events.partition_point(|e| e.last_seen + window < first);
+    let end = events.partition_point(|e| e.first_seen - window <= last);
+    start..end.max(start)
+}
+
+fn sort_events(events: &mut [DedupEvent]) {
+    events.sort_by(|a, b| {
+        a.first_seen
+            .total_cmp(&b.first_seen)
+            .then_with(|| a.elem.prefix.to_string().cmp(&b.elem.prefix.to_string()))
+            .then_with(|| b.count.cmp(&a.count))
+    });
+}
+
+/// Search sink that deduplicates elements instead of emitting them
+///
+/// Each batch is deduplicated on the worker thread that produced it and the
+/// partial result is then merged into the shared one.
+pub struct DedupSink {
+    window_secs: u64,
+    dedup: Mutex<ElemDeduplicator>,
+    progress_callback: Option<SearchProgressCallback>,
+}
+
+impl DedupSink {
+    /// Create a sink collapsing observations up to `window_secs` apart
+    pub fn new(window_secs: u64) -> Self {
+        Self {
+            window_secs,
+            dedup: Mutex::new(ElemDeduplicator::new(window_secs)),
+            progress_callback: None,
+        }
+    }
+
+    /// Forward search progress updates to `callback`
+    pub fn with_progress(mut self, callback: SearchProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
+    }
+
+    /// Take the events collected so far, leaving an empty deduplicator in place
+    pub fn take(&self) -> ElemDeduplicator {
+        match self.dedup.lock() {
+            Ok(mut dedup) => {
+                std::mem::replace(&mut *dedup, ElemDeduplicator::new(self.window_secs))
+            }
+            Err(_) => ElemDeduplicator::new(self.window_secs),
+        }
+    }
+}
+
+impl SearchSink for DedupSink {
+    fn on_progress(&self, progress: SearchProgress) {
+        if let Some(ref cb) = self.progress_callback {
+            cb(progress);
+        }
+    }
+
+    fn on_elements(&self, batch: SearchElementBatch) -> SearchControl {
+        let mut partial = ElemDeduplicator::new(self.window_secs);
+        for elem in &batch.elements {
+            partial.add(elem, Some(batch.collector.as_str()));
+        }
+        match self.dedup.lock() {
+            Ok(mut dedup) => {
+                dedup.merge(partial);
+                SearchControl::Continue
+            }
+            Err(_) => SearchControl::Stop,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgpkit_parser::models::NetworkPrefix;
+    use std::str::FromStr;
+
+    fn elem(prefix: &str, peer: &str, timestamp: f64) -> BgpElem {
+        BgpElem {
+            timestamp,
+            prefix: NetworkPrefix::from_str(prefix).unwrap(),
+            peer_ip: peer.parse().unwrap(),
+            peer_asn: 65000.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_dedup_window() {
+        assert_eq!(parse_dedup_window("0").unwrap(), 0);
+        assert_eq!(parse_dedup_window("30").unwrap(), 30);
+        assert_eq!(parse_dedup_window("1m").unwrap(), 60);
+        assert!(parse_dedup_window("soon").is_err());
+    }
+
+    #[test]
+    fn test_dedup_collapses_within_window() {
+        let mut dedup = ElemDeduplicator::new(10);
+        dedup.add(&elem("10.0.0.0/8", "192.0.2.1", 100.0), Some("rrc00"));
+        dedup.add(&elem("10.0.0.0/8", "192.0.2.2", 105.0), Some("rrc01"));
+        dedup.add(&elem("10.0.0.0/8", "192.0.2.1", 112.0), Some("rrc00"));
+        // More than 10s after the last observation: a new event
+        dedup.add(&elem("10.0.0.0/8", "192.0.2.1", 130.0), Some("rrc00"));
+        dedup.add(&elem("192.0.2.0/24", "192.0.2.1", 100.0), Some("rrc00"));
+
+        let events = dedup.into_events();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].elem.prefix.to_string(), "10.0.0.0/8");
+        assert_eq!(events[0].count, 3);
+        assert_eq!(events[0].peer_count(), 2);
+        assert_eq!(events[0].collector_count(), 2);
+        assert_eq!(events[0].last_seen, 112.0);
+        assert_eq!(events[1].elem.prefix.to_string(), "192.0.2.0/24");
+        assert_eq!(events[2].first_seen, 130.0);
+    }
+
+    #[test]
+    fn test_dedup_bridging_element_joins_events() {
+        let mut dedup = ElemDeduplicator::new(10);
+        dedup.add(&elem("10.0.0.0/8", "192.0.2.1", 100.0), None);
+        dedup.add(&elem("10.0.0.0/8", "192.0.2.1", 118.0), None);
+        assert_eq!(dedup.len(), 2);
+        dedup.add(&elem("10.0.0.0/8", "192.0.2.1", 109.0), None);
+        assert_eq!(dedup.len(), 1);
+        assert_eq!(dedup.events()[0].count, 3);
+    }
+
+    #[test]
+    fn test_dedup_merge_matches_single_pass() {
+        let elems: Vec<BgpElem> = [0.0, 50.0, 3.0, 8.0, 55.0, 14.0, 200.0, 61.0]
+            .iter()
+            .enumerate()
+            .map(|(i, ts)| elem("10.0.0.0/8", &format!("192.0.2.{}", i % 3), *ts))
+            .collect();
+
+        let mut single = ElemDeduplicator::new(6);
+        elems.iter().for_each(|e| single.add(e, Some("rrc00")));
+
+        let mut merged = ElemDeduplicator::new(6);
+        for chunk in elems.chunks(3).rev() {
+            let mut partial = ElemDeduplicator::new(6);
+            chunk.iter().for_each(|e| partial.add(e, Some("rrc00")));
+            merged.merge(partial);
+        }
+
+        assert_eq!(single.into_events(), merged.into_events());
+    }
+
+    #[test]
+    fn test_dedup_sink_merges_batches() {
+        let sink = DedupSink::new(60);
+        for (collector, peer) in [("rrc00", "192.0.2.1"), ("route-views2", "192.0.2.2")] {
+            let control = sink.on_elements(SearchElementBatch {
+                file_index: 0,
+                file_url: String::new(),
+                collector: collector.to_string(),
+                elements: vec![elem("10.0.0.0/8", peer, 1.0)],
+            });
+            assert_eq!(control, SearchControl::Continue);
+        }
+
+        let events = sink.take().into_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].collector, "route-views2");
+        assert_eq!(events[0].collector_count(), 2);
+        assert!(sink.take().is_empty());
+    }
+}
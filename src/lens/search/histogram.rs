@@ -0,0 +1,518 @@
+//! Time-bucketed element histograms
+//!
+//! A [`TimeHistogram`] counts elements per fixed-width time bucket, optionally
+//! split into separate series by [`GroupByField`] values (for example element
+//! type and collector). Like [`ElemAggregator`](super::ElemAggregator), partial
+//! histograms built on worker threads are combined with [`TimeHistogram::merge`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use bgpkit_parser::BgpElem;
+use serde::{Deserialize, Serialize};
+
+use super::aggregate::group_keys;
+use super::{
+    SearchControl, SearchElementBatch, SearchProgress, SearchProgressCallback, SearchSink,
+};
+use crate::utils::GroupByField;
+
+/// Maximum number of buckets a dense [`HistogramSeries`] may span
+pub const MAX_HISTOGRAM_BUCKETS: usize = 1_000_000;
+
+/// Parse a bucket width such as `60`, `30s`, `1m`, `5min` or `1h` into seconds
+///
+/// Plain integers are taken as seconds.
+pub fn parse_bucket_width(width: &str) -> Result<u64> {
+    let width = width.trim();
+    let secs = match width.parse::<u64>() {
+        Ok(secs) => secs,
+        Err(_) => humantime::parse_duration(width)
+            .map_err(|e| anyhow!("invalid bucket width '{}': {}", width, e))?
+            .as_secs(),
+    };
+    if secs == 0 {
+        return Err(anyhow!(
+            "invalid bucket width '{}': must be at least one second",
+            width
+        ));
+    }
+    Ok(secs)
+}
+
+/// Render counts as a unicode sparkline of at most `max_width` characters
+///
+/// When there are more counts than `max_width`, adjacent buckets are summed.
+pub fn sparkline(counts: &[u64], max_width: usize) -> String {
+    const TICKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    if counts.is_empty() || max_width == 0 {
+        return String::new();
+    }
+    let chunk = counts.len().div_ceil(max_width);
+    let values: Vec<u64> = counts.chunks(chunk).map(|c| c.iter().sum()).collect();
+    let max = values.iter().copied().max().unwrap_or(0);
+
+    values
+        .iter()
+        .map(|&v| {
+            if v == 0 || max == 0 {
+                ' '
+            } else {
+                let idx = ((v as f64 / max as f64) * (TICKS.len() - 1) as f64).round() as usize;
+                TICKS[idx.min(TICKS.len() - 1)]
+            }
+        })
+        .collect()
+}
+
+/// New total of one (bucket, series) pair after a merge
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistogramUpdate {
+    /// Unix timestamp of the bucket start
+    pub bucket_start: i64,
+    /// Series key, in the same order as the histogram's split fields
+    pub key: Vec<String>,
+    /// Updated count of the bucket in this series
+    pub count: u64,
+}
+
+/// One series of a [`HistogramSeries`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistogramSeriesEntry {
+    /// Series key, in the same order as `split_by`
+    pub key: Vec<String>,
+    /// Total count over all buckets
+    pub total: u64,
+    /// Counts aligned with [`HistogramSeries::buckets`]
+    pub counts: Vec<u64>,
+}
+
+/// Dense, serializable time series of a [`TimeHistogram`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistogramSeries {
+    /// Bucket width in seconds
+    pub bucket_secs: u64,
+    /// Fields the series are split by (empty for a single series)
+    pub split_by: Vec<GroupByField>,
+    /// Bucket start timestamps, without gaps
+    pub buckets: Vec<i64>,
+    /// One entry per series, busiest first
+    pub series: Vec<HistogramSeriesEntry>,
+    /// Elements whose timestamp fell outside the configured range
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub outside_range: u64,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// Counts elements per time bucket and series
+#[derive(Debug, Clone)]
+pub struct TimeHistogram {
+    bucket_secs: i64,
+    split_by: Vec<GroupByField>,
+    range: Option<(i64, i64)>,
+    counts: HashMap<Vec<String>, BTreeMap<i64, u64>>,
+    outside_range: u64,
+}
+
+impl TimeHistogram {
+    /// Create an empty histogram with `bucket_secs` wide buckets, split by `split_by`
+    pub fn new(bucket_secs: u64, split_by: Vec<GroupByField>) -> Self {
+        Self {
+            bucket_secs: bucket_secs.clamp(1, i64::MAX as u64) as i64,
+            split_by,
+            range: None,
+            counts: HashMap::new(),
+            outside_range: 0,
+        }
+    }
+
+    /// Restrict the histogram to `[start, end)` (unix seconds)
+    ///
+    /// The series then covers the whole range even where no element was seen,
+    /// and elements outside it are only counted in `outside_range`.
+    pub fn with_range(mut self, start: i64, end: i64) -> Self {
+        self.range = Some((start, end.max(start)));
+        self
+    }
+
+    /// An empty histogram with the same width, split and range
+    pub fn empty_like(&self) -> Self {
+        Self {
+            bucket_secs: self.bucket_secs,
+            split_by: self.split_by.clone(),
+            range: self.range,
+            counts: HashMap::new(),
+            outside_range: 0,
+        }
+    }
+
+    /// Bucket width in seconds
+    pub fn bucket_secs(&self) -> u64 {
+        self.bucket_secs as u64
+    }
+
+    /// Fields the series are split by
+    pub fn split_by(&self) -> &[GroupByField] {
+        &self.split_by
+    }
+
+    /// Start of the bucket containing `timestamp`
+    pub fn bucket_start(&self, timestamp: f64) -> i64 {
+        (timestamp.floor() as i64).div_euclid(self.bucket_secs) * self.bucket_secs
+    }
+
+    /// Number of buckets spanned by the configured range (0 without a range)
+    pub fn range_buckets(&self) -> u64 {
+        match self.range {
+            Some((start, end)) if end > start => {
+                ((self.bucket_start((end - 1) as f64) - self.bucket_start(start as f64))
+                    / self.bucket_secs
+                    + 1) as u64
+            }
+            _ => 0,
+        }
+    }
+
+    /// Fail early when the configured range would exceed [`MAX_HISTOGRAM_BUCKETS`]
+    pub fn check_range(&self) -> Result<()> {
+        let buckets = self.range_buckets();
+        if buckets > MAX_HISTOGRAM_BUCKETS as u64 {
+            return Err(anyhow!(
+                "{} buckets of {}s exceed the maximum of {}; use a wider bucket",
+                buckets,
+                self.bucket_secs,
+                MAX_HISTOGRAM_BUCKETS
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether no element has been counted
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Total number of counted elements over all buckets and series
+    pub fn total(&self) -> u64 {
+        self.counts.values().flat_map(|b| b.values()).sum()
+    }
+
+    /// Count one element; `collector` is used when splitting by collector
+    pub fn add(&mut self, elem: &BgpElem, collector: Option<&str>) {
+        if let Some((start, end)) = self.range {
+            let ts = elem.timestamp.floor() as i64;
+            if ts < start || ts >= end {
+                self.outside_range += 1;
+                return;
+            }
+        }
+        let bucket = self.bucket_start(elem.timestamp);
+        for key in group_keys(&self.split_by, elem, collector) {
+            *self
+                .counts
+                .entry(key)
+                .or_default()
+                .entry(bucket)
+                .or_insert(0) += 1;
+        }
+    }
+
+    /// Merge a partial histogram (with the same width and split) into this one
+    pub fn merge(&mut self, other: TimeHistogram) {
+        self.outside_range += other.outside_range;
+        for (key, buckets) in other.counts {
+            let series = self.counts.entry(key).or_default();
+            for (bucket_start, count) in buckets {
+                *series.entry(bucket_start).or_insert(0) += count;
+            }
+        }
+    }
+
+    /// Merge a partial histogram and return the new totals of every bucket it touched
+    pub fn merge_updates(&mut self, other: TimeHistogram) -> Vec<HistogramUpdate> {
+        self.outside_range += other.outside_range;
+        let mut updates = Vec::new();
+        for (key, buckets) in other.counts {
+            let series = self.counts.entry(key.clone()).or_default();
+            for (bucket_start, count) in buckets {
+                let total = series.entry(bucket_start).or_insert(0);
+                *total += count;
+                updates.push(HistogramUpdate {
+                    bucket_start,
+                    key: key.clone(),
+                    count: *total,
+                });
+            }
+        }
+        updates.sort_by(|a, b| {
+            a.bucket_start
+                .cmp(&b.bucket_start)
+                .then_with(|| a.key.cmp(&b.key))
+        });
+        updates
+    }
+
+    /// Build the dense time series, filling buckets without elements with zeros
+    ///
+    /// Fails if the series would span more than [`MAX_HISTOGRAM_BUCKETS`] buckets.
+    pub fn series(&self) -> Result<HistogramSeries> {
+        let data_buckets = self.counts.values().flat_map(|b| b.keys().copied());
+        let range_buckets = self.range.into_iter().flat_map(|(start, end)| {
+            [
+                self.bucket_start(start as f64),
+                self.bucket_start((end - 1).max(start) as f64),
+            ]
+        });
+        let (first, last) = data_buckets
+            .chain(range_buckets)
+            .fold(None, |acc: Option<(i64, i64)>, b| match acc {
+                None => Some((b, b)),
+                Some((lo, hi)) => Some((lo.min(b), hi.max(b))),
+            })
+            .unwrap_or((0, -1));
+
+        let num_buckets = if last < first {
+            0
+        } else {
+            ((last - first) / self.bucket_secs + 1) as usize
+        };
+        if num_buckets > MAX_HISTOGRAM_BUCKETS {
+            return Err(anyhow!(
+                "histogram would span {} buckets of {}s; use a wider bucket",
+                num_buckets,
+                self.bucket_secs
+            ));
+        }
+
+        let buckets: Vec<i64> = (0..num_buckets as i64)
+            .map(|i| first + i * self.bucket_secs)
+            .collect();
+        let mut series: Vec<HistogramSeriesEntry> = self
+            .counts
+            .iter()
+            .map(|(key, counts)| {
+                let mut dense = vec![0; num_buckets];
+                for (bucket, count) in counts {
+                    dense[((bucket - first) / self.bucket_secs) as usize] = *count;
+                }
+                HistogramSeriesEntry {
+                    key: key.clone(),
+                    total: counts.values().sum(),
+                    counts: dense,
+                }
+            })
+            .collect();
+        series.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.key.cmp(&b.key)));
+
+        Ok(HistogramSeries {
+            bucket_secs: self.bucket_secs as u64,
+            split_by: self.split_by.clone(),
+            buckets,
+            series,
+            outside_range: self.outside_range,
+        })
+    }
+}
+
+/// Callback receiving the updated buckets after each merged batch
+///
+/// Returning [`SearchControl::Stop`] stops the search, e.g. when the receiving
+/// client has gone away.
+pub type HistogramUpdateCallback = Arc<dyn Fn(Vec<HistogramUpdate>) -> SearchControl + Send + Sync>;
+
+/// Search sink that builds a [`TimeHistogram`] instead of emitting elements
+///
+/// Each batch is bucketed on its worker thread and merged into the shared
+/// histogram. The optional update callback is invoked while the histogram is
+/// locked, so the totals it receives never go backwards.
+pub struct HistogramSink {
+    template: TimeHistogram,
+    histogram: Mutex<TimeHistogram>,
+    progress_callback: Option<SearchProgressCallback>,
+    update_callback: Option<HistogramUpdateCallback>,
+}
+
+impl HistogramSink {
+    /// Create a sink collecting into an empty copy of `histogram`'s configuration
+    pub fn new(histogram: TimeHistogram) -> Self {
+        let template = histogram.empty_like();
+        Self {
+            histogram: Mutex::new(template.clone()),
+            template,
+            progress_callback: None,
+            update_callback: None,
+        }
+    }
+
+    /// Forward search progress updates to `callback`
+    pub fn with_progress(mut self, callback: SearchProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
+    }
+
+    /// Report updated bucket totals to `callback` after every merged batch
+    pub fn with_updates(mut self, callback: HistogramUpdateCallback) -> Self {
+        self.update_callback = Some(callback);
+        self
+    }
+
+    /// Take the histogram collected so far, leaving an empty one in its place
+    pub fn take(&self) -> TimeHistogram {
+        match self.histogram.lock() {
+            Ok(mut histogram) => std::mem::replace(&mut *histogram, self.template.empty_like()),
+            Err(_) => self.template.empty_like(),
+        }
+    }
+}
+
+impl SearchSink for HistogramSink {
+    fn on_progress(&self, progress: SearchProgress) {
+        if let Some(ref cb) = self.progress_callback {
+            cb(progress);
+        }
+    }
+
+    fn on_elements(&self, batch: SearchElementBatch) -> SearchControl {
+        let mut partial = self.template.empty_like();
+        for elem in &batch.elements {
+            partial.add(elem, Some(batch.collector.as_str()));
+        }
+
+        let mut histogram = match self.histogram.lock() {
+            Ok(histogram) => histogram,
+            Err(_) => return SearchControl::Stop,
+        };
+        match &self.update_callback {
+            Some(cb) => {
+                let updates = histogram.merge_updates(partial);
+                if updates.is_empty() {
+                    SearchControl::Continue
+                } else {
+                    cb(updates)
+                }
+            }
+            None => {
+                histogram.merge(partial);
+                SearchControl::Continue
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgpkit_parser::models::ElemType;
+
+    fn elem(timestamp: f64, elem_type: ElemType) -> BgpElem {
+        BgpElem {
+            timestamp,
+            elem_type,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_bucket_width() {
+        assert_eq!(parse_bucket_width("60").unwrap(), 60);
+        assert_eq!(parse_bucket_width("1m").unwrap(), 60);
+        assert_eq!(parse_bucket_width("5min").unwrap(), 300);
+        assert_eq!(parse_bucket_width("1h").unwrap(), 3600);
+        assert!(parse_bucket_width("0").is_err());
+        assert!(parse_bucket_width("soon").is_err());
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[0, 1, 2, 4], 10), " ▃▅█");
+        // Downsampled by summing pairs: [1, 2, 8]
+        assert_eq!(sparkline(&[0, 1, 1, 1, 4, 4], 3), "▂▃█");
+        assert_eq!(sparkline(&[], 10), "");
+    }
+
+    #[test]
+    fn test_histogram_series_fills_gaps() {
+        let mut hist = TimeHistogram::new(60, vec![GroupByField::Type]);
+        hist.add(&elem(0.0, ElemType::ANNOUNCE), None);
+        hist.add(&elem(59.9, ElemType::ANNOUNCE), None);
+        hist.add(&elem(185.0, ElemType::WITHDRAW), None);
+
+        let series = hist.series().unwrap();
+        assert_eq!(series.buckets, vec![0, 60, 120, 180]);
+        assert_eq!(series.series[0].key, vec!["A"]);
+        assert_eq!(series.series[0].counts, vec![2, 0, 0, 0]);
+        assert_eq!(series.series[1].key, vec!["W"]);
+        assert_eq!(series.series[1].counts, vec![0, 0, 0, 1]);
+        assert_eq!(hist.total(), 3);
+    }
+
+    #[test]
+    fn test_histogram_range() {
+        let mut hist = TimeHistogram::new(60, vec![]).with_range(60, 240);
+        hist.add(&elem(30.0, ElemType::ANNOUNCE), None);
+        hist.add(&elem(100.0, ElemType::ANNOUNCE), None);
+
+        let series = hist.series().unwrap();
+        assert_eq!(series.buckets, vec![60, 120, 180]);
+        assert_eq!(series.series[0].counts, vec![1, 0, 0]);
+        assert_eq!(series.outside_range, 1);
+    }
+
+    #[test]
+    fn test_histogram_range_buckets() {
+        let hist = TimeHistogram::new(60, vec![]).with_range(60, 240);
+        assert_eq!(hist.range_buckets(), 3);
+        assert!(hist.check_range().is_ok());
+        assert_eq!(TimeHistogram::new(60, vec![]).range_buckets(), 0);
+
+        let too_many = TimeHistogram::new(1, vec![]).with_range(0, 10_000_000);
+        assert!(too_many.check_range().is_err());
+    }
+
+    #[test]
+    fn test_histogram_merge_updates() {
+        let mut total = TimeHistogram::new(60, vec![]);
+        let mut partial = TimeHistogram::new(60, vec![]);
+        partial.add(&elem(10.0, ElemType::ANNOUNCE), None);
+        total.merge(partial.clone());
+
+        let updates = total.merge_updates(partial);
+        assert_eq!(
+            updates,
+            vec![HistogramUpdate {
+                bucket_start: 0,
+                key: vec![],
+                count: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_histogram_sink_reports_updates() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let received_clone = received.clone();
+        let sink = HistogramSink::new(TimeHistogram::new(60, vec![GroupByField::Collector]))
+            .with_updates(Arc::new(move |updates| {
+                received_clone.lock().unwrap().extend(updates);
+                SearchControl::Continue
+            }));
+
+        for _ in 0..2 {
+            sink.on_elements(SearchElementBatch {
+                file_index: 0,
+                file_url: String::new(),
+                collector: "rrc00".to_string(),
+                elements: vec![elem(61.0, ElemType::ANNOUNCE)],
+            });
+        }
+
+        let counts: Vec<u64> = received.lock().unwrap().iter().map(|u| u.count).collect();
+        assert_eq!(counts, vec![1, 2]);
+        assert_eq!(sink.take().total(), 2);
+    }
+}
@@ -0,0 +1,349 @@
+//! Local MRT archive index
+//!
+//! Scans a directory tree laid out like the public RouteViews and RIPE RIS
+//! archives and produces `BrokerItem` lists, so searches and RIB
+//! reconstruction can run against an on-prem mirror without reaching the
+//! BGPKIT broker.
+//!
+//! Recognized layouts (relative to the archive root):
+//!
+//! ```text
+//! route-views2/bgpdata/2024.01/UPDATES/updates.20240101.0000.bz2
+//! route-views2/bgpdata/2024.01/RIBS/rib.20240101.0000.bz2
+//! bgpdata/2024.01/UPDATES/updates.20240101.0000.bz2      (route-views2 at the root)
+//! rrc00/2024.01/updates.20240101.0000.gz
+//! rrc00/2024.01/bview.20240101.0000.gz
+//! ```
+//!
+//! Collector, dump type and timestamp are derived from the path alone; file
+//! contents are never opened while indexing. Month directories (`YYYY.MM`)
+//! outside the queried time range are skipped without being read.
+
+use anyhow::{anyhow, Result};
+use bgpkit_broker::BrokerItem;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use std::path::{Path, PathBuf};
+
+use super::backend::{BrokerBackend, BrokerQuery};
+
+/// Duration covered by a RIPE RIS updates file, in seconds.
+const RIS_UPDATES_INTERVAL_SECS: i64 = 5 * 60;
+
+/// Duration covered by a RouteViews updates file, in seconds.
+const ROUTEVIEWS_UPDATES_INTERVAL_SECS: i64 = 15 * 60;
+
+/// Collector name used when a RouteViews `bgpdata` tree sits directly at the
+/// archive root (the layout of the original route-views2 archive).
+const ROUTEVIEWS_ROOT_COLLECTOR: &str = "route-views2";
+
+/// Index over a local directory of RouteViews/RIS-style MRT archives.
+#[derive(Debug, Clone)]
+pub struct LocalArchive {
+    root: PathBuf,
+}
+
+impl LocalArchive {
+    /// Create an index rooted at `root`. The directory must exist.
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        if !root.is_dir() {
+            return Err(anyhow!(
+                "local archive directory does not exist or is not a directory: {}",
+                root.display()
+            ));
+        }
+        Ok(Self { root })
+    }
+
+    /// Archive root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl BrokerBackend for LocalArchive {
+    fn name(&self) -> String {
+        format!("local archive {}", self.root.display())
+    }
+
+    /// Find all archive files matching the query, sorted by start time and collector.
+    fn query(&self, query: &BrokerQuery) -> Result<Vec<BrokerItem>> {
+        query.validate()?;
+        let mut items = Vec::new();
+        let mut pending = vec![self.root.clone()];
+
+        while let Some(dir) = pending.pop() {
+            let entries = std::fs::read_dir(&dir).map_err(|e| {
+                anyhow!("failed to read archive directory {}: {}", dir.display(), e)
+            })?;
+
+            for entry in entries {
+                let entry = entry.map_err(|e| {
+                    anyhow!("failed to read archive directory {}: {}", dir.display(), e)
+                })?;
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with('.') {
+                    continue;
+                }
+                let path = entry.path();
+                let file_type = entry.file_type().map_err(|e| {
+                    anyhow!("failed to stat archive entry {}: {}", path.display(), e)
+                })?;
+
+                if file_type.is_dir() || (file_type.is_symlink() && path.is_dir()) {
+                    if let Some((year, month)) = parse_month_dir(&name) {
+                        if !overlaps_month(query, year, month) {
+                            continue;
+                        }
+                    }
+                    pending.push(path);
+                    continue;
+                }
+
+                let Ok(relative) = path.strip_prefix(&self.root) else {
+                    continue;
+                };
+                let Some(mut item) = parse_archive_path(relative) else {
+                    continue;
+                };
+                if !query.matches(&item) {
+                    continue;
+                }
+                let size = std::fs::metadata(&path)
+                    .map(|m| m.len() as i64)
+                    .unwrap_or_default();
+                item.url = path.to_string_lossy().to_string();
+                item.rough_size = size;
+                item.exact_size = size;
+                items.push(item);
+            }
+        }
+
+        items.sort_by(|a, b| {
+            a.ts_start
+                .cmp(&b.ts_start)
+                .then_with(|| a.collector_id.cmp(&b.collector_id))
+                .then_with(|| a.data_type.cmp(&b.data_type))
+        });
+        Ok(items)
+    }
+}
+
+/// Whether a `YYYY.MM` month directory can contain files for this query.
+fn overlaps_month(query: &BrokerQuery, year: i32, month: u32) -> bool {
+    let Some(first) = NaiveDate::from_ymd_opt(year, month, 1) else {
+        return true;
+    };
+    let next = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    };
+    let Some(next) = next else {
+        return true;
+    };
+    let month_start = first.and_hms_opt(0, 0, 0).map(|t| t.and_utc().timestamp());
+    let month_end = next.and_hms_opt(0, 0, 0).map(|t| t.and_utc().timestamp());
+    match (month_start, month_end) {
+        // An updates file starting in the previous month can still end
+        // inside the range, so the lower bound is padded by one interval.
+        (Some(start), Some(end)) => {
+            start < query.ts_end && end + ROUTEVIEWS_UPDATES_INTERVAL_SECS > query.ts_start
+        }
+        _ => true,
+    }
+}
+
+fn parse_month_dir(name: &str) -> Option<(i32, u32)> {
+    let (year, month) = name.split_once('.')?;
+    if year.len() != 4 || month.len() != 2 {
+        return None;
+    }
+    Some((year.parse().ok()?, month.parse().ok()?))
+}
+
+pub(super) fn is_ris_collector(name: &str) -> bool {
+    name.strip_prefix("rrc")
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Parse collector, dump type and time range from a path relative to the
+/// archive root. The returned item has an empty URL and zero sizes.
+pub(super) fn parse_archive_path(relative: &Path) -> Option<BrokerItem> {
+    let components: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    let (file_name, dirs) = components.split_last()?;
+    if file_name.ends_with(".partial") {
+        return None;
+    }
+
+    let collector = match dirs.iter().position(|d| d == "bgpdata") {
+        Some(0) => ROUTEVIEWS_ROOT_COLLECTOR.to_string(),
+        Some(idx) => dirs[idx - 1].clone(),
+        None => dirs.iter().find(|d| is_ris_collector(d))?.clone(),
+    };
+
+    let mut parts = file_name.split('.');
+    let data_type = match parts.next()? {
+        "updates" => "updates",
+        "rib" | "bview" => "rib",
+        _ => return None,
+    };
+    let date = parts.next()?;
+    let time = parts.next()?;
+    let ts_start = NaiveDateTime::parse_from_str(&format!("{date}{time}"), "%Y%m%d%H%M").ok()?;
+
+    let ts_end = if data_type == "rib" {
+        ts_start
+    } else {
+        let interval = if is_ris_collector(&collector) {
+            RIS_UPDATES_INTERVAL_SECS
+        } else {
+            ROUTEVIEWS_UPDATES_INTERVAL_SECS
+        };
+        DateTime::from_timestamp(ts_start.and_utc().timestamp() + interval, 0)?.naive_utc()
+    };
+
+    Some(BrokerItem {
+        ts_start,
+        ts_end,
+        collector_id: collector,
+        data_type: data_type.to_string(),
+        url: String::new(),
+        rough_size: 0,
+        exact_size: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> i64 {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+            .unwrap()
+            .and_utc()
+            .timestamp()
+    }
+
+    fn touch(root: &Path, relative: &str) {
+        let path = root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"mrt").unwrap();
+    }
+
+    #[test]
+    fn test_parse_routeviews_paths() {
+        let item = parse_archive_path(Path::new(
+            "route-views.amsix/bgpdata/2024.01/UPDATES/updates.20240101.0015.bz2",
+        ))
+        .unwrap();
+        assert_eq!(item.collector_id, "route-views.amsix");
+        assert_eq!(item.data_type, "updates");
+        assert_eq!(item.ts_start.and_utc().timestamp(), ts("2024-01-01 00:15"));
+        assert_eq!(item.ts_end.and_utc().timestamp(), ts("2024-01-01 00:30"));
+
+        let item =
+            parse_archive_path(Path::new("bgpdata/2024.01/RIBS/rib.20240101.0200.bz2")).unwrap();
+        assert_eq!(item.collector_id, "route-views2");
+        assert_eq!(item.data_type, "rib");
+        assert_eq!(item.ts_start, item.ts_end);
+    }
+
+    #[test]
+    fn test_parse_ris_paths() {
+        let item = parse_archive_path(Path::new("rrc00/2024.01/updates.20240101.0005.gz")).unwrap();
+        assert_eq!(item.collector_id, "rrc00");
+        assert_eq!(item.data_type, "updates");
+        assert_eq!(item.ts_end.and_utc().timestamp(), ts("2024-01-01 00:10"));
+
+        let item =
+            parse_archive_path(Path::new("mirror/rrc21/2024.01/bview.20240101.0800.gz")).unwrap();
+        assert_eq!(item.collector_id, "rrc21");
+        assert_eq!(item.data_type, "rib");
+    }
+
+    #[test]
+    fn test_parse_rejects_unrelated_files() {
+        assert!(parse_archive_path(Path::new("rrc00/2024.01/README.txt")).is_none());
+        assert!(parse_archive_path(Path::new("rrc00/2024.01/updates.2024.gz")).is_none());
+        assert!(parse_archive_path(Path::new("misc/updates.20240101.0000.gz")).is_none());
+        assert!(
+            parse_archive_path(Path::new("rrc00/2024.01/updates.20240101.0000.gz.partial"))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_query_filters_and_sorts() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        touch(root, "rrc00/2024.01/updates.20240101.0000.gz");
+        touch(root, "rrc00/2024.01/updates.20240101.0005.gz");
+        touch(root, "rrc00/2024.01/updates.20240101.0100.gz");
+        touch(root, "rrc00/2024.01/bview.20240101.0000.gz");
+        touch(root, "rrc00/2023.12/updates.20231231.2355.gz");
+        touch(
+            root,
+            "route-views2/bgpdata/2024.01/UPDATES/updates.20240101.0000.bz2",
+        );
+        touch(
+            root,
+            "route-views2/bgpdata/2024.01/RIBS/rib.20240101.0000.bz2",
+        );
+
+        let archive = LocalArchive::new(root).unwrap();
+        let query = BrokerQuery {
+            ts_start: ts("2024-01-01 00:00"),
+            ts_end: ts("2024-01-01 00:10"),
+            data_type: Some("updates".to_string()),
+            ..Default::default()
+        };
+        let items = archive.query(&query).unwrap();
+        let names: Vec<_> = items
+            .iter()
+            .map(|i| {
+                (
+                    i.collector_id.as_str(),
+                    i.ts_start.format("%H%M").to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("rrc00", "2355".to_string()),
+                ("route-views2", "0000".to_string()),
+                ("rrc00", "0000".to_string()),
+                ("rrc00", "0005".to_string()),
+            ]
+        );
+        assert!(items.iter().all(|i| i.exact_size == 3));
+
+        let query = BrokerQuery {
+            project: Some("riperis".to_string()),
+            ..query
+        };
+        let items = archive.query(&query).unwrap();
+        assert!(items.iter().all(|i| i.collector_id == "rrc00"));
+        assert_eq!(items.len(), 3);
+
+        let query = BrokerQuery {
+            ts_start: ts("2023-12-31 23:58"),
+            ts_end: ts("2024-01-01 00:01"),
+            collector: Some("rrc00".to_string()),
+            project: None,
+            data_type: None,
+        };
+        let items = archive.query(&query).unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].ts_start.format("%Y%m").to_string(), "202312");
+    }
+
+    #[test]
+    fn test_missing_root_is_an_error() {
+        assert!(LocalArchive::new("/nonexistent/monocle-archive").is_err());
+    }
+}
@@ -0,0 +1,382 @@
+//! Local file lists for the search executor
+//!
+//! Expands file paths, glob patterns and directories given on the command
+//! line into `BrokerItem`s, so a set of downloaded MRT files can be parsed by
+//! the same parallel executor as a broker search.
+//!
+//! - Plain paths and remote URLs are used as given.
+//! - Directories are walked recursively; hidden entries and `.partial`
+//!   downloads are skipped.
+//! - Patterns may use `*`, `?` and `[...]` within a path component, and `**`
+//!   as a component matching any number of directories.
+//!
+//! Collector, dump type and start time are derived from the path: archive
+//! layouts (see [`LocalArchive`](super::LocalArchive)) are recognized
+//! anywhere in the path, then any `rrcNN` or `route-views*` component, and
+//! the parent directory name otherwise.
+
+use anyhow::{anyhow, Result};
+use bgpkit_broker::BrokerItem;
+use chrono::DateTime;
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+use super::backend::is_remote;
+use super::local_archive::{is_ris_collector, parse_archive_path};
+use crate::lens::parse::text_dump::infer_timestamp_from_path;
+
+/// Expand paths, glob patterns and directories into items for
+/// [`SearchLens::search_items`](super::SearchLens::search_items).
+///
+/// Files keep the order of `paths`, with directory and pattern matches sorted
+/// by path; files listed more than once are only returned once. A path that
+/// does not exist or a pattern that matches nothing is an error.
+pub fn local_file_items<S: AsRef<str>>(paths: &[S]) -> Result<Vec<BrokerItem>> {
+    let mut seen = HashSet::new();
+    let mut items = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        let files = if is_remote(path) {
+            vec![path.to_string()]
+        } else if is_pattern(path) {
+            let files = expand_pattern(path)?;
+            if files.is_empty() {
+                return Err(anyhow!("pattern matched no files: {}", path));
+            }
+            files
+        } else if Path::new(path).is_dir() {
+            let mut files = Vec::new();
+            walk_dir(Path::new(path), &mut files)?;
+            files.sort();
+            files
+                .into_iter()
+                .map(|file| file.to_string_lossy().to_string())
+                .collect()
+        } else if Path::new(path).exists() {
+            vec![path.to_string()]
+        } else {
+            return Err(anyhow!("no such file or directory: {}", path));
+        };
+
+        for file in files {
+            if seen.insert(file.clone()) {
+                items.push(local_file_item(&file));
+            }
+        }
+    }
+    Ok(items)
+}
+
+/// Describe one local file or URL as a `BrokerItem`.
+///
+/// Files without a recognizable dump time get a start time of 0, and files
+/// not named like RIB dumps (`rib.*`, `bview.*`) are treated as updates.
+pub fn local_file_item(path: &str) -> BrokerItem {
+    let size = std::fs::metadata(path)
+        .map(|m| m.len() as i64)
+        .unwrap_or_default();
+    let url = path.to_string();
+    let path = path.split_once("://").map(|(_, rest)| rest).unwrap_or(path);
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+
+    // Try the path as relative to every possible archive root
+    let archive_item = (0..components.len())
+        .find_map(|start| parse_archive_path(Path::new(&components[start..].join("/"))));
+    if let Some(item) = archive_item {
+        return BrokerItem {
+            url,
+            rough_size: size,
+            exact_size: size,
+            ..item
+        };
+    }
+
+    let (file_name, dirs) = components.split_last().unwrap_or((&"", &[]));
+    let collector = dirs
+        .iter()
+        .rev()
+        .find(|dir| is_ris_collector(dir) || dir.starts_with("route-views"))
+        .or(dirs.last())
+        .map(|dir| dir.to_string())
+        .unwrap_or_default();
+    let data_type = if file_name.starts_with("rib") || file_name.starts_with("bview") {
+        "rib"
+    } else {
+        "updates"
+    };
+    let ts_start = infer_timestamp_from_path(file_name)
+        .and_then(|ts| DateTime::from_timestamp(ts as i64, 0))
+        .map(|ts| ts.naive_utc())
+        .unwrap_or_default();
+
+    BrokerItem {
+        ts_start,
+        ts_end: ts_start,
+        collector_id: collector,
+        data_type: data_type.to_string(),
+        url,
+        rough_size: size,
+        exact_size: size,
+    }
+}
+
+fn is_pattern(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+fn is_skipped(name: &str) -> bool {
+    name.starts_with('.') || name.ends_with(".partial")
+}
+
+/// Collect all files below `dir`
+fn walk_dir(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| anyhow!("failed to read directory {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| anyhow!("failed to read directory {}: {}", dir.display(), e))?;
+        if is_skipped(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            walk_dir(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Expand a glob pattern into the sorted list of matching files
+fn expand_pattern(pattern: &str) -> Result<Vec<String>> {
+    let pattern = Path::new(pattern);
+    let mut base = PathBuf::new();
+    let mut parts = Vec::new();
+    for component in pattern.components() {
+        match component {
+            Component::Normal(part) => {
+                let part = part.to_string_lossy().to_string();
+                if parts.is_empty() && !is_pattern(&part) {
+                    base.push(part);
+                } else {
+                    parts.push(part);
+                }
+            }
+            other if parts.is_empty() => base.push(other.as_os_str()),
+            other => parts.push(other.as_os_str().to_string_lossy().to_string()),
+        }
+    }
+
+    let mut files = Vec::new();
+    match_components(&base, &parts, &mut files)?;
+    files.sort();
+    files.dedup();
+    Ok(files
+        .into_iter()
+        .map(|file| file.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Collect the files below `dir` matching the remaining pattern components
+fn match_components(dir: &Path, parts: &[String], files: &mut Vec<PathBuf>) -> Result<()> {
+    let Some((part, rest)) = parts.split_first() else {
+        if dir.is_file() {
+            files.push(dir.to_path_buf());
+        }
+        return Ok(());
+    };
+    let read_dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    if !read_dir.is_dir() {
+        return Ok(());
+    }
+    if !is_pattern(part) && part != "**" {
+        return match_components(&dir.join(part), rest, files);
+    }
+
+    if part == "**" {
+        // Zero directories, then one more level of any directory
+        match_components(dir, rest, files)?;
+    }
+    let entries = std::fs::read_dir(read_dir)
+        .map_err(|e| anyhow!("failed to read directory {}: {}", read_dir.display(), e))?;
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| anyhow!("failed to read directory {}: {}", read_dir.display(), e))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if is_skipped(&name) {
+            continue;
+        }
+        let path = dir.join(&name);
+        if part == "**" {
+            if path.is_dir() {
+                match_components(&path, parts, files)?;
+            }
+        } else if glob_match(part, &name) {
+            match_components(&path, rest, files)?;
+        }
+    }
+    Ok(())
+}
+
+/// Match a file name against a pattern with `*`, `?` and `[...]` (`[!...]`
+/// negates, `a-z` ranges)
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Position after the last `*` and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut n) = (0, 0);
+
+    while n < name.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(p + 1),
+            Some('[') => match_class(&pattern[p..], name[n]).map(|len| p + len),
+            Some(c) if *c == name[n] => Some(p + 1),
+            _ => None,
+        };
+        match (step, star) {
+            (Some(next), _) => {
+                p = next;
+                n += 1;
+            }
+            (None, Some((star_p, star_n))) => {
+                p = star_p;
+                n = star_n + 1;
+                star = Some((star_p, star_n + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Match `c` against the character class at the start of `pattern`; returns
+/// the length of the class when it matches
+fn match_class(pattern: &[char], c: char) -> Option<usize> {
+    let end = pattern.iter().skip(2).position(|c| *c == ']')? + 2;
+    let class = &pattern[1..end];
+    let (negated, class) = match class.first() {
+        Some('!' | '^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut matched = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            matched |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            matched |= class[i] == c;
+            i += 1;
+        }
+    }
+    (matched != negated).then_some(end + 1)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn item_timestamp(item: &BrokerItem) -> i64 {
+        item.ts_start.and_utc().timestamp()
+    }
+
+    fn touch(root: &Path, relative: &str) {
+        let path = root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"mrt").unwrap();
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("updates.*.gz", "updates.20240101.0000.gz"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match(
+            "updates.2024010?.*",
+            "updates.20240101.0000.bz2"
+        ));
+        assert!(glob_match("rrc[0-2][0-9]", "rrc21"));
+        assert!(!glob_match("rrc[!0]*", "rrc00"));
+        assert!(!glob_match("updates.*.gz", "updates.20240101.0000.bz2"));
+        assert!(!glob_match("rib.*", "updates.20240101.0000.gz"));
+    }
+
+    #[test]
+    fn test_local_file_item_collectors() {
+        let item = local_file_item("/data/mirror/rrc00/2024.01/updates.20240101.0005.gz");
+        assert_eq!(item.collector_id, "rrc00");
+        assert_eq!(item.data_type, "updates");
+        assert_eq!(item_timestamp(&item), 1704067500);
+
+        let item = local_file_item(
+            "http://archive.routeviews.org/route-views.sg/bgpdata/2024.01/RIBS/rib.20240101.0000.bz2",
+        );
+        assert_eq!(item.collector_id, "route-views.sg");
+        assert_eq!(item.data_type, "rib");
+
+        let item = local_file_item("downloads/rrc21/bview.gz");
+        assert_eq!(item.collector_id, "rrc21");
+        assert_eq!(item.data_type, "rib");
+
+        let item = local_file_item("captures/edge1/session.mrt");
+        assert_eq!(item.collector_id, "edge1");
+        assert_eq!(item.data_type, "updates");
+        assert_eq!(item_timestamp(&item), 0);
+    }
+
+    #[test]
+    fn test_local_file_items_expansion() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        touch(root, "rrc00/2024.01/updates.20240101.0000.gz");
+        touch(root, "rrc00/2024.01/updates.20240101.0005.gz");
+        touch(root, "rrc00/2024.01/updates.20240101.0010.gz.partial");
+        touch(root, "rrc00/2024.01/.hidden");
+        touch(root, "rrc01/2024.01/updates.20240101.0000.gz");
+        touch(root, "rrc01/2024.01/bview.20240101.0000.gz");
+
+        let root_str = root.to_string_lossy();
+        let items = local_file_items(&[format!("{root_str}/rrc00")]).unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|item| item.collector_id == "rrc00"));
+        assert_eq!(items[0].exact_size, 3);
+
+        let pattern = format!("{root_str}/rrc*/*/updates.*.gz");
+        let items = local_file_items(&[pattern]).unwrap();
+        let names: Vec<_> = items
+            .iter()
+            .map(|item| (item.collector_id.as_str(), item_timestamp(item)))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("rrc00", 1704067200),
+                ("rrc00", 1704067500),
+                ("rrc01", 1704067200)
+            ]
+        );
+
+        // `**` matches any depth; files are listed once
+        let items = local_file_items(&[
+            format!("{root_str}/**/bview.*"),
+            format!("{root_str}/rrc01"),
+        ])
+        .unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].data_type, "rib");
+
+        assert!(local_file_items(&[format!("{root_str}/rrc9*")]).is_err());
+        assert!(local_file_items(&[format!("{root_str}/missing.gz")]).is_err());
+    }
+}
@@ -34,11 +34,35 @@
 //! })?;
 //! ```
 
+mod aggregate;
+mod backend;
+mod dedup;
+mod histogram;
+mod local_archive;
+mod local_files;
+mod ordered;
 mod query_builder;
 
+pub use aggregate::{AggregateRow, AggregateSink, ElemAggregator, GroupStats};
+pub use backend::{
+    broker_backend_from_config, is_remote, merge_broker_items, query_all, BgpkitBrokerBackend,
+    BrokerBackend, BrokerQuery, ManifestBackend, BROKER_PAGE_SIZE,
+};
+pub use dedup::{parse_dedup_window, DedupEvent, DedupPeer, DedupSink, ElemDeduplicator};
+pub use histogram::{
+    parse_bucket_width, sparkline, HistogramSeries, HistogramSeriesEntry, HistogramSink,
+    HistogramUpdate, HistogramUpdateCallback, TimeHistogram, MAX_HISTOGRAM_BUCKETS,
+};
+pub use local_archive::LocalArchive;
+pub use local_files::{local_file_item, local_file_items};
+pub use ordered::ORDERED_BUFFER_BATCHES;
 pub use query_builder::{build_prefix_filter, SearchFilterSpec, SearchQueryBuilder};
 
+use crate::config::MonocleConfig;
+use crate::lens::parse::attr_filter::AttributeFilters;
+use crate::lens::parse::time_window::TimeWindows;
 use crate::lens::parse::ParseFilters;
+use crate::utils::GroupByField;
 use anyhow::Result;
 use bgpkit_broker::BrokerItem;
 use bgpkit_parser::BgpElem;
@@ -163,6 +187,13 @@ pub struct SearchExecutionOptions {
     pub cancel_flag: Option<Arc<AtomicBool>>,
     /// Number of elements per batch passed to [`SearchSink::on_elements`].
     pub batch_size: usize,
+    /// Emit elements in global timestamp order by merging per-file streams
+    /// instead of passing each file's batches on as they are parsed.
+    pub ordered: bool,
+    /// Detect the format of each file from its content, so that BMP, RIS Live
+    /// and table dump files are parsed as well as MRT (see
+    /// [`SearchFilters::to_input_elem_iter`]). Broker listings are always MRT.
+    pub detect_format: bool,
 }
 
 impl Default for SearchExecutionOptions {
@@ -174,6 +205,8 @@ impl Default for SearchExecutionOptions {
             timeout: None,
             cancel_flag: None,
             batch_size: DEFAULT_SEARCH_BATCH_SIZE,
+            ordered: false,
+            detect_format: false,
         }
     }
 }
@@ -241,6 +274,23 @@ pub enum SearchDumpType {
     RibUpdates,
 }
 
+impl std::str::FromStr for SearchDumpType {
+    type Err = anyhow::Error;
+
+    /// Parse `updates`, `rib` or `rib_updates` (also `all`)
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "updates" => Ok(SearchDumpType::Updates),
+            "rib" => Ok(SearchDumpType::Rib),
+            "rib_updates" | "all" => Ok(SearchDumpType::RibUpdates),
+            other => Err(anyhow::anyhow!(
+                "invalid dump_type '{}': expected 'updates', 'rib', or 'rib_updates'",
+                other
+            )),
+        }
+    }
+}
+
 // =============================================================================
 // Args
 // =============================================================================
@@ -269,46 +319,86 @@ pub struct SearchFilters {
 
 impl SearchFilters {
     /// Query broker items based on filters
+    ///
+    /// Always queries the default BGPKIT broker, ignoring any configured
+    /// broker backend.
+    #[deprecated(
+        since = "1.5.0",
+        note = "Use SearchLens::query_broker, which uses the configured backend"
+    )]
     pub fn to_broker_items(&self) -> Result<Vec<BrokerItem>> {
-        self.build_broker()?
-            .query()
-            .map_err(|_| anyhow::anyhow!("broker query error: please check filters are valid"))
+        query_all(&BgpkitBrokerBackend::new(), &self.to_broker_queries()?)
     }
 
     /// Build a broker from the filters
+    ///
+    /// Always targets the default BGPKIT broker, ignoring any configured
+    /// broker backend.
+    #[deprecated(
+        since = "1.5.0",
+        note = "Use SearchLens::build_broker, which uses the configured backend"
+    )]
     pub fn build_broker(&self) -> Result<bgpkit_broker::BgpkitBroker> {
+        Ok(BgpkitBrokerBackend::new().build_broker(&self.to_broker_query()?))
+    }
+
+    /// Build the backend-neutral file listing query for these filters
+    ///
+    /// With several time windows, the query covers the span of all windows;
+    /// use [`to_broker_queries`](Self::to_broker_queries) to list files.
+    pub fn to_broker_query(&self) -> Result<BrokerQuery> {
         let (ts_start, ts_end) = self.parse_filters.parse_start_end_strings()?;
+        let data_type = match self.dump_type {
+            SearchDumpType::Updates => Some("updates".to_string()),
+            SearchDumpType::Rib => Some("rib".to_string()),
+            // no data type filter -> getting all RIB and updates
+            SearchDumpType::RibUpdates => None,
+        };
 
-        let mut broker = bgpkit_broker::BgpkitBroker::new()
-            .ts_start(ts_start)
-            .ts_end(ts_end)
-            .page_size(1000);
+        Ok(BrokerQuery {
+            ts_start,
+            ts_end,
+            collector: self.collector.clone(),
+            project: self.project.clone(),
+            data_type,
+        })
+    }
 
-        if let Some(project) = &self.project {
-            broker = broker.project(project.as_str());
-        }
-        if let Some(collector) = &self.collector {
-            broker = broker.collector_id(collector.as_str());
+    /// Build the file listing queries for these filters: one per group of
+    /// overlapping time windows, or a single query without windows
+    pub fn to_broker_queries(&self) -> Result<Vec<BrokerQuery>> {
+        let query = self.to_broker_query()?;
+        let windows = self.parse_filters.time_windows()?;
+        if windows.is_empty() {
+            return Ok(vec![query]);
         }
+        Ok(windows
+            .merged_ranges()
+            .into_iter()
+            .map(|(ts_start, ts_end)| BrokerQuery {
+                ts_start,
+                ts_end,
+                ..query.clone()
+            })
+            .collect())
+    }
 
-        match self.dump_type {
-            SearchDumpType::Updates => {
-                broker = broker.data_type("updates");
-            }
-            SearchDumpType::Rib => {
-                broker = broker.data_type("rib");
-            }
-            SearchDumpType::RibUpdates => {
-                // do nothing here -> getting all RIB and updates
-            }
+    /// Time windows elements must fall into; empty without windows.
+    ///
+    /// Like the single search window, time windows do not apply to RIB dumps.
+    pub fn time_windows(&self) -> Result<TimeWindows> {
+        if self.dump_type == SearchDumpType::Rib {
+            return Ok(TimeWindows::default());
         }
-
-        Ok(broker)
+        self.parse_filters.time_windows()
     }
 
-    /// Validate the filters
+    /// Validate the filters: the time range, the path attribute filters and
+    /// the `where` expression
     pub fn validate(&self) -> Result<()> {
         let _ = self.parse_filters.parse_start_end_strings()?;
+        AttributeFilters::new(&self.parse_filters)?;
+        self.parse_filters.where_expr()?;
         Ok(())
     }
 
@@ -321,12 +411,28 @@ impl SearchFilters {
         self.parser_filters().to_parser(file_path)
     }
 
+    /// Convert filters to an iterator over the matching elements of a file,
+    /// including the time window check (see [`ParseFilters::to_elem_iter`])
+    pub fn to_elem_iter(&self, file_path: &str) -> Result<impl Iterator<Item = BgpElem> + Send> {
+        self.parser_filters().to_elem_iter(file_path)
+    }
+
+    /// Like [`to_elem_iter`](Self::to_elem_iter), detecting the format of the
+    /// file (see [`ParseFilters::to_input_elem_iter`])
+    pub fn to_input_elem_iter(
+        &self,
+        file_path: &str,
+    ) -> Result<Box<dyn Iterator<Item = BgpElem> + Send>> {
+        self.parser_filters().to_input_elem_iter(file_path)
+    }
+
     fn parser_filters(&self) -> ParseFilters {
         let mut parse_filters = self.parse_filters.clone();
         if self.dump_type == SearchDumpType::Rib {
             parse_filters.start_ts = None;
             parse_filters.end_ts = None;
             parse_filters.duration = None;
+            parse_filters.windows.clear();
         }
         parse_filters
     }
@@ -358,8 +464,8 @@ pub struct SearchSummary {
 /// Search lens for BGP message search operations
 ///
 /// This lens provides high-level operations for searching BGP messages
-/// across multiple MRT files using the BGPKIT broker, with optional
-/// progress tracking support.
+/// across multiple MRT files listed by a [`BrokerBackend`] (the BGPKIT broker
+/// by default), with optional progress tracking support.
 ///
 /// # Example
 ///
@@ -373,8 +479,7 @@ pub struct SearchSummary {
 /// // Simple search without progress tracking
 /// let items = lens.query_broker(&filters)?;
 /// for item in items {
-///     let parser = lens.create_parser(&filters, &item.url)?;
-///     for elem in parser {
+///     for elem in filters.to_elem_iter(&item.url)? {
 ///         println!("{}", elem);
 ///     }
 /// }
@@ -393,25 +498,55 @@ pub struct SearchSummary {
 /// let summary = lens.search_with_progress(&filters, Some(callback), handler)?;
 /// println!("Found {} messages", summary.total_messages);
 /// ```
-pub struct SearchLens;
+pub struct SearchLens {
+    backend: Arc<dyn BrokerBackend>,
+}
 
 impl SearchLens {
-    /// Create a new search lens
+    /// Create a new search lens using the default BGPKIT broker
     pub fn new() -> Self {
-        Self
+        Self::with_backend(Arc::new(BgpkitBrokerBackend::new()))
+    }
+
+    /// Create a search lens that lists files from the given backend
+    pub fn with_backend(backend: Arc<dyn BrokerBackend>) -> Self {
+        Self { backend }
+    }
+
+    /// Create a search lens using the broker backend configured in `config`
+    pub fn from_config(config: &MonocleConfig) -> Result<Self> {
+        Ok(Self::with_backend(broker_backend_from_config(config)?))
+    }
+
+    /// Broker backend used to list files
+    pub fn backend(&self) -> &Arc<dyn BrokerBackend> {
+        &self.backend
     }
 
     /// Query broker items based on filters
     pub fn query_broker(&self, filters: &SearchFilters) -> Result<Vec<BrokerItem>> {
-        filters.to_broker_items()
+        query_all(self.backend.as_ref(), &filters.to_broker_queries()?)
     }
 
     /// Build a broker from filters
+    ///
+    /// Fails if the lens backend is not a BGPKIT broker (e.g. a local archive
+    /// or a static manifest); use [`query_broker`](Self::query_broker) to list
+    /// files from any backend.
     pub fn build_broker(&self, filters: &SearchFilters) -> Result<bgpkit_broker::BgpkitBroker> {
-        filters.build_broker()
+        self.backend
+            .bgpkit_broker(&filters.to_broker_query()?)
+            .ok_or_else(|| anyhow::anyhow!("{} is not a BGPKIT broker", self.backend.name()))
     }
 
     /// Create a parser for a specific file
+    ///
+    /// The parser does not apply time windows, path attribute filters or the
+    /// `where` expression, see [`ParseFilters::to_parser`].
+    #[deprecated(
+        since = "1.5.0",
+        note = "Use SearchFilters::to_elem_iter, which applies all filters"
+    )]
     pub fn create_parser(
         &self,
         filters: &SearchFilters,
@@ -512,154 +647,93 @@ impl SearchLens {
         sink: Arc<dyn SearchSink>,
     ) -> Result<SearchOutcome> {
         let start_time = Instant::now();
-        let deadline = options.timeout.map(|timeout| start_time + timeout);
 
         sink.on_progress(SearchProgress::QueryingBroker);
 
         let items = self.query_broker(filters)?;
-        let total_files = items.len();
-        let source_bytes_exact = items.iter().all(|item| item.exact_size > 0);
-        let source_bytes_compressed = items
-            .iter()
-            .map(|item| {
-                let size = if item.exact_size > 0 {
-                    item.exact_size
-                } else {
-                    item.rough_size
-                };
-                size.max(0) as u64
-            })
-            .sum();
-        sink.on_progress(SearchProgress::FilesFound { count: total_files });
-
-        if deadline.is_some_and(|dl| Instant::now() >= dl) {
-            return Ok(SearchOutcome {
-                summary: SearchSummary {
-                    total_files,
-                    successful_files: 0,
-                    failed_files: 0,
-                    total_messages: 0,
-                    duration_secs: start_time.elapsed().as_secs_f64(),
-                },
-                exit_reason: SearchExitReason::Timeout,
-                source_bytes_compressed,
-                source_bytes_exact,
-            });
-        }
-
-        if total_files == 0 {
-            let duration_secs = start_time.elapsed().as_secs_f64();
-            let summary = SearchSummary {
-                total_files: 0,
-                successful_files: 0,
-                failed_files: 0,
-                total_messages: 0,
-                duration_secs,
-            };
-            sink.on_progress(SearchProgress::Completed {
-                total_files: 0,
-                successful_files: 0,
-                failed_files: 0,
-                total_messages: 0,
-                duration_secs,
-                files_per_sec: None,
-            });
-            return Ok(SearchOutcome {
-                summary,
-                exit_reason: SearchExitReason::Completed,
-                source_bytes_compressed,
-                source_bytes_exact,
-            });
-        }
-
-        let batch_size = options.batch_size.max(1);
-        let max_results = options.max_results.filter(|max| *max > 0);
-        let external_cancel = options.cancel_flag.clone();
-        let stop_flag = AtomicBool::new(false);
-        let exit_reason = AtomicU64::new(0);
-        let files_completed = AtomicU64::new(0);
-        let successful_files = AtomicU64::new(0);
-        let failed_files = AtomicU64::new(0);
-        let total_messages = AtomicU64::new(0);
+        execute_search(filters, items, options, sink, start_time)
+    }
 
-        let run = || {
-            items.into_par_iter().enumerate().for_each(|(index, item)| {
-                let state = SearchWorkerState {
-                    total_files,
-                    start_time,
-                    deadline,
-                    batch_size,
-                    max_results,
-                    external_cancel: external_cancel.as_deref(),
-                    stop_flag: &stop_flag,
-                    exit_reason: &exit_reason,
-                    files_completed: &files_completed,
-                    successful_files: &successful_files,
-                    failed_files: &failed_files,
-                    total_messages: &total_messages,
-                    sink: sink.as_ref(),
-                };
-                process_search_item(filters, index, item, state);
-            });
-        };
+    /// Search the given files using the shared executor, without listing
+    /// files from the broker backend.
+    ///
+    /// Items only need a URL or local path and a collector name; their time
+    /// range is used to open files in order with
+    /// [`SearchExecutionOptions::ordered`]. See
+    /// [`local_file_items`] for building items from local paths.
+    pub fn search_items(
+        &self,
+        filters: &SearchFilters,
+        items: Vec<BrokerItem>,
+        options: SearchExecutionOptions,
+        sink: Arc<dyn SearchSink>,
+    ) -> Result<SearchOutcome> {
+        execute_search(filters, items, options, sink, Instant::now())
+    }
 
-        if let Some(pool) = options.thread_pool.as_ref() {
-            pool.install(run);
-        } else {
-            match options.concurrency {
-                Some(n) if n > 0 => {
-                    let pool = rayon::ThreadPoolBuilder::new().num_threads(n).build()?;
-                    pool.install(run);
-                }
-                _ => run(),
-            }
+    /// Search and aggregate matching elements by `group_by` fields
+    ///
+    /// Elements are counted per group instead of being collected, so memory use
+    /// depends on the number of distinct groups rather than on the number of
+    /// matched elements.
+    pub fn search_aggregate(
+        &self,
+        filters: &SearchFilters,
+        group_by: Vec<GroupByField>,
+        options: SearchExecutionOptions,
+        progress_callback: Option<SearchProgressCallback>,
+    ) -> Result<(ElemAggregator, SearchOutcome)> {
+        let mut sink = AggregateSink::new(group_by);
+        if let Some(callback) = progress_callback {
+            sink = sink.with_progress(callback);
         }
+        let sink = Arc::new(sink);
+        let outcome = self.search_with_options(filters, options, sink.clone())?;
+        Ok((sink.take(), outcome))
+    }
 
-        let duration_secs = start_time.elapsed().as_secs_f64();
-        let final_successful = successful_files.load(Ordering::Relaxed) as usize;
-        let final_failed = failed_files.load(Ordering::Relaxed) as usize;
-        let final_messages = total_messages.load(Ordering::Relaxed);
-        let files_per_sec = if duration_secs > 0.0 {
-            Some(total_files as f64 / duration_secs)
-        } else {
-            None
-        };
-
-        let exit_reason = match exit_reason.load(Ordering::Relaxed) {
-            1 => SearchExitReason::Cancelled,
-            2 => SearchExitReason::Timeout,
-            3 => SearchExitReason::MaxResultsReached,
-            _ => SearchExitReason::Completed,
-        };
-
-        if matches!(
-            exit_reason,
-            SearchExitReason::Completed | SearchExitReason::MaxResultsReached
-        ) {
-            sink.on_progress(SearchProgress::Completed {
-                total_files,
-                successful_files: final_successful,
-                failed_files: final_failed,
-                total_messages: final_messages,
-                duration_secs,
-                files_per_sec,
-            });
+    /// Search and collapse duplicate observations of the same routing event
+    ///
+    /// Elements with the same prefix, AS path, communities and type are merged
+    /// into one [`DedupEvent`] while consecutive observations are at most
+    /// `window_secs` apart, across all peers and collectors.
+    pub fn search_dedup(
+        &self,
+        filters: &SearchFilters,
+        window_secs: u64,
+        options: SearchExecutionOptions,
+        progress_callback: Option<SearchProgressCallback>,
+    ) -> Result<(ElemDeduplicator, SearchOutcome)> {
+        let mut sink = DedupSink::new(window_secs);
+        if let Some(callback) = progress_callback {
+            sink = sink.with_progress(callback);
         }
+        let sink = Arc::new(sink);
+        let outcome = self.search_with_options(filters, options, sink.clone())?;
+        Ok((sink.take(), outcome))
+    }
 
-        let summary = SearchSummary {
-            total_files,
-            successful_files: final_successful,
-            failed_files: final_failed,
-            total_messages: final_messages,
-            duration_secs,
-        };
-
-        Ok(SearchOutcome {
-            summary,
-            exit_reason,
-            source_bytes_compressed,
-            source_bytes_exact,
-        })
+    /// Search and count matching elements per time bucket
+    ///
+    /// The histogram covers the whole search window with `bucket_secs` wide
+    /// buckets, split into one series per combination of `split_by` values.
+    pub fn search_histogram(
+        &self,
+        filters: &SearchFilters,
+        bucket_secs: u64,
+        split_by: Vec<GroupByField>,
+        options: SearchExecutionOptions,
+        progress_callback: Option<SearchProgressCallback>,
+    ) -> Result<(TimeHistogram, SearchOutcome)> {
+        let (ts_start, ts_end) = filters.parse_filters.parse_start_end_strings()?;
+        let histogram = TimeHistogram::new(bucket_secs, split_by).with_range(ts_start, ts_end);
+        let mut sink = HistogramSink::new(histogram);
+        if let Some(callback) = progress_callback {
+            sink = sink.with_progress(callback);
+        }
+        let sink = Arc::new(sink);
+        let outcome = self.search_with_options(filters, options, sink.clone())?;
+        Ok((sink.take(), outcome))
     }
 
     /// Search and collect all BGP elements with progress tracking
@@ -703,6 +777,190 @@ impl SearchLens {
     }
 }
 
+/// Run the shared executor over `items`; `start_time` is when the search
+/// started, including the file listing.
+fn execute_search(
+    filters: &SearchFilters,
+    items: Vec<BrokerItem>,
+    options: SearchExecutionOptions,
+    sink: Arc<dyn SearchSink>,
+    start_time: Instant,
+) -> Result<SearchOutcome> {
+    let deadline = options.timeout.map(|timeout| start_time + timeout);
+    let total_files = items.len();
+    let source_bytes_exact = items.iter().all(|item| item.exact_size > 0);
+    let source_bytes_compressed = items
+        .iter()
+        .map(|item| {
+            let size = if item.exact_size > 0 {
+                item.exact_size
+            } else {
+                item.rough_size
+            };
+            size.max(0) as u64
+        })
+        .sum();
+    sink.on_progress(SearchProgress::FilesFound { count: total_files });
+
+    if deadline.is_some_and(|dl| Instant::now() >= dl) {
+        return Ok(SearchOutcome {
+            summary: SearchSummary {
+                total_files,
+                successful_files: 0,
+                failed_files: 0,
+                total_messages: 0,
+                duration_secs: start_time.elapsed().as_secs_f64(),
+            },
+            exit_reason: SearchExitReason::Timeout,
+            source_bytes_compressed,
+            source_bytes_exact,
+        });
+    }
+
+    if total_files == 0 {
+        let duration_secs = start_time.elapsed().as_secs_f64();
+        let summary = SearchSummary {
+            total_files: 0,
+            successful_files: 0,
+            failed_files: 0,
+            total_messages: 0,
+            duration_secs,
+        };
+        sink.on_progress(SearchProgress::Completed {
+            total_files: 0,
+            successful_files: 0,
+            failed_files: 0,
+            total_messages: 0,
+            duration_secs,
+            files_per_sec: None,
+        });
+        return Ok(SearchOutcome {
+            summary,
+            exit_reason: SearchExitReason::Completed,
+            source_bytes_compressed,
+            source_bytes_exact,
+        });
+    }
+
+    let batch_size = options.batch_size.max(1);
+    let max_results = options.max_results.filter(|max| *max > 0);
+    let external_cancel = options.cancel_flag.clone();
+    let stop_flag = AtomicBool::new(false);
+    let exit_reason = AtomicU64::new(0);
+    let files_completed = AtomicU64::new(0);
+    let successful_files = AtomicU64::new(0);
+    let failed_files = AtomicU64::new(0);
+    let total_messages = AtomicU64::new(0);
+
+    let run = |items: Vec<BrokerItem>| {
+        items.into_par_iter().enumerate().for_each(|(index, item)| {
+            let state = SearchWorkerState {
+                total_files,
+                start_time,
+                deadline,
+                batch_size,
+                max_results,
+                external_cancel: external_cancel.as_deref(),
+                stop_flag: &stop_flag,
+                exit_reason: &exit_reason,
+                files_completed: &files_completed,
+                successful_files: &successful_files,
+                failed_files: &failed_files,
+                total_messages: &total_messages,
+                detect_format: options.detect_format,
+                sink: sink.as_ref(),
+            };
+            process_search_item(filters, index, item, state);
+        });
+    };
+
+    if options.ordered {
+        let owned_pool;
+        let pool = match (options.thread_pool.as_deref(), options.concurrency) {
+            (Some(pool), _) => Some(pool),
+            (None, Some(n)) if n > 0 => {
+                owned_pool = rayon::ThreadPoolBuilder::new().num_threads(n).build()?;
+                Some(&owned_pool)
+            }
+            _ => None,
+        };
+        let state = SearchWorkerState {
+            total_files,
+            start_time,
+            deadline,
+            batch_size,
+            max_results,
+            external_cancel: external_cancel.as_deref(),
+            stop_flag: &stop_flag,
+            exit_reason: &exit_reason,
+            files_completed: &files_completed,
+            successful_files: &successful_files,
+            failed_files: &failed_files,
+            total_messages: &total_messages,
+            detect_format: options.detect_format,
+            sink: sink.as_ref(),
+        };
+        ordered::search_ordered(filters, items, pool, state);
+    } else if let Some(pool) = options.thread_pool.as_ref() {
+        pool.install(|| run(items));
+    } else {
+        match options.concurrency {
+            Some(n) if n > 0 => {
+                let pool = rayon::ThreadPoolBuilder::new().num_threads(n).build()?;
+                pool.install(|| run(items));
+            }
+            _ => run(items),
+        }
+    }
+
+    let duration_secs = start_time.elapsed().as_secs_f64();
+    let final_successful = successful_files.load(Ordering::Relaxed) as usize;
+    let final_failed = failed_files.load(Ordering::Relaxed) as usize;
+    let final_messages = total_messages.load(Ordering::Relaxed);
+    let files_per_sec = if duration_secs > 0.0 {
+        Some(total_files as f64 / duration_secs)
+    } else {
+        None
+    };
+
+    let exit_reason = match exit_reason.load(Ordering::Relaxed) {
+        1 => SearchExitReason::Cancelled,
+        2 => SearchExitReason::Timeout,
+        3 => SearchExitReason::MaxResultsReached,
+        _ => SearchExitReason::Completed,
+    };
+
+    if matches!(
+        exit_reason,
+        SearchExitReason::Completed | SearchExitReason::MaxResultsReached
+    ) {
+        sink.on_progress(SearchProgress::Completed {
+            total_files,
+            successful_files: final_successful,
+            failed_files: final_failed,
+            total_messages: final_messages,
+            duration_secs,
+            files_per_sec,
+        });
+    }
+
+    let summary = SearchSummary {
+        total_files,
+        successful_files: final_successful,
+        failed_files: final_failed,
+        total_messages: final_messages,
+        duration_secs,
+    };
+
+    Ok(SearchOutcome {
+        summary,
+        exit_reason,
+        source_bytes_compressed,
+        source_bytes_exact,
+    })
+}
+
+#[derive(Clone, Copy)]
 struct SearchWorkerState<'a> {
     total_files: usize,
     start_time: Instant,
@@ -716,6 +974,7 @@ struct SearchWorkerState<'a> {
     successful_files: &'a AtomicU64,
     failed_files: &'a AtomicU64,
     total_messages: &'a AtomicU64,
+    detect_format: bool,
     sink: &'a dyn SearchSink,
 }
 
@@ -725,82 +984,141 @@ fn process_search_item(
     item: BrokerItem,
     state: SearchWorkerState<'_>,
 ) {
-    if search_should_stop(&state) {
+    let Some(mut scan) = SearchFileScan::open(filters, index, item, &state) else {
         return;
-    }
+    };
+    while scan.next_batch(&state) {}
+    scan.finish(&state);
+}
 
-    let url = item.url.clone();
-    let collector = item.collector_id.clone();
+/// One file being searched, parsed one batch at a time
+struct SearchFileScan {
+    index: usize,
+    url: String,
+    collector: String,
+    parser: Box<dyn Iterator<Item = BgpElem> + Send>,
+    batch: Vec<BgpElem>,
+    file_messages: u64,
+}
 
-    state.sink.on_progress(SearchProgress::FileStarted {
-        file_index: index,
-        total_files: state.total_files,
-        file_url: url.clone(),
-        collector: collector.clone(),
-    });
+impl SearchFileScan {
+    /// Report the file as started and open its parser
+    ///
+    /// Returns `None` if the search should stop, or if the file cannot be
+    /// opened, in which case it is reported as a failed file.
+    fn open(
+        filters: &SearchFilters,
+        index: usize,
+        item: BrokerItem,
+        state: &SearchWorkerState<'_>,
+    ) -> Option<Self> {
+        if search_should_stop(state) {
+            return None;
+        }
 
-    let parser = match filters.to_parser(url.as_str()) {
-        Ok(parser) => parser,
-        Err(e) => {
-            state.failed_files.fetch_add(1, Ordering::Relaxed);
-            let completed = state.files_completed.fetch_add(1, Ordering::Relaxed) + 1;
-            state.sink.on_progress(SearchProgress::FileCompleted {
-                file_index: index,
-                total_files: state.total_files,
-                messages_found: 0,
-                success: false,
-                error: Some(e.to_string()),
-            });
-            send_progress_update(&state, completed);
-            return;
+        let url = item.url;
+        let collector = item.collector_id;
+
+        state.sink.on_progress(SearchProgress::FileStarted {
+            file_index: index,
+            total_files: state.total_files,
+            file_url: url.clone(),
+            collector: collector.clone(),
+        });
+
+        let parser = if state.detect_format {
+            filters.to_input_elem_iter(url.as_str())
+        } else {
+            filters
+                .to_elem_iter(url.as_str())
+                .map(|parser| Box::new(parser) as Box<dyn Iterator<Item = BgpElem> + Send>)
+        };
+        match parser {
+            Ok(parser) => Some(Self {
+                index,
+                url,
+                collector,
+                parser,
+                batch: Vec::with_capacity(state.batch_size),
+                file_messages: 0,
+            }),
+            Err(e) => {
+                state.failed_files.fetch_add(1, Ordering::Relaxed);
+                let completed = state.files_completed.fetch_add(1, Ordering::Relaxed) + 1;
+                state.sink.on_progress(SearchProgress::FileCompleted {
+                    file_index: index,
+                    total_files: state.total_files,
+                    messages_found: 0,
+                    success: false,
+                    error: Some(e.to_string()),
+                });
+                send_progress_update(state, completed);
+                None
+            }
         }
-    };
+    }
 
-    let mut file_messages = 0_u64;
-    let mut batch = Vec::with_capacity(state.batch_size);
+    /// Parse until one batch is emitted; returns false once the file is
+    /// exhausted or the search should stop
+    fn next_batch(&mut self, state: &SearchWorkerState<'_>) -> bool {
+        for elem in self.parser.by_ref() {
+            if search_should_stop(state) {
+                return false;
+            }
 
-    for elem in parser {
-        if search_should_stop(&state) {
-            break;
+            self.batch.push(elem);
+            if should_emit_batch(state, self.batch.len()) {
+                self.file_messages += emit_search_batch(
+                    state,
+                    self.index,
+                    &self.url,
+                    &self.collector,
+                    &mut self.batch,
+                );
+                return true;
+            }
         }
 
-        batch.push(elem);
-        if should_emit_batch(&state, batch.len()) {
-            let accepted = emit_search_batch(&state, index, &url, &collector, &mut batch);
-            file_messages += accepted;
+        if !self.batch.is_empty() && !search_should_stop(state) {
+            self.file_messages += emit_search_batch(
+                state,
+                self.index,
+                &self.url,
+                &self.collector,
+                &mut self.batch,
+            );
         }
+        false
     }
 
-    if !batch.is_empty() && !search_should_stop(&state) {
-        let accepted = emit_search_batch(&state, index, &url, &collector, &mut batch);
-        file_messages += accepted;
-    }
+    /// Report the file as completed
+    fn finish(self, state: &SearchWorkerState<'_>) {
+        let stopped_reason = current_exit_reason(state);
+        let stopped_before_success = matches!(
+            stopped_reason,
+            Some(SearchExitReason::Cancelled | SearchExitReason::Timeout)
+        );
 
-    let stopped_reason = current_exit_reason(&state);
-    let stopped_before_success = matches!(
-        stopped_reason,
-        Some(SearchExitReason::Cancelled | SearchExitReason::Timeout)
-    );
+        if stopped_before_success {
+            state.failed_files.fetch_add(1, Ordering::Relaxed);
+        } else {
+            state.successful_files.fetch_add(1, Ordering::Relaxed);
+        }
 
-    if stopped_before_success {
-        state.failed_files.fetch_add(1, Ordering::Relaxed);
-    } else {
-        state.successful_files.fetch_add(1, Ordering::Relaxed);
+        let completed = state.files_completed.fetch_add(1, Ordering::Relaxed) + 1;
+        state.sink.on_progress(SearchProgress::FileCompleted {
+            file_index: self.index,
+            total_files: state.total_files,
+            messages_found: self.file_messages,
+            success: !stopped_before_success,
+            error: stopped_reason.and_then(|reason| match reason {
+                SearchExitReason::Cancelled => Some("search cancelled".to_string()),
+                SearchExitReason::Timeout => Some("search timed out".to_string()),
+                _ => None,
+            }),
+        });
+        send_progress_update(state, completed);
     }
-
-    let completed = state.files_completed.fetch_add(1, Ordering::Relaxed) + 1;
-    state.sink.on_progress(SearchProgress::FileCompleted {
-        file_index: index,
-        total_files: state.total_files,
-        messages_found: file_messages,
-        success: !stopped_before_success,
-        error: stopped_reason.and_then(|reason| match reason {
-            SearchExitReason::Cancelled => Some("search cancelled".to_string()),
-            SearchExitReason::Timeout => Some("search timed out".to_string()),
-            _ => None,
-        }),
-    });
-    send_progress_update(&state, completed);
 }
 
 fn search_should_stop(state: &SearchWorkerState<'_>) -> bool {
@@ -986,6 +1304,41 @@ mod tests {
         assert_eq!(parser_filters.end_ts, None);
     }
 
+    #[test]
+    fn test_broker_queries_per_window_group() {
+        let filters = SearchFilters {
+            parse_filters: ParseFilters {
+                windows: ["a=1704067200/1h", "b=1704069000/1h", "c=1704153600/1h"]
+                    .iter()
+                    .map(|w| w.parse().unwrap())
+                    .collect(),
+                ..Default::default()
+            },
+            collector: Some("rrc00".to_string()),
+            ..Default::default()
+        };
+
+        let query = filters.to_broker_query().unwrap();
+        assert_eq!((query.ts_start, query.ts_end), (1704067200, 1704157200));
+
+        let queries = filters.to_broker_queries().unwrap();
+        let ranges: Vec<_> = queries.iter().map(|q| (q.ts_start, q.ts_end)).collect();
+        assert_eq!(
+            ranges,
+            vec![(1704067200, 1704072600), (1704153600, 1704157200)]
+        );
+        assert!(queries
+            .iter()
+            .all(|q| q.collector.as_deref() == Some("rrc00")));
+
+        let rib = SearchFilters {
+            dump_type: SearchDumpType::Rib,
+            ..filters
+        };
+        assert!(rib.time_windows().unwrap().is_empty());
+        assert!(rib.parser_filters().windows.is_empty());
+    }
+
     #[test]
     fn test_pagination_logic() {
         // Create a test filter with a short time range to get manageable results
@@ -1002,7 +1355,20 @@ mod tests {
                 start_ts: Some("2022-01-01T00:00:00Z".to_string()),
                 end_ts: Some("2022-01-01T01:00:00Z".to_string()), // 1 hour window
                 duration: None,
+                windows: Vec::new(),
                 as_path: None,
+                as_path_style: None,
+                as_path_filters: Default::default(),
+                ext_communities: Vec::new(),
+                med: Vec::new(),
+                local_pref: Vec::new(),
+                next_hop: Vec::new(),
+                origin: Vec::new(),
+                aggr_asn: Vec::new(),
+                atomic: None,
+                rpki_filters: Default::default(),
+                asinfo_filters: Default::default(),
+                where_expr: None,
             },
             collector: None,
             project: None,
@@ -1010,8 +1376,8 @@ mod tests {
         };
 
         // Test broker creation
-        let base_broker = search_filters
-            .build_broker()
+        let base_broker = SearchLens::new()
+            .build_broker(&search_filters)
             .expect("Failed to build broker");
 
         // Test pagination with small page size for testing
@@ -1091,15 +1457,28 @@ mod tests {
                 start_ts: Some("2022-01-01T00:00:00Z".to_string()),
                 end_ts: Some("2022-01-01T01:00:00Z".to_string()),
                 duration: None,
+                windows: Vec::new(),
                 as_path: None,
+                as_path_style: None,
+                as_path_filters: Default::default(),
+                ext_communities: Vec::new(),
+                med: Vec::new(),
+                local_pref: Vec::new(),
+                next_hop: Vec::new(),
+                origin: Vec::new(),
+                aggr_asn: Vec::new(),
+                atomic: None,
+                rpki_filters: Default::default(),
+                asinfo_filters: Default::default(),
+                where_expr: None,
             },
             collector: Some("rrc00".to_string()),
             project: Some("riperis".to_string()),
             dump_type: SearchDumpType::Updates,
         };
 
-        let broker = search_filters
-            .build_broker()
+        let broker = SearchLens::new()
+            .build_broker(&search_filters)
             .expect("Failed to build broker");
 
         // Test that we can get at least one page
@@ -1122,6 +1501,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_build_broker_uses_lens_backend() {
+        let filters = SearchFilters {
+            parse_filters: ParseFilters {
+                start_ts: Some("2024-01-01T00:00:00Z".to_string()),
+                end_ts: Some("2024-01-01T01:00:00Z".to_string()),
+                ..Default::default()
+            },
+            collector: None,
+            project: None,
+            dump_type: SearchDumpType::Updates,
+        };
+
+        let manifest = SearchLens::with_backend(Arc::new(ManifestBackend::from_items(
+            "test.json",
+            Vec::new(),
+        )));
+        let err = manifest
+            .build_broker(&filters)
+            .err()
+            .expect("manifest backend is not a broker");
+        assert!(err.to_string().contains("is not a BGPKIT broker"));
+
+        let custom = SearchLens::with_backend(Arc::new(BgpkitBrokerBackend::with_url(
+            "https://broker.example.com/v3/broker",
+        )));
+        assert!(custom.build_broker(&filters).is_ok());
+    }
+
     #[test]
     fn test_search_progress_serialization() {
         // Test that progress types can be serialized for GUI communication
@@ -1193,10 +1601,50 @@ mod tests {
             successful_files: &successful_files,
             failed_files: &failed_files,
             total_messages: &total_messages,
+            detect_format: false,
             sink,
         });
     }
 
+    #[test]
+    fn test_search_items_detects_text_dumps() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = "BGP table version is 1, local router ID is 192.0.2.1, vrf id 0\n\
+                      Default local pref 100, local AS 64496\n\n\
+                      \x20   Network          Next Hop            Metric LocPrf Weight Path\n";
+        let paths: Vec<String> = [("a.txt", "192.0.2.0/24"), ("b.txt", "198.51.100.0/24")]
+            .into_iter()
+            .map(|(name, prefix)| {
+                let path = dir.path().join(name);
+                let route =
+                    format!(" *> {prefix:<17}192.0.2.2                0             0 64497 i\n");
+                std::fs::write(&path, format!("{header}{route}")).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        let search = |detect_format: bool| {
+            let sink = Arc::new(CountingSink::new());
+            let options = SearchExecutionOptions {
+                detect_format,
+                ..Default::default()
+            };
+            let outcome = SearchLens::new()
+                .search_items(
+                    &SearchFilters::default(),
+                    local_file_items(&paths).unwrap(),
+                    options,
+                    sink.clone(),
+                )
+                .unwrap();
+            assert_eq!(outcome.summary.total_files, 2);
+            sink.elements.load(Ordering::Relaxed)
+        };
+        assert_eq!(search(true), 2);
+        // Without format detection the files are read as MRT
+        assert_eq!(search(false), 0);
+    }
+
     #[test]
     fn test_reserve_result_slots_truncates_at_max_results() {
         let sink = CountingSink::new();
@@ -0,0 +1,668 @@
+//! Globally time-ordered search execution
+//!
+//! The default executor hands each file's batches to the sink as soon as they
+//! are parsed, so output from files parsed in parallel interleaves
+//! arbitrarily. In ordered mode ([`SearchExecutionOptions::ordered`]) each
+//! file is parsed ahead of the merge into a buffer of at most
+//! [`ORDERED_BUFFER_BATCHES`] batches, and a k-way merge emits the element
+//! with the smallest timestamp across all open files next.
+//!
+//! Files are parsed by tasks on the search thread pool, one batch per task,
+//! with at most one task per pool thread in flight; a file whose buffer is
+//! full waits without holding a thread until the merge consumes a batch.
+//! Files the merge is waiting for are scheduled first.
+//!
+//! Files are opened in order of their start time. A file is opened as soon as
+//! the merge reaches its start time (it may hold the next element), and
+//! additional files are opened ahead of time while fewer than one file per
+//! pool thread is being merged. Memory use is bounded by the number of open
+//! files times the buffer size, independent of the total number of results.
+//!
+//! The merge relies on elements within one file being in timestamp order and
+//! not earlier than the file's start time, which holds for well-formed MRT
+//! updates files and RIB dumps. Each batch a file produces is sorted by
+//! timestamp before merging, which absorbs small reordering within a file.
+//! Elements that are still earlier than an element already emitted, such as
+//! an element before its file's start time or out of order across batches,
+//! are emitted as soon as they are read, out of order, and counted in a
+//! warning per file.
+//!
+//! [`SearchExecutionOptions::ordered`]: super::SearchExecutionOptions::ordered
+
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::sync::atomic::AtomicU64;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use bgpkit_broker::BrokerItem;
+use bgpkit_parser::BgpElem;
+use rayon::{Scope, ThreadPool};
+use tracing::warn;
+
+use super::{
+    emit_search_batch, search_should_stop, SearchControl, SearchElementBatch, SearchFileScan,
+    SearchFilters, SearchProgress, SearchSink, SearchWorkerState,
+};
+
+/// Number of element batches each file may buffer ahead of the merge
+pub const ORDERED_BUFFER_BATCHES: usize = 4;
+
+/// Message from a parsing task to the merge
+enum ProducerEvent {
+    /// A parsed batch of the file
+    Batch(usize, Vec<BgpElem>),
+    /// The task emitted one batch; the file continues in a later task
+    Parked(usize, SearchFileScan),
+    /// The file is exhausted, failed to open or the search stopped
+    Finished(usize),
+}
+
+/// Sink used by parsing tasks: forwards batches to the merge and progress to
+/// the real sink
+struct ProducerSink<'a> {
+    source: usize,
+    sender: Sender<ProducerEvent>,
+    progress: &'a dyn SearchSink,
+}
+
+impl SearchSink for ProducerSink<'_> {
+    fn on_progress(&self, progress: SearchProgress) {
+        self.progress.on_progress(progress);
+    }
+
+    fn on_elements(&self, batch: SearchElementBatch) -> SearchControl {
+        // The merge only drops its receiver once all tasks finished
+        match self
+            .sender
+            .send(ProducerEvent::Batch(self.source, batch.elements))
+        {
+            Ok(()) => SearchControl::Continue,
+            Err(_) => SearchControl::Stop,
+        }
+    }
+}
+
+/// Parsing state of a file between tasks
+enum Producer {
+    Unopened(BrokerItem),
+    Open(SearchFileScan),
+}
+
+/// Open a file if needed and parse its next batch
+fn produce(
+    filters: &SearchFilters,
+    source: usize,
+    producer: Producer,
+    state: SearchWorkerState<'_>,
+    sender: Sender<ProducerEvent>,
+) {
+    let sink = ProducerSink {
+        source,
+        sender: sender.clone(),
+        progress: state.sink,
+    };
+    let state = SearchWorkerState {
+        sink: &sink,
+        ..state
+    };
+    let scan = match producer {
+        Producer::Unopened(item) => SearchFileScan::open(filters, source, item, &state),
+        Producer::Open(scan) => Some(scan),
+    };
+    let event = match scan {
+        Some(mut scan) => {
+            if scan.next_batch(&state) {
+                ProducerEvent::Parked(source, scan)
+            } else {
+                scan.finish(&state);
+                ProducerEvent::Finished(source)
+            }
+        }
+        None => ProducerEvent::Finished(source),
+    };
+    sender.send(event).ok();
+}
+
+/// One open file in the merge
+struct MergeSource {
+    url: String,
+    collector: String,
+    /// Waiting for its next task; `None` while a task runs or once finished
+    producer: Option<Producer>,
+    /// Batches received from tasks, not yet merged
+    batches: VecDeque<Vec<BgpElem>>,
+    buffer: VecDeque<BgpElem>,
+    /// The file's last task has run
+    finished: bool,
+    /// Finished and every element merged
+    done: bool,
+    /// Elements emitted after a later element of any file
+    late: u64,
+}
+
+impl MergeSource {
+    fn new(item: BrokerItem) -> Self {
+        Self {
+            url: item.url.clone(),
+            collector: item.collector_id.clone(),
+            producer: Some(Producer::Unopened(item)),
+            batches: VecDeque::new(),
+            buffer: VecDeque::new(),
+            finished: false,
+            done: false,
+            late: 0,
+        }
+    }
+}
+
+/// Open files and the parsing tasks feeding them
+struct Merge<'a, 'scope> {
+    scope: &'a Scope<'scope>,
+    filters: &'scope SearchFilters,
+    /// State for parsing tasks
+    worker_state: SearchWorkerState<'scope>,
+    concurrency: usize,
+    /// Start times of all files, in the order they are opened
+    starts: Vec<f64>,
+    pending: std::vec::IntoIter<BrokerItem>,
+    sources: Vec<MergeSource>,
+    /// Tasks in flight
+    running: usize,
+    sender: Sender<ProducerEvent>,
+    events: Receiver<ProducerEvent>,
+}
+
+impl<'a, 'scope> Merge<'a, 'scope> {
+    fn new(
+        scope: &'a Scope<'scope>,
+        filters: &'scope SearchFilters,
+        worker_state: SearchWorkerState<'scope>,
+        concurrency: usize,
+        items: Vec<BrokerItem>,
+    ) -> Self {
+        let (sender, events) = channel();
+        Self {
+            scope,
+            filters,
+            worker_state,
+            concurrency,
+            starts: items.iter().map(item_start).collect(),
+            sources: Vec::with_capacity(items.len()),
+            pending: items.into_iter(),
+            running: 0,
+            sender,
+            events,
+        }
+    }
+
+    /// Add the next file by start time to the merge
+    fn open_next(&mut self) {
+        if let Some(item) = self.pending.next() {
+            self.sources.push(MergeSource::new(item));
+            self.schedule();
+        }
+    }
+
+    /// Start tasks for files with room in their buffer until `concurrency`
+    /// tasks are in flight, files the merge is waiting for first
+    fn schedule(&mut self) {
+        for waiting in [true, false] {
+            for index in 0..self.sources.len() {
+                if self.running >= self.concurrency {
+                    return;
+                }
+                let source = &mut self.sources[index];
+                let is_waiting = source.batches.is_empty() && source.buffer.is_empty();
+                if is_waiting != waiting || source.batches.len() >= ORDERED_BUFFER_BATCHES {
+                    continue;
+                }
+                let Some(producer) = source.producer.take() else {
+                    continue;
+                };
+                let (filters, worker_state) = (self.filters, self.worker_state);
+                let sender = self.sender.clone();
+                self.running += 1;
+                self.scope
+                    .spawn(move |_| produce(filters, index, producer, worker_state, sender));
+            }
+        }
+    }
+
+    /// Wait for the next task event; returns false if no task is in flight
+    fn receive(&mut self) -> bool {
+        if self.running == 0 {
+            return false;
+        }
+        let Ok(event) = self.events.recv() else {
+            return false;
+        };
+        match event {
+            ProducerEvent::Batch(index, elements) => {
+                self.sources[index].batches.push_back(elements);
+            }
+            ProducerEvent::Parked(index, scan) => {
+                self.running -= 1;
+                self.sources[index].producer = Some(Producer::Open(scan));
+            }
+            ProducerEvent::Finished(index) => {
+                self.running -= 1;
+                self.sources[index].finished = true;
+            }
+        }
+        true
+    }
+
+    /// Wait until the file's buffer holds an element or the file is done;
+    /// returns the timestamp of the next element, if any
+    ///
+    /// Each received batch is sorted by timestamp.
+    fn fill(&mut self, index: usize) -> Option<f64> {
+        loop {
+            let source = &mut self.sources[index];
+            if let Some(elem) = source.buffer.front() {
+                return Some(elem.timestamp);
+            }
+            if source.done {
+                return None;
+            }
+            if let Some(mut elements) = source.batches.pop_front() {
+                elements.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+                source.buffer.extend(elements);
+            } else if source.finished {
+                source.done = true;
+            } else {
+                self.schedule();
+                if !self.receive() {
+                    // Unreachable: an unfinished file always has a task
+                    // scheduled once one is free
+                    self.sources[index].done = true;
+                }
+                continue;
+            }
+            self.schedule();
+        }
+    }
+
+    /// Merge all files, emitting to the sink of `state`
+    fn run(mut self, state: SearchWorkerState<'_>) {
+        // Number of opened sources whose first element was looked at
+        let mut loaded = 0;
+        let mut heap: BinaryHeap<MergeHead> = BinaryHeap::new();
+        let mut output: Vec<BgpElem> = Vec::with_capacity(state.batch_size);
+        let mut output_source: Option<usize> = None;
+        // Timestamp of the latest element emitted so far
+        let mut emitted_until = f64::NEG_INFINITY;
+
+        loop {
+            if search_should_stop(&state) {
+                break;
+            }
+
+            // Keep one file per pool thread in the merge, then wait for the
+            // first element of every newly opened file
+            while self.sources.len() < self.starts.len()
+                && self.sources.iter().filter(|source| !source.done).count() < self.concurrency
+            {
+                self.open_next();
+            }
+            while loaded < self.sources.len() {
+                if let Some(timestamp) = self.fill(loaded) {
+                    heap.push(MergeHead {
+                        timestamp,
+                        source: loaded,
+                    });
+                }
+                loaded += 1;
+            }
+
+            // The next unopened file may hold an earlier element than any open one
+            let frontier = heap.peek().map(|head| head.timestamp);
+            if self.sources.len() < self.starts.len()
+                && frontier.is_none_or(|timestamp| self.starts[self.sources.len()] <= timestamp)
+            {
+                self.open_next();
+                continue;
+            }
+
+            let Some(head) = heap.pop() else {
+                break;
+            };
+            if output_source != Some(head.source) || output.len() >= state.batch_size {
+                if !flush(&state, &self.sources, output_source, &mut output) {
+                    break;
+                }
+                output_source = Some(head.source);
+            }
+            let source = &mut self.sources[head.source];
+            if let Some(elem) = source.buffer.pop_front() {
+                if elem.timestamp < emitted_until {
+                    source.late += 1;
+                } else {
+                    emitted_until = elem.timestamp;
+                }
+                output.push(elem);
+            }
+            if let Some(timestamp) = self.fill(head.source) {
+                heap.push(MergeHead {
+                    timestamp,
+                    source: head.source,
+                });
+            }
+        }
+
+        flush(&state, &self.sources, output_source, &mut output);
+        for source in self.sources.iter().filter(|source| source.late > 0) {
+            warn!(
+                "{} elements of {} were earlier than already emitted elements and are out of order",
+                source.late, source.url
+            );
+        }
+        self.close();
+    }
+
+    /// Wait for tasks in flight and report files that were still being
+    /// parsed as completed
+    fn close(mut self) {
+        loop {
+            for source in &mut self.sources {
+                if let Some(Producer::Open(scan)) = source.producer.take() {
+                    scan.finish(&self.worker_state);
+                }
+            }
+            if !self.receive() {
+                break;
+            }
+        }
+    }
+}
+
+/// Next element of one source, ordered so that [`BinaryHeap`] pops the
+/// earliest timestamp first and breaks ties by file order
+#[derive(Debug, PartialEq)]
+struct MergeHead {
+    timestamp: f64,
+    source: usize,
+}
+
+impl Eq for MergeHead {}
+
+impl Ord for MergeHead {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        other
+            .timestamp
+            .total_cmp(&self.timestamp)
+            .then_with(|| other.source.cmp(&self.source))
+    }
+}
+
+impl PartialOrd for MergeHead {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Start time of a file as Unix seconds
+fn item_start(item: &BrokerItem) -> f64 {
+    item.ts_start.and_utc().timestamp() as f64
+}
+
+/// Run a search emitting elements in global timestamp order.
+///
+/// Files are parsed on `pool`, or on the current rayon pool if `None`.
+/// `state` is the executor state used for emitting merged batches to the real
+/// sink; it enforces `max_results` and counts emitted elements. Parsing tasks
+/// share its stop flag, exit reason and file counters, but count parsed
+/// elements separately for progress updates.
+pub(super) fn search_ordered(
+    filters: &SearchFilters,
+    mut items: Vec<BrokerItem>,
+    pool: Option<&ThreadPool>,
+    state: SearchWorkerState<'_>,
+) {
+    items.sort_by_key(|item| item.ts_start);
+    let concurrency = pool
+        .map_or_else(rayon::current_num_threads, ThreadPool::current_num_threads)
+        .max(1);
+    let parsed_messages = AtomicU64::new(0);
+    let worker_state = SearchWorkerState {
+        max_results: None,
+        total_messages: &parsed_messages,
+        ..state
+    };
+
+    match pool {
+        Some(pool) => pool.in_place_scope(|scope| {
+            Merge::new(scope, filters, worker_state, concurrency, items).run(state)
+        }),
+        None => rayon::in_place_scope(|scope| {
+            Merge::new(scope, filters, worker_state, concurrency, items).run(state)
+        }),
+    }
+}
+
+/// Emit the merged elements collected from one source; returns false if the
+/// search should stop
+fn flush(
+    state: &SearchWorkerState<'_>,
+    sources: &[MergeSource],
+    source: Option<usize>,
+    output: &mut Vec<BgpElem>,
+) -> bool {
+    let Some(index) = source else {
+        return true;
+    };
+    if output.is_empty() {
+        return true;
+    }
+    let source = &sources[index];
+    emit_search_batch(state, index, &source.url, &source.collector, output);
+    output.clear();
+    !search_should_stop(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lens::search::{local_file_item, SearchExecutionOptions, SearchLens};
+    use bgpkit_parser::encoder::MrtUpdatesEncoder;
+    use bgpkit_parser::models::NetworkPrefix;
+    use std::str::FromStr;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
+    /// Collects the timestamps of all emitted elements in order
+    #[derive(Default)]
+    struct TimestampSink(Mutex<Vec<f64>>);
+
+    impl SearchSink for TimestampSink {
+        fn on_elements(&self, batch: SearchElementBatch) -> SearchControl {
+            let mut timestamps = self.0.lock().unwrap();
+            timestamps.extend(batch.elements.iter().map(|elem| elem.timestamp));
+            SearchControl::Continue
+        }
+    }
+
+    /// Write an updates file with one announcement per timestamp, in the given
+    /// order, and return its item starting at `start`
+    fn updates_file(
+        dir: &std::path::Path,
+        name: &str,
+        start: i64,
+        timestamps: &[f64],
+    ) -> BrokerItem {
+        let mut encoder = MrtUpdatesEncoder::new();
+        for timestamp in timestamps {
+            encoder.process_elem(&BgpElem {
+                timestamp: *timestamp,
+                peer_ip: "192.0.2.1".parse().unwrap(),
+                peer_asn: 64496.into(),
+                prefix: NetworkPrefix::from_str("1.0.0.0/24").unwrap(),
+                next_hop: Some("192.0.2.1".parse().unwrap()),
+                ..Default::default()
+            });
+        }
+        let path = dir.join(name);
+        std::fs::write(&path, encoder.export_bytes()).unwrap();
+        let mut item = local_file_item(path.to_str().unwrap());
+        item.ts_start = chrono::DateTime::from_timestamp(start, 0)
+            .unwrap()
+            .naive_utc();
+        item
+    }
+
+    #[test]
+    fn test_search_ordered_merges_overlapping_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let items = vec![
+            // Opened only once the merge reaches 65; its element at 35 is
+            // before its start and the others are out of order
+            updates_file(dir.path(), "d.mrt", 65, &[90.0, 80.0, 35.0]),
+            updates_file(dir.path(), "a.mrt", 0, &[10.0, 30.0, 50.0, 70.0]),
+            updates_file(dir.path(), "b.mrt", 20, &[20.0, 40.0, 60.0]),
+            updates_file(dir.path(), "c.mrt", 45, &[45.0, 55.0]),
+        ];
+        let sink = Arc::new(TimestampSink::default());
+        let options = SearchExecutionOptions {
+            concurrency: Some(1),
+            batch_size: 3,
+            ordered: true,
+            ..Default::default()
+        };
+        let outcome = SearchLens::new()
+            .search_items(&SearchFilters::default(), items, options, sink.clone())
+            .unwrap();
+
+        assert_eq!(outcome.summary.successful_files, 4);
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            vec![10.0, 20.0, 30.0, 40.0, 45.0, 50.0, 55.0, 60.0, 35.0, 70.0, 80.0, 90.0]
+        );
+    }
+
+    #[test]
+    fn test_merge_head_pops_earliest_then_file_order() {
+        let mut heap = BinaryHeap::new();
+        for (timestamp, source) in [(3.0, 0), (1.0, 2), (1.0, 1), (2.0, 0)] {
+            heap.push(MergeHead { timestamp, source });
+        }
+        let popped: Vec<(f64, usize)> = std::iter::from_fn(|| heap.pop())
+            .map(|head| (head.timestamp, head.source))
+            .collect();
+        assert_eq!(popped, vec![(1.0, 1), (1.0, 2), (2.0, 0), (3.0, 0)]);
+    }
+
+    #[test]
+    fn test_search_ordered_runs_producers_on_thread_pool() {
+        /// Records the threads that opened files
+        #[derive(Default)]
+        struct ThreadSink {
+            threads: Mutex<Vec<Option<String>>>,
+            timestamps: TimestampSink,
+        }
+
+        impl SearchSink for ThreadSink {
+            fn on_progress(&self, progress: SearchProgress) {
+                if let SearchProgress::FileStarted { .. } = progress {
+                    let name = std::thread::current().name().map(str::to_string);
+                    self.threads.lock().unwrap().push(name);
+                }
+            }
+
+            fn on_elements(&self, batch: SearchElementBatch) -> SearchControl {
+                self.timestamps.on_elements(batch)
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        // All files start together, so all of them are merged at once
+        let items: Vec<BrokerItem> = (0..8)
+            .map(|file| {
+                let timestamps: Vec<f64> = (0..5).map(|i| (i * 8 + file) as f64).collect();
+                updates_file(dir.path(), &format!("{file}.mrt"), 0, &timestamps)
+            })
+            .collect();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .thread_name(|i| format!("ordered-test-{i}"))
+            .build()
+            .unwrap();
+        let sink = Arc::new(ThreadSink::default());
+        let options = SearchExecutionOptions {
+            thread_pool: Some(Arc::new(pool)),
+            batch_size: 2,
+            ordered: true,
+            ..Default::default()
+        };
+        let outcome = SearchLens::new()
+            .search_items(&SearchFilters::default(), items, options, sink.clone())
+            .unwrap();
+
+        assert_eq!(outcome.summary.successful_files, 8);
+        let threads = sink.threads.lock().unwrap();
+        assert_eq!(threads.len(), 8);
+        assert!(threads.iter().all(|name| name
+            .as_deref()
+            .is_some_and(|name| name.starts_with("ordered-test-"))));
+        let expected: Vec<f64> = (0..40).map(f64::from).collect();
+        assert_eq!(*sink.timestamps.0.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_merge_fill_waits_for_producer() {
+        let filters = SearchFilters::default();
+        let (stop_flag, exit_reason) = (AtomicBool::new(false), AtomicU64::new(0));
+        let counters: [AtomicU64; 4] = Default::default();
+        let sink = TimestampSink::default();
+        let state = SearchWorkerState {
+            total_files: 1,
+            start_time: Instant::now(),
+            deadline: None,
+            batch_size: 3,
+            max_results: None,
+            external_cancel: None,
+            stop_flag: &stop_flag,
+            exit_reason: &exit_reason,
+            files_completed: &counters[0],
+            successful_files: &counters[1],
+            failed_files: &counters[2],
+            total_messages: &counters[3],
+            detect_format: false,
+            sink: &sink,
+        };
+
+        rayon::in_place_scope(|scope| {
+            let mut merge = Merge::new(scope, &filters, state, 1, vec![local_file_item("a.mrt")]);
+            merge
+                .sources
+                .push(MergeSource::new(merge.pending.next().unwrap()));
+            // Stand in for a running task of the file
+            merge.sources[0].producer = None;
+            merge.running = 1;
+            let sender = merge.sender.clone();
+            let producer = std::thread::spawn(move || {
+                let elements = [7.0, 5.0, 6.0].map(|timestamp| BgpElem {
+                    timestamp,
+                    ..Default::default()
+                });
+                sender.send(ProducerEvent::Batch(0, Vec::new())).unwrap();
+                sender
+                    .send(ProducerEvent::Batch(0, elements.to_vec()))
+                    .unwrap();
+                sender.send(ProducerEvent::Finished(0)).unwrap();
+            });
+
+            assert_eq!(merge.fill(0), Some(5.0));
+            let timestamps: Vec<f64> = merge.sources[0]
+                .buffer
+                .drain(..)
+                .map(|elem| elem.timestamp)
+                .collect();
+            assert_eq!(timestamps, vec![5.0, 6.0, 7.0]);
+            producer.join().unwrap();
+            assert_eq!(merge.fill(0), None);
+            assert!(merge.sources[0].done);
+            assert_eq!(merge.running, 0);
+        });
+    }
+}
@@ -9,13 +9,14 @@
 //!
 //! # Feature Flags
 //!
-//! Monocle uses a simplified feature system with three options:
+//! Monocle uses a simplified feature system with four options:
 //!
 //! | Feature | Description | Implies |
 //! |---------|-------------|---------|
 //! | `lib` | Complete library (database + all lenses + display) | - |
 //! | `server` | HTTP/SSE server for programmatic API access | `lib` |
-//! | `cli` | Full CLI binary with all functionality | `lib`, `server` |
+//! | `parquet` | Apache Parquet export of BGP elements and RIB snapshots | `lib` |
+//! | `cli` | Full CLI binary with all functionality | `lib`, `server`, `parquet` |
 //!
 //! ## Choosing Features
 //!
@@ -26,6 +27,9 @@
 //! # Library + HTTP server
 //! monocle = { version = "1.0", default-features = false, features = ["server"] }
 //!
+//! # Library + Parquet output
+//! monocle = { version = "1.0", default-features = false, features = ["parquet"] }
+//!
 //! # Default (full CLI binary)
 //! monocle = "1.0"
 //! ```
@@ -33,6 +33,7 @@ use tokio::sync::Semaphore;
 use tower_http::cors::{Any, CorsLayer};
 
 use crate::config::MonocleConfig;
+use crate::lens::search::{broker_backend_from_config, BrokerBackend};
 
 // =============================================================================
 // Server State
@@ -44,6 +45,7 @@ pub struct ServerState {
     pub config: Arc<MonocleConfig>,
     pub search_permits: Option<Arc<Semaphore>>,
     pub search_pool: Option<Arc<rayon::ThreadPool>>,
+    pub broker_backend: Arc<dyn BrokerBackend>,
 }
 
 // =============================================================================
@@ -57,6 +59,9 @@ pub async fn start_server(config: MonocleConfig) -> anyhow::Result<()> {
     let auth_enabled = config.server_auth_enabled;
     let auth_token = config.server_auth_token.trim().to_string();
 
+    // Fail at startup rather than per request if the broker backend is misconfigured
+    let broker_backend = broker_backend_from_config(&config)?;
+
     let max_concurrent_searches = config.server_max_concurrent_searches;
     let search_concurrency = config.search_concurrency;
     let search_pool = if search_concurrency > 0 {
@@ -73,6 +78,7 @@ pub async fn start_server(config: MonocleConfig) -> anyhow::Result<()> {
         search_permits: (max_concurrent_searches > 0)
             .then(|| Arc::new(Semaphore::new(max_concurrent_searches))),
         search_pool,
+        broker_backend,
     };
 
     let cors = CorsLayer::new()
@@ -124,6 +130,7 @@ async fn health_handler() -> &'static str {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lens::search::BgpkitBrokerBackend;
 
     #[test]
     fn test_server_state_clone() {
@@ -132,6 +139,7 @@ mod tests {
             config: Arc::new(config),
             search_permits: Some(Arc::new(Semaphore::new(3))),
             search_pool: None,
+            broker_backend: Arc::new(BgpkitBrokerBackend::new()),
         };
         let _cloned = state.clone();
     }
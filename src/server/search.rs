@@ -18,18 +18,28 @@ use axum::extract::State;
 use axum::response::sse::{Event as SseEvent, Sse};
 use axum::Json;
 use bgpkit_parser::BgpElem;
+use chrono::NaiveDate;
 use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 
-use crate::lens::parse::ParseElemType;
+use crate::database::MonocleDatabase;
+use crate::lens::parse::as_path_regex::AsPathStyle;
+use crate::lens::parse::enrich::{open_asinfo_database, AsInfoEnricher, ElemAsInfo};
+use crate::lens::parse::time_window::{TimeWindowSpec, TimeWindows};
+use crate::lens::parse::{ParseElemType, ParseFilters};
+use crate::lens::rpki::{RpkiDataSource, RpkiLens};
 use crate::lens::search::{
-    SearchControl, SearchElementBatch, SearchExecutionOptions, SearchExitReason, SearchFilters,
-    SearchLens, SearchOutcome, SearchProgress, SearchSink,
+    parse_bucket_width, parse_dedup_window, DedupEvent, DedupSink, ElemDeduplicator,
+    HistogramSeries, HistogramSink, HistogramUpdate, SearchControl, SearchElementBatch,
+    SearchExecutionOptions, SearchExitReason, SearchFilters, SearchLens, SearchOutcome,
+    SearchProgress, SearchSink, TimeHistogram,
 };
 use crate::server::http::{ApiError, ApiErrorCode, ApiErrorResponse};
 use crate::server::ServerState;
+use crate::utils::GroupByField;
+use crate::MonocleConfig;
 
 // =============================================================================
 // Wire DTOs
@@ -42,13 +52,48 @@ use crate::server::ServerState;
 /// the API contract.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchStreamRequest {
+    #[serde(default)]
     pub filters: SearchStreamFilters,
+    /// Name of a saved query from the server's config, merged into `filters`
+    /// (values in `filters` win for single-value fields)
+    #[serde(default)]
+    pub query: Option<String>,
     /// Elements per SSE batch (clamped to server max)
     #[serde(default)]
     pub batch_size: Option<usize>,
     /// Maximum total results (0 or None = unlimited, clamped to server max)
     #[serde(default)]
     pub max_results: Option<u64>,
+    /// Stream time-bucketed counts (`histogram` events) instead of elements
+    #[serde(default)]
+    pub histogram: Option<HistogramRequest>,
+    /// Stream deduplicated events (`dedup` events) instead of elements
+    #[serde(default)]
+    pub dedup: Option<DedupRequest>,
+    /// Stream elements in global timestamp order across all files
+    #[serde(default)]
+    pub ordered: bool,
+    /// Add the AS names, countries and organizations of streamed elements
+    #[serde(default)]
+    pub as_info: bool,
+}
+
+/// Histogram mode options for `SearchStreamRequest`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistogramRequest {
+    /// Bucket width in seconds (`"300"`) or as a duration (`"5m"`, `"1h"`)
+    pub bucket_width: String,
+    /// Fields to split the histogram into separate series by
+    #[serde(default)]
+    pub split_by: Vec<GroupByField>,
+}
+
+/// Dedup mode options for `SearchStreamRequest`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupRequest {
+    /// Maximum gap between observations of one event, in seconds (`"30"`) or
+    /// as a duration (`"30s"`, `"5m"`)
+    pub window: String,
 }
 
 /// Wire-level filters mirroring `SearchFilters` + `ParseFilters` field names.
@@ -72,10 +117,75 @@ pub struct SearchStreamFilters {
     pub elem_type: Option<String>,
     #[serde(default)]
     pub as_path: Option<String>,
-    /// Start timestamp (unix or human-readable). Required.
-    pub start_ts: String,
-    /// End timestamp (unix or human-readable). Required.
-    pub end_ts: String,
+    /// Dialect of `as_path`: `raw` (default), `cisco` or `junos`
+    #[serde(default)]
+    pub as_path_style: Option<AsPathStyle>,
+    /// Extended community patterns (`rt:A:B`, `soo:A:B`, `*:A:B`)
+    #[serde(default)]
+    pub ext_communities: Vec<String>,
+    /// MED values or inclusive ranges (`N`, `A..B`, `A..`, `..B`)
+    #[serde(default)]
+    pub med: Vec<String>,
+    /// LOCAL_PREF values or inclusive ranges
+    #[serde(default)]
+    pub local_pref: Vec<String>,
+    /// Next hop IP addresses or prefixes
+    #[serde(default)]
+    pub next_hop: Vec<String>,
+    /// ORIGIN attribute values (`igp`, `egp`, `incomplete`)
+    #[serde(default)]
+    pub origin: Vec<String>,
+    #[serde(default)]
+    pub aggr_asn: Vec<String>,
+    #[serde(default)]
+    pub atomic: Option<bool>,
+    /// RPKI validation states (`valid`, `invalid`, `not-found`), validated
+    /// against the server's RPKI cache unless `rpki_date` is set
+    #[serde(default)]
+    pub rpki: Vec<String>,
+    /// Validate `rpki` against the historical RPKI data of this date
+    #[serde(default)]
+    pub rpki_date: Option<NaiveDate>,
+    /// Source of `rpki_date` data: `Ripe`, `RpkiViews` or `RpkiSpools` (default)
+    #[serde(default)]
+    pub rpki_source: Option<RpkiDataSource>,
+    /// Origin AS registration countries (codes or names), resolved with the
+    /// server's ASInfo data
+    #[serde(default)]
+    pub origin_country: Vec<String>,
+    /// Peer AS registration countries (codes or names)
+    #[serde(default)]
+    pub peer_country: Vec<String>,
+    /// Origin AS organizations (CAIDA AS2Org org IDs)
+    #[serde(default)]
+    pub origin_org: Vec<String>,
+    /// AS path length ranges (`N`, `A..B`, `A..`, `..B`)
+    #[serde(default)]
+    pub path_len: Vec<String>,
+    #[serde(default)]
+    pub unique_path_len: Vec<String>,
+    #[serde(default)]
+    pub prepend_count: Vec<String>,
+    #[serde(default)]
+    pub as_set: Option<bool>,
+    #[serde(default)]
+    pub private_asn: Option<bool>,
+    #[serde(default)]
+    pub reserved_asn: Option<bool>,
+    #[serde(default)]
+    pub path_loop: Option<bool>,
+    /// Filter expression, see `monocle::lens::parse::expr`
+    #[serde(default, rename = "where")]
+    pub where_expr: Option<String>,
+    /// Start timestamp (unix or human-readable). Required unless `windows` is set.
+    #[serde(default)]
+    pub start_ts: Option<String>,
+    /// End timestamp (unix or human-readable). Required unless `windows` is set.
+    #[serde(default)]
+    pub end_ts: Option<String>,
+    /// Several time windows to search instead of `start_ts`/`end_ts`
+    #[serde(default)]
+    pub windows: Vec<TimeWindowSpec>,
     #[serde(default)]
     pub collector: Option<String>,
     #[serde(default)]
@@ -88,19 +198,14 @@ impl TryFrom<SearchStreamFilters> for SearchFilters {
     type Error = anyhow::Error;
 
     fn try_from(f: SearchStreamFilters) -> Result<Self, Self::Error> {
-        use crate::lens::parse::ParseFilters;
+        use crate::lens::parse::as_path_filter::AsPathFilters;
+        use crate::lens::parse::asinfo_filter::AsinfoFilters;
+        use crate::lens::parse::rpki_filter::RpkiFilters;
         use crate::lens::search::SearchDumpType;
 
         let dump_type = match f.dump_type.as_deref() {
-            None | Some("updates") => SearchDumpType::Updates,
-            Some("rib") => SearchDumpType::Rib,
-            Some("rib_updates") | Some("all") => SearchDumpType::RibUpdates,
-            Some(other) => {
-                anyhow::bail!(
-                    "invalid dump_type '{}': expected 'updates', 'rib', or 'rib_updates'",
-                    other
-                )
-            }
+            None => SearchDumpType::Updates,
+            Some(dump_type) => dump_type.parse()?,
         };
 
         let parse_filters = ParseFilters {
@@ -127,10 +232,41 @@ impl TryFrom<SearchStreamFilters> for SearchFilters {
                     other
                 ),
             },
-            start_ts: Some(f.start_ts),
-            end_ts: Some(f.end_ts),
+            start_ts: f.start_ts,
+            end_ts: f.end_ts,
             duration: None,
+            windows: f.windows,
             as_path: f.as_path,
+            as_path_style: f.as_path_style,
+            as_path_filters: AsPathFilters {
+                path_len: f.path_len,
+                unique_path_len: f.unique_path_len,
+                prepend_count: f.prepend_count,
+                as_set: f.as_set,
+                private_asn: f.private_asn,
+                reserved_asn: f.reserved_asn,
+                path_loop: f.path_loop,
+            },
+            ext_communities: f.ext_communities,
+            med: f.med,
+            local_pref: f.local_pref,
+            next_hop: f.next_hop,
+            origin: f.origin,
+            aggr_asn: f.aggr_asn,
+            atomic: f.atomic,
+            rpki_filters: RpkiFilters {
+                rpki: f.rpki,
+                rpki_date: f.rpki_date,
+                rpki_source: f.rpki_source,
+                validator: None,
+            },
+            asinfo_filters: AsinfoFilters {
+                origin_country: f.origin_country,
+                peer_country: f.peer_country,
+                origin_org: f.origin_org,
+                resolved: None,
+            },
+            where_expr: f.where_expr,
         };
 
         Ok(SearchFilters {
@@ -154,6 +290,15 @@ pub struct SearchStarted {
     pub max_results: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timeout_secs: Option<u64>,
+    /// Histogram bucket width in seconds, present in histogram mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucket_secs: Option<u64>,
+    /// Dedup window in seconds, present in dedup mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedup_window_secs: Option<u64>,
+    /// Whether elements are streamed in global timestamp order
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub ordered: bool,
 }
 
 /// A batch of elements sent in an `elements` event.
@@ -167,6 +312,33 @@ pub struct ElementsBatch {
     pub collector: Option<String>,
     pub file_url: String,
     pub elements: Vec<BgpElem>,
+    /// Labels of the time windows each element matched, in element order;
+    /// present when the search has time windows
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<Vec<String>>,
+    /// AS information of each element, in element order; present when the
+    /// request sets `as_info`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub as_info: Vec<ElemAsInfo>,
+}
+
+/// Updated bucket totals sent in a `histogram` event.
+///
+/// Each update carries the new cumulative count of one bucket of one series,
+/// so clients can simply overwrite their copy of that bucket.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistogramBatch {
+    pub updates: Vec<HistogramUpdate>,
+}
+
+/// A batch of deduplicated events sent in a `dedup` event.
+///
+/// Events are only complete once every file has been searched, so all `dedup`
+/// events are sent after the search finishes and before the final event.
+#[derive(Debug, Clone, Serialize)]
+pub struct DedupBatch {
+    pub total_so_far: u64,
+    pub events: Vec<DedupEvent>,
 }
 
 /// Final result for a completed, cancelled, timed-out, or failed SSE search.
@@ -176,6 +348,9 @@ pub struct SearchStreamResult {
     pub stats: SearchStreamStats,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<ApiErrorResponse>,
+    /// Complete zero-filled time series, present in histogram mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub histogram: Option<HistogramSeries>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -247,6 +422,8 @@ enum SearchStreamEvent {
     Started(SearchStarted),
     Progress(SearchProgress),
     Elements(ElementsBatch),
+    Histogram(HistogramBatch),
+    Dedup(DedupBatch),
     Completed(SearchStreamResult),
     Cancelled(SearchStreamResult),
     Error(SearchStreamResult),
@@ -259,6 +436,8 @@ impl SearchStreamEvent {
             SearchStreamEvent::Started(data) => ("started", serde_json::to_value(data).ok()?),
             SearchStreamEvent::Progress(data) => ("progress", serde_json::to_value(data).ok()?),
             SearchStreamEvent::Elements(data) => ("elements", serde_json::to_value(data).ok()?),
+            SearchStreamEvent::Histogram(data) => ("histogram", serde_json::to_value(data).ok()?),
+            SearchStreamEvent::Dedup(data) => ("dedup", serde_json::to_value(data).ok()?),
             SearchStreamEvent::Completed(data) => ("completed", serde_json::to_value(data).ok()?),
             SearchStreamEvent::Cancelled(data) => ("cancelled", serde_json::to_value(data).ok()?),
             SearchStreamEvent::Error(data) => ("error", serde_json::to_value(data).ok()?),
@@ -285,19 +464,47 @@ pub async fn stream_search(
     State(state): State<ServerState>,
     Json(request): Json<SearchStreamRequest>,
 ) -> Result<Sse<impl Stream<Item = Result<SseEvent, std::convert::Infallible>>>, ApiError> {
-    // 1. Convert wire DTO to internal SearchFilters
-    let filters: SearchFilters = request
+    // 1. Convert wire DTO to internal SearchFilters, applying the saved query
+    let mut filters: SearchFilters = request
         .filters
         .try_into()
         .map_err(|e: anyhow::Error| ApiError::invalid_params(e.to_string()))?;
+    if let Some(name) = request.query.as_deref() {
+        state
+            .config
+            .saved_query(name)
+            .and_then(|query| query.merge_into_search(&mut filters))
+            .map_err(|e| ApiError::invalid_params(e.to_string()))?;
+    }
 
     // 2. Validate filters (time range parse, etc.)
     filters
         .validate()
         .map_err(|e| ApiError::invalid_params(e.to_string()))?;
 
-    // 3. Clamp batch_size and max_results to server-configured limits
+    // 3. Load the ROAs of an RPKI filter and the ASNs of country and
+    //    organization filters, refreshing stale data first
     let config = &state.config;
+    if !filters.parse_filters.rpki_filters.is_empty() {
+        let rpki_config = config.clone();
+        filters = tokio::task::spawn_blocking(move || {
+            load_rpki(&rpki_config, &mut filters.parse_filters).map(|_| filters)
+        })
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?
+        .map_err(|e| ApiError::internal(format!("failed to load RPKI data: {e}")))?;
+    }
+    if !filters.parse_filters.asinfo_filters.is_empty() {
+        let asinfo_config = config.clone();
+        filters = tokio::task::spawn_blocking(move || {
+            load_asinfo(&asinfo_config, &mut filters.parse_filters).map(|_| filters)
+        })
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?
+        .map_err(|e| ApiError::internal(format!("failed to load ASInfo data: {e}")))?;
+    }
+
+    // 4. Clamp batch_size and max_results to server-configured limits
     let batch_size = request
         .batch_size
         .unwrap_or(config.server_max_search_batch_size)
@@ -312,13 +519,56 @@ pub async fn stream_search(
         (None, limit) => Some(limit),           // server limit
     };
 
+    let histogram = match request.histogram {
+        Some(histogram) => Some(
+            build_histogram(&filters, histogram)
+                .map_err(|e| ApiError::invalid_params(e.to_string()))?,
+        ),
+        None => None,
+    };
+    let dedup_window_secs = match request.dedup {
+        Some(_) if histogram.is_some() => {
+            return Err(ApiError::invalid_params(
+                "histogram and dedup cannot be combined",
+            ));
+        }
+        Some(dedup) => Some(
+            parse_dedup_window(&dedup.window)
+                .map_err(|e| ApiError::invalid_params(e.to_string()))?,
+        ),
+        None => None,
+    };
+    if request.ordered && (histogram.is_some() || dedup_window_secs.is_some()) {
+        return Err(ApiError::invalid_params(
+            "ordered cannot be combined with histogram or dedup",
+        ));
+    }
+    if request.as_info && (histogram.is_some() || dedup_window_secs.is_some()) {
+        return Err(ApiError::invalid_params(
+            "as_info cannot be combined with histogram or dedup",
+        ));
+    }
+
     let timeout_secs = if config.server_search_timeout_secs > 0 {
         Some(config.server_search_timeout_secs)
     } else {
         None
     };
 
-    // 4. Enforce server-side concurrent search limit. Requests are rejected
+    // 5. Load the ASInfo data for AS information, refreshing stale data first
+    let as_info = if request.as_info {
+        let as_info_config = config.clone();
+        let enricher =
+            tokio::task::spawn_blocking(move || AsInfoEnricher::from_config(&as_info_config))
+                .await
+                .map_err(|e| ApiError::internal(e.to_string()))?
+                .map_err(|e| ApiError::internal(format!("failed to load ASInfo data: {e}")))?;
+        Some(enricher)
+    } else {
+        None
+    };
+
+    // 6. Enforce server-side concurrent search limit. Requests are rejected
     // immediately instead of queued so clients get deterministic feedback.
     let search_permit = match state.search_permits.as_ref() {
         Some(search_permits) => Some(
@@ -336,22 +586,28 @@ pub async fn stream_search(
         None
     };
     let search_pool = state.search_pool.clone();
+    let lens = SearchLens::with_backend(state.broker_backend.clone());
 
-    // 5. Create bounded channel and cancellation flag
+    // 7. Create bounded channel and cancellation flag
     let (tx, rx) = mpsc::channel::<SearchStreamEvent>(32);
     let cancel_flag = Arc::new(AtomicBool::new(false));
 
-    // 6. Spawn the search worker in a blocking task
+    // 8. Spawn the search worker in a blocking task
     let worker_cancel_flag = cancel_flag.clone();
     let worker_tx = tx.clone();
 
     tokio::task::spawn_blocking(move || {
         let _search_permit = search_permit;
         run_search_worker(SearchWorkerConfig {
+            lens,
             filters,
             batch_size,
             max_results,
             timeout_secs,
+            histogram,
+            dedup_window_secs,
+            ordered: request.ordered,
+            as_info,
             concurrency,
             search_pool,
             cancel_flag: worker_cancel_flag,
@@ -359,7 +615,7 @@ pub async fn stream_search(
         });
     });
 
-    // 6. Build SSE stream from the channel receiver.
+    // 9. Build SSE stream from the channel receiver.
     // When the client disconnects, the Sse response is dropped, which drops
     // `rx`, which causes `tx.send()` to fail in the worker, which sets
     // `cancel_flag`. The worker checks `cancel_flag` and stops.
@@ -384,15 +640,55 @@ pub async fn stream_search(
     Ok(Sse::new(stream))
 }
 
+/// Load the ROAs for the RPKI filter of `filters`, from historical data or
+/// from the RPKI cache, which is refreshed first if it is empty or outdated
+fn load_rpki(config: &MonocleConfig, filters: &mut ParseFilters) -> anyhow::Result<()> {
+    if filters.rpki_filters.rpki_date.is_some() {
+        filters.load_rpki(None)?;
+        return Ok(());
+    }
+    let db = MonocleDatabase::open_in_dir(&config.data_dir)?;
+    let lens = RpkiLens::new(&db);
+    if lens.refresh_reason(config.rpki_cache_ttl())?.is_some() {
+        lens.refresh()?;
+    }
+    filters.load_rpki(Some(&db))?;
+    Ok(())
+}
+
+/// Resolve the country and organization filters of `filters` to ASNs, from
+/// the ASInfo data, which is refreshed first if it is empty or outdated
+fn load_asinfo(config: &MonocleConfig, filters: &mut ParseFilters) -> anyhow::Result<()> {
+    filters.load_asinfo(&open_asinfo_database(config)?)
+}
+
+/// Build the empty histogram for a histogram-mode request, covering the
+/// search window and rejecting widths that would produce too many buckets.
+fn build_histogram(
+    filters: &SearchFilters,
+    request: HistogramRequest,
+) -> anyhow::Result<TimeHistogram> {
+    let bucket_secs = parse_bucket_width(&request.bucket_width)?;
+    let (ts_start, ts_end) = filters.parse_filters.parse_start_end_strings()?;
+    let histogram = TimeHistogram::new(bucket_secs, request.split_by).with_range(ts_start, ts_end);
+    histogram.check_range()?;
+    Ok(histogram)
+}
+
 // =============================================================================
 // Search Worker (runs in spawn_blocking)
 // =============================================================================
 
 struct SearchWorkerConfig {
+    lens: SearchLens,
     filters: SearchFilters,
     batch_size: usize,
     max_results: Option<u64>,
     timeout_secs: Option<u64>,
+    histogram: Option<TimeHistogram>,
+    dedup_window_secs: Option<u64>,
+    ordered: bool,
+    as_info: Option<AsInfoEnricher>,
     concurrency: Option<usize>,
     search_pool: Option<Arc<rayon::ThreadPool>>,
     cancel_flag: Arc<AtomicBool>,
@@ -401,10 +697,15 @@ struct SearchWorkerConfig {
 
 fn run_search_worker(config: SearchWorkerConfig) {
     let SearchWorkerConfig {
+        lens,
         filters,
         batch_size,
         max_results,
         timeout_secs,
+        histogram,
+        dedup_window_secs,
+        ordered,
+        as_info,
         concurrency,
         search_pool,
         cancel_flag,
@@ -417,23 +718,57 @@ fn run_search_worker(config: SearchWorkerConfig) {
             batch_size,
             max_results,
             timeout_secs,
+            bucket_secs: histogram.as_ref().map(|h| h.bucket_secs()),
+            dedup_window_secs,
+            ordered,
         }),
     );
 
-    let sink = Arc::new(SseSearchSink::new(event_tx.clone(), cancel_flag.clone()));
-    let stats_sink = Arc::clone(&sink);
     let options = SearchExecutionOptions {
         concurrency,
         thread_pool: search_pool,
         max_results,
         timeout: timeout_secs.map(Duration::from_secs),
-        cancel_flag: Some(cancel_flag),
+        cancel_flag: Some(cancel_flag.clone()),
         batch_size,
+        ordered,
+        detect_format: false,
     };
 
-    let lens = SearchLens::new();
-    let outcome = match lens.search_with_options(&filters, options, sink) {
-        Ok(outcome) => outcome,
+    let searched = match (histogram, dedup_window_secs) {
+        (Some(histogram), _) => {
+            let inner = histogram_sink(histogram, event_tx.clone(), cancel_flag.clone());
+            let sink = Arc::new(SseSummarySink::new(inner, event_tx.clone(), cancel_flag));
+            lens.search_with_options(&filters, options, sink.clone())
+                .map(|outcome| {
+                    let series = sink.inner.take().series().ok();
+                    (outcome, sink.matching_files(), series)
+                })
+        }
+        (None, Some(window_secs)) => {
+            let inner = DedupSink::new(window_secs);
+            let sink = Arc::new(SseSummarySink::new(inner, event_tx.clone(), cancel_flag));
+            lens.search_with_options(&filters, options, sink.clone())
+                .map(|outcome| {
+                    // Nobody is listening any more after a cancellation
+                    if outcome.exit_reason != SearchExitReason::Cancelled {
+                        send_dedup_events(&event_tx, sink.inner.take(), batch_size);
+                    }
+                    (outcome, sink.matching_files(), None)
+                })
+        }
+        (None, None) => {
+            let windows = filters.time_windows().unwrap_or_default();
+            let sink = Arc::new(
+                SseSearchSink::new(event_tx.clone(), cancel_flag, windows).with_as_info(as_info),
+            );
+            lens.search_with_options(&filters, options, sink.clone())
+                .map(|outcome| (outcome, sink.matching_files(), None))
+        }
+    };
+
+    let (outcome, matching_files, histogram) = match searched {
+        Ok(searched) => searched,
         Err(e) => {
             let _ = send_event(
                 &event_tx,
@@ -444,6 +779,7 @@ fn run_search_worker(config: SearchWorkerConfig) {
                         ApiErrorCode::SearchFailed,
                         e.to_string(),
                     )),
+                    histogram: None,
                 }),
             );
             return;
@@ -452,8 +788,9 @@ fn run_search_worker(config: SearchWorkerConfig) {
 
     let result = SearchStreamResult {
         exit_reason: outcome.exit_reason,
-        stats: SearchStreamStats::from_outcome(&outcome, stats_sink.matching_files()),
+        stats: SearchStreamStats::from_outcome(&outcome, matching_files),
         error: None,
+        histogram,
     };
     match outcome.exit_reason {
         SearchExitReason::Cancelled => {
@@ -492,10 +829,16 @@ struct SseSearchSink {
     state: Mutex<SseSearchState>,
     event_tx: mpsc::Sender<SearchStreamEvent>,
     cancel_flag: Arc<AtomicBool>,
+    windows: TimeWindows,
+    as_info: Option<AsInfoEnricher>,
 }
 
 impl SseSearchSink {
-    fn new(event_tx: mpsc::Sender<SearchStreamEvent>, cancel_flag: Arc<AtomicBool>) -> Self {
+    fn new(
+        event_tx: mpsc::Sender<SearchStreamEvent>,
+        cancel_flag: Arc<AtomicBool>,
+        windows: TimeWindows,
+    ) -> Self {
         Self {
             state: Mutex::new(SseSearchState {
                 total_so_far: 0,
@@ -503,7 +846,32 @@ impl SseSearchSink {
             }),
             event_tx,
             cancel_flag,
+            windows,
+            as_info: None,
+        }
+    }
+
+    /// Add the AS information of elements to `elements` events
+    fn with_as_info(mut self, as_info: Option<AsInfoEnricher>) -> Self {
+        self.as_info = as_info;
+        self
+    }
+
+    /// Window labels of every element in `elements`, or nothing without windows
+    fn window_labels(&self, elements: &[BgpElem]) -> Vec<Vec<String>> {
+        if self.windows.is_empty() {
+            return Vec::new();
         }
+        elements
+            .iter()
+            .map(|elem| {
+                self.windows
+                    .labels_at(elem.timestamp)
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect()
+            })
+            .collect()
     }
 
     fn matching_files(&self) -> Vec<MatchingFile> {
@@ -516,16 +884,7 @@ impl SseSearchSink {
 
 impl SearchSink for SseSearchSink {
     fn on_progress(&self, progress: SearchProgress) {
-        // The executor emits SearchProgress::Completed as a progress event,
-        // but run_search_worker sends the terminal SearchStreamEvent::Completed
-        // (or Cancelled/Error) itself. Suppress the redundant progress-level
-        // Completed to avoid sending two completion events to the client.
-        if matches!(progress, SearchProgress::Completed { .. }) {
-            return;
-        }
-        if send_event(&self.event_tx, SearchStreamEvent::Progress(progress)).is_err() {
-            self.cancel_flag.store(true, Ordering::Relaxed);
-        }
+        send_progress(&self.event_tx, &self.cancel_flag, progress);
     }
 
     fn on_elements(&self, batch: SearchElementBatch) -> SearchControl {
@@ -546,6 +905,12 @@ impl SearchSink for SseSearchSink {
             total_so_far: state.total_so_far,
             collector: Some(batch.collector),
             file_url: batch.file_url,
+            windows: self.window_labels(&batch.elements),
+            as_info: self
+                .as_info
+                .as_ref()
+                .map(|enricher| enricher.lookup_all(&batch.elements))
+                .unwrap_or_default(),
             elements: batch.elements,
         });
 
@@ -558,11 +923,124 @@ impl SearchSink for SseSearchSink {
     }
 }
 
+/// Build the histogram sink for histogram-mode searches, which reports the
+/// updated bucket totals of every batch as a `histogram` event.
+fn histogram_sink(
+    histogram: TimeHistogram,
+    event_tx: mpsc::Sender<SearchStreamEvent>,
+    cancel_flag: Arc<AtomicBool>,
+) -> HistogramSink {
+    HistogramSink::new(histogram).with_updates(Arc::new(move |updates| {
+        let event = SearchStreamEvent::Histogram(HistogramBatch { updates });
+        if send_event(&event_tx, event).is_err() {
+            cancel_flag.store(true, Ordering::Relaxed);
+            SearchControl::Stop
+        } else {
+            SearchControl::Continue
+        }
+    }))
+}
+
+/// Send all deduplicated events in `dedup` events of at most `batch_size` events.
+fn send_dedup_events(
+    tx: &mpsc::Sender<SearchStreamEvent>,
+    dedup: ElemDeduplicator,
+    batch_size: usize,
+) {
+    let mut total_so_far = 0;
+    let events = dedup.into_events();
+    for chunk in events.chunks(batch_size.max(1)) {
+        total_so_far += chunk.len() as u64;
+        let batch = DedupBatch {
+            total_so_far,
+            events: chunk.to_vec(),
+        };
+        if send_event(tx, SearchStreamEvent::Dedup(batch)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Sink for histogram and dedup searches.
+///
+/// Elements are summarized by the wrapped sink instead of being streamed;
+/// this wrapper forwards progress and records the matching files for the
+/// final statistics.
+struct SseSummarySink<S> {
+    inner: S,
+    matching_files: Mutex<BTreeSet<MatchingFile>>,
+    event_tx: mpsc::Sender<SearchStreamEvent>,
+    cancel_flag: Arc<AtomicBool>,
+}
+
+impl<S: SearchSink> SseSummarySink<S> {
+    fn new(
+        inner: S,
+        event_tx: mpsc::Sender<SearchStreamEvent>,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            inner,
+            matching_files: Mutex::new(BTreeSet::new()),
+            event_tx,
+            cancel_flag,
+        }
+    }
+
+    fn matching_files(&self) -> Vec<MatchingFile> {
+        self.matching_files
+            .lock()
+            .map(|files| files.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+impl<S: SearchSink> SearchSink for SseSummarySink<S> {
+    fn on_progress(&self, progress: SearchProgress) {
+        send_progress(&self.event_tx, &self.cancel_flag, progress);
+    }
+
+    fn on_elements(&self, batch: SearchElementBatch) -> SearchControl {
+        match self.matching_files.lock() {
+            Ok(mut files) => {
+                files.insert(MatchingFile {
+                    collector: batch.collector.clone(),
+                    file_url: batch.file_url.clone(),
+                });
+            }
+            Err(_) => {
+                self.cancel_flag.store(true, Ordering::Relaxed);
+                return SearchControl::Stop;
+            }
+        }
+        self.inner.on_elements(batch)
+    }
+}
+
+/// Forward a progress update to the client.
+///
+/// The executor emits SearchProgress::Completed as a progress event, but
+/// run_search_worker sends the terminal SearchStreamEvent::Completed (or
+/// Cancelled/Error) itself. Suppress the redundant progress-level Completed
+/// to avoid sending two completion events to the client.
+fn send_progress(
+    tx: &mpsc::Sender<SearchStreamEvent>,
+    cancel_flag: &AtomicBool,
+    progress: SearchProgress,
+) {
+    if matches!(progress, SearchProgress::Completed { .. }) {
+        return;
+    }
+    if send_event(tx, SearchStreamEvent::Progress(progress)).is_err() {
+        cancel_flag.store(true, Ordering::Relaxed);
+    }
+}
+
 /// Send an event on the channel, handling backpressure.
 ///
 /// For progress events, use `try_send` and skip if the channel is full
 /// (progress is informational and can be coalesced). For all other events
-/// (started, elements, completed, cancelled, error), block until the receiver
+/// (started, elements, histogram, dedup, completed, cancelled, error), block until the receiver
 /// is ready — these are contractual and must not be dropped.
 fn send_event(tx: &mpsc::Sender<SearchStreamEvent>, event: SearchStreamEvent) -> Result<(), ()> {
     match &event {
@@ -626,8 +1104,8 @@ mod tests {
     fn test_search_stream_filters_conversion() {
         let wire = SearchStreamFilters {
             prefix: vec!["1.1.1.0/24".to_string()],
-            start_ts: "2024-01-01T00:00:00Z".to_string(),
-            end_ts: "2024-01-01T00:10:00Z".to_string(),
+            start_ts: Some("2024-01-01T00:00:00Z".to_string()),
+            end_ts: Some("2024-01-01T00:10:00Z".to_string()),
             collector: Some("rrc00".to_string()),
             dump_type: Some("updates".to_string()),
             ..Default::default()
@@ -638,11 +1116,74 @@ mod tests {
         assert_eq!(filters.parse_filters.prefix, vec!["1.1.1.0/24"]);
     }
 
+    #[test]
+    fn test_search_stream_attribute_filters() {
+        let request: SearchStreamRequest = serde_json::from_str(
+            r#"{"filters": {"start_ts": "2024-01-01T00:00:00Z", "end_ts": "2024-01-01T00:10:00Z",
+                "local_pref": ["100..200"], "ext_communities": ["rt:64500:*"],
+                "origin": ["!incomplete"], "atomic": false}}"#,
+        )
+        .expect("request should parse");
+        let filters: SearchFilters = request
+            .filters
+            .try_into()
+            .expect("conversion should succeed");
+        assert_eq!(filters.parse_filters.local_pref, vec!["100..200"]);
+        assert_eq!(filters.parse_filters.ext_communities, vec!["rt:64500:*"]);
+        assert_eq!(filters.parse_filters.atomic, Some(false));
+        assert!(filters.validate().is_ok());
+
+        let invalid: SearchFilters = SearchStreamFilters {
+            start_ts: Some("2024-01-01T00:00:00Z".to_string()),
+            end_ts: Some("2024-01-01T00:10:00Z".to_string()),
+            med: vec!["200..100".to_string()],
+            ..Default::default()
+        }
+        .try_into()
+        .expect("conversion should succeed");
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_search_stream_where_expr() {
+        let request: SearchStreamRequest = serde_json::from_str(
+            r#"{"filters": {"start_ts": "2024-01-01T00:00:00Z", "end_ts": "2024-01-01T00:10:00Z",
+                "where": "origin_asn == 64496 or (community == 64496:666 and type == w)"}}"#,
+        )
+        .expect("request should parse");
+        let filters: SearchFilters = request
+            .filters
+            .try_into()
+            .expect("conversion should succeed");
+        assert!(filters.parse_filters.where_expr.is_some());
+        assert!(filters.validate().is_ok());
+
+        let invalid: SearchFilters = SearchStreamFilters {
+            start_ts: Some("2024-01-01T00:00:00Z".to_string()),
+            end_ts: Some("2024-01-01T00:10:00Z".to_string()),
+            where_expr: Some("med <".to_string()),
+            ..Default::default()
+        }
+        .try_into()
+        .expect("conversion should succeed");
+        let err = invalid.validate().expect_err("expression is invalid");
+        assert!(err.to_string().contains("column 6"), "{err}");
+    }
+
+    #[test]
+    fn test_request_with_saved_query_only() {
+        let request: SearchStreamRequest =
+            serde_json::from_str(r#"{"query": "daily"}"#).expect("request should parse");
+        assert_eq!(request.query.as_deref(), Some("daily"));
+        assert!(request.filters.prefix.is_empty());
+        assert!(request.filters.start_ts.is_none());
+    }
+
     #[test]
     fn test_invalid_dump_type() {
         let wire = SearchStreamFilters {
-            start_ts: "2024-01-01T00:00:00Z".to_string(),
-            end_ts: "2024-01-01T00:10:00Z".to_string(),
+            start_ts: Some("2024-01-01T00:00:00Z".to_string()),
+            end_ts: Some("2024-01-01T00:10:00Z".to_string()),
             dump_type: Some("invalid".to_string()),
             ..Default::default()
         };
@@ -657,6 +1198,9 @@ mod tests {
             batch_size: 100,
             max_results: Some(1000),
             timeout_secs: None,
+            bucket_secs: None,
+            dedup_window_secs: None,
+            ordered: false,
         });
         assert!(event.to_sse().is_some());
     }
@@ -700,7 +1244,135 @@ mod tests {
             exit_reason: SearchExitReason::Cancelled,
             stats: SearchStreamStats::empty(),
             error: None,
+            histogram: None,
         });
         assert!(event.to_sse().is_some());
     }
+
+    #[test]
+    fn test_build_histogram_validates_width() {
+        let filters: SearchFilters = SearchStreamFilters {
+            start_ts: Some("2024-01-01T00:00:00Z".to_string()),
+            end_ts: Some("2024-01-01T01:00:00Z".to_string()),
+            ..Default::default()
+        }
+        .try_into()
+        .expect("conversion should succeed");
+
+        let histogram = build_histogram(
+            &filters,
+            HistogramRequest {
+                bucket_width: "5m".to_string(),
+                split_by: vec![GroupByField::Collector],
+            },
+        )
+        .expect("valid histogram");
+        assert_eq!(histogram.bucket_secs(), 300);
+        let series = histogram.series().expect("series");
+        assert_eq!(series.buckets.len(), 12);
+
+        for width in ["0", "soon", "1ns"] {
+            let request = HistogramRequest {
+                bucket_width: width.to_string(),
+                split_by: Vec::new(),
+            };
+            assert!(build_histogram(&filters, request).is_err(), "{width}");
+        }
+    }
+
+    #[test]
+    fn test_histogram_sink_emits_bucket_updates() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let histogram = TimeHistogram::new(60, Vec::new()).with_range(0, 600);
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let inner = histogram_sink(histogram, tx.clone(), cancel_flag.clone());
+        let sink = SseSummarySink::new(inner, tx, cancel_flag);
+
+        let elements = [10.0, 20.0, 130.0]
+            .into_iter()
+            .map(|timestamp| BgpElem {
+                timestamp,
+                ..Default::default()
+            })
+            .collect();
+        let control = sink.on_elements(SearchElementBatch {
+            file_index: 0,
+            file_url: "a".to_string(),
+            collector: "rrc00".to_string(),
+            elements,
+        });
+        assert_eq!(control, SearchControl::Continue);
+
+        match rx.try_recv() {
+            Ok(SearchStreamEvent::Histogram(batch)) => {
+                let counts: Vec<(i64, u64)> = batch
+                    .updates
+                    .iter()
+                    .map(|u| (u.bucket_start, u.count))
+                    .collect();
+                assert_eq!(counts, vec![(0, 2), (120, 1)]);
+            }
+            _ => panic!("expected a histogram event"),
+        }
+        assert_eq!(sink.matching_files().len(), 1);
+        let series = sink.inner.take().series().expect("series");
+        assert_eq!(series.buckets.len(), 10);
+    }
+
+    #[test]
+    fn test_send_dedup_events_in_batches() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut dedup = ElemDeduplicator::new(0);
+        for timestamp in [1.0, 2.0, 3.0] {
+            dedup.add(
+                &BgpElem {
+                    timestamp,
+                    ..Default::default()
+                },
+                Some("rrc00"),
+            );
+        }
+
+        send_dedup_events(&tx, dedup, 2);
+        let mut totals = Vec::new();
+        while let Ok(SearchStreamEvent::Dedup(batch)) = rx.try_recv() {
+            totals.push((batch.events.len(), batch.total_so_far));
+        }
+        assert_eq!(totals, vec![(2, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn test_elements_event_with_as_info() {
+        let db = MonocleDatabase::open_in_memory().expect("database");
+        let records: Vec<crate::database::JsonlRecord> =
+            serde_json::from_str(r#"[{"asn": 13335, "name": "CLOUDFLARENET", "country": "US"}]"#)
+                .expect("records");
+        db.asinfo()
+            .store_from_jsonl(&records, "test://source")
+            .expect("store");
+        let enricher = AsInfoEnricher::new(db).expect("enricher");
+
+        let (tx, mut rx) = mpsc::channel(4);
+        let sink = SseSearchSink::new(tx, Arc::new(AtomicBool::new(false)), TimeWindows::default())
+            .with_as_info(Some(enricher));
+        let elements = vec![BgpElem {
+            peer_asn: 13335.into(),
+            ..Default::default()
+        }];
+        sink.on_elements(SearchElementBatch {
+            file_index: 0,
+            file_url: "a".to_string(),
+            collector: "rrc00".to_string(),
+            elements,
+        });
+
+        match rx.try_recv() {
+            Ok(SearchStreamEvent::Elements(batch)) => {
+                assert_eq!(batch.as_info.len(), 1);
+                assert_eq!(batch.as_info[0].peer_name.as_deref(), Some("CLOUDFLARENET"));
+                assert_eq!(batch.as_info[0].origin_name, None);
+            }
+            _ => panic!("expected an elements event"),
+        }
+    }
 }
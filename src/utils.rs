@@ -681,6 +681,86 @@ impl FromStr for OrderDirection {
     }
 }
 
+// =============================================================================
+// Grouping Utilities for BGP Elements
+// =============================================================================
+
+/// Fields available for grouping BGP elements in aggregation mode
+///
+/// Field names match the element output fields of parse and search commands.
+/// `Community` groups by individual community, so an element carrying several
+/// communities is counted once for each of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "cli", clap(rename_all = "snake_case"))]
+pub enum GroupByField {
+    /// Group by network prefix
+    Prefix,
+    /// Group by origin AS number(s)
+    OriginAsns,
+    /// Group by peer AS number
+    PeerAsn,
+    /// Group by peer IP address
+    PeerIp,
+    /// Group by route collector
+    Collector,
+    /// Group by element type (A or W)
+    Type,
+    /// Group by individual community value
+    Community,
+}
+
+impl GroupByField {
+    /// Get a list of all field names for help text
+    pub fn all_names() -> &'static [&'static str] {
+        &[
+            "prefix",
+            "origin_asns",
+            "peer_asn",
+            "peer_ip",
+            "collector",
+            "type",
+            "community",
+        ]
+    }
+}
+
+impl fmt::Display for GroupByField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Prefix => write!(f, "prefix"),
+            Self::OriginAsns => write!(f, "origin_asns"),
+            Self::PeerAsn => write!(f, "peer_asn"),
+            Self::PeerIp => write!(f, "peer_ip"),
+            Self::Collector => write!(f, "collector"),
+            Self::Type => write!(f, "type"),
+            Self::Community => write!(f, "community"),
+        }
+    }
+}
+
+impl FromStr for GroupByField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "prefix" | "pfx" => Ok(Self::Prefix),
+            "origin_asns" | "origin_asn" | "origin-asn" | "origin" => Ok(Self::OriginAsns),
+            "peer_asn" | "peerasn" | "peer-asn" => Ok(Self::PeerAsn),
+            "peer_ip" | "peerip" | "peer-ip" => Ok(Self::PeerIp),
+            "collector" => Ok(Self::Collector),
+            "type" | "elem_type" => Ok(Self::Type),
+            "community" | "communities" => Ok(Self::Community),
+            _ => Err(format!(
+                "Unknown group-by field '{}'. Valid fields: {}",
+                s,
+                Self::all_names().join(", ")
+            )),
+        }
+    }
+}
+
 // =============================================================================
 // Timestamp Format for BGP Elements
 // =============================================================================
@@ -1186,6 +1266,31 @@ mod tests {
         assert_eq!(OrderDirection::Desc.to_string(), "desc");
     }
 
+    #[test]
+    fn test_group_by_field_from_str() {
+        assert_eq!(
+            GroupByField::from_str("prefix").unwrap(),
+            GroupByField::Prefix
+        );
+        assert_eq!(
+            GroupByField::from_str("origin_asns").unwrap(),
+            GroupByField::OriginAsns
+        );
+        assert_eq!(
+            GroupByField::from_str("peer-asn").unwrap(),
+            GroupByField::PeerAsn
+        );
+        assert_eq!(
+            GroupByField::from_str("communities").unwrap(),
+            GroupByField::Community
+        );
+        assert_eq!(GroupByField::from_str("type").unwrap(), GroupByField::Type);
+        assert!(GroupByField::from_str("as_path").is_err());
+        for name in GroupByField::all_names() {
+            assert_eq!(GroupByField::from_str(name).unwrap().to_string(), *name);
+        }
+    }
+
     #[test]
     fn test_timestamp_format_from_str() {
         assert_eq!(